@@ -1,7 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde_json::json;
 
-use rocket::form::Form;
+use rocket::response::Redirect;
 use rocket::Route;
 use rocket::State;
 
@@ -13,10 +13,10 @@ use surrealdb::Surreal;
 
 use crate::db;
 use crate::notify;
-use crate::web::AdminUser;
+use crate::web::{AdminUser, CsrfForm, Visitor};
 
-use crate::{get_public_config, MyConfig, User};
-use meetings::{id_user_pairs, AuditType, Group};
+use crate::{get_public_config, MyConfig, PublicConfig, User};
+use meetings::{escape_html, id_user_pairs, slugify, AuditType, Group, Topic};
 
 #[derive(FromForm)]
 struct GroupForm<'r> {
@@ -26,6 +26,30 @@ struct GroupForm<'r> {
     owner: usize,
 }
 
+#[derive(FromForm)]
+struct GroupIdForm {
+    gid: usize,
+}
+
+#[derive(FromForm)]
+struct GroupRequestForm<'r> {
+    id: &'r str,
+    #[field(default = "")]
+    reason: &'r str,
+}
+
+#[derive(FromForm)]
+struct TopicForm<'r> {
+    name: &'r str,
+    #[field(default = "")]
+    description: &'r str,
+}
+
+#[derive(FromForm)]
+struct TopicNameForm<'r> {
+    name: &'r str,
+}
+
 #[derive(FromForm)]
 struct SearchForm<'r> {
     query: &'r str,
@@ -39,21 +63,45 @@ pub fn routes() -> Vec<Route> {
         audit_get,
         create_group_get,
         create_group_post,
+        approve_group_request_post,
+        reject_group_request_post,
+        delete_group_post,
         search_get,
         search_post,
+        topics_get,
+        add_topic_post,
+        delete_topic_post,
+        enable_second_factor,
     ]
 }
 
+/// The `AdminUser` guard forwards the admins here who need to enable two-factor authentication
+/// because of `admins_require_2fa`. Everyone else gets 404 as before.
+#[get("/<_..>", rank = 100)]
+fn enable_second_factor(myconfig: &State<MyConfig>, visitor: Visitor) -> Option<Redirect> {
+    let user = visitor.user?;
+    (!visitor.admin && myconfig.admins_require_2fa && myconfig.admins.contains(&user.email))
+        .then(|| Redirect::to("/totp-setup"))
+}
+
 #[get("/")]
-fn admin(visitor: AdminUser) -> Template {
+async fn admin(dbh: &State<Surreal<Client>>, visitor: AdminUser) -> Template {
     let config = get_public_config();
 
+    let group_requests = db::get_group_requests(dbh)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(user, request)| (request.id.id.to_raw(), user, request))
+        .collect::<Vec<_>>();
+
     Template::render(
         "admin",
         context! {
             title: "Admin",
             config ,
             visitor,
+            group_requests,
         },
     )
 }
@@ -95,7 +143,7 @@ fn search_get(visitor: AdminUser) -> Template {
 async fn search_post(
     dbh: &State<Surreal<Client>>,
     visitor: AdminUser,
-    input: Form<SearchForm<'_>>,
+    input: CsrfForm<SearchForm<'_>>,
 ) -> Template {
     rocket::info!("search_post: {:?}", input.query);
     let config = get_public_config();
@@ -144,66 +192,240 @@ async fn create_group_post(
     dbh: &State<Surreal<Client>>,
     myconfig: &State<MyConfig>,
     visitor: AdminUser,
-    input: Form<GroupForm<'_>>,
+    input: CsrfForm<GroupForm<'_>>,
 ) -> Template {
     rocket::info!("create_group_post: {:?}", input.name);
     let config = get_public_config();
 
-    let gid = db::increment(dbh, "group").await.unwrap();
-    // TODO verify that the given owner is a valid user-id (FOREIGN KEY should handle this)
-    // //let owner = get_user_by_email(db, input.owner).await.unwrap();
-    // if owner.is_none() {
-    //     return Template::render(
-    //         "message",
-    //         context! {title: "Invalid email", message: "Invalid email", config, visitor},
-    //     );
-    // }
-    //let owner_id = owner.unwrap().uid;
+    let Some(owner) = db::get_user_by_uid(dbh, input.owner).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such user", message: format!("The user ID <b>{}</b> does not exist.", input.owner), config, visitor},
+        );
+    };
+
+    let user = visitor.user.clone().unwrap();
+    let group = create_group(
+        dbh,
+        myconfig,
+        &user,
+        &owner,
+        input.name,
+        input.location,
+        input.description,
+    )
+    .await;
+
+    Template::render(
+        "message",
+        context! {title: "Group created", message: format!(r#"Group <b><a href="/group/{}">{}</a></b> created"#, group.gid, escape_html(&group.name)), config, visitor},
+    )
+}
 
+/// Create the group, let the owner know about it and record it in the audit log.
+async fn create_group(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    user: &User,
+    owner: &User,
+    name: &str,
+    location: &str,
+    description: &str,
+) -> Group {
+    let gid = db::increment(dbh, "group").await.unwrap();
     rocket::info!("group_id: {gid}");
     let creation_date: DateTime<Utc> = Utc::now();
     let id = Id::ulid();
+    let slug = db::unique_group_slug(dbh, name, gid).await.unwrap();
     let group = Group {
         id: Thing::from(("group", id)),
-        name: input.name.to_owned(),
-        location: input.location.to_owned(),
-        description: input.description.to_owned(),
-        owner: input.owner,
+        name: name.to_owned(),
+        location: location.to_owned(),
+        description: description.to_owned(),
+        owner: owner.uid,
         gid,
         creation_date,
+        requires_approval: false,
+        questions: vec![],
+        archived: None,
+        slug,
+        old_slugs: vec![],
+        topics: vec![],
+        timezone: String::from("UTC"),
     };
 
-    let owner = db::get_user_by_uid(dbh, input.owner)
+    db::add_group(dbh, &group).await.unwrap();
+    notify::owner_group_was_created(dbh, myconfig, owner, &group).await;
+    db::audit(
+        dbh,
+        AuditType::GroupCreated,
+        json!({
+            "group": {
+                "id": gid,
+                "name": group.name,
+            },
+            "owner": {
+                "id": owner.uid,
+                "name": owner.name,
+            },
+            "user": {
+                "id": user.uid,
+                "name": user.name,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+
+    group
+}
+
+#[post("/approve-group-request", data = "<input>")]
+async fn approve_group_request_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: AdminUser,
+    input: CsrfForm<GroupRequestForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+
+    let Some(request) = db::delete_group_request(dbh, input.id).await.unwrap() else {
+        return no_such_group_request(input.id, config, visitor);
+    };
+
+    let owner = db::get_user_by_uid(dbh, request.uid)
         .await
         .unwrap()
         .unwrap();
+    let user = visitor.user.clone().unwrap();
+    let group = create_group(
+        dbh,
+        myconfig,
+        &user,
+        &owner,
+        &request.name,
+        &request.location,
+        &request.description,
+    )
+    .await;
 
-    db::add_group(dbh, &group).await.unwrap();
-    notify::owner_group_was_created(dbh, myconfig, &owner, &group).await;
+    Template::render(
+        "message",
+        context! {title: "Group created", message: format!(r#"Group <b><a href="/group/{}">{}</a></b> created"#, group.gid, escape_html(&group.name)), config, visitor},
+    )
+}
+
+#[post("/reject-group-request", data = "<input>")]
+async fn reject_group_request_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: AdminUser,
+    input: CsrfForm<GroupRequestForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+
+    let reason = input.reason.trim();
+    if reason.is_empty() {
+        return Template::render(
+            "message",
+            context! {title: "Missing reason", message: "Please explain why the group is not approved.", config, visitor},
+        );
+    }
+
+    let Some(request) = db::delete_group_request(dbh, input.id).await.unwrap() else {
+        return no_such_group_request(input.id, config, visitor);
+    };
+
+    let requester = db::get_user_by_uid(dbh, request.uid)
+        .await
+        .unwrap()
+        .unwrap();
     let user = visitor.user.clone().unwrap();
     db::audit(
         dbh,
-        AuditType::GroupCreated,
+        AuditType::GroupRequestRejected,
+        json!({
+            "group": {
+                "name": request.name,
+            },
+            "requester": {
+                "id": requester.uid,
+                "name": requester.name,
+            },
+            "user": {
+                "id": user.uid,
+                "name": user.name,
+            },
+            "reason": reason,
+        }),
+    )
+    .await
+    .unwrap();
+    notify::requester_group_request_rejected(myconfig, &requester, &request, reason).await;
+
+    Template::render(
+        "message",
+        context! {title: "Group request rejected", message: format!("The request of <b>{}</b> to start <b>{}</b> was rejected.", escape_html(&requester.name), escape_html(&request.name)), config, visitor},
+    )
+}
+
+fn no_such_group_request(id: &str, config: PublicConfig, visitor: AdminUser) -> Template {
+    Template::render(
+        "message",
+        context! {title: "No such request", message: format!("There is no pending group request <b>{id}</b>.", id = escape_html(id)), config, visitor},
+    )
+}
+
+#[post("/delete-group", data = "<input>")]
+async fn delete_group_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: AdminUser,
+    input: CsrfForm<GroupIdForm>,
+) -> Template {
+    let config = get_public_config();
+    let gid = input.gid;
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        );
+    };
+
+    let events = db::get_events_by_group_id(dbh, gid).await;
+    let members = db::get_members_of_group(dbh, gid).await.unwrap();
+
+    // The members need to be notified while we still know who they are
+    notify::members_group_deleted(dbh, myconfig, &group).await;
+    db::delete_group(dbh, gid).await.unwrap();
+
+    let user = visitor.user.clone().unwrap();
+    db::audit(
+        dbh,
+        AuditType::GroupDeleted,
         json!({
             "group": {
                 "id": gid,
                 "name": group.name,
             },
             "owner": {
-                "id": owner.uid,
-                "name": owner.name,
+                "id": group.owner,
             },
             "user": {
                 "id": user.uid,
                 "name": user.name,
             },
+            "events": events.len(),
+            "members": members.len(),
         }),
     )
     .await
     .unwrap();
+
     Template::render(
         "message",
-        context! {title: "Group created", message: format!(r#"Group <b><a href="/group/{}">{}</a></b> created"#, gid, group.name), config, visitor},
+        context! {title: "Group deleted", message: format!("The group <b>{}</b> was deleted together with its {} events.", escape_html(&group.name), events.len()), config, visitor},
     )
 }
 
@@ -220,3 +442,72 @@ async fn audit_get(dbh: &State<Surreal<Client>>, visitor: AdminUser) -> Template
         context! {title: "Audit", audit, user: user, config, visitor},
     )
 }
+
+#[get("/topics")]
+async fn topics_get(dbh: &State<Surreal<Client>>, visitor: AdminUser) -> Template {
+    let config = get_public_config();
+
+    let topics = db::get_topics(dbh).await.unwrap();
+
+    Template::render(
+        "admin_topics",
+        context! {title: "Topics", topics, config, visitor},
+    )
+}
+
+#[post("/add-topic", data = "<input>")]
+async fn add_topic_post(
+    dbh: &State<Surreal<Client>>,
+    visitor: AdminUser,
+    input: CsrfForm<TopicForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+
+    let name = input.name.trim();
+    if name.is_empty() || slugify(name) != name {
+        return Template::render(
+            "message",
+            context! {title: "Invalid topic", message: format!("The name of the topic <b>{name}</b> can only contain lower case letters, digits and single dashes. e.g. <b>{}</b>", slugify(name), name = escape_html(name)), config, visitor},
+        );
+    }
+
+    let topic = Topic {
+        id: Thing::from(("topic", Id::ulid())),
+        name: name.to_owned(),
+        description: input.description.trim().to_owned(),
+        created: Utc::now(),
+    };
+    if db::add_topic(dbh, &topic).await.is_err() {
+        return Template::render(
+            "message",
+            context! {title: "Topic exists", message: format!("The topic <b>{name}</b> already exists.", name = escape_html(name)), config, visitor},
+        );
+    }
+
+    Template::render(
+        "message",
+        context! {title: "Topic added", message: format!(r#"The topic <a href="/topic/{name}">{name}</a> was added. Check the <a href="/admin/topics">topics</a>."#, name = escape_html(name)), config, visitor},
+    )
+}
+
+#[post("/delete-topic", data = "<input>")]
+async fn delete_topic_post(
+    dbh: &State<Surreal<Client>>,
+    visitor: AdminUser,
+    input: CsrfForm<TopicNameForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+    let name = input.name;
+
+    if db::delete_topic(dbh, name).await.unwrap().is_none() {
+        return Template::render(
+            "message",
+            context! {title: "No such topic", message: format!("There is no topic <b>{name}</b>.", name = escape_html(name)), config, visitor},
+        );
+    }
+
+    Template::render(
+        "message",
+        context! {title: "Topic deleted", message: format!(r#"The topic <b>{name}</b> was deleted and removed from all the groups. Check the <a href="/admin/topics">topics</a>."#, name = escape_html(name)), config, visitor},
+    )
+}
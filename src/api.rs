@@ -0,0 +1,338 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{Catcher, Request, Route, State};
+
+use surrealdb::engine::remote::ws::Client;
+use surrealdb::sql::{Id, Thing};
+use surrealdb::Surreal;
+
+use crate::db;
+use crate::markdown2html;
+use crate::notify;
+use crate::web::ApiUser;
+use crate::{change_group_slug, check_event, check_group_name, SlugProblem};
+
+use meetings::timezone;
+use meetings::{event_slug, ApiScope, Event, EventStatus, Group, MyConfig};
+
+type ApiResponse = (Status, Json<Value>);
+
+#[derive(Deserialize)]
+struct NewEvent {
+    gid: usize,
+    title: String,
+    date: DateTime<Utc>,
+    location: String,
+    description: String,
+    #[serde(default)]
+    capacity: Option<usize>,
+    /// The IANA time zone the event is shown in. Defaults to the time zone of the group.
+    #[serde(default)]
+    timezone: Option<String>,
+    #[serde(default)]
+    end: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct GroupUpdate {
+    name: String,
+    location: String,
+    description: String,
+    /// The new address of the group. Missing or empty to keep the current one.
+    #[serde(default)]
+    slug: String,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    subject: String,
+    content: String,
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![me, group_events, add_event, update_group, contact_members]
+}
+
+pub fn catchers() -> Vec<Catcher> {
+    catchers![api_400, api_401, api_403, api_404, api_422]
+}
+
+fn error(status: Status, message: &str) -> ApiResponse {
+    (status, Json(json!({"error": message})))
+}
+
+fn missing_scope(scope: ApiScope) -> ApiResponse {
+    error(
+        Status::Forbidden,
+        &format!("The token does not have the '{}' scope", scope.name()),
+    )
+}
+
+/// The group if it exists, it is not archived and the user of the token is one of its organizers.
+async fn organized_group(
+    dbh: &Surreal<Client>,
+    api: &ApiUser,
+    gid: usize,
+) -> Result<Group, ApiResponse> {
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Err(error(Status::NotFound, &format!("No group with id {gid}")));
+    };
+
+    if !db::get_group_role(dbh, &group, api.user.uid)
+        .await
+        .unwrap()
+        .can_organize()
+    {
+        return Err(error(
+            Status::Forbidden,
+            &format!("You are not an organizer of the group {gid}"),
+        ));
+    }
+
+    if group.archived.is_some() {
+        return Err(error(
+            Status::Conflict,
+            &format!("The group {gid} is archived"),
+        ));
+    }
+
+    Ok(group)
+}
+
+#[get("/me")]
+fn me(api: ApiUser) -> ApiResponse {
+    if !api.allows(ApiScope::Read) {
+        return missing_scope(ApiScope::Read);
+    }
+
+    (
+        Status::Ok,
+        Json(json!({
+            "uid": api.user.uid,
+            "name": api.user.name,
+            "email": api.user.email,
+            "github": api.user.github,
+            "gitlab": api.user.gitlab,
+            "linkedin": api.user.linkedin,
+            "admin": api.admin,
+            "scopes": api.scopes,
+        })),
+    )
+}
+
+#[get("/groups/<gid>/events")]
+async fn group_events(dbh: &State<Surreal<Client>>, api: ApiUser, gid: usize) -> ApiResponse {
+    if !api.allows(ApiScope::Read) {
+        return missing_scope(ApiScope::Read);
+    }
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return error(Status::NotFound, &format!("No group with id {gid}"));
+    };
+
+    let can_organize = db::get_group_role(dbh, &group, api.user.uid)
+        .await
+        .unwrap()
+        .can_organize();
+    let events = db::get_events_by_group_id(dbh, gid)
+        .await
+        .into_iter()
+        .filter(|event| can_organize || event.is_public())
+        .collect::<Vec<_>>();
+    (
+        Status::Ok,
+        Json(json!({
+            "events": events.iter().map(|event| json!({
+                "eid": event.eid,
+                "title": event.title,
+                "date": event.date,
+                "location": event.location,
+                "description": event.description,
+                "status": event.status,
+            })).collect::<Vec<_>>(),
+        })),
+    )
+}
+
+#[post("/events", data = "<input>")]
+async fn add_event(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    api: ApiUser,
+    input: Json<NewEvent>,
+) -> ApiResponse {
+    if !api.allows(ApiScope::EventsWrite) {
+        return missing_scope(ApiScope::EventsWrite);
+    }
+
+    let group = match organized_group(dbh, &api, input.gid).await {
+        Ok(group) => group,
+        Err(response) => return response,
+    };
+
+    let title = input.title.trim().to_owned();
+    if let Err((_, message)) = check_event(&title, input.date, input.end) {
+        return error(Status::UnprocessableEntity, &message);
+    }
+
+    let zone = input
+        .timezone
+        .clone()
+        .unwrap_or_else(|| group.timezone.clone());
+    if timezone::parse(&zone).is_none() {
+        return error(
+            Status::UnprocessableEntity,
+            &format!("Unknown time zone '{zone}'"),
+        );
+    }
+
+    let eid = db::increment(dbh, "event").await.unwrap();
+    let slug = db::unique_event_slug(dbh, group.gid, &event_slug(&title, &input.date), eid)
+        .await
+        .unwrap();
+    let event = Event {
+        id: Thing::from(("event", Id::ulid())),
+        eid,
+        title,
+        description: input.description.clone(),
+        date: input.date,
+        location: input.location.trim().to_owned(),
+        group_id: group.gid,
+        status: EventStatus::Published,
+        slug,
+        old_slugs: vec![],
+        capacity: input.capacity,
+        cancel_reason: None,
+        occurrence: None,
+        sequence: 0,
+        timezone: zone,
+        end: input.end,
+    };
+    db::add_event(dbh, &event).await.unwrap();
+    notify::members_event_published(dbh, myconfig, &group, &event).await;
+
+    (
+        Status::Created,
+        Json(json!({
+            "eid": eid,
+            "url": format!("{}/event/{eid}", myconfig.base_url),
+            "slug_url": format!("{}/g/{}/e/{}", myconfig.base_url, group.slug, event.slug),
+        })),
+    )
+}
+
+#[post("/groups/<gid>", data = "<input>")]
+async fn update_group(
+    dbh: &State<Surreal<Client>>,
+    api: ApiUser,
+    gid: usize,
+    input: Json<GroupUpdate>,
+) -> ApiResponse {
+    if !api.allows(ApiScope::GroupsWrite) {
+        return missing_scope(ApiScope::GroupsWrite);
+    }
+
+    let group = match organized_group(dbh, &api, gid).await {
+        Ok(group) => group,
+        Err(response) => return response,
+    };
+
+    let name = input.name.trim();
+    if let Err(message) = check_group_name(name) {
+        return error(Status::UnprocessableEntity, &message);
+    }
+
+    let slug = input.slug.trim();
+    let role = db::get_group_role(dbh, &group, api.user.uid).await.unwrap();
+    match change_group_slug(dbh, &group, role, slug).await {
+        Ok(()) => {}
+        Err(SlugProblem::NotOwner) => {
+            return error(
+                Status::Forbidden,
+                "Only the owner of the group can change its address",
+            );
+        }
+        Err(SlugProblem::Invalid) => {
+            return error(
+                Status::UnprocessableEntity,
+                &format!("The address '{slug}' can only contain lower case letters, digits and single dashes"),
+            );
+        }
+        Err(SlugProblem::Taken) => {
+            return error(
+                Status::Conflict,
+                &format!("The address '{slug}' is already in use"),
+            );
+        }
+    }
+
+    db::update_group(dbh, gid, name, input.location.trim(), &input.description)
+        .await
+        .unwrap();
+
+    let current_slug = if slug.is_empty() { &group.slug } else { slug };
+    (Status::Ok, Json(json!({"gid": gid, "slug": current_slug})))
+}
+
+#[post("/groups/<gid>/messages", data = "<input>")]
+async fn contact_members(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    api: ApiUser,
+    gid: usize,
+    input: Json<Message>,
+) -> ApiResponse {
+    if !api.allows(ApiScope::MessagesSend) {
+        return missing_scope(ApiScope::MessagesSend);
+    }
+
+    if let Err(response) = organized_group(dbh, &api, gid).await {
+        return response;
+    }
+
+    let min_subject_length = 5;
+    let subject = input.subject.trim();
+    if subject.len() < min_subject_length {
+        return error(
+            Status::UnprocessableEntity,
+            &format!("Minimal subject length {min_subject_length}"),
+        );
+    }
+
+    let Ok(html) = markdown2html(&input.content) else {
+        return error(Status::UnprocessableEntity, "Invalid content");
+    };
+
+    notify::group_members(dbh, myconfig, subject, &html, gid).await;
+
+    (Status::Ok, Json(json!({"sent": true})))
+}
+
+#[catch(400)]
+fn api_400(_request: &Request<'_>) -> Json<Value> {
+    Json(json!({"error": "Bad request"}))
+}
+
+#[catch(401)]
+fn api_401(_request: &Request<'_>) -> Json<Value> {
+    Json(json!({"error": "Missing or invalid API token"}))
+}
+
+#[catch(403)]
+fn api_403(_request: &Request<'_>) -> Json<Value> {
+    Json(json!({"error": "Forbidden"}))
+}
+
+#[catch(404)]
+fn api_404(_request: &Request<'_>) -> Json<Value> {
+    Json(json!({"error": "Not found"}))
+}
+
+#[catch(422)]
+fn api_422(_request: &Request<'_>) -> Json<Value> {
+    Json(json!({"error": "Invalid request body"}))
+}
@@ -0,0 +1,145 @@
+//! Calendar feeds in the iCalendar format, see `meetings::ical`.
+
+use chrono::Utc;
+use rocket::http::ContentType;
+use rocket::serde::uuid::Uuid;
+use rocket::{Route, State};
+use rocket_dyn_templates::{context, Template};
+use surrealdb::engine::remote::ws::Client;
+use surrealdb::sql::{Id, Thing};
+use surrealdb::Surreal;
+
+use meetings::{db, get_public_config, hash_token, ical, CalendarToken, MyConfig};
+
+use crate::web::{CsrfForm, LoggedIn};
+use crate::CsrfOnlyForm;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        event_ics,
+        group_events_ics,
+        personal_events_ics,
+        calendar_token_create,
+        calendar_token_revoke
+    ]
+}
+
+/// The `.ics` file of a single event for the "Add to calendar" link.
+#[get("/event/<file>", rank = 2)]
+async fn event_ics(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    file: &str,
+) -> Option<(ContentType, String)> {
+    let eid = file.strip_suffix(".ics")?.parse::<usize>().ok()?;
+    let event = db::get_event_by_eid(dbh, eid).await.unwrap()?;
+    if !event.is_public() {
+        return None;
+    }
+
+    let name = event.title.clone();
+    Some((
+        ContentType::Calendar,
+        ical::calendar(&name, &myconfig.base_url, &[event], Utc::now()),
+    ))
+}
+
+/// A feed of the upcoming events of the group that calendar applications can subscribe to.
+#[get("/group/<gid>/events.ics")]
+async fn group_events_ics(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    gid: usize,
+) -> Option<(ContentType, String)> {
+    let group = db::get_group_by_gid(dbh, gid).await.unwrap()?;
+
+    let now = Utc::now();
+    let mut events = db::get_events_by_group_id(dbh, gid)
+        .await
+        .into_iter()
+        .filter(|event| now < event.date)
+        .collect::<Vec<_>>();
+    events.sort_by_key(|event| event.date);
+
+    Some((
+        ContentType::Calendar,
+        ical::calendar(&group.name, &myconfig.base_url, &events, now),
+    ))
+}
+
+/// The private feed of the events the user said yes to. The secret token replaces the login.
+#[get("/calendar/<token>/events.ics")]
+async fn personal_events_ics(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    token: &str,
+) -> Option<(ContentType, String)> {
+    let calendar_token = db::get_calendar_token_by_hash(dbh, &hash_token(token))
+        .await
+        .unwrap()?;
+
+    let mut events = vec![];
+    for rsvp in db::get_rsvps_by_uid(dbh, calendar_token.uid).await.unwrap() {
+        if !rsvp.status {
+            continue;
+        }
+        if let Some(event) = db::get_event_by_eid(dbh, rsvp.eid).await.unwrap() {
+            events.push(event);
+        }
+    }
+    events.sort_by_key(|event| event.date);
+
+    Some((
+        ContentType::Calendar,
+        ical::calendar("Meet-OS", &myconfig.base_url, &events, Utc::now()),
+    ))
+}
+
+/// Create the secret URL of the personal calendar feed. Creating a new one disables the old one.
+#[post("/profile/calendar-token", data = "<_input>")]
+async fn calendar_token_create(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    _input: CsrfForm<CsrfOnlyForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+    let uid = visitor.user.clone().unwrap().uid;
+
+    let token = Uuid::new_v4().simple().to_string();
+    let calendar_token = CalendarToken {
+        id: Thing::from(("calendar_token", Id::ulid())),
+        uid,
+        hash: hash_token(&token),
+        created: Utc::now(),
+    };
+    db::set_calendar_token(dbh, &calendar_token).await.unwrap();
+
+    let url = format!("{}/calendar/{token}/events.ics", myconfig.base_url);
+    Template::render(
+        "message",
+        context! {title: "Calendar feed created", message: format!(r#"Subscribe to <code id="calendar-url">{url}</code> in your calendar application. Copy it now, we won't show it again. Anyone with this link can see the events you are going to. Check your <a href="/profile">profile</a>."#), config, visitor},
+    )
+}
+
+#[post("/profile/calendar-token/revoke", data = "<_input>")]
+async fn calendar_token_revoke(
+    dbh: &State<Surreal<Client>>,
+    visitor: LoggedIn,
+    _input: CsrfForm<CsrfOnlyForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+    let uid = visitor.user.clone().unwrap().uid;
+
+    if db::delete_calendar_token(dbh, uid).await.unwrap().is_none() {
+        return Template::render(
+            "message",
+            context! {title: "No calendar feed", message: r#"You don't have a calendar feed. Check your <a href="/profile">profile</a>."#, config, visitor},
+        );
+    }
+
+    Template::render(
+        "message",
+        context! {title: "Calendar feed disabled", message: r#"The link of your calendar feed does not work any more. Check your <a href="/profile">profile</a>."#, config, visitor},
+    )
+}
@@ -10,8 +10,14 @@ use surrealdb::opt::Resource;
 use surrealdb::sql::{Id, Thing};
 use surrealdb::Surreal;
 
+use crate::oauth::Kind;
 use crate::EventStatus;
-use crate::{Audit, AuditType, Counter, Event, Group, Membership, MyConfig, User, RSVP};
+use crate::{
+    event_slug, slugify, ApiToken, Audit, AuditType, Ban, CalendarToken, Counter, Event,
+    EventSeries, Group, GroupRequest, GroupRole, Identity, Invitation, JoinRequest, LoginFailure,
+    MagicLinkRequest, Membership, MyConfig, Session, Token, TokenPurpose, Topic, Totp, User,
+    WaitlistEntry, RSVP,
+};
 
 /// # Panics
 ///
@@ -57,29 +63,69 @@ pub async fn get_database(
 
 /// # Panics
 ///
-/// Panics when there is an error
+/// Panics when there is an error.
+#[expect(clippy::cognitive_complexity)]
 pub async fn upgrade(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
     let version = get_schema_version(dbh).await.unwrap();
     rocket::info!("Upgrade from {version}");
 
-    if version == 0 {
+    if version < 1 {
         upgrade_to_1(dbh).await?;
-        upgrade_to_2(dbh).await?;
-        upgrade_to_3(dbh).await?;
     }
-    if version == 1 {
+    if version < 2 {
         upgrade_to_2(dbh).await?;
     }
-    if version == 2 {
+    if version < 3 {
         upgrade_to_3(dbh).await?;
     }
+    if version < 4 {
+        upgrade_to_4(dbh).await?;
+    }
+    if version < 5 {
+        upgrade_to_5(dbh).await?;
+    }
+    if version < 6 {
+        upgrade_to_6(dbh).await?;
+    }
+    if version < 7 {
+        upgrade_to_7(dbh).await?;
+    }
+    if version < 8 {
+        upgrade_to_8(dbh).await?;
+    }
+    if version < 9 {
+        upgrade_to_9(dbh).await?;
+    }
+    if version < 10 {
+        upgrade_to_10(dbh).await?;
+    }
+    if version < 11 {
+        upgrade_to_11(dbh).await?;
+    }
+    if version < 12 {
+        upgrade_to_12(dbh).await?;
+    }
+    if version < 13 {
+        upgrade_to_13(dbh).await?;
+    }
+    if version < 14 {
+        upgrade_to_14(dbh).await?;
+    }
+
+    if version < 15 {
+        upgrade_to_15(dbh).await?;
+    }
+
+    if version < 16 {
+        upgrade_to_16(dbh).await?;
+    }
 
     Ok(())
 }
 
 /// # Panics
 ///
-/// Panics when there is an error
+/// Panics when there is an error.
 pub async fn upgrade_to_1(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
     rocket::info!("upgrade_to_1");
 
@@ -109,7 +155,7 @@ pub async fn upgrade_to_1(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
 
 /// # Panics
 ///
-/// Panics when there is an error
+/// Panics when there is an error.
 pub async fn upgrade_to_2(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
     rocket::info!("upgrade_to_2");
 
@@ -123,7 +169,7 @@ pub async fn upgrade_to_2(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
 
 /// # Panics
 ///
-/// Panics when there is an error
+/// Panics when there is an error.
 pub async fn upgrade_to_3(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
     rocket::info!("upgrade_to_3");
 
@@ -133,6 +179,211 @@ pub async fn upgrade_to_3(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
     Ok(())
 }
 
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn upgrade_to_4(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
+    rocket::info!("upgrade_to_4");
+
+    dbh.query("DEFINE INDEX session_sid ON TABLE session COLUMNS sid UNIQUE")
+        .await?;
+
+    update_schema_version(dbh, 4).await?;
+    Ok(())
+}
+
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn upgrade_to_5(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
+    rocket::info!("upgrade_to_5");
+
+    dbh.query("DEFINE INDEX totp_uid ON TABLE totp COLUMNS uid UNIQUE")
+        .await?;
+
+    update_schema_version(dbh, 5).await?;
+    Ok(())
+}
+
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn upgrade_to_6(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
+    rocket::info!("upgrade_to_6");
+
+    dbh.query("DEFINE INDEX token_hash ON TABLE token COLUMNS hash UNIQUE")
+        .await?;
+
+    // The codes moved to the token table. The old ones are dropped, people can ask for a new one.
+    dbh.query("UPDATE user UNSET code, process, code_generated_date")
+        .await?;
+
+    update_schema_version(dbh, 6).await?;
+    Ok(())
+}
+
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn upgrade_to_7(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
+    rocket::info!("upgrade_to_7");
+
+    dbh.query("DEFINE INDEX identity_subject ON TABLE identity COLUMNS provider, subject UNIQUE")
+        .await?;
+
+    update_schema_version(dbh, 7).await?;
+    Ok(())
+}
+
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn upgrade_to_8(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
+    rocket::info!("upgrade_to_8");
+
+    dbh.query("DEFINE INDEX api_token_hash ON TABLE api_token COLUMNS hash UNIQUE")
+        .await?;
+
+    update_schema_version(dbh, 8).await?;
+    Ok(())
+}
+
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn upgrade_to_9(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
+    rocket::info!("upgrade_to_9");
+
+    dbh.query("DEFINE INDEX join_request_index ON TABLE join_request COLUMNS uid, gid UNIQUE")
+        .await?;
+
+    update_schema_version(dbh, 9).await?;
+    Ok(())
+}
+
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn upgrade_to_10(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
+    rocket::info!("upgrade_to_10");
+
+    dbh.query("DEFINE INDEX invitation_hash ON TABLE invitation COLUMNS hash UNIQUE")
+        .await?;
+
+    update_schema_version(dbh, 10).await?;
+    Ok(())
+}
+
+/// Give a slug to every existing group and event.
+///
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn upgrade_to_11(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
+    rocket::info!("upgrade_to_11");
+
+    let groups: Vec<Group> = dbh
+        .query("SELECT * FROM group ORDER BY gid;")
+        .await?
+        .take(0)?;
+    for group in groups {
+        let slug = unique_group_slug(dbh, &group.name, group.gid).await?;
+        set_group_slug(dbh, group.gid, &slug, &[]).await?;
+    }
+
+    let events: Vec<Event> = dbh
+        .query("SELECT * FROM event ORDER BY eid;")
+        .await?
+        .take(0)?;
+    for event in events {
+        let slug = unique_event_slug(
+            dbh,
+            event.group_id,
+            &event_slug(&event.title, &event.date),
+            event.eid,
+        )
+        .await?;
+        set_event_slug(dbh, event.eid, &slug, &[]).await?;
+    }
+
+    dbh.query("DEFINE INDEX group_slug ON TABLE group COLUMNS slug UNIQUE")
+        .await?;
+    dbh.query("DEFINE INDEX event_slug ON TABLE event COLUMNS group_id, slug UNIQUE")
+        .await?;
+
+    update_schema_version(dbh, 11).await?;
+    Ok(())
+}
+
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn upgrade_to_12(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
+    rocket::info!("upgrade_to_12");
+
+    dbh.query("DEFINE INDEX topic_name ON TABLE topic COLUMNS name UNIQUE")
+        .await?;
+    dbh.query("UPDATE group SET topics=[] WHERE topics IS NONE")
+        .await?;
+
+    update_schema_version(dbh, 12).await?;
+    Ok(())
+}
+
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn upgrade_to_13(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
+    rocket::info!("upgrade_to_13");
+
+    dbh.query("DEFINE INDEX ban_gid_uid ON TABLE ban COLUMNS gid, uid UNIQUE")
+        .await?;
+
+    update_schema_version(dbh, 13).await?;
+    Ok(())
+}
+
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn upgrade_to_14(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
+    rocket::info!("upgrade_to_14");
+
+    dbh.query("DEFINE INDEX waitlist_eid_uid ON TABLE waitlist COLUMNS eid, uid UNIQUE")
+        .await?;
+
+    update_schema_version(dbh, 14).await?;
+    Ok(())
+}
+
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn upgrade_to_15(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
+    rocket::info!("upgrade_to_15");
+
+    dbh.query("DEFINE INDEX series_sid ON TABLE series COLUMNS sid UNIQUE")
+        .await?;
+
+    update_schema_version(dbh, 15).await?;
+    Ok(())
+}
+
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn upgrade_to_16(dbh: &Surreal<Client>) -> surrealdb::Result<()> {
+    rocket::info!("upgrade_to_16");
+
+    dbh.query("DEFINE INDEX calendar_token_uid ON TABLE calendar_token COLUMNS uid UNIQUE")
+        .await?;
+    dbh.query("DEFINE INDEX calendar_token_hash ON TABLE calendar_token COLUMNS hash UNIQUE")
+        .await?;
+
+    update_schema_version(dbh, 16).await?;
+    Ok(())
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct Schema {
     version: u64,
@@ -201,13 +452,22 @@ pub async fn update_event(dbh: &Surreal<Client>, event: &Event) -> surrealdb::Re
                     title=$title,
                     date=$date,
                     location=$location,
-                    description=$description
+                    description=$description,
+                    capacity=$capacity,
+                    occurrence=$occurrence,
+                    timezone=$timezone,
+                    end=$end,
+                    sequence+=1
                 WHERE eid=$eid;",
         )
         .bind(("title", event.title.clone()))
         .bind(("location", event.location.clone()))
         .bind(("date", event.date))
         .bind(("description", event.description.clone()))
+        .bind(("capacity", event.capacity))
+        .bind(("occurrence", event.occurrence.clone()))
+        .bind(("timezone", event.timezone.clone()))
+        .bind(("end", event.end))
         .bind(("eid", event.eid))
         .await?;
 
@@ -242,7 +502,6 @@ pub async fn set_user_verified(
             UPDATE user
                 SET
                     verified=$verified,
-                    code='',
                     verification_date=$date
                 WHERE uid=$uid;",
         )
@@ -254,12 +513,7 @@ pub async fn set_user_verified(
     let entry: Option<User> = response.take(0)?;
 
     if let Some(entry) = entry.as_ref() {
-        rocket::info!(
-            "verification ok '{}', '{}', '{}'",
-            entry.name,
-            entry.email,
-            entry.process
-        );
+        rocket::info!("verification ok '{}', '{}'", entry.name, entry.email);
     }
 
     Ok(entry)
@@ -294,25 +548,140 @@ pub async fn update_group(
     Ok(entry)
 }
 
-pub async fn remove_code(dbh: &Surreal<Client>, uid: usize) -> surrealdb::Result<Option<User>> {
-    rocket::info!("remove code '{uid}'");
+pub async fn set_group_timezone(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    timezone: &str,
+) -> surrealdb::Result<Option<Group>> {
+    rocket::info!("set group timezone: '{gid}' '{timezone}'");
 
     let mut response = dbh
-        .query(
-            "
-            UPDATE user
-            SET
-                code='',
-                code_generated_date=None
-            WHERE uid=$uid;",
-        )
-        .bind(("uid", uid))
+        .query("UPDATE group SET timezone=$timezone WHERE gid=$gid;")
+        .bind(("timezone", timezone.to_owned()))
+        .bind(("gid", gid))
         .await?;
 
-    let entry: Option<User> = response.take(0)?;
+    let entry: Option<Group> = response.take(0)?;
+    Ok(entry)
+}
+
+pub async fn update_group_access(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    requires_approval: bool,
+    questions: Vec<String>,
+) -> surrealdb::Result<Option<Group>> {
+    rocket::info!("update group access: '{gid}' requires_approval: {requires_approval}");
+
+    let mut response = dbh
+        .query("UPDATE group SET requires_approval=$requires_approval, questions=$questions WHERE gid=$gid;")
+        .bind(("requires_approval", requires_approval))
+        .bind(("questions", questions))
+        .bind(("gid", gid))
+        .await?;
+
+    let entry: Option<Group> = response.take(0)?;
+    Ok(entry)
+}
+
+/// Replace the topics of the group. The names must be on the list of topics.
+pub async fn set_group_topics(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    topics: Vec<String>,
+) -> surrealdb::Result<Option<Group>> {
+    rocket::info!("set group topics: '{gid}' {topics:?}");
+
+    let mut response = dbh
+        .query("UPDATE group SET topics=$topics WHERE gid=$gid;")
+        .bind(("topics", topics))
+        .bind(("gid", gid))
+        .await?;
+
+    let entry: Option<Group> = response.take(0)?;
+    Ok(entry)
+}
+
+/// Archive the group at the given time or reopen it if the time is None.
+pub async fn set_group_archived(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    archived: Option<DateTime<Utc>>,
+) -> surrealdb::Result<Option<Group>> {
+    rocket::info!("set group archived: '{gid}' {archived:?}");
+
+    let mut response = dbh
+        .query("UPDATE group SET archived=$archived WHERE gid=$gid;")
+        .bind(("archived", archived))
+        .bind(("gid", gid))
+        .await?;
+
+    let entry: Option<Group> = response.take(0)?;
     Ok(entry)
 }
 
+/// Remove the group together with its events and every row that refers to them.
+pub async fn delete_group(dbh: &Surreal<Client>, gid: usize) -> surrealdb::Result<()> {
+    rocket::info!("delete group: '{gid}'");
+
+    dbh.query(
+        "
+        BEGIN TRANSACTION;
+        LET $eids = (SELECT VALUE eid FROM event WHERE group_id=$gid);
+        DELETE rsvp WHERE eid INSIDE $eids;
+        DELETE waitlist WHERE eid INSIDE $eids;
+        DELETE event WHERE group_id=$gid;
+        DELETE series WHERE gid=$gid;
+        DELETE membership WHERE gid=$gid;
+        DELETE join_request WHERE gid=$gid;
+        DELETE ban WHERE gid=$gid;
+        DELETE invitation WHERE gid=$gid;
+        DELETE token WHERE gid=$gid;
+        DELETE group WHERE gid=$gid;
+        COMMIT TRANSACTION;",
+    )
+    .bind(("gid", gid))
+    .await?
+    .check()?;
+
+    Ok(())
+}
+
+/// Make the member the owner of the group. The previous owner stays as a regular member.
+pub async fn transfer_group(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    old_owner: usize,
+    new_owner: usize,
+) -> surrealdb::Result<()> {
+    rocket::info!("transfer group: '{gid}' from '{old_owner}' to '{new_owner}'");
+
+    dbh.query(
+        "
+        BEGIN TRANSACTION;
+        UPDATE group SET owner=$new_owner WHERE gid=$gid;
+        DELETE membership WHERE gid=$gid AND uid=$new_owner;
+        DELETE membership WHERE gid=$gid AND uid=$old_owner;
+        CREATE membership CONTENT {
+            uid: $old_owner,
+            gid: $gid,
+            join_date: $join_date,
+            admin: false
+        };
+        DELETE token WHERE purpose=$purpose AND gid=$gid;
+        COMMIT TRANSACTION;",
+    )
+    .bind(("purpose", TokenPurpose::GroupTransfer))
+    .bind(("gid", gid))
+    .bind(("old_owner", old_owner))
+    .bind(("new_owner", new_owner))
+    .bind(("join_date", Utc::now()))
+    .await?
+    .check()?;
+
+    Ok(())
+}
+
 pub async fn save_password(
     dbh: &Surreal<Client>,
     uid: usize,
@@ -336,6 +705,31 @@ pub async fn save_password(
     Ok(entry)
 }
 
+/// The query failed because the value is already in the given unique index.
+#[must_use]
+pub fn is_duplicate(err: &surrealdb::Error, index: &str) -> bool {
+    err.to_string()
+        .contains(&format!("Database index `{index}` already contains"))
+}
+
+/// Fails if the address is already used by someone else as `user_email` is a unique index.
+pub async fn update_user_email(
+    dbh: &Surreal<Client>,
+    uid: usize,
+    email: &str,
+) -> surrealdb::Result<Option<User>> {
+    rocket::info!("update email of user: '{uid}'");
+
+    let mut response = dbh
+        .query("UPDATE user SET email=$email WHERE uid=$uid;")
+        .bind(("email", email.to_owned()))
+        .bind(("uid", uid))
+        .await?;
+
+    let entry: Option<User> = response.take(0)?;
+    Ok(entry)
+}
+
 pub async fn update_user(
     dbh: &Surreal<Client>,
     uid: usize,
@@ -420,31 +814,6 @@ pub async fn get_user_by_email(
     Ok(entry)
 }
 
-pub async fn add_login_code_to_user(
-    dbh: &Surreal<Client>,
-    email: &str,
-    process: &str,
-    code: &str,
-) -> surrealdb::Result<Option<User>> {
-    rocket::info!("add_login_code_to_user: '{email}', '{process}', '{code}'");
-    let utc: DateTime<Utc> = Utc::now();
-    let mut response = dbh
-    .query("UPDATE user SET code=$code, process=$process, code_generated_date=$date WHERE email=$email;")
-        .bind(("email", email.to_owned()))
-        .bind(("process", process.to_owned()))
-        .bind(("code", code.to_owned()))
-        .bind(("date", utc))
-        .await?;
-
-    let entry: Option<User> = response.take(0)?;
-
-    if let Some(entry) = entry.as_ref() {
-        rocket::info!("entry: '{}' '{}'", entry.email, entry.process);
-    }
-
-    Ok(entry)
-}
-
 #[must_use]
 pub async fn get_events_by_group_id(dbh: &Surreal<Client>, gid: usize) -> Vec<Event> {
     match get_events(dbh).await {
@@ -468,7 +837,9 @@ pub async fn get_users(dbh: &Surreal<Client>) -> surrealdb::Result<Vec<User>> {
 
 pub async fn get_groups(dbh: &Surreal<Client>) -> surrealdb::Result<Vec<Group>> {
     rocket::info!("get_groups");
-    let mut response = dbh.query("SELECT * FROM group ORDER BY name;").await?;
+    let mut response = dbh
+        .query("SELECT * FROM group WHERE archived IS NONE ORDER BY name;")
+        .await?;
     let entries: Vec<Group> = response.take(0)?;
     for ent in &entries {
         rocket::info!("group name {}", ent.name);
@@ -476,14 +847,31 @@ pub async fn get_groups(dbh: &Surreal<Client>) -> surrealdb::Result<Vec<Group>>
     Ok(entries)
 }
 
-/// # Panics
-///
-/// Panics when there is an error
-pub async fn get_groups_by_membership_id(
+/// The groups tagged with the topic that were not archived.
+pub async fn get_groups_by_topic(
     dbh: &Surreal<Client>,
-    uid: usize,
-) -> surrealdb::Result<Vec<(Group, Membership)>> {
-    rocket::info!("get_groups_by_membership_id: '{uid}'");
+    topic: &str,
+) -> surrealdb::Result<Vec<Group>> {
+    rocket::info!("get_groups_by_topic: '{topic}'");
+    let mut response = dbh
+        .query(
+            "SELECT * FROM group WHERE topics CONTAINS $topic AND archived IS NONE ORDER BY name;",
+        )
+        .bind(("topic", topic.to_owned()))
+        .await?;
+
+    let entries: Vec<Group> = response.take(0)?;
+    Ok(entries)
+}
+
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn get_groups_by_membership_id(
+    dbh: &Surreal<Client>,
+    uid: usize,
+) -> surrealdb::Result<Vec<(Group, Membership)>> {
+    rocket::info!("get_groups_by_membership_id: '{uid}'");
 
     // let mut response = dbh
     // .query("SELECT * FROM membership WHERE uid=$uid;")
@@ -540,7 +928,7 @@ pub async fn get_groups_by_membership_id(
 
 /// # Panics
 ///
-/// Panics when there is an error
+/// Panics when there is an error.
 pub async fn get_members_of_group(
     dbh: &Surreal<Client>,
     gid: usize,
@@ -606,6 +994,139 @@ pub async fn get_group_by_gid(
     Ok(entry)
 }
 
+/// Find a group by its current slug or by one it used before a rename.
+pub async fn get_group_by_slug(
+    dbh: &Surreal<Client>,
+    slug: &str,
+) -> surrealdb::Result<Option<Group>> {
+    rocket::info!("get_group_by_slug: '{slug}'");
+    let mut response = dbh
+        .query("SELECT * FROM group WHERE slug=$slug OR old_slugs CONTAINS $slug;")
+        .bind(("slug", slug.to_owned()))
+        .await?;
+
+    let entry: Option<Group> = response.take(0)?;
+
+    Ok(entry)
+}
+
+/// The slug derived from the name, with a number appended if another group already uses it.
+pub async fn unique_group_slug(
+    dbh: &Surreal<Client>,
+    name: &str,
+    gid: usize,
+) -> surrealdb::Result<String> {
+    let mut base = slugify(name);
+    if base.is_empty() {
+        base = String::from("group");
+    }
+
+    let mut slug = base.clone();
+    let mut counter: usize = 1;
+    while !is_group_slug_free(dbh, &slug, gid).await? {
+        counter = counter.saturating_add(1);
+        slug = format!("{base}-{counter}");
+    }
+
+    Ok(slug)
+}
+
+/// The slug is not used, neither now nor in the past, by any group other than `gid`.
+pub async fn is_group_slug_free(
+    dbh: &Surreal<Client>,
+    slug: &str,
+    gid: usize,
+) -> surrealdb::Result<bool> {
+    Ok(get_group_by_slug(dbh, slug)
+        .await?
+        .is_none_or(|group| group.gid == gid))
+}
+
+pub async fn set_group_slug(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    slug: &str,
+    old_slugs: &[String],
+) -> surrealdb::Result<()> {
+    rocket::info!("set_group_slug: '{gid}' '{slug}'");
+
+    dbh.query("UPDATE group SET slug=$slug, old_slugs=$old_slugs WHERE gid=$gid;")
+        .bind(("slug", slug.to_owned()))
+        .bind(("old_slugs", old_slugs.to_vec()))
+        .bind(("gid", gid))
+        .await?
+        .check()?;
+
+    Ok(())
+}
+
+/// Find an event of the group by its current slug or by one it used before a rename.
+pub async fn get_event_by_slug(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    slug: &str,
+) -> surrealdb::Result<Option<Event>> {
+    rocket::info!("get_event_by_slug: '{gid}' '{slug}'");
+    let mut response = dbh
+        .query(
+            "SELECT * FROM event WHERE group_id=$gid AND (slug=$slug OR old_slugs CONTAINS $slug);",
+        )
+        .bind(("gid", gid))
+        .bind(("slug", slug.to_owned()))
+        .await?;
+
+    let entry: Option<Event> = response.take(0)?;
+
+    Ok(entry)
+}
+
+/// The given slug, with a number appended if another event of the group already uses it.
+pub async fn unique_event_slug(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    base: &str,
+    eid: usize,
+) -> surrealdb::Result<String> {
+    let mut slug = base.to_owned();
+    let mut counter: usize = 1;
+    while !is_event_slug_free(dbh, gid, &slug, eid).await? {
+        counter = counter.saturating_add(1);
+        slug = format!("{base}-{counter}");
+    }
+
+    Ok(slug)
+}
+
+/// The slug is not used, neither now nor in the past, by any other event of the group.
+pub async fn is_event_slug_free(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    slug: &str,
+    eid: usize,
+) -> surrealdb::Result<bool> {
+    Ok(get_event_by_slug(dbh, gid, slug)
+        .await?
+        .is_none_or(|event| event.eid == eid))
+}
+
+pub async fn set_event_slug(
+    dbh: &Surreal<Client>,
+    eid: usize,
+    slug: &str,
+    old_slugs: &[String],
+) -> surrealdb::Result<()> {
+    rocket::info!("set_event_slug: '{eid}' '{slug}'");
+
+    dbh.query("UPDATE event SET slug=$slug, old_slugs=$old_slugs WHERE eid=$eid;")
+        .bind(("slug", slug.to_owned()))
+        .bind(("old_slugs", old_slugs.to_vec()))
+        .bind(("eid", eid))
+        .await?
+        .check()?;
+
+    Ok(())
+}
+
 pub async fn get_events(dbh: &Surreal<Client>) -> surrealdb::Result<Vec<Event>> {
     rocket::info!("get_events");
     let mut response = dbh.query("SELECT * FROM event;").await?;
@@ -616,9 +1137,84 @@ pub async fn get_events(dbh: &Surreal<Client>) -> surrealdb::Result<Vec<Event>>
     Ok(entries)
 }
 
+pub async fn add_series(dbh: &Surreal<Client>, series: &EventSeries) -> surrealdb::Result<()> {
+    rocket::info!("add series sid: '{}' rule: '{}'", series.sid, series.rule);
+
+    dbh.create(Resource::from("series"))
+        .content(series.clone())
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_series_by_sid(
+    dbh: &Surreal<Client>,
+    sid: usize,
+) -> surrealdb::Result<Option<EventSeries>> {
+    rocket::info!("get_series_by_sid: '{sid}'");
+    let mut response = dbh
+        .query("SELECT * FROM series WHERE sid=$sid;")
+        .bind(("sid", sid))
+        .await?;
+
+    let entry: Option<EventSeries> = response.take(0)?;
+    Ok(entry)
+}
+
+pub async fn get_all_series(dbh: &Surreal<Client>) -> surrealdb::Result<Vec<EventSeries>> {
+    rocket::info!("get_all_series");
+    let mut response = dbh.query("SELECT * FROM series;").await?;
+
+    let entries: Vec<EventSeries> = response.take(0)?;
+    Ok(entries)
+}
+
+/// Change the details used for the events created from now on. The existing events are not changed.
+pub async fn update_series(dbh: &Surreal<Client>, series: &EventSeries) -> surrealdb::Result<()> {
+    rocket::info!("update_series: '{}'", series.sid);
+    dbh.query(
+        "
+        UPDATE series
+            SET
+                title=$title,
+                description=$description,
+                location=$location,
+                capacity=$capacity,
+                start=$start,
+                duration=$duration
+            WHERE sid=$sid;",
+    )
+    .bind(("title", series.title.clone()))
+    .bind(("description", series.description.clone()))
+    .bind(("location", series.location.clone()))
+    .bind(("capacity", series.capacity))
+    .bind(("start", series.start))
+    .bind(("duration", series.duration))
+    .bind(("sid", series.sid))
+    .await?
+    .check()?;
+
+    Ok(())
+}
+
+/// The events created from the series, ordered by the date of their occurrence.
+pub async fn get_events_of_series(
+    dbh: &Surreal<Client>,
+    sid: usize,
+) -> surrealdb::Result<Vec<Event>> {
+    rocket::info!("get_events_of_series: '{sid}'");
+    let mut response = dbh
+        .query("SELECT * FROM event WHERE occurrence.sid=$sid ORDER BY occurrence.date;")
+        .bind(("sid", sid))
+        .await?;
+
+    let entries: Vec<Event> = response.take(0)?;
+    Ok(entries)
+}
+
 /// # Panics
 ///
-/// Panics when there is an error
+/// Panics when there is an error.
 pub async fn increment(dbh: &Surreal<Client>, name: &str) -> surrealdb::Result<usize> {
     // TODO: do this only when creatig the database
     let _response = dbh
@@ -686,7 +1282,7 @@ pub async fn join_group(dbh: &Surreal<Client>, gid: usize, uid: usize) -> surrea
 
 /// # Panics
 ///
-/// Panics when it fails
+/// Panics when it fails.
 pub async fn leave_group(dbh: &Surreal<Client>, gid: usize, uid: usize) -> surrealdb::Result<()> {
     rocket::info!("user {} leaves group: {}", uid, gid);
 
@@ -716,125 +1312,1286 @@ pub async fn get_membership(
     Ok(entry)
 }
 
+pub async fn add_join_request(
+    dbh: &Surreal<Client>,
+    request: &JoinRequest,
+) -> surrealdb::Result<()> {
+    rocket::info!("user {} asks to join group: {}", request.uid, request.gid);
+
+    dbh.create(Resource::from("join_request"))
+        .content(request.clone())
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_join_request(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    uid: usize,
+) -> surrealdb::Result<Option<JoinRequest>> {
+    let mut response = dbh
+        .query("SELECT * FROM join_request WHERE gid=$gid AND uid=$uid;")
+        .bind(("gid", gid))
+        .bind(("uid", uid))
+        .await?;
+
+    let entry: Option<JoinRequest> = response.take(0)?;
+
+    Ok(entry)
+}
+
 /// # Panics
 ///
 /// Panics when there is an error.
-pub async fn get_all_rsvps_for_event(
+pub async fn get_join_requests_of_group(
     dbh: &Surreal<Client>,
-    eid: usize,
-) -> surrealdb::Result<Vec<(RSVP, User)>> {
+    gid: usize,
+) -> surrealdb::Result<Vec<(User, JoinRequest)>> {
     let mut response = dbh
-        .query("SELECT * FROM rsvp WHERE eid=$eid ORDER BY uid;")
-        .bind(("eid", eid))
+        .query("SELECT * FROM join_request WHERE gid=$gid ORDER BY date;")
+        .bind(("gid", gid))
         .await?;
 
-    let entries: Vec<RSVP> = response.take(0)?;
+    let entries: Vec<JoinRequest> = response.take(0)?;
 
-    let mut people = vec![];
+    let mut requests = vec![];
     for entry in entries {
         // We assume that each uid will have a user
         let user = get_user_by_uid(dbh, entry.uid).await.unwrap().unwrap();
-        people.push((entry, user));
+        requests.push((user, entry));
     }
 
-    Ok(people)
+    Ok(requests)
 }
 
-pub async fn get_rsvp(
+pub async fn get_join_requests_by_uid(
     dbh: &Surreal<Client>,
-    eid: usize,
     uid: usize,
-) -> surrealdb::Result<Option<RSVP>> {
+) -> surrealdb::Result<Vec<JoinRequest>> {
     let mut response = dbh
-        .query("SELECT * FROM rsvp WHERE eid=$eid AND uid=$uid;")
-        .bind(("eid", eid))
+        .query("SELECT * FROM join_request WHERE uid=$uid ORDER BY date;")
         .bind(("uid", uid))
         .await?;
 
-    let entry: Option<RSVP> = response.take(0)?;
-
-    Ok(entry)
+    let entries: Vec<JoinRequest> = response.take(0)?;
+    Ok(entries)
 }
 
-pub async fn new_rsvp(
+/// Remove the pending request and return it. Returns None if there was no such request.
+pub async fn delete_join_request(
     dbh: &Surreal<Client>,
-    eid: usize,
+    gid: usize,
     uid: usize,
-    status: bool,
-) -> surrealdb::Result<()> {
-    rocket::info!("user {} RSVP: {} status: {}", uid, eid, status);
+) -> surrealdb::Result<Option<JoinRequest>> {
+    rocket::info!("delete join request of user {uid} to group: {gid}");
 
-    let date: DateTime<Utc> = Utc::now();
-
-    let rsvp = RSVP {
-        id: Thing::from(("rsvp", Id::ulid())),
-        eid,
-        uid,
-        date,
-        status,
-    };
+    let mut response = dbh
+        .query("DELETE join_request WHERE gid=$gid AND uid=$uid RETURN BEFORE;")
+        .bind(("gid", gid))
+        .bind(("uid", uid))
+        .await?;
 
-    dbh.create(Resource::from("rsvp")).content(rsvp).await?;
+    let entry: Option<JoinRequest> = response.take(0)?;
 
-    Ok(())
+    Ok(entry)
 }
 
-pub async fn update_rsvp(
+pub async fn add_group_request(
     dbh: &Surreal<Client>,
-    eid: usize,
-    uid: usize,
-    status: bool,
+    request: &GroupRequest,
 ) -> surrealdb::Result<()> {
-    rocket::info!("user {} RSVP: {} status: {}", uid, eid, status);
-
-    let date: DateTime<Utc> = Utc::now();
+    rocket::info!("user {} asks to start group: {}", request.uid, request.name);
 
-    dbh.query("UPDATE rsvp SET status=$status, date=$date WHERE uid=$uid AND eid=$eid")
-        .bind(("status", status))
-        .bind(("uid", uid))
-        .bind(("eid", eid))
-        .bind(("date", date))
+    dbh.create(Resource::from("group_request"))
+        .content(request.clone())
         .await?;
 
     Ok(())
 }
 
-pub async fn audit(dbh: &Surreal<Client>, atype: AuditType, json: Value) -> surrealdb::Result<()> {
-    let text = json.to_string();
-    rocket::info!("audit {text}");
-
-    let date: DateTime<Utc> = Utc::now();
-    let entry = Audit {
-        id: Thing::from(("audit", Id::ulid())),
-        date,
-        atype,
-        text,
-    };
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn get_group_requests(
+    dbh: &Surreal<Client>,
+) -> surrealdb::Result<Vec<(User, GroupRequest)>> {
+    let mut response = dbh
+        .query("SELECT * FROM group_request ORDER BY date;")
+        .await?;
 
-    dbh.create(Resource::from("audit")).content(entry).await?;
+    let entries: Vec<GroupRequest> = response.take(0)?;
 
-    Ok(())
-}
+    let mut requests = vec![];
+    for entry in entries {
+        // We assume that each uid will have a user
+        let user = get_user_by_uid(dbh, entry.uid).await.unwrap().unwrap();
+        requests.push((user, entry));
+    }
 
-pub async fn get_audit(dbh: &Surreal<Client>) -> surrealdb::Result<Vec<Audit>> {
-    rocket::info!("get_audits");
-    let mut response = dbh.query("SELECT * FROM audit ORDER BY date;").await?;
-    let entries: Vec<Audit> = response.take(0)?;
-    Ok(entries)
+    Ok(requests)
 }
 
-pub async fn get_user_by_id_str(
+/// Remove the pending request and return it. Returns None if there was no such request.
+pub async fn delete_group_request(
     dbh: &Surreal<Client>,
     id: &str,
-) -> surrealdb::Result<Option<User>> {
-    rocket::info!("get_user_by_id: '{id}'");
+) -> surrealdb::Result<Option<GroupRequest>> {
+    rocket::info!("delete group request: '{id}'");
 
     let mut response = dbh
-        .query("SELECT * FROM user WHERE id=$id;")
-        .bind(("id", Thing::from(("user", id))))
+        .query("DELETE group_request WHERE id=$id RETURN BEFORE;")
+        .bind(("id", Thing::from(("group_request", id))))
         .await?;
 
-    let entry: Option<User> = response.take(0)?;
+    let entry: Option<GroupRequest> = response.take(0)?;
+    Ok(entry)
+}
+
+pub async fn add_ban(dbh: &Surreal<Client>, ban: &Ban) -> surrealdb::Result<()> {
+    rocket::info!("user {} is banned from group: {}", ban.uid, ban.gid);
+
+    dbh.create(Resource::from("ban"))
+        .content(ban.clone())
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_ban(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    uid: usize,
+) -> surrealdb::Result<Option<Ban>> {
+    let mut response = dbh
+        .query("SELECT * FROM ban WHERE gid=$gid AND uid=$uid;")
+        .bind(("gid", gid))
+        .bind(("uid", uid))
+        .await?;
+
+    let entry: Option<Ban> = response.take(0)?;
+
+    Ok(entry)
+}
+
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn get_bans_of_group(
+    dbh: &Surreal<Client>,
+    gid: usize,
+) -> surrealdb::Result<Vec<(User, Ban)>> {
+    let mut response = dbh
+        .query("SELECT * FROM ban WHERE gid=$gid ORDER BY date;")
+        .bind(("gid", gid))
+        .await?;
+
+    let entries: Vec<Ban> = response.take(0)?;
+
+    let mut bans = vec![];
+    for entry in entries {
+        // We assume that each uid will have a user
+        let user = get_user_by_uid(dbh, entry.uid).await.unwrap().unwrap();
+        bans.push((user, entry));
+    }
+
+    Ok(bans)
+}
+
+/// Lift the ban and return it. Returns None if the user was not banned.
+pub async fn delete_ban(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    uid: usize,
+) -> surrealdb::Result<Option<Ban>> {
+    rocket::info!("delete ban of user {uid} in group: {gid}");
+
+    let mut response = dbh
+        .query("DELETE ban WHERE gid=$gid AND uid=$uid RETURN BEFORE;")
+        .bind(("gid", gid))
+        .bind(("uid", uid))
+        .await?;
+
+    let entry: Option<Ban> = response.take(0)?;
+
+    Ok(entry)
+}
+
+/// Change the RSVPs of the user to the future events of the group to "no" and return the changed ones.
+/// The user is also removed from the waitlists of these events.
+pub async fn cancel_future_rsvps(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    uid: usize,
+) -> surrealdb::Result<Vec<RSVP>> {
+    rocket::info!("cancel future RSVPs of user {uid} in group: {gid}");
+
+    let date: DateTime<Utc> = Utc::now();
+
+    let mut response = dbh
+        .query("UPDATE rsvp SET status=false, date=$date WHERE uid=$uid AND status=true AND eid IN (SELECT VALUE eid FROM event WHERE group_id=$gid AND date > $date);")
+        .bind(("uid", uid))
+        .bind(("gid", gid))
+        .bind(("date", date))
+        .await?;
+
+    let entries: Vec<RSVP> = response.take(0)?;
+
+    dbh.query("DELETE waitlist WHERE uid=$uid AND eid IN (SELECT VALUE eid FROM event WHERE group_id=$gid AND date > $date);")
+        .bind(("uid", uid))
+        .bind(("gid", gid))
+        .bind(("date", date))
+        .await?
+        .check()?;
+
+    Ok(entries)
+}
+
+pub async fn get_group_role(
+    dbh: &Surreal<Client>,
+    group: &Group,
+    uid: usize,
+) -> surrealdb::Result<GroupRole> {
+    let membership = get_membership(dbh, group.gid, uid).await?;
+    Ok(GroupRole::new(group, uid, membership.as_ref()))
+}
+
+pub async fn set_group_admin(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    uid: usize,
+    admin: bool,
+) -> surrealdb::Result<Option<Membership>> {
+    rocket::info!("set_group_admin gid: {gid} uid: {uid} admin: {admin}");
+
+    let mut response = dbh
+        .query("UPDATE membership SET admin=$admin WHERE gid=$gid AND uid=$uid;")
+        .bind(("admin", admin))
+        .bind(("gid", gid))
+        .bind(("uid", uid))
+        .await?;
+
+    let entry: Option<Membership> = response.take(0)?;
+
+    Ok(entry)
+}
+
+/// # Panics
+///
+/// Panics when there is an error.
+pub async fn get_all_rsvps_for_event(
+    dbh: &Surreal<Client>,
+    eid: usize,
+) -> surrealdb::Result<Vec<(RSVP, User)>> {
+    let mut response = dbh
+        .query("SELECT * FROM rsvp WHERE eid=$eid ORDER BY uid;")
+        .bind(("eid", eid))
+        .await?;
+
+    let entries: Vec<RSVP> = response.take(0)?;
+
+    let mut people = vec![];
+    for entry in entries {
+        // We assume that each uid will have a user
+        let user = get_user_by_uid(dbh, entry.uid).await.unwrap().unwrap();
+        people.push((entry, user));
+    }
+
+    Ok(people)
+}
+
+pub async fn get_rsvp(
+    dbh: &Surreal<Client>,
+    eid: usize,
+    uid: usize,
+) -> surrealdb::Result<Option<RSVP>> {
+    let mut response = dbh
+        .query("SELECT * FROM rsvp WHERE eid=$eid AND uid=$uid;")
+        .bind(("eid", eid))
+        .bind(("uid", uid))
+        .await?;
+
+    let entry: Option<RSVP> = response.take(0)?;
+
+    Ok(entry)
+}
+
+pub async fn new_rsvp(
+    dbh: &Surreal<Client>,
+    eid: usize,
+    uid: usize,
+    status: bool,
+) -> surrealdb::Result<()> {
+    rocket::info!("user {} RSVP: {} status: {}", uid, eid, status);
+
+    let date: DateTime<Utc> = Utc::now();
+
+    let rsvp = RSVP {
+        id: Thing::from(("rsvp", Id::ulid())),
+        eid,
+        uid,
+        date,
+        status,
+    };
+
+    dbh.create(Resource::from("rsvp")).content(rsvp).await?;
+
+    Ok(())
+}
+
+pub async fn update_rsvp(
+    dbh: &Surreal<Client>,
+    eid: usize,
+    uid: usize,
+    status: bool,
+) -> surrealdb::Result<()> {
+    rocket::info!("user {} RSVP: {} status: {}", uid, eid, status);
+
+    let date: DateTime<Utc> = Utc::now();
+
+    dbh.query("UPDATE rsvp SET status=$status, date=$date WHERE uid=$uid AND eid=$eid")
+        .bind(("status", status))
+        .bind(("uid", uid))
+        .bind(("eid", eid))
+        .bind(("date", date))
+        .await?;
+
+    Ok(())
+}
+
+/// Change the status of the event. The reason is only kept for cancelled events.
+pub async fn set_event_status(
+    dbh: &Surreal<Client>,
+    eid: usize,
+    status: EventStatus,
+    cancel_reason: Option<String>,
+) -> surrealdb::Result<Option<Event>> {
+    rocket::info!("set_event_status eid: {eid} status: {status:?}");
+
+    let mut response = dbh
+        .query("UPDATE event SET status=$status, cancel_reason=$cancel_reason, sequence+=1 WHERE eid=$eid;")
+        .bind(("status", status))
+        .bind(("cancel_reason", cancel_reason))
+        .bind(("eid", eid))
+        .await?;
+
+    let entry: Option<Event> = response.take(0)?;
+
+    Ok(entry)
+}
+
+/// Say yes to the event if it still has a free spot. Returns false if the event is full.
+/// Counting the attendees and adding the new one happen in the same transaction.
+/// Both concurrent transactions write the event, so the database lets only one of them commit.
+/// The other one is retried, and it sees the spot already taken.
+pub async fn rsvp_yes_within_capacity(
+    dbh: &Surreal<Client>,
+    eid: usize,
+    uid: usize,
+    capacity: usize,
+) -> surrealdb::Result<bool> {
+    rocket::info!("user {uid} RSVP: {eid} within capacity: {capacity}");
+
+    let mut attempts = 0_u8;
+    loop {
+        let rsvp = RSVP {
+            id: Thing::from(("rsvp", Id::ulid())),
+            eid,
+            uid,
+            date: Utc::now(),
+            status: true,
+        };
+
+        let result = dbh
+            .query(
+                "
+                BEGIN TRANSACTION;
+                UPDATE event SET capacity=capacity WHERE eid=$eid;
+                IF count(SELECT * FROM rsvp WHERE eid=$eid AND status=true) < $capacity {
+                    IF count(SELECT * FROM rsvp WHERE eid=$eid AND uid=$uid) > 0 {
+                        UPDATE rsvp SET status=true, date=$date WHERE eid=$eid AND uid=$uid;
+                    } ELSE {
+                        CREATE rsvp CONTENT $rsvp;
+                    };
+                };
+                SELECT * FROM rsvp WHERE eid=$eid AND uid=$uid AND status=true;
+                COMMIT TRANSACTION;",
+            )
+            .bind(("eid", eid))
+            .bind(("uid", uid))
+            .bind(("capacity", capacity))
+            .bind(("date", rsvp.date))
+            .bind(("rsvp", rsvp))
+            .await
+            .and_then(surrealdb::Response::check);
+
+        match result {
+            Ok(mut response) => {
+                let entry: Option<RSVP> = response.take(2)?;
+                return Ok(entry.is_some());
+            }
+            Err(err) if attempts < 5 && is_conflict(&err) => {
+                rocket::info!("retry RSVP of user {uid} to event {eid}: {err}");
+                attempts = attempts.saturating_add(1);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// The transaction failed because another one changed the same records at the same time.
+#[must_use]
+pub fn is_conflict(err: &surrealdb::Error) -> bool {
+    err.to_string().contains("can be retried")
+}
+
+/// The number of people who are attending the event.
+pub async fn count_attendees(dbh: &Surreal<Client>, eid: usize) -> surrealdb::Result<usize> {
+    let mut response = dbh
+        .query("SELECT count() FROM rsvp WHERE eid=$eid AND status=true GROUP ALL;")
+        .bind(("eid", eid))
+        .await?;
+
+    let count: Option<usize> = response.take((0, "count"))?;
+
+    Ok(count.unwrap_or(0))
+}
+
+pub async fn add_to_waitlist(
+    dbh: &Surreal<Client>,
+    eid: usize,
+    uid: usize,
+) -> surrealdb::Result<()> {
+    rocket::info!("user {uid} is on the waitlist of event: {eid}");
+
+    let entry = WaitlistEntry {
+        id: Thing::from(("waitlist", Id::ulid())),
+        eid,
+        uid,
+        date: Utc::now(),
+    };
+
+    dbh.create(Resource::from("waitlist"))
+        .content(entry)
+        .await?;
+
+    Ok(())
+}
+
+/// The people waiting for a spot, the first one to get the next free spot first.
+pub async fn get_waitlist(
+    dbh: &Surreal<Client>,
+    eid: usize,
+) -> surrealdb::Result<Vec<WaitlistEntry>> {
+    let mut response = dbh
+        .query("SELECT * FROM waitlist WHERE eid=$eid ORDER BY date;")
+        .bind(("eid", eid))
+        .await?;
+
+    let entries: Vec<WaitlistEntry> = response.take(0)?;
+
+    Ok(entries)
+}
+
+/// Remove the user from the waitlist. Returns None if the user was not on it.
+pub async fn remove_from_waitlist(
+    dbh: &Surreal<Client>,
+    eid: usize,
+    uid: usize,
+) -> surrealdb::Result<Option<WaitlistEntry>> {
+    rocket::info!("user {uid} leaves the waitlist of event: {eid}");
+
+    let mut response = dbh
+        .query("DELETE waitlist WHERE eid=$eid AND uid=$uid RETURN BEFORE;")
+        .bind(("eid", eid))
+        .bind(("uid", uid))
+        .await?;
+
+    let entry: Option<WaitlistEntry> = response.take(0)?;
+
+    Ok(entry)
+}
+
+pub async fn audit(dbh: &Surreal<Client>, atype: AuditType, json: Value) -> surrealdb::Result<()> {
+    let text = json.to_string();
+    rocket::info!("audit {text}");
+
+    let date: DateTime<Utc> = Utc::now();
+    let entry = Audit {
+        id: Thing::from(("audit", Id::ulid())),
+        date,
+        atype,
+        text,
+    };
+
+    dbh.create(Resource::from("audit")).content(entry).await?;
+
+    Ok(())
+}
+
+pub async fn get_audit(dbh: &Surreal<Client>) -> surrealdb::Result<Vec<Audit>> {
+    rocket::info!("get_audits");
+    let mut response = dbh.query("SELECT * FROM audit ORDER BY date;").await?;
+    let entries: Vec<Audit> = response.take(0)?;
+    Ok(entries)
+}
+
+pub async fn get_user_by_id_str(
+    dbh: &Surreal<Client>,
+    id: &str,
+) -> surrealdb::Result<Option<User>> {
+    rocket::info!("get_user_by_id: '{id}'");
+
+    let mut response = dbh
+        .query("SELECT * FROM user WHERE id=$id;")
+        .bind(("id", Thing::from(("user", id))))
+        .await?;
+
+    let entry: Option<User> = response.take(0)?;
+
+    Ok(entry)
+}
+
+pub async fn add_session(dbh: &Surreal<Client>, session: &Session) -> surrealdb::Result<()> {
+    rocket::info!("add session for uid: '{}'", session.uid);
+
+    dbh.create(Resource::from("session"))
+        .content(session.clone())
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_session_by_sid(
+    dbh: &Surreal<Client>,
+    sid: &str,
+) -> surrealdb::Result<Option<Session>> {
+    let mut response = dbh
+        .query("SELECT * FROM session WHERE sid=$sid;")
+        .bind(("sid", sid.to_owned()))
+        .await?;
+
+    let entry: Option<Session> = response.take(0)?;
+
+    Ok(entry)
+}
+
+pub async fn get_sessions_by_uid(
+    dbh: &Surreal<Client>,
+    uid: usize,
+) -> surrealdb::Result<Vec<Session>> {
+    rocket::info!("get_sessions_by_uid: '{uid}'");
+
+    let mut response = dbh
+        .query("SELECT * FROM session WHERE uid=$uid ORDER BY last_seen DESC;")
+        .bind(("uid", uid))
+        .await?;
+
+    let entries: Vec<Session> = response.take(0)?;
+
+    Ok(entries)
+}
+
+pub async fn touch_session(dbh: &Surreal<Client>, sid: &str) -> surrealdb::Result<()> {
+    let date: DateTime<Utc> = Utc::now();
+
+    dbh.query("UPDATE session SET last_seen=$date WHERE sid=$sid;")
+        .bind(("sid", sid.to_owned()))
+        .bind(("date", date))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete_session(dbh: &Surreal<Client>, sid: &str) -> surrealdb::Result<()> {
+    dbh.query("DELETE session WHERE sid=$sid;")
+        .bind(("sid", sid.to_owned()))
+        .await?;
+
+    Ok(())
+}
+
+/// Delete a session by its record id, but only if it belongs to the given user.
+pub async fn delete_session_by_id(
+    dbh: &Surreal<Client>,
+    uid: usize,
+    id: &str,
+) -> surrealdb::Result<Option<Session>> {
+    rocket::info!("delete_session_by_id: '{uid}' '{id}'");
+
+    let mut response = dbh
+        .query("DELETE session WHERE id=$id AND uid=$uid RETURN BEFORE;")
+        .bind(("id", Thing::from(("session", id))))
+        .bind(("uid", uid))
+        .await?;
+
+    let entry: Option<Session> = response.take(0)?;
+
+    Ok(entry)
+}
+
+/// Delete all the sessions of a user except the one with the `keep` session id (if given).
+pub async fn delete_sessions_of_user(
+    dbh: &Surreal<Client>,
+    uid: usize,
+    keep: Option<&str>,
+) -> surrealdb::Result<()> {
+    rocket::info!("delete_sessions_of_user: '{uid}'");
+
+    dbh.query("DELETE session WHERE uid=$uid AND sid!=$keep;")
+        .bind(("uid", uid))
+        .bind(("keep", keep.unwrap_or_default().to_owned()))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn add_login_failure(
+    dbh: &Surreal<Client>,
+    email: &str,
+    ip: &str,
+) -> surrealdb::Result<()> {
+    rocket::info!("add_login_failure: '{email}' from '{ip}'");
+
+    let failure = LoginFailure {
+        id: Thing::from(("login_failure", Id::ulid())),
+        email: email.to_owned(),
+        ip: ip.to_owned(),
+        date: Utc::now(),
+    };
+
+    dbh.create(Resource::from("login_failure"))
+        .content(failure)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn count_login_failures_by_email(
+    dbh: &Surreal<Client>,
+    email: &str,
+    since: DateTime<Utc>,
+) -> surrealdb::Result<usize> {
+    let mut response = dbh
+        .query("SELECT count() FROM login_failure WHERE email=$email AND date > $since GROUP ALL;")
+        .bind(("email", email.to_owned()))
+        .bind(("since", since))
+        .await?;
+
+    let count: Option<usize> = response.take((0, "count"))?;
+
+    Ok(count.unwrap_or(0))
+}
+
+pub async fn count_login_failures_by_ip(
+    dbh: &Surreal<Client>,
+    ip: &str,
+    since: DateTime<Utc>,
+) -> surrealdb::Result<usize> {
+    let mut response = dbh
+        .query("SELECT count() FROM login_failure WHERE ip=$ip AND date > $since GROUP ALL;")
+        .bind(("ip", ip.to_owned()))
+        .bind(("since", since))
+        .await?;
+
+    let count: Option<usize> = response.take((0, "count"))?;
+
+    Ok(count.unwrap_or(0))
+}
+
+/// Failures before the lockout window no longer count, remove them.
+pub async fn delete_old_login_failures(
+    dbh: &Surreal<Client>,
+    before: DateTime<Utc>,
+) -> surrealdb::Result<()> {
+    dbh.query("DELETE login_failure WHERE date <= $before;")
+        .bind(("before", before))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn clear_login_failures(dbh: &Surreal<Client>, email: &str) -> surrealdb::Result<()> {
+    dbh.query("DELETE login_failure WHERE email=$email;")
+        .bind(("email", email.to_owned()))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn add_magic_link_request(dbh: &Surreal<Client>, email: &str) -> surrealdb::Result<()> {
+    rocket::info!("add_magic_link_request: '{email}'");
+
+    let request = MagicLinkRequest {
+        id: Thing::from(("magic_link_request", Id::ulid())),
+        email: email.to_owned(),
+        date: Utc::now(),
+    };
+
+    dbh.create(Resource::from("magic_link_request"))
+        .content(request)
+        .await?;
+
+    Ok(())
+}
+
+/// The requests since the given time. The earlier ones are removed.
+pub async fn count_magic_link_requests(
+    dbh: &Surreal<Client>,
+    email: &str,
+    since: DateTime<Utc>,
+) -> surrealdb::Result<usize> {
+    let mut response = dbh
+        .query(
+            "
+            DELETE magic_link_request WHERE date <= $since;
+            SELECT count() FROM magic_link_request WHERE email=$email GROUP ALL;",
+        )
+        .bind(("email", email.to_owned()))
+        .bind(("since", since))
+        .await?;
+
+    let count: Option<usize> = response.take((1, "count"))?;
+
+    Ok(count.unwrap_or(0))
+}
+
+pub async fn get_totp(dbh: &Surreal<Client>, uid: usize) -> surrealdb::Result<Option<Totp>> {
+    let mut response = dbh
+        .query("SELECT * FROM totp WHERE uid=$uid;")
+        .bind(("uid", uid))
+        .await?;
+
+    let entry: Option<Totp> = response.take(0)?;
+
+    Ok(entry)
+}
+
+/// Store a new, not yet confirmed secret for the user replacing any earlier one.
+pub async fn add_pending_totp(
+    dbh: &Surreal<Client>,
+    uid: usize,
+    secret: &str,
+) -> surrealdb::Result<()> {
+    rocket::info!("add_pending_totp: '{uid}'");
+
+    delete_totp(dbh, uid).await?;
+
+    let totp = Totp {
+        id: Thing::from(("totp", Id::ulid())),
+        uid,
+        secret: secret.to_owned(),
+        enabled: false,
+        recovery_codes: vec![],
+        last_code: String::new(),
+        created: Utc::now(),
+    };
+
+    dbh.create(Resource::from("totp")).content(totp).await?;
+
+    Ok(())
+}
+
+pub async fn enable_totp(
+    dbh: &Surreal<Client>,
+    uid: usize,
+    recovery_codes: Vec<String>,
+    code: &str,
+) -> surrealdb::Result<()> {
+    rocket::info!("enable_totp: '{uid}'");
+
+    dbh.query(
+        "UPDATE totp SET enabled=true, recovery_codes=$recovery_codes, last_code=$code WHERE uid=$uid;",
+    )
+    .bind(("uid", uid))
+    .bind(("recovery_codes", recovery_codes))
+    .bind(("code", code.to_owned()))
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_totp_last_code(
+    dbh: &Surreal<Client>,
+    uid: usize,
+    code: &str,
+) -> surrealdb::Result<()> {
+    dbh.query("UPDATE totp SET last_code=$code WHERE uid=$uid;")
+        .bind(("uid", uid))
+        .bind(("code", code.to_owned()))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn remove_recovery_code(
+    dbh: &Surreal<Client>,
+    uid: usize,
+    hash: &str,
+) -> surrealdb::Result<()> {
+    rocket::info!("remove_recovery_code: '{uid}'");
+
+    dbh.query("UPDATE totp SET recovery_codes -= $hash WHERE uid=$uid;")
+        .bind(("uid", uid))
+        .bind(("hash", hash.to_owned()))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete_totp(dbh: &Surreal<Client>, uid: usize) -> surrealdb::Result<()> {
+    dbh.query("DELETE totp WHERE uid=$uid;")
+        .bind(("uid", uid))
+        .await?;
+
+    Ok(())
+}
+
+/// Add a new token. Earlier unused tokens of the same user for the same purpose become invalid.
+pub async fn add_token(dbh: &Surreal<Client>, token: &Token) -> surrealdb::Result<()> {
+    rocket::info!("add token for '{}' {:?}", token.uid, token.purpose);
+
+    // The transfer offers of different groups can be open at the same time.
+    dbh.query("DELETE token WHERE uid=$uid AND purpose=$purpose AND gid=$gid AND used IS NONE")
+        .bind(("uid", token.uid))
+        .bind(("purpose", token.purpose))
+        .bind(("gid", token.gid))
+        .await?;
+
+    dbh.create(Resource::from("token"))
+        .content(token.clone())
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_token(dbh: &Surreal<Client>, hash: &str) -> surrealdb::Result<Option<Token>> {
+    let mut response = dbh
+        .query("SELECT * FROM token WHERE hash=$hash")
+        .bind(("hash", hash.to_owned()))
+        .await?;
+
+    let entry: Option<Token> = response.take(0)?;
+    Ok(entry)
+}
+
+/// Mark the token as used. Returns None if it was already used.
+pub async fn use_token(dbh: &Surreal<Client>, hash: &str) -> surrealdb::Result<Option<Token>> {
+    let utc: DateTime<Utc> = Utc::now();
+    let mut response = dbh
+        .query("UPDATE token SET used=$date WHERE hash=$hash AND used IS NONE")
+        .bind(("hash", hash.to_owned()))
+        .bind(("date", utc))
+        .await?;
+
+    let entry: Option<Token> = response.take(0)?;
+    Ok(entry)
+}
+
+pub async fn get_rsvps_by_uid(dbh: &Surreal<Client>, uid: usize) -> surrealdb::Result<Vec<RSVP>> {
+    let mut response = dbh
+        .query("SELECT * FROM rsvp WHERE uid=$uid ORDER BY date;")
+        .bind(("uid", uid))
+        .await?;
+
+    let entries: Vec<RSVP> = response.take(0)?;
+    Ok(entries)
+}
+
+/// Replace the calendar token of the user. The URL with the old token stops working.
+pub async fn set_calendar_token(
+    dbh: &Surreal<Client>,
+    token: &CalendarToken,
+) -> surrealdb::Result<()> {
+    rocket::info!("set_calendar_token uid: '{}'", token.uid);
+
+    dbh.query(
+        "
+        BEGIN TRANSACTION;
+        DELETE calendar_token WHERE uid=$uid;
+        CREATE calendar_token CONTENT $token;
+        COMMIT TRANSACTION;",
+    )
+    .bind(("uid", token.uid))
+    .bind(("token", token.clone()))
+    .await?
+    .check()?;
+
+    Ok(())
+}
+
+pub async fn get_calendar_token_by_uid(
+    dbh: &Surreal<Client>,
+    uid: usize,
+) -> surrealdb::Result<Option<CalendarToken>> {
+    let mut response = dbh
+        .query("SELECT * FROM calendar_token WHERE uid=$uid;")
+        .bind(("uid", uid))
+        .await?;
+
+    let entry: Option<CalendarToken> = response.take(0)?;
+    Ok(entry)
+}
+
+pub async fn get_calendar_token_by_hash(
+    dbh: &Surreal<Client>,
+    hash: &str,
+) -> surrealdb::Result<Option<CalendarToken>> {
+    let mut response = dbh
+        .query("SELECT * FROM calendar_token WHERE hash=$hash;")
+        .bind(("hash", hash.to_owned()))
+        .await?;
+
+    let entry: Option<CalendarToken> = response.take(0)?;
+    Ok(entry)
+}
+
+pub async fn delete_calendar_token(
+    dbh: &Surreal<Client>,
+    uid: usize,
+) -> surrealdb::Result<Option<CalendarToken>> {
+    rocket::info!("delete_calendar_token uid: '{uid}'");
+    let mut response = dbh
+        .query("DELETE calendar_token WHERE uid=$uid RETURN BEFORE;")
+        .bind(("uid", uid))
+        .await?;
+
+    let entry: Option<CalendarToken> = response.take(0)?;
+    Ok(entry)
+}
+
+/// Remove the user and every row that belongs only to them.
+pub async fn delete_user(dbh: &Surreal<Client>, uid: usize) -> surrealdb::Result<()> {
+    rocket::info!("delete user: '{uid}'");
+
+    dbh.query(
+        "
+        BEGIN TRANSACTION;
+        DELETE membership WHERE uid=$uid;
+        DELETE join_request WHERE uid=$uid;
+        DELETE group_request WHERE uid=$uid;
+        DELETE ban WHERE uid=$uid;
+        DELETE rsvp WHERE uid=$uid;
+        DELETE waitlist WHERE uid=$uid;
+        DELETE calendar_token WHERE uid=$uid;
+        DELETE session WHERE uid=$uid;
+        DELETE token WHERE uid=$uid;
+        DELETE totp WHERE uid=$uid;
+        DELETE identity WHERE uid=$uid;
+        DELETE api_token WHERE uid=$uid;
+        DELETE user WHERE uid=$uid;
+        COMMIT TRANSACTION;",
+    )
+    .bind(("uid", uid))
+    .await?
+    .check()?;
+
+    Ok(())
+}
+
+pub async fn add_identity(dbh: &Surreal<Client>, identity: &Identity) -> surrealdb::Result<()> {
+    rocket::info!(
+        "add identity {} for uid: '{}'",
+        identity.provider,
+        identity.uid
+    );
+
+    dbh.create(Resource::from("identity"))
+        .content(identity.clone())
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_identity(
+    dbh: &Surreal<Client>,
+    provider: &str,
+    subject: &str,
+) -> surrealdb::Result<Option<Identity>> {
+    let mut response = dbh
+        .query("SELECT * FROM identity WHERE provider=$provider AND subject=$subject;")
+        .bind(("provider", provider.to_owned()))
+        .bind(("subject", subject.to_owned()))
+        .await?;
+
+    let entry: Option<Identity> = response.take(0)?;
+    Ok(entry)
+}
+
+pub async fn get_identities_by_uid(
+    dbh: &Surreal<Client>,
+    uid: usize,
+) -> surrealdb::Result<Vec<Identity>> {
+    let mut response = dbh
+        .query("SELECT * FROM identity WHERE uid=$uid ORDER BY provider;")
+        .bind(("uid", uid))
+        .await?;
+
+    let entries: Vec<Identity> = response.take(0)?;
+    Ok(entries)
+}
+
+pub async fn delete_identity(
+    dbh: &Surreal<Client>,
+    uid: usize,
+    provider: &str,
+) -> surrealdb::Result<()> {
+    dbh.query("DELETE identity WHERE uid=$uid AND provider=$provider;")
+        .bind(("uid", uid))
+        .bind(("provider", provider.to_owned()))
+        .await?;
+
+    Ok(())
+}
+
+/// Save the username verified by GitHub or GitLab in the profile of the user.
+pub async fn set_user_handle(
+    dbh: &Surreal<Client>,
+    uid: usize,
+    kind: Kind,
+    handle: &str,
+) -> surrealdb::Result<()> {
+    let query = match kind {
+        Kind::GitHub => "UPDATE user SET github=$handle WHERE uid=$uid;",
+        Kind::GitLab => "UPDATE user SET gitlab=$handle WHERE uid=$uid;",
+        Kind::Oidc => return Ok(()),
+    };
+
+    dbh.query(query)
+        .bind(("handle", handle.to_owned()))
+        .bind(("uid", uid))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn add_api_token(dbh: &Surreal<Client>, token: &ApiToken) -> surrealdb::Result<()> {
+    rocket::info!("add api token '{}' for uid: '{}'", token.name, token.uid);
+
+    dbh.create(Resource::from("api_token"))
+        .content(token.clone())
+        .await?;
+
+    Ok(())
+}
+
+/// Find the token by its hash and record that it was used.
+pub async fn use_api_token(
+    dbh: &Surreal<Client>,
+    hash: &str,
+) -> surrealdb::Result<Option<ApiToken>> {
+    let utc: DateTime<Utc> = Utc::now();
+
+    let mut response = dbh
+        .query("UPDATE api_token SET last_used=$date WHERE hash=$hash;")
+        .bind(("date", utc))
+        .bind(("hash", hash.to_owned()))
+        .await?;
+
+    let entry: Option<ApiToken> = response.take(0)?;
+    Ok(entry)
+}
+
+pub async fn get_api_tokens_by_uid(
+    dbh: &Surreal<Client>,
+    uid: usize,
+) -> surrealdb::Result<Vec<ApiToken>> {
+    let mut response = dbh
+        .query("SELECT * FROM api_token WHERE uid=$uid ORDER BY created DESC;")
+        .bind(("uid", uid))
+        .await?;
+
+    let entries: Vec<ApiToken> = response.take(0)?;
+    Ok(entries)
+}
+
+/// Delete a token by its record id, but only if it belongs to the given user.
+pub async fn delete_api_token(
+    dbh: &Surreal<Client>,
+    uid: usize,
+    id: &str,
+) -> surrealdb::Result<Option<ApiToken>> {
+    rocket::info!("delete_api_token: '{uid}' '{id}'");
+
+    let mut response = dbh
+        .query("DELETE api_token WHERE id=$id AND uid=$uid RETURN BEFORE;")
+        .bind(("id", Thing::from(("api_token", id))))
+        .bind(("uid", uid))
+        .await?;
+
+    let entry: Option<ApiToken> = response.take(0)?;
+    Ok(entry)
+}
+
+/// Add a new invitation. An earlier invitation of the same address to the same group that was not accepted yet is replaced.
+pub async fn add_invitation(
+    dbh: &Surreal<Client>,
+    invitation: &Invitation,
+) -> surrealdb::Result<()> {
+    rocket::info!(
+        "add invitation of '{}' to group {}",
+        invitation.email,
+        invitation.gid
+    );
+
+    dbh.query("DELETE invitation WHERE gid=$gid AND email=$email AND accepted IS NONE")
+        .bind(("gid", invitation.gid))
+        .bind(("email", invitation.email.clone()))
+        .await?
+        .check()?;
+
+    dbh.create(Resource::from("invitation"))
+        .content(invitation.clone())
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_invitation_by_hash(
+    dbh: &Surreal<Client>,
+    hash: &str,
+) -> surrealdb::Result<Option<Invitation>> {
+    let mut response = dbh
+        .query("SELECT * FROM invitation WHERE hash=$hash;")
+        .bind(("hash", hash.to_owned()))
+        .await?;
+
+    let entry: Option<Invitation> = response.take(0)?;
+    Ok(entry)
+}
+
+pub async fn get_invitations_of_group(
+    dbh: &Surreal<Client>,
+    gid: usize,
+) -> surrealdb::Result<Vec<Invitation>> {
+    let mut response = dbh
+        .query("SELECT * FROM invitation WHERE gid=$gid ORDER BY created DESC;")
+        .bind(("gid", gid))
+        .await?;
+
+    let entries: Vec<Invitation> = response.take(0)?;
+    Ok(entries)
+}
+
+/// The invitations to the given address that were neither accepted nor expired.
+pub async fn get_pending_invitations_by_email(
+    dbh: &Surreal<Client>,
+    email: &str,
+) -> surrealdb::Result<Vec<Invitation>> {
+    let mut response = dbh
+        .query(
+            "SELECT * FROM invitation WHERE email=$email AND accepted IS NONE AND expires > $now;",
+        )
+        .bind(("email", email.to_owned()))
+        .bind(("now", Utc::now()))
+        .await?;
+
+    let entries: Vec<Invitation> = response.take(0)?;
+    Ok(entries)
+}
+
+/// Replace the code of an invitation that was not accepted yet and extend its validity.
+pub async fn renew_invitation(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    id: &str,
+    hash: &str,
+    expires: DateTime<Utc>,
+) -> surrealdb::Result<Option<Invitation>> {
+    rocket::info!("renew invitation: '{gid}' '{id}'");
+
+    let mut response = dbh
+        .query("UPDATE invitation SET hash=$hash, expires=$expires WHERE id=$id AND gid=$gid AND accepted IS NONE;")
+        .bind(("id", Thing::from(("invitation", id))))
+        .bind(("gid", gid))
+        .bind(("hash", hash.to_owned()))
+        .bind(("expires", expires))
+        .await?;
+
+    let entry: Option<Invitation> = response.take(0)?;
+    Ok(entry)
+}
+
+/// Delete an invitation by its record id, but only if it belongs to the given group.
+pub async fn delete_invitation(
+    dbh: &Surreal<Client>,
+    gid: usize,
+    id: &str,
+) -> surrealdb::Result<Option<Invitation>> {
+    rocket::info!("delete invitation: '{gid}' '{id}'");
+
+    let mut response = dbh
+        .query("DELETE invitation WHERE id=$id AND gid=$gid RETURN BEFORE;")
+        .bind(("id", Thing::from(("invitation", id))))
+        .bind(("gid", gid))
+        .await?;
+
+    let entry: Option<Invitation> = response.take(0)?;
+    Ok(entry)
+}
+
+/// Mark the invitation as accepted. Returns None if it was already accepted.
+pub async fn accept_invitation(
+    dbh: &Surreal<Client>,
+    hash: &str,
+) -> surrealdb::Result<Option<Invitation>> {
+    let mut response = dbh
+        .query("UPDATE invitation SET accepted=$date WHERE hash=$hash AND accepted IS NONE;")
+        .bind(("hash", hash.to_owned()))
+        .bind(("date", Utc::now()))
+        .await?;
+
+    let entry: Option<Invitation> = response.take(0)?;
+    Ok(entry)
+}
+
+/// Fails if there is already a topic with the same name as `topic_name` is a unique index.
+pub async fn add_topic(dbh: &Surreal<Client>, topic: &Topic) -> surrealdb::Result<()> {
+    rocket::info!("add topic: '{}'", topic.name);
+
+    dbh.create(Resource::from("topic"))
+        .content(topic.clone())
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_topics(dbh: &Surreal<Client>) -> surrealdb::Result<Vec<Topic>> {
+    let mut response = dbh.query("SELECT * FROM topic ORDER BY name;").await?;
+
+    let entries: Vec<Topic> = response.take(0)?;
+    Ok(entries)
+}
+
+pub async fn get_topic_by_name(
+    dbh: &Surreal<Client>,
+    name: &str,
+) -> surrealdb::Result<Option<Topic>> {
+    let mut response = dbh
+        .query("SELECT * FROM topic WHERE name=$name;")
+        .bind(("name", name.to_owned()))
+        .await?;
+
+    let entry: Option<Topic> = response.take(0)?;
+    Ok(entry)
+}
+
+/// Remove the topic from the list and from every group tagged with it. Returns None if there was no such topic.
+pub async fn delete_topic(dbh: &Surreal<Client>, name: &str) -> surrealdb::Result<Option<Topic>> {
+    rocket::info!("delete topic: '{name}'");
+
+    let mut response = dbh
+        .query("DELETE topic WHERE name=$name RETURN BEFORE;")
+        .bind(("name", name.to_owned()))
+        .await?;
+
+    let entry: Option<Topic> = response.take(0)?;
+    if entry.is_some() {
+        dbh.query("UPDATE group SET topics -= $name WHERE topics CONTAINS $name;")
+            .bind(("name", name.to_owned()))
+            .await?
+            .check()?;
+    }
 
     Ok(entry)
 }
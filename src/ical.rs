@@ -0,0 +1,91 @@
+//! iCalendar (RFC 5545) export of the events for calendar applications.
+
+use chrono::{DateTime, Utc};
+
+use crate::{Event, EventStatus};
+
+const PRODID: &str = "-//Meet-OS//Meet-OS//EN";
+
+/// Lines longer than this many octets are folded.
+const MAX_LINE_LENGTH: usize = 75;
+
+/// A `VCALENDAR` with one `VEVENT` for each event. Drafts and hidden events are left out.
+#[must_use]
+pub fn calendar(name: &str, base_url: &str, events: &[Event], now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        format!("PRODID:{PRODID}"),
+        String::from("CALSCALE:GREGORIAN"),
+        String::from("METHOD:PUBLISH"),
+        format!("X-WR-CALNAME:{}", escape(name)),
+    ];
+    for event in events.iter().filter(|event| event.is_public()) {
+        lines.extend(vevent(base_url, event, now));
+    }
+    lines.push(String::from("END:VCALENDAR"));
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+fn vevent(base_url: &str, event: &Event, now: DateTime<Utc>) -> Vec<String> {
+    let host = base_url
+        .split_once("://")
+        .map_or(base_url, |parts| parts.1)
+        .trim_end_matches('/');
+    let status = if event.status == EventStatus::Cancelled {
+        "CANCELLED"
+    } else {
+        "CONFIRMED"
+    };
+
+    let mut lines = vec![
+        String::from("BEGIN:VEVENT"),
+        format!("UID:event-{}@{host}", event.eid),
+        format!("DTSTAMP:{}", format_date(now)),
+        format!("DTSTART:{}", format_date(event.date)),
+    ];
+    if let Some(end) = event.end {
+        lines.push(format!("DTEND:{}", format_date(end)));
+    }
+    lines.extend([
+        format!("SEQUENCE:{}", event.sequence),
+        format!("SUMMARY:{}", escape(&event.title)),
+        format!("LOCATION:{}", escape(&event.location)),
+        format!("DESCRIPTION:{}", escape(&event.description)),
+        format!("URL:{base_url}/event/{}", event.eid),
+        format!("STATUS:{status}"),
+        String::from("END:VEVENT"),
+    ]);
+    lines
+}
+
+fn format_date(date: DateTime<Utc>) -> String {
+    date.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape the special characters of TEXT values.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\r', "")
+        .replace('\n', "\\n")
+}
+
+/// Split the line into parts of at most 75 octets without breaking UTF-8 characters.
+/// The continuation lines start with a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length: usize = 0;
+    for ch in line.chars() {
+        if MAX_LINE_LENGTH < length.saturating_add(ch.len_utf8()) {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(ch);
+        length = length.saturating_add(ch.len_utf8());
+    }
+    folded.push_str("\r\n");
+    folded
+}
@@ -1,16 +1,25 @@
 #![allow(clippy::allow_attributes_without_reason)]
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use core::cmp::Reverse;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
 use surrealdb::sql::Thing;
 
 use std::fs::read_to_string;
 
 pub mod db;
+pub mod ical;
 
 pub mod notifications;
 pub use notifications::*;
 
+pub mod oauth;
+pub mod password;
+pub mod rrule;
+pub mod timezone;
+pub mod totp;
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct User {
     pub id: Thing,
@@ -18,12 +27,9 @@ pub struct User {
     pub email: String,
     pub password: String,
     pub name: String,
-    pub code: String,
-    pub process: String,
     pub verified: bool,
     pub registration_date: DateTime<Utc>,
     pub verification_date: Option<DateTime<Utc>>,
-    pub code_generated_date: Option<DateTime<Utc>>,
     pub github: Option<String>,
     pub gitlab: Option<String>,
     pub linkedin: Option<String>,
@@ -39,6 +45,141 @@ pub struct Group {
     pub description: String,
     pub owner: usize,
     pub creation_date: DateTime<Utc>,
+    /// New members need the approval of an organizer.
+    #[serde(default)]
+    pub requires_approval: bool,
+    /// Questions people have to answer when they ask to join.
+    #[serde(default)]
+    pub questions: Vec<String>,
+    /// When the group was closed. Archived groups are kept for the history, but they are read-only.
+    pub archived: Option<DateTime<Utc>>,
+    /// The name of the group in the `/g/<slug>` URL.
+    #[serde(default)]
+    pub slug: String,
+    /// Slugs used before a rename. They redirect to the current one.
+    #[serde(default)]
+    pub old_slugs: Vec<String>,
+    /// The names of the topics from the list curated by the site admins.
+    #[serde(default)]
+    pub topics: Vec<String>,
+    /// The IANA time zone offered for the new events of the group.
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+}
+
+/// A subject groups can be tagged with e.g. "python". The list is curated by the site admins.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct Topic {
+    pub id: Thing,
+    /// Used in the `/topic/<name>` URL so it is a slug.
+    pub name: String,
+    pub description: String,
+    pub created: DateTime<Utc>,
+}
+
+/// The relation of a user to a group, from the least to the most privileged.
+#[non_exhaustive]
+#[derive(Serialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum GroupRole {
+    Visitor,
+    Member,
+    Admin,
+    Owner,
+}
+
+impl GroupRole {
+    #[must_use]
+    pub fn new(group: &Group, uid: usize, membership: Option<&Membership>) -> Self {
+        if group.owner == uid {
+            return Self::Owner;
+        }
+        match membership {
+            Some(membership) if membership.admin => Self::Admin,
+            Some(_) => Self::Member,
+            None => Self::Visitor,
+        }
+    }
+
+    /// The owner and the group admins can manage the events, contact the members and edit the group.
+    #[must_use]
+    pub fn can_organize(self) -> bool {
+        self >= Self::Admin
+    }
+
+    /// Only the owner can promote members to admins and demote them.
+    #[must_use]
+    pub fn can_manage_admins(self) -> bool {
+        self == Self::Owner
+    }
+}
+
+/// A pending request to join a group that requires approval.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct JoinRequest {
+    pub id: Thing,
+    pub gid: usize,
+    pub uid: usize,
+    /// The questions of the group at the time of the request and the answers.
+    pub answers: Vec<(String, String)>,
+    pub date: DateTime<Utc>,
+}
+
+/// An invitation to join a group sent to an email address. Only the hash of the code is stored.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct Invitation {
+    pub id: Thing,
+    pub gid: usize,
+    pub email: String,
+    pub hash: String,
+    pub invited_by: usize,
+    pub created: DateTime<Utc>,
+    pub expires: DateTime<Utc>,
+    pub accepted: Option<DateTime<Utc>>,
+}
+
+/// A request of a user to start a new group. The site admins approve or reject it.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct GroupRequest {
+    pub id: Thing,
+    pub uid: usize,
+    pub name: String,
+    pub location: String,
+    pub description: String,
+    /// Why the user would like to start the group.
+    pub why: String,
+    pub date: DateTime<Utc>,
+}
+
+/// A user removed from a group who is not allowed to join it again.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct Ban {
+    pub id: Thing,
+    pub gid: usize,
+    pub uid: usize,
+    pub reason: String,
+    pub banned_by: usize,
+    pub date: DateTime<Utc>,
+}
+
+#[non_exhaustive]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum InvitationStatus {
+    Sent,
+    Accepted,
+    Expired,
+}
+
+impl Invitation {
+    #[must_use]
+    pub fn status(&self, now: DateTime<Utc>) -> InvitationStatus {
+        if self.accepted.is_some() {
+            InvitationStatus::Accepted
+        } else if self.expires <= now {
+            InvitationStatus::Expired
+        } else {
+            InvitationStatus::Sent
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq)]
@@ -59,6 +200,144 @@ pub struct RSVP {
     pub status: bool,
 }
 
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct Session {
+    pub id: Thing,
+    pub sid: String,
+    pub uid: usize,
+    pub created: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub user_agent: String,
+    pub ip: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct LoginFailure {
+    pub id: Thing,
+    pub email: String,
+    pub ip: String,
+    pub date: DateTime<Utc>,
+}
+
+/// A request for a login link, registered address or not. Counted to limit the emails we send.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct MagicLinkRequest {
+    pub id: Thing,
+    pub email: String,
+    pub date: DateTime<Utc>,
+}
+
+/// A user account at an `OAuth2` provider linked to one of our users.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct Identity {
+    pub id: Thing,
+    pub uid: usize,
+    pub provider: String,
+    pub subject: String,
+    pub username: Option<String>,
+    pub created: DateTime<Utc>,
+}
+
+/// What a personal API token is allowed to do.
+#[non_exhaustive]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ApiScope {
+    #[serde(rename = "read")]
+    Read,
+    #[serde(rename = "events:write")]
+    EventsWrite,
+    #[serde(rename = "groups:write")]
+    GroupsWrite,
+    #[serde(rename = "messages:send")]
+    MessagesSend,
+}
+
+impl ApiScope {
+    pub const ALL: [Self; 4] = [
+        Self::Read,
+        Self::EventsWrite,
+        Self::GroupsWrite,
+        Self::MessagesSend,
+    ];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::EventsWrite => "events:write",
+            Self::GroupsWrite => "groups:write",
+            Self::MessagesSend => "messages:send",
+        }
+    }
+
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scope| scope.name() == name)
+    }
+}
+
+/// A personal access token used in the `Authorization: Bearer` header. Only the hash is stored.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct ApiToken {
+    pub id: Thing,
+    pub uid: usize,
+    pub name: String,
+    pub hash: String,
+    pub scopes: Vec<ApiScope>,
+    pub created: DateTime<Utc>,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct Totp {
+    pub id: Thing,
+    pub uid: usize,
+    pub secret: String,
+    pub enabled: bool,
+    pub recovery_codes: Vec<String>,
+    pub last_code: String,
+    pub created: DateTime<Utc>,
+}
+
+#[non_exhaustive]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TokenPurpose {
+    Register,
+    ResetPassword,
+    MagicLink,
+    EmailChange,
+    GroupTransfer,
+    DeleteAccount,
+}
+
+/// The secret in the URL of the personal calendar feed of a user. Only the hash is stored.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct CalendarToken {
+    pub id: Thing,
+    pub uid: usize,
+    pub hash: String,
+    pub created: DateTime<Utc>,
+}
+
+/// A single-use code we send by email. Only the hash of the code is stored.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct Token {
+    pub id: Thing,
+    pub uid: usize,
+    pub purpose: TokenPurpose,
+    pub hash: String,
+    pub created: DateTime<Utc>,
+    pub expires: DateTime<Utc>,
+    pub used: Option<DateTime<Utc>>,
+    /// The new address in case of an email change.
+    pub email: Option<String>,
+    /// The group offered to the user in case of an ownership transfer.
+    pub gid: Option<usize>,
+    /// The owner of the group when the transfer was offered. The offer is void once it changes.
+    #[serde(default)]
+    pub owner: Option<usize>,
+}
+
 #[non_exhaustive]
 #[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 pub enum EventStatus {
@@ -78,6 +357,77 @@ pub struct Event {
     pub group_id: usize,
     pub description: String,
     pub status: EventStatus,
+    /// The name of the event in the `/g/<group-slug>/e/<slug>` URL. Unique within the group.
+    #[serde(default)]
+    pub slug: String,
+    /// Slugs used before a rename. They redirect to the current one.
+    #[serde(default)]
+    pub old_slugs: Vec<String>,
+    /// The maximum number of attendees. Further RSVPs go to the waitlist. None means unlimited.
+    #[serde(default)]
+    pub capacity: Option<usize>,
+    /// Why the organizers cancelled the event. Shown on the page of the event.
+    #[serde(default)]
+    pub cancel_reason: Option<String>,
+    /// Set for the events created from a recurring series.
+    #[serde(default)]
+    pub occurrence: Option<Occurrence>,
+    /// The revision of the event in the calendar applications. Increased on every change.
+    #[serde(default)]
+    pub sequence: u32,
+    /// The IANA time zone the event was entered in. `date` and `end` are stored in UTC.
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    #[serde(default)]
+    pub end: Option<DateTime<Utc>>,
+}
+
+/// Which date of which series an event was created for. Moving only this event does not change it.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct Occurrence {
+    pub sid: usize,
+    pub date: DateTime<Utc>,
+}
+
+/// An event that repeats according to an RRULE, e.g. `FREQ=WEEKLY;INTERVAL=2;BYDAY=TU`.
+/// Its events are created a few weeks ahead, see `recurring_events_horizon_days`.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct EventSeries {
+    pub id: Thing,
+    pub sid: usize,
+    pub gid: usize,
+    pub title: String,
+    pub description: String,
+    pub location: String,
+    pub capacity: Option<usize>,
+    pub rule: String,
+    /// The date and time of the first event. All the events start at the same time of the day.
+    pub start: DateTime<Utc>,
+    /// Days on which the series skips the event.
+    pub exceptions: Vec<NaiveDate>,
+    /// The events keep the local time of the day in this zone, even when the clocks change.
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    /// The length of the events in minutes.
+    #[serde(default)]
+    pub duration: Option<i64>,
+}
+
+impl Event {
+    /// Drafts and hidden events are only visible to the organizers of the group.
+    #[must_use]
+    pub const fn is_public(&self) -> bool {
+        matches!(self.status, EventStatus::Published | EventStatus::Cancelled)
+    }
+}
+
+/// A user waiting for a spot at a full event. The earliest one gets the next free spot.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct WaitlistEntry {
+    pub id: Thing,
+    pub eid: usize,
+    pub uid: usize,
+    pub date: DateTime<Utc>,
 }
 
 #[non_exhaustive]
@@ -89,6 +439,32 @@ pub enum AuditType {
     RSVPYes,
     RSVPYesAgain,
     RSVPNo,
+    AccountLocked,
+    IpLocked,
+    EmailChanged,
+    UserDeleted,
+    GroupAdminAdded,
+    GroupAdminRemoved,
+    GroupTransferRequested,
+    GroupTransferred,
+    JoinRequested,
+    JoinRequestApproved,
+    JoinRequestRejected,
+    GroupArchived,
+    GroupUnarchived,
+    GroupDeleted,
+    MemberRemoved,
+    MemberUnbanned,
+    GroupRequested,
+    GroupRequestRejected,
+    Waitlisted,
+    WaitlistLeft,
+    WaitlistPromoted,
+    EventPublished,
+    EventCancelled,
+    EventHidden,
+    SeriesCreated,
+    SeriesChanged,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -118,13 +494,13 @@ pub struct PublicConfig {
 }
 
 #[non_exhaustive]
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
 pub enum EmailMethod {
     Sendgrid,
     Folder,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct MyConfig {
     pub base_url: String,
 
@@ -134,6 +510,8 @@ pub struct MyConfig {
     pub email_folder: Option<String>,
 
     pub admins: Vec<String>,
+    #[serde(default)]
+    pub admins_require_2fa: bool,
 
     pub from_name: String,
     pub from_email: String,
@@ -142,6 +520,145 @@ pub struct MyConfig {
     pub database_password: String,
     pub database_namespace: String,
     pub database_name: String,
+
+    #[serde(default = "default_session_timeout_days")]
+    pub session_timeout_days: i64,
+
+    #[serde(default = "default_login_max_failures")]
+    pub login_max_failures: usize,
+    #[serde(default = "default_login_max_failures_per_ip")]
+    pub login_max_failures_per_ip: usize,
+    #[serde(default = "default_login_lockout_minutes")]
+    pub login_lockout_minutes: i64,
+
+    #[serde(default = "default_verify_email_token_hours")]
+    pub verify_email_token_hours: i64,
+    #[serde(default = "default_reset_password_token_hours")]
+    pub reset_password_token_hours: i64,
+    #[serde(default = "default_email_change_token_hours")]
+    pub email_change_token_hours: i64,
+    #[serde(default = "default_group_transfer_token_hours")]
+    pub group_transfer_token_hours: i64,
+    #[serde(default = "default_delete_account_token_minutes")]
+    pub delete_account_token_minutes: i64,
+    #[serde(default = "default_invitation_days")]
+    pub invitation_days: i64,
+
+    /// How far ahead we create the events of the recurring series.
+    #[serde(default = "default_recurring_events_horizon_days")]
+    pub recurring_events_horizon_days: i64,
+
+    #[serde(default = "default_true")]
+    pub magic_link_login: bool,
+    #[serde(default = "default_magic_link_token_minutes")]
+    pub magic_link_token_minutes: i64,
+    /// How many login links can be requested for the same address while the earlier ones are valid.
+    #[serde(default = "default_magic_link_max_requests")]
+    pub magic_link_max_requests: usize,
+
+    #[serde(default)]
+    pub oauth_providers: Vec<oauth::Provider>,
+
+    #[serde(default = "default_argon2_memory_kib")]
+    pub argon2_memory_kib: u32,
+    #[serde(default = "default_argon2_iterations")]
+    pub argon2_iterations: u32,
+    #[serde(default = "default_argon2_parallelism")]
+    pub argon2_parallelism: u32,
+}
+
+impl MyConfig {
+    #[must_use]
+    pub fn get_oauth_provider(&self, name: &str) -> Option<&oauth::Provider> {
+        self.oauth_providers
+            .iter()
+            .find(|provider| provider.name == name)
+    }
+
+    /// How long the links we send by email for the given purpose are valid.
+    #[must_use]
+    pub const fn token_lifetime(&self, purpose: TokenPurpose) -> Duration {
+        match purpose {
+            TokenPurpose::Register => Duration::hours(self.verify_email_token_hours),
+            TokenPurpose::ResetPassword => Duration::hours(self.reset_password_token_hours),
+            TokenPurpose::MagicLink => Duration::minutes(self.magic_link_token_minutes),
+            TokenPurpose::EmailChange => Duration::hours(self.email_change_token_hours),
+            TokenPurpose::GroupTransfer => Duration::hours(self.group_transfer_token_hours),
+            TokenPurpose::DeleteAccount => Duration::minutes(self.delete_account_token_minutes),
+        }
+    }
+}
+
+const fn default_session_timeout_days() -> i64 {
+    30
+}
+
+const fn default_login_max_failures() -> usize {
+    5
+}
+
+const fn default_login_max_failures_per_ip() -> usize {
+    20
+}
+
+const fn default_login_lockout_minutes() -> i64 {
+    15
+}
+
+const fn default_verify_email_token_hours() -> i64 {
+    48
+}
+
+const fn default_reset_password_token_hours() -> i64 {
+    2
+}
+
+const fn default_email_change_token_hours() -> i64 {
+    24
+}
+
+const fn default_group_transfer_token_hours() -> i64 {
+    72
+}
+
+const fn default_delete_account_token_minutes() -> i64 {
+    30
+}
+
+const fn default_invitation_days() -> i64 {
+    14
+}
+
+const fn default_recurring_events_horizon_days() -> i64 {
+    90
+}
+
+fn default_timezone() -> String {
+    String::from(timezone::DEFAULT_TIMEZONE)
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+const fn default_magic_link_token_minutes() -> i64 {
+    15
+}
+
+const fn default_magic_link_max_requests() -> usize {
+    3
+}
+
+const fn default_argon2_memory_kib() -> u32 {
+    19_456
+}
+
+const fn default_argon2_iterations() -> u32 {
+    2
+}
+
+const fn default_argon2_parallelism() -> u32 {
+    1
 }
 
 /// # Panics
@@ -155,6 +672,87 @@ pub fn get_public_config() -> PublicConfig {
     data
 }
 
+/// The value we store in the database instead of the code we sent by email.
+#[must_use]
+pub fn hash_token(code: &str) -> String {
+    hex::encode(Sha256::digest(code.as_bytes()))
+}
+
+/// Escape the text the users typed in before including it in a message that is shown as HTML.
+#[must_use]
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Turn a name into the part of a URL: lower case ASCII letters and digits separated by single dashes.
+/// e.g. "Rust Maven!" becomes "rust-maven".
+#[must_use]
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    let mut separator = false;
+    for chr in text.chars() {
+        if chr.is_ascii_alphanumeric() {
+            if separator && !slug.is_empty() {
+                slug.push('-');
+            }
+            separator = false;
+            slug.push(chr.to_ascii_lowercase());
+        } else {
+            separator = true;
+        }
+    }
+    slug
+}
+
+/// The default slug of an event: the year and month of the event followed by the title.
+/// e.g. "2026-11-async-rust".
+#[must_use]
+pub fn event_slug(title: &str, date: &DateTime<Utc>) -> String {
+    let title = slugify(title);
+    let month = date.format("%Y-%m");
+    if title.is_empty() {
+        format!("{month}-event")
+    } else {
+        format!("{month}-{title}")
+    }
+}
+
+/// The groups that share topics with the groups of the user, the ones with the most common topics first.
+/// `groups` are the candidates, the ones the user already belongs to are skipped.
+#[must_use]
+pub fn suggest_groups(mine: &[&Group], groups: Vec<Group>, limit: usize) -> Vec<Group> {
+    let topics = mine
+        .iter()
+        .flat_map(|group| group.topics.iter())
+        .collect::<Vec<_>>();
+
+    let mut suggested = groups
+        .into_iter()
+        .filter(|group| !mine.iter().any(|my_group| my_group.gid == group.gid))
+        .map(|group| {
+            let common = group
+                .topics
+                .iter()
+                .filter(|topic| topics.contains(topic))
+                .count();
+            (common, group)
+        })
+        .filter(|&(common, _)| 0 < common)
+        .collect::<Vec<_>>();
+
+    // sort_by is stable so groups with the same number of common topics keep their order
+    suggested.sort_by_key(|&(common, _)| Reverse(common));
+    suggested
+        .into_iter()
+        .take(limit)
+        .map(|(_, group)| group)
+        .collect()
+}
+
 /// # Panics
 ///
 /// Panics when there is an error.
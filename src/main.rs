@@ -1,5 +1,6 @@
 #![allow(clippy::allow_attributes_without_reason)]
 #![allow(clippy::needless_pass_by_value)]
+#![cfg_attr(test, allow(clippy::all, clippy::pedantic, clippy::restriction))]
 
 #[macro_use]
 extern crate rocket;
@@ -7,6 +8,10 @@ extern crate rocket;
 #[expect(clippy::pub_with_shorthand)]
 pub(crate) mod admin;
 #[expect(clippy::pub_with_shorthand)]
+pub(crate) mod api;
+#[expect(clippy::pub_with_shorthand)]
+pub(crate) mod calendar;
+#[expect(clippy::pub_with_shorthand)]
 pub(crate) mod public;
 #[expect(clippy::pub_with_shorthand)]
 pub(crate) mod web;
@@ -14,40 +19,49 @@ pub(crate) mod web;
 mod notify;
 const MAX_NAME_LEN: usize = 50;
 const MIN_PASSWORD_LENGTH: usize = 6;
-
-use chrono::{DateTime, Duration, Utc};
+const MAX_SUGGESTED_GROUPS: usize = 5;
+/// A week.
+const MAX_EVENT_MINUTES: i64 = 7 * 24 * 60;
+const MIN_TITLE_LEN: usize = 10;
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use core::time::Duration as StdDuration;
+use serde::Serialize;
 use serde_json::json;
 use surrealdb::sql::{Id, Thing};
 
-use rocket::form::Form;
 use rocket::fs::{relative, FileServer};
-use rocket::http::CookieJar;
+use rocket::http::{ContentType, CookieJar, Header, Status};
+use rocket::response::Redirect;
 use rocket::serde::uuid::Uuid;
-use rocket::{fairing::AdHoc, Request, State};
+use rocket::tokio::spawn;
+use rocket::tokio::time::interval;
+use rocket::{fairing::AdHoc, Request, Route, State};
 use rocket_dyn_templates::{context, Template};
 
 use markdown::message;
 
 use regex::Regex;
 
-use pbkdf2::{
-    password_hash::{
-        rand_core::OsRng, PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString,
-    },
-    Pbkdf2,
-};
-
 use surrealdb::engine::remote::ws::Client;
 use surrealdb::Surreal;
 
 use meetings::db;
+use meetings::oauth;
+use meetings::password;
+use meetings::rrule::Rule;
+use meetings::timezone;
+use meetings::totp;
 
 use meetings::{
-    get_public_config, id_user_pairs, sendmail, AuditType, EmailAddress, Event, EventStatus,
-    MyConfig, User,
+    escape_html, event_slug, get_public_config, hash_token, id_user_pairs, sendmail, slugify,
+    suggest_groups, ApiScope, ApiToken, Audit, AuditType, Ban, EmailAddress, Event, EventSeries,
+    EventStatus, Group, GroupRequest, GroupRole, Identity, Invitation, InvitationStatus,
+    JoinRequest, MyConfig, Occurrence, PublicConfig, Token, TokenPurpose, Totp, User,
 };
 
-use web::{LoggedIn, Visitor};
+use web::{ClientInfo, CsrfForm, LoggedIn, Visitor};
 
 #[derive(FromForm)]
 struct ContactMembersForm<'r> {
@@ -62,8 +76,23 @@ struct AddEventForm<'r> {
     date: &'r str,
     location: &'r str,
     description: &'r str,
-    offset: i64,
+    /// The IANA time zone of the date. Empty for the time zone of the group.
+    #[field(default = "")]
+    timezone: &'r str,
+    /// The length of the event in minutes. Empty if it is not known.
+    duration: Option<i64>,
     gid: usize,
+    /// Empty for unlimited.
+    capacity: Option<usize>,
+    /// Only the organizers see the event until it is published.
+    #[field(default = false)]
+    draft: bool,
+    /// Empty for a single event, e.g. `FREQ=WEEKLY;BYDAY=TU` for a recurring one.
+    #[field(default = "")]
+    rrule: &'r str,
+    /// Comma separated list of days (YYYY-MM-DD) skipped by the recurring event.
+    #[field(default = "")]
+    exceptions: &'r str,
 }
 
 #[derive(FromForm)]
@@ -72,8 +101,20 @@ struct EditEventForm<'r> {
     date: &'r str,
     location: &'r str,
     description: &'r str,
-    offset: i64,
+    /// The IANA time zone of the date. Empty to keep the time zone of the event.
+    #[field(default = "")]
+    timezone: &'r str,
+    /// The length of the event in minutes. Empty if it is not known.
+    duration: Option<i64>,
     eid: usize,
+    /// Empty to keep the current slug.
+    #[field(default = "")]
+    slug: &'r str,
+    /// Empty for unlimited.
+    capacity: Option<usize>,
+    /// For events of a recurring series: "this" event only or "following", this and the following events.
+    #[field(default = "this")]
+    scope: &'r str,
 }
 
 #[derive(FromForm)]
@@ -92,12 +133,57 @@ struct ProfileForm<'r> {
     //about: &'r str,
 }
 
+#[derive(FromForm)]
+struct StartGroupForm<'r> {
+    name: &'r str,
+    location: &'r str,
+    description: &'r str,
+    why: &'r str,
+}
+
 #[derive(FromForm)]
 struct GroupForm<'r> {
     gid: usize,
     name: &'r str,
     location: &'r str,
     description: &'r str,
+    requires_approval: bool,
+    /// One question per line.
+    #[field(default = "")]
+    questions: &'r str,
+    /// Empty to keep the current slug.
+    #[field(default = "")]
+    slug: &'r str,
+    topics: Vec<&'r str>,
+    /// The IANA time zone of the new events. Empty to keep the current one.
+    #[field(default = "")]
+    timezone: &'r str,
+}
+
+#[derive(FromForm)]
+struct JoinGroupForm {
+    gid: usize,
+    /// The answers to the questions of the group, in the same order.
+    answers: Vec<String>,
+}
+
+#[derive(FromForm)]
+struct InviteForm<'r> {
+    gid: usize,
+    /// Separated by spaces, commas or new lines.
+    emails: &'r str,
+}
+
+#[derive(FromForm)]
+struct InvitationForm<'r> {
+    gid: usize,
+    id: &'r str,
+}
+
+#[derive(FromForm)]
+struct JoinRequestForm {
+    gid: usize,
+    uid: usize,
 }
 
 #[derive(FromForm)]
@@ -106,6 +192,84 @@ struct LoginForm<'r> {
     password: &'r str,
 }
 
+#[derive(FromForm)]
+struct GroupIdForm {
+    gid: usize,
+}
+
+#[derive(FromForm)]
+struct RemoveMemberForm {
+    gid: usize,
+    uid: usize,
+    #[field(default = "")]
+    reason: String,
+    #[field(default = false)]
+    ban: bool,
+    #[field(default = false)]
+    notify: bool,
+}
+
+#[derive(FromForm)]
+struct SetGroupAdminForm {
+    gid: usize,
+    uid: usize,
+    admin: bool,
+}
+
+#[derive(FromForm)]
+struct TransferGroupForm {
+    gid: usize,
+    uid: usize,
+}
+
+#[derive(FromForm)]
+struct EventIdForm {
+    eid: usize,
+}
+
+#[derive(FromForm)]
+struct CancelEventForm<'r> {
+    eid: usize,
+    reason: &'r str,
+}
+
+/// The id of a database record, e.g. a session or an API token.
+#[derive(FromForm)]
+struct RecordIdForm<'r> {
+    id: &'r str,
+}
+
+#[derive(FromForm)]
+struct ApiTokenForm<'r> {
+    name: &'r str,
+    scopes: Vec<&'r str>,
+}
+
+/// For buttons that send nothing but the CSRF token.
+#[derive(FromForm)]
+struct CsrfOnlyForm<'r> {
+    #[field(name = "csrf_token")]
+    _csrf_token: &'r str,
+}
+
+#[derive(FromForm)]
+struct OAuthCallback<'r> {
+    code: Option<&'r str>,
+    state: Option<&'r str>,
+}
+
+#[derive(FromForm)]
+struct DeleteAccountForm<'r> {
+    /// Empty for the users who only log in with an external provider.
+    #[field(default = "")]
+    password: &'r str,
+}
+
+#[derive(FromForm)]
+struct TotpForm<'r> {
+    code: &'r str,
+}
+
 #[derive(FromForm)]
 struct ResetPasswordForm<'r> {
     email: &'r str,
@@ -139,7 +303,12 @@ fn get_re_name() -> Regex {
 async fn index(dbh: &State<Surreal<Client>>, visitor: Visitor) -> Template {
     let config = get_public_config();
 
-    let events = db::get_events(dbh).await.unwrap();
+    let events = db::get_events(dbh)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(Event::is_public)
+        .collect::<Vec<_>>();
     let groups = db::get_groups(dbh).await.unwrap();
 
     Template::render(
@@ -154,31 +323,74 @@ async fn index(dbh: &State<Surreal<Client>>, visitor: Visitor) -> Template {
     )
 }
 
-#[get("/events")]
-async fn events(dbh: &State<Surreal<Client>>, visitor: Visitor) -> Template {
+#[get("/events?<topic>&<location>")]
+async fn events(
+    dbh: &State<Surreal<Client>>,
+    visitor: Visitor,
+    topic: Option<&str>,
+    location: Option<&str>,
+) -> Template {
     let config = get_public_config();
 
-    let events = db::get_events(dbh).await.unwrap();
+    let topic = topic.filter(|topic| !topic.is_empty());
+    let location = location
+        .map(str::trim)
+        .filter(|location| !location.is_empty());
+
+    let gids = match topic {
+        Some(topic) => Some(
+            db::get_groups_by_topic(dbh, topic)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|group| group.gid)
+                .collect::<Vec<_>>(),
+        ),
+        None => None,
+    };
+
+    let events = db::get_events(dbh)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(Event::is_public)
+        .filter(|event| {
+            gids.as_ref()
+                .is_none_or(|gids| gids.contains(&event.group_id))
+        })
+        .filter(|event| location.is_none_or(|location| location_matches(&event.location, location)))
+        .collect::<Vec<_>>();
+    let topics = db::get_topics(dbh).await.unwrap();
 
     Template::render(
         "events",
         context! {
             title: "Events",
             events,
+            topics,
+            topic,
+            location,
             config,
             visitor,
         },
     )
 }
 
+/// Case insensitive search for the filter in the location e.g. "tel aviv" matches "Tel Aviv, Israel".
+fn location_matches(location: &str, filter: &str) -> bool {
+    location.to_lowercase().contains(&filter.to_lowercase())
+}
+
 #[get("/login")]
-fn login_get(visitor: Visitor) -> Template {
+fn login_get(myconfig: &State<MyConfig>, visitor: Visitor) -> Template {
     let config = get_public_config();
 
     Template::render(
         "login",
         context! {
             title: "Login",
+            magic_link_login: myconfig.magic_link_login,
+            oauth_providers: &myconfig.oauth_providers,
             config,
             visitor,
         },
@@ -190,8 +402,9 @@ async fn login_post(
     cookies: &CookieJar<'_>,
     dbh: &State<Surreal<Client>>,
     myconfig: &State<MyConfig>,
+    client: ClientInfo,
     visitor: Visitor,
-    input: Form<LoginForm<'_>>,
+    input: CsrfForm<LoginForm<'_>>,
 ) -> Template {
     rocket::info!("rocket login: {:?}", input.email);
 
@@ -201,14 +414,31 @@ async fn login_post(
     if !validator::validate_email(&email) {
         return Template::render(
             "message",
-            context! {title: "Invalid email address", message: format!("Invalid email address <b>{}</b>. Please try again", input.email), config, visitor},
+            context! {title: "Invalid email address", message: format!("Invalid email address <b>{}</b>. Please try again", escape_html(input.email)), config, visitor},
         );
     }
 
-    let Some(user) = db::get_user_by_email(dbh, &email).await.unwrap() else {
+    if is_login_locked(dbh, myconfig, &email, &client.ip).await {
+        return Template::render(
+            "message",
+            context! {title: "Too many failed attempts", message: format!("Too many failed login attempts. Please try again in {} minutes.", myconfig.login_lockout_minutes), config, visitor},
+        );
+    }
+
+    let password = input.password.trim().as_bytes();
+
+    let user = db::get_user_by_email(dbh, &email).await.unwrap();
+    let valid = match user.as_ref() {
+        Some(user) if !user.password.is_empty() => password::verify(&user.password, password),
+        // Take as long as a real check so the response time does not reveal the registered addresses
+        _ => password::verify_dummy(myconfig, password),
+    };
+
+    let Some(user) = user.filter(|_| valid) else {
+        login_failed(dbh, myconfig, &email, &client.ip).await;
         return Template::render(
             "message",
-            context! {title: "No such user", message: format!("No user with address <b>{}</b>. Please try again", input.email), config,visitor},
+            context! {title: "Invalid email or password", message: "Invalid email or password. Please try again", config, visitor},
         );
     };
 
@@ -221,202 +451,389 @@ async fn login_post(
 
     rocket::info!("email: {}", user.email);
 
-    let password = input.password.trim().as_bytes();
+    if password::needs_rehash(myconfig, &user.password) {
+        let hashed_password = password::hash(myconfig, password);
+        db::save_password(dbh, user.uid, &hashed_password)
+            .await
+            .unwrap();
+    }
 
-    let parsed_hash = PasswordHash::new(&user.password).unwrap();
+    finish_login(cookies, dbh, myconfig, &client, &user, config, visitor).await
+}
 
-    if Pbkdf2.verify_password(password, &parsed_hash).is_err() {
+/// The user proved they own the account. Ask for the second factor if it is enabled, otherwise log them in.
+async fn finish_login(
+    cookies: &CookieJar<'_>,
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    client: &ClientInfo,
+    user: &User,
+    config: PublicConfig,
+    visitor: Visitor,
+) -> Template {
+    if db::get_totp(dbh, user.uid)
+        .await
+        .unwrap()
+        .is_some_and(|totp| totp.enabled)
+    {
+        web::start_second_factor(cookies, user.uid);
         return Template::render(
-            "message",
-            context! {title: "Invalid password", message: "Invalid password", config, visitor},
+            "login_totp",
+            context! {title: "Two-factor authentication", config, visitor},
         );
     }
 
-    cookies.add_private(("meet-os", user.email.clone())); // TODO this should be the user ID, right?
+    db::clear_login_failures(dbh, &user.email).await.unwrap();
+    web::start_session(cookies, dbh, client, user).await;
 
     // It seems despite calling add_private, the cookies will still return the old value so
     // for now we have a separate constructor for the Visitor
     #[expect(clippy::shadow_unrelated)]
-    let visitor = Visitor::new_after_login(&email, dbh, myconfig).await;
+    let visitor = Visitor::new_after_login(cookies, &user.email, dbh, myconfig).await;
     Template::render(
         "message",
         context! {title: "Welcome back", message: r#"Welcome back. <a href="/profile">profile</a>"#, config, visitor},
     )
 }
 
-#[get("/logout")]
-fn logout_get(cookies: &CookieJar<'_>, _visitor: LoggedIn) -> Template {
-    cookies.remove_private("meet-os");
+#[get("/oauth/<name>/login")]
+fn oauth_login(
+    cookies: &CookieJar<'_>,
+    myconfig: &State<MyConfig>,
+    visitor: Visitor,
+    name: &str,
+) -> Result<Redirect, Box<Template>> {
     let config = get_public_config();
 
-    let visitor = Visitor::new_after_logout();
-
-    Template::render(
-        "message",
-        context! {title: "Logged out", message: "We have logged you out from the system", config, visitor},
-    )
-}
-
-#[get("/reset-password")]
-fn reset_password_get(visitor: Visitor) -> Template {
-    let config = get_public_config();
+    let Some(provider) = myconfig.get_oauth_provider(name) else {
+        return Err(Box::new(Template::render(
+            "message",
+            context! {title: "No such provider", message: "We don't support login with this provider.", config, visitor},
+        )));
+    };
 
-    Template::render(
-        "reset_password",
-        context! {
-            title: "Reset password",
-            config,
-            visitor,
-        },
-    )
+    let state = Uuid::new_v4().to_string();
+    let verifier = oauth::generate_verifier();
+    web::start_oauth(cookies, name, &state, &verifier);
+
+    let redirect_uri = format!("{}/oauth/{name}/callback", myconfig.base_url);
+    Ok(Redirect::to(oauth::authorize_url(
+        provider,
+        &redirect_uri,
+        &state,
+        &verifier,
+    )))
 }
 
-#[post("/reset-password", data = "<input>")]
-async fn reset_password_post(
+/// The provider sends the visitor back here. If the visitor is logged in we link the account,
+/// otherwise we log them in, creating a new user if necessary.
+#[get("/oauth/<name>/callback?<params..>")]
+async fn oauth_callback(
+    cookies: &CookieJar<'_>,
     dbh: &State<Surreal<Client>>,
     myconfig: &State<MyConfig>,
+    client: ClientInfo,
     visitor: Visitor,
-    input: Form<ResetPasswordForm<'_>>,
+    name: &str,
+    params: OAuthCallback<'_>,
 ) -> Template {
-    rocket::info!("reset password for: {:?}", input.email);
     let config = get_public_config();
 
-    let email = input.email.to_lowercase().trim().to_owned();
+    let Some(provider) = myconfig.get_oauth_provider(name) else {
+        return Template::render(
+            "message",
+            context! {title: "No such provider", message: "We don't support login with this provider.", config, visitor},
+        );
+    };
 
-    let Some(user) = db::get_user_by_email(dbh, &email).await.unwrap() else {
-        // TODO: we should probably limit the number of such request from the same visitor so a bot won't be able to try to guess email addresses
+    let Some((_, _, verifier)) = web::take_oauth(cookies)
+        .filter(|saved| saved.0 == name && params.state == Some(saved.1.as_str()))
+    else {
         return Template::render(
             "message",
-            context! {title: "No such user", message: format!("No user with address <b>{}</b>. Please try again", input.email), config, visitor},
+            context! {title: "Login expired", message: r#"Please <a href="/login">login</a> again."#, config, visitor},
         );
     };
 
-    let process = "reset";
-    let code = Uuid::new_v4();
-    let uid = user.uid;
+    let Some(code) = params.code else {
+        return Template::render(
+            "message",
+            context! {title: "Login cancelled", message: format!(r#"The login with {} was cancelled. Please <a href="/login">login</a> again."#, provider.display_name), config, visitor},
+        );
+    };
 
-    db::add_login_code_to_user(dbh, &email, process, code.to_string().as_str())
-        .await
-        .unwrap();
+    let redirect_uri = format!("{}/oauth/{name}/callback", myconfig.base_url);
+    let info = match oauth::exchange_code(provider, &redirect_uri, code, &verifier).await {
+        Ok(access_token) => oauth::fetch_userinfo(provider, &access_token).await,
+        Err(err) => Err(err),
+    };
+    let info = match info {
+        Ok(info) => info,
+        Err(err) => {
+            rocket::error!("OAuth login with {name} failed: {err}");
+            return Template::render(
+                "message",
+                context! {title: "Login failed", message: format!(r#"We could not log you in with {}. Please <a href="/login">try again</a>."#, provider.display_name), config, visitor},
+            );
+        }
+    };
 
-    let base_url = &myconfig.base_url;
+    let identity = db::get_identity(dbh, name, &info.subject).await.unwrap();
 
-    let subject = "Reset your Meet-OS password!";
-    let text = format!(
-        r#"Hi,
-    <p>
-    Someone asked to reset the password on the Meet-OS web site connected to this email address.
-    If it was you, please <a href="{base_url}/save-password/{uid}/{code}">click on this link</a> to set your new password.
-    <p>
-    <p>
-    If it was not you, we would like to apologize. You don't need to do anything...
-    "#
-    );
+    if let Some(user) = visitor.user.as_ref() {
+        return link_identity(
+            dbh,
+            provider,
+            user,
+            identity.as_ref(),
+            &info,
+            config,
+            &visitor,
+        )
+        .await;
+    }
 
-    let from = EmailAddress {
-        name: myconfig.from_name.clone(),
-        email: myconfig.from_email.clone(),
-    };
-    let to_address = &EmailAddress {
-        name: user.name.clone(),
-        email: user.email.clone(),
+    if let Some(identity) = identity {
+        let user = db::get_user_by_uid(dbh, identity.uid)
+            .await
+            .unwrap()
+            .unwrap();
+        if let Some(username) = info.username.as_ref() {
+            db::set_user_handle(dbh, user.uid, provider.kind, username)
+                .await
+                .unwrap();
+        }
+        return finish_login(cookies, dbh, myconfig, &client, &user, config, visitor).await;
+    }
+
+    let Some(email) = info.email.as_ref().map(|email| email.to_lowercase()) else {
+        return Template::render(
+            "message",
+            context! {title: "No email address", message: format!(r#"We did not receive a verified email address from {}. Please <a href="/register">register</a> and then link your account from your profile."#, provider.display_name), config, visitor},
+        );
     };
 
-    sendmail(myconfig, &from, to_address, subject, &text).await;
-    //notify::admin_user_asked_to_reset_password(myconfig, &user).await;
+    if db::get_user_by_email(dbh, &email).await.unwrap().is_some() {
+        return Template::render(
+            "message",
+            context! {title: "Account exists", message: format!(r#"There is already an account with the email address <b>{email}</b>. Please <a href="/login">login</a> and link your {} account from your profile."#, provider.display_name, email = escape_html(&email)), config, visitor},
+        );
+    }
+
+    let user = add_oauth_user(dbh, provider, email, &info).await;
+    notify::admin_new_user_verified(myconfig, &user).await;
+
+    finish_login(cookies, dbh, myconfig, &client, &user, config, visitor).await
+}
+
+/// Link the account at the provider to the user who is already logged in.
+async fn link_identity(
+    dbh: &Surreal<Client>,
+    provider: &oauth::Provider,
+    user: &User,
+    identity: Option<&Identity>,
+    info: &oauth::UserInfo,
+    config: PublicConfig,
+    visitor: &Visitor,
+) -> Template {
+    if let Some(identity) = identity {
+        let message = if identity.uid == user.uid {
+            format!("Your {} account is already linked.", provider.display_name)
+        } else {
+            format!(
+                "This {} account is already linked to another user.",
+                provider.display_name
+            )
+        };
+        return Template::render(
+            "message",
+            context! {title: "Already linked", message, config, visitor},
+        );
+    }
 
+    add_identity(dbh, provider, user.uid, info).await;
     Template::render(
         "message",
-        context! {title: "We sent you an email", message: format!("We sent you an email to <b>{}</b> Please click on the link to reset your password.", to_address.email), config, visitor},
+        context! {title: "Account linked", message: format!(r#"Your {} account was linked. Check your <a href="/profile">profile</a>."#, provider.display_name), config, visitor},
     )
 }
 
-#[get("/save-password/<uid>/<code>")]
-async fn save_password_get(
-    dbh: &State<Surreal<Client>>,
-    visitor: Visitor,
+/// Register a new user based on the information we received from the provider.
+async fn add_oauth_user(
+    dbh: &Surreal<Client>,
+    provider: &oauth::Provider,
+    email: String,
+    info: &oauth::UserInfo,
+) -> User {
+    let uid = db::increment(dbh, "user").await.unwrap();
+    let utc: DateTime<Utc> = Utc::now();
+    let user = User {
+        id: Thing::from(("user", Id::ulid())),
+        uid,
+        name: info
+            .name
+            .clone()
+            .or_else(|| info.username.clone())
+            .unwrap_or_else(|| email.clone()),
+        email,
+        // There is no password. The user can set one using the reset password process.
+        password: String::new(),
+        registration_date: utc,
+        verification_date: Some(utc),
+        verified: true,
+        github: None,
+        gitlab: None,
+        linkedin: None,
+        about: None,
+    };
+    db::add_user(dbh, &user).await.unwrap();
+    add_identity(dbh, provider, uid, info).await;
+
+    user
+}
+
+async fn add_identity(
+    dbh: &Surreal<Client>,
+    provider: &oauth::Provider,
     uid: usize,
-    code: &str,
+    info: &oauth::UserInfo,
+) {
+    let identity = Identity {
+        id: Thing::from(("identity", Id::ulid())),
+        uid,
+        provider: provider.name.clone(),
+        subject: info.subject.clone(),
+        username: info.username.clone(),
+        created: Utc::now(),
+    };
+    db::add_identity(dbh, &identity).await.unwrap();
+
+    if let Some(username) = info.username.as_ref() {
+        db::set_user_handle(dbh, uid, provider.kind, username)
+            .await
+            .unwrap();
+    }
+}
+
+#[post("/oauth/<name>/unlink", data = "<_input>")]
+async fn oauth_unlink(
+    dbh: &State<Surreal<Client>>,
+    visitor: LoggedIn,
+    name: &str,
+    _input: CsrfForm<CsrfOnlyForm<'_>>,
 ) -> Template {
-    rocket::info!("save-password for uid={uid} with code: {code}");
     let config = get_public_config();
+    let user = visitor.user.clone().unwrap();
 
-    let Some(user) = db::get_user_by_uid(dbh, uid).await.unwrap() else {
+    let identities = db::get_identities_by_uid(dbh, user.uid).await.unwrap();
+    let Some(identity) = identities.iter().find(|identity| identity.provider == name) else {
         return Template::render(
             "message",
-            context! {title: "Invalid id", message: format!("Invalid id <b>{uid}</b>"), config, visitor},
+            context! {title: "Not linked", message: "There is no linked account of this provider.", config, visitor},
         );
     };
 
-    if user.code != code {
+    if user.password.is_empty() && identities.len() == 1 {
         return Template::render(
             "message",
-            context! {title: "Invalid code", message: format!("Invalid code <b>{code}</b>"), config, visitor},
+            context! {title: "Cannot unlink", message: r#"This is the only way you can login. Please <a href="/reset-password">set a password</a> first."#, config, visitor},
         );
     }
 
+    db::delete_identity(dbh, user.uid, &identity.provider)
+        .await
+        .unwrap();
+
     Template::render(
-        "save_password",
-        context! {
-            title: "Type in your new password",
-            config,
-            visitor,
-            uid,
-            code,
-        },
+        "message",
+        context! {title: "Account unlinked", message: format!(r#"The <b>{name}</b> account was unlinked. Check your <a href="/profile">profile</a>."#, name = escape_html(name)), config, visitor},
     )
 }
 
-#[post("/save-password", data = "<input>")]
-async fn save_password_post(
+#[post("/login-by-email", data = "<input>")]
+async fn login_by_email_post(
     dbh: &State<Surreal<Client>>,
     myconfig: &State<MyConfig>,
+    client: ClientInfo,
     visitor: Visitor,
-    input: Form<SavePasswordForm<'_>>,
+    input: CsrfForm<ResetPasswordForm<'_>>,
 ) -> Template {
+    rocket::info!("login by email: {:?}", input.email);
     let config = get_public_config();
-    let uid = input.uid;
-    let code = input.code;
 
-    let Some(user) = db::get_user_by_uid(dbh, uid).await.unwrap() else {
+    if !myconfig.magic_link_login {
         return Template::render(
             "message",
-            context! {title: "Invalid userid", message: format!("Invalid userid <b>{uid}</b>."), config, visitor},
+            context! {title: "Not available", message: r#"Login by email is not available. Please <a href="/login">login</a> with your password."#, config, visitor},
         );
-    };
+    }
 
-    if code != user.code {
-        rocket::warn!("Invalid code {code} for uid {uid}");
+    let email = input.email.to_lowercase().trim().to_owned();
+    if !validator::validate_email(&email) {
         return Template::render(
             "message",
-            context! {title: "Invalid code", message: format!("Invalid code <b>{code}</b>."), config, visitor},
+            context! {title: "Invalid email address", message: format!("Invalid email address <b>{}</b>. Please try again", escape_html(input.email)), config, visitor},
         );
     }
 
-    let password = input.password.trim().as_bytes();
-    if password.len() < MIN_PASSWORD_LENGTH {
+    if is_login_locked(dbh, myconfig, &email, &client.ip).await {
         return Template::render(
             "message",
-            context! {title: "Invalid password", message: format!("The password must be at least {MIN_PASSWORD_LENGTH} characters long."), config, visitor},
+            context! {title: "Too many failed attempts", message: format!("Too many failed login attempts. Please try again in {} minutes.", myconfig.login_lockout_minutes), config, visitor},
+        );
+    }
+    // Counted separately from the failed logins, so asking for links cannot lock the account
+    #[expect(clippy::arithmetic_side_effects)]
+    let since = Utc::now() - Duration::minutes(myconfig.magic_link_token_minutes);
+    let requests = db::count_magic_link_requests(dbh, &email, since)
+        .await
+        .unwrap();
+    if myconfig.magic_link_max_requests <= requests {
+        return Template::render(
+            "message",
+            context! {title: "Too many requests", message: format!("Too many login links were requested for this address. Please try again in {} minutes.", myconfig.magic_link_token_minutes), config, visitor},
         );
     }
+    db::add_magic_link_request(dbh, &email).await.unwrap();
 
-    let salt = SaltString::generate(&mut OsRng);
-    let hashed_password = Pbkdf2.hash_password(password, &salt).unwrap().to_string();
+    // The same answer whether the address is registered or not
+    let sent = Template::render(
+        "message",
+        context! {title: "We sent you an email", message: "If the address belongs to a verified account we sent an email to it. Please click on the link in the email to login.", config, visitor},
+    );
 
-    db::save_password(dbh, uid, &hashed_password).await.unwrap();
-    db::remove_code(dbh, uid).await.unwrap();
+    let Some(user) = db::get_user_by_email(dbh, &email)
+        .await
+        .unwrap()
+        .filter(|user| user.verified)
+    else {
+        return sent;
+    };
 
-    let base_url = &myconfig.base_url;
+    let uid = user.uid;
+    let code = create_token(
+        dbh,
+        myconfig,
+        uid,
+        TokenPurpose::MagicLink,
+        None,
+        None,
+        None,
+    )
+    .await;
 
-    let subject = "Your Meet-OS password was reset!";
+    let base_url = &myconfig.base_url;
+    let minutes = myconfig.magic_link_token_minutes;
+    let subject = "Login to Meet-OS";
     let text = format!(
         r#"Hi,
     <p>
-    The password of your <a href="{base_url}/">Meet-OS</a> account was reset. Please log in.
+    Someone asked to login to the Meet-OS web site using this email address.
+    If it was you, please <a href="{base_url}/login-by-email/{uid}/{code}">click on this link</a> to login.
+    The link can be used only once and it is valid for {minutes} minutes.
     <p>
-    If it was not done by you, please <a href="{base_url}">reset your password</a> and contact us ASAP!
-    ";
+    <p>
+    If it was not you, we would like to apologize. You don't need to do anything...
     "#
     );
 
@@ -430,1048 +847,5009 @@ async fn save_password_post(
     };
 
     sendmail(myconfig, &from, to_address, subject, &text).await;
-    //notify::admin_user_asked_to_reset_password(myconfig, &user).await;
 
-    Template::render(
-        "message",
-        context! {title: "Password updated", message: "Your password was updated.", config, visitor},
-    )
+    sent
 }
 
-#[get("/register")]
-fn register_get(visitor: Visitor) -> Template {
+/// Mail clients and link scanners open the links, so only the button logs in.
+#[get("/login-by-email/<uid>/<code>")]
+fn login_by_email_get(
+    myconfig: &State<MyConfig>,
+    visitor: Visitor,
+    uid: usize,
+    code: &str,
+) -> Template {
     let config = get_public_config();
 
+    if !myconfig.magic_link_login {
+        return Template::render(
+            "message",
+            context! {title: "Not available", message: r#"Login by email is not available. Please <a href="/login">login</a> with your password."#, config, visitor},
+        );
+    }
+
     Template::render(
-        "register",
+        "confirm",
         context! {
-            title: "Register",
+            title: "Login",
+            question: "Do you want to login to Meet-OS?",
+            action: format!("/login-by-email/{uid}/{code}"),
+            button: "Login",
             config,
             visitor,
-            min_password_length: MIN_PASSWORD_LENGTH,
         },
     )
 }
 
-#[post("/register", data = "<input>")]
-async fn register_post(
+#[post("/login-by-email/<uid>/<code>", data = "<_input>")]
+#[expect(clippy::too_many_arguments)]
+async fn login_by_email_confirm_post(
+    cookies: &CookieJar<'_>,
     dbh: &State<Surreal<Client>>,
     myconfig: &State<MyConfig>,
+    client: ClientInfo,
     visitor: Visitor,
-    input: Form<RegistrationForm<'_>>,
+    uid: usize,
+    code: &str,
+    _input: CsrfForm<CsrfOnlyForm<'_>>,
 ) -> Template {
-    rocket::info!("rocket input: {:?} {:?}", input.email, input.name);
-
+    rocket::info!("login-by-email for uid={uid}");
     let config = get_public_config();
 
-    let name = input.name.trim().to_owned();
-    if MAX_NAME_LEN < name.len() {
-        return Template::render(
-            "message",
-            context! {title: "Name is too long", message: format!("Name is too long. Max {MAX_NAME_LEN} while the current name is {} long. Please try again.", name.len()), config, visitor},
-        );
-    }
-
-    let re_name = get_re_name();
-
-    if !re_name.is_match(&name) {
-        return Template::render(
-            "message",
-            context! {title: "Invalid character", message: format!(r#"The name '{name}' contains a character that we currently don't accept. Use Latin letters for now and comment on <a href="https://github.com/szabgab/meet-os.rs/issues/38">this issue</a> where this topic is discussed."#), config, visitor},
-        );
-    }
-
-    // email: lowerase, remove spaces from sides
-    // validate format @
-    let email = input.email.to_lowercase().trim().to_owned();
-    if !validator::validate_email(&email) {
+    if !myconfig.magic_link_login {
         return Template::render(
             "message",
-            context! {title: "Invalid email address", message: format!("Invalid email address <b>{}</b> Please try again", input.email), config, visitor},
+            context! {title: "Not available", message: r#"Login by email is not available. Please <a href="/login">login</a> with your password."#, config, visitor},
         );
     }
 
-    let password = input.password.trim().as_bytes();
-    if password.len() < MIN_PASSWORD_LENGTH {
+    let Some(user) = db::get_user_by_uid(dbh, uid).await.unwrap() else {
         return Template::render(
             "message",
-            context! {title: "Invalid password", message: format!("The password must be at least {MIN_PASSWORD_LENGTH} characters long."), config, visitor},
+            context! {title: "Invalid id", message: format!("Invalid id <b>{uid}</b>"), config, visitor},
         );
-    }
-    let process = "register";
-    let code = Uuid::new_v4();
-    let salt = SaltString::generate(&mut OsRng);
-    let hashed_password = Pbkdf2.hash_password(password, &salt).unwrap().to_string();
-
-    let uid = db::increment(dbh, "user").await.unwrap();
-    let utc: DateTime<Utc> = Utc::now();
-    let id = Id::ulid();
-    let user = User {
-        id: Thing::from(("user", id.clone())),
-        uid,
-        name: name.clone(),
-        email: email.clone(),
-        password: hashed_password,
-        process: process.to_owned(),
-        code: format!("{code}"),
-        registration_date: utc,
-        verification_date: None,
-        code_generated_date: Some(utc),
-        verified: false,
-        github: None,
-        gitlab: None,
-        linkedin: None,
-        about: None,
     };
-    match db::add_user(dbh, &user).await {
-        Ok(result) => result,
-        Err(err) => {
-            rocket::info!("Error while trying to add user {err}");
-            // TODO special reporting when the email is already in the system
+
+    match check_token(dbh, uid, code, TokenPurpose::MagicLink).await {
+        Ok(_token) => {}
+        Err(TokenProblem::Invalid) => {
             return Template::render(
                 "message",
-                context! {title: "Registration failed", message: format!("Could not register <b>{}</b>.", user.email), config, visitor},
+                context! {title: "Invalid code", message: "Invalid code", config, visitor},
             );
         }
+        Err(problem) => {
+            return token_problem_page(problem, "/login", config, visitor);
+        }
     }
 
-    let base_url = &myconfig.base_url;
-    let subject = "Verify your Meet-OS registration!";
-    let text = format!(
-        r#"Hi,
-    <p>
-    Someone used your email to register on the Meet-OS web site.
-    If it was you, please <a href="{base_url}/verify-email/{id}/{code}">click on this link</a> to verify your email address.
-    <p>
-    <p>
-    If it was not you, we would like to apologize. You don't need to do anything. We'll discard your registration if it is not validated.
-    "#
-    );
-
-    let from = EmailAddress {
-        name: myconfig.from_name.clone(),
-        email: myconfig.from_email.clone(),
-    };
-    let to_address = &EmailAddress { name, email };
-
-    sendmail(myconfig, &from, to_address, subject, &text).await;
-    notify::admin_new_user_registered(myconfig, &user).await;
+    if db::use_token(dbh, &hash_token(code))
+        .await
+        .unwrap()
+        .is_none()
+    {
+        return token_problem_page(TokenProblem::Used, "/login", config, visitor);
+    }
 
-    Template::render(
-        "message",
-        context! {title: "We sent you an email", message: format!("We sent you an email to <b>{}</b> Please check your inbox and verify your email address.", to_address.email), config, visitor},
-    )
+    finish_login(cookies, dbh, myconfig, &client, &user, config, visitor).await
 }
 
-// TODO limit the possible values for the process to register and login
-#[get("/verify-email/<uid>/<code>")]
-async fn verify_email(
+#[post("/login-totp", data = "<input>")]
+async fn login_totp_post(
     cookies: &CookieJar<'_>,
     dbh: &State<Surreal<Client>>,
     myconfig: &State<MyConfig>,
+    client: ClientInfo,
     visitor: Visitor,
-    uid: String,
-    code: &str,
+    input: CsrfForm<TotpForm<'_>>,
 ) -> Template {
-    rocket::info!("verify-email of uid='{uid}' using code='{code}'");
-
     let config = get_public_config();
 
-    let Some(user) = db::get_user_by_id_str(dbh, &uid).await.unwrap() else {
+    let Some(uid) = web::get_second_factor_uid(cookies) else {
         return Template::render(
             "message",
-            context! {title: "Invalid id", message: format!("Invalid id <b>{uid}</b>"), config, visitor},
+            context! {title: "Login expired", message: r#"Please <a href="/login">login</a> again."#, config, visitor},
         );
     };
 
-    if code != user.code {
-        rocket::warn!("Received code='{code}' Expected code='{}'", user.code);
+    // The account was deleted or two-factor authentication was disabled since the password step
+    let (Some(user), Some(totp)) = (
+        db::get_user_by_uid(dbh, uid).await.unwrap(),
+        db::get_totp(dbh, uid)
+            .await
+            .unwrap()
+            .filter(|totp| totp.enabled),
+    ) else {
+        web::end_second_factor(cookies);
+        return Template::render(
+            "message",
+            context! {title: "Login expired", message: r#"Please <a href="/login">login</a> again."#, config, visitor},
+        );
+    };
+
+    if is_login_locked(dbh, myconfig, &user.email, &client.ip).await {
+        web::end_second_factor(cookies);
         return Template::render(
             "message",
-            context! {title: "Invalid code", message: format!("Invalid code <b>{code}</b>"), config, visitor},
+            context! {title: "Too many failed attempts", message: format!("Too many failed login attempts. Please try again in {} minutes.", myconfig.login_lockout_minutes), config, visitor},
         );
     }
 
-    db::set_user_verified(dbh, user.uid).await.unwrap();
-    db::remove_code(dbh, user.uid).await.unwrap();
+    if !check_second_factor(dbh, &totp, &user.email, input.code).await {
+        login_failed(dbh, myconfig, &user.email, &client.ip).await;
+        return Template::render(
+            "login_totp",
+            context! {title: "Two-factor authentication", error: "Invalid code. Please try again", config, visitor},
+        );
+    }
 
-    rocket::info!("verified code for '{}'", user.email);
-    cookies.add_private(("meet-os", user.email.clone())); // TODO this should be the user ID, right?
-    notify::admin_new_user_verified(myconfig, &user).await;
+    web::end_second_factor(cookies);
+    db::clear_login_failures(dbh, &user.email).await.unwrap();
+    web::start_session(cookies, dbh, &client, &user).await;
 
-    // take into account the newly set cookie value
     #[expect(clippy::shadow_unrelated)]
-    let visitor = Visitor::new_after_login(&user.email, dbh, myconfig).await;
+    let visitor = Visitor::new_after_login(cookies, &user.email, dbh, myconfig).await;
+    Template::render(
+        "message",
+        context! {title: "Welcome back", message: r#"Welcome back. <a href="/profile">profile</a>"#, config, visitor},
+    )
+}
+
+/// Accept either the current code of the authenticator app or one of the unused recovery codes.
+async fn check_second_factor(dbh: &Surreal<Client>, totp: &Totp, email: &str, code: &str) -> bool {
+    let code = code.trim();
+
+    if code != totp.last_code && totp::check_code(&totp.secret, email, code) {
+        db::set_totp_last_code(dbh, totp.uid, code).await.unwrap();
+        return true;
+    }
+
+    let hash = totp::hash_code(code);
+    if totp.recovery_codes.contains(&hash) {
+        db::remove_recovery_code(dbh, totp.uid, &hash)
+            .await
+            .unwrap();
+        return true;
+    }
+
+    false
+}
+
+#[get("/totp-setup")]
+async fn totp_setup_get(dbh: &State<Surreal<Client>>, visitor: LoggedIn) -> Template {
+    let config = get_public_config();
+    let user = visitor.user.clone().unwrap();
+
+    if db::get_totp(dbh, user.uid)
+        .await
+        .unwrap()
+        .is_some_and(|totp| totp.enabled)
+    {
+        return totp_already_enabled_page(config, visitor);
+    }
+
+    Template::render(
+        "totp_setup",
+        context! {title: "Enable two-factor authentication", config, visitor},
+    )
+}
 
+fn totp_already_enabled_page(config: PublicConfig, visitor: LoggedIn) -> Template {
     Template::render(
         "message",
-        context! {title: "Thank you for registering", message: "Your email was verified.", config, visitor},
+        context! {title: "Two-factor authentication is enabled", message: r#"Two-factor authentication is already enabled. You can disable it on your <a href="/profile">profile</a>."#, config, visitor},
     )
 }
 
-#[get("/join-group?<gid>")]
-async fn join_group_get(
+/// Generate a new secret and show it as a QR code. It is enabled once the user types in a code.
+#[post("/totp-setup/start", data = "<_input>")]
+async fn totp_setup_start_post(
     dbh: &State<Surreal<Client>>,
-    myconfig: &State<MyConfig>,
     visitor: LoggedIn,
-    gid: usize,
+    _input: CsrfForm<CsrfOnlyForm<'_>>,
 ) -> Template {
     let config = get_public_config();
+    let user = visitor.user.clone().unwrap();
 
-    let group = db::get_group_by_gid(dbh, gid).await.unwrap();
-    if group.is_none() {
-        return Template::render(
-            "message",
-            context! {title: "No such group", message: format!("There is not group with id <b>{gid}</b>"), config, visitor},
-        );
+    if db::get_totp(dbh, user.uid)
+        .await
+        .unwrap()
+        .is_some_and(|totp| totp.enabled)
+    {
+        return totp_already_enabled_page(config, visitor);
     }
-    let group = group.unwrap();
 
+    let secret = totp::generate_secret();
+    db::add_pending_totp(dbh, user.uid, &secret).await.unwrap();
+    let qr_code = totp::qr_code_svg(&secret, &user.email);
+
+    Template::render(
+        "totp_setup",
+        context! {title: "Enable two-factor authentication", secret, qr_code, config, visitor},
+    )
+}
+
+#[post("/totp-setup", data = "<input>")]
+async fn totp_setup_post(
+    dbh: &State<Surreal<Client>>,
+    visitor: LoggedIn,
+    input: CsrfForm<TotpForm<'_>>,
+) -> Template {
+    let config = get_public_config();
     let user = visitor.user.clone().unwrap();
-    let uid = visitor.user.clone().unwrap().uid;
-    if uid == group.owner {
+
+    let Some(totp) = db::get_totp(dbh, user.uid).await.unwrap() else {
         return Template::render(
             "message",
-            context! {title: "You are the owner of this group", message: "You cannot join a group you own.", config, visitor},
+            context! {title: "No pending setup", message: r#"Please <a href="/totp-setup">start again</a>."#, config, visitor},
         );
+    };
+
+    if totp.enabled {
+        return totp_already_enabled_page(config, visitor);
     }
 
-    let member = db::get_membership(dbh, gid, uid).await.unwrap();
-    if member.is_some() {
+    let code = input.code.trim();
+    if !totp::check_code(&totp.secret, &user.email, code) {
         return Template::render(
             "message",
-            context! {title: "You are already a member of this group", message: format!(r#"You are already a member of the <a href="/group/{gid}">{}</a> group"#, group.name), config, visitor},
+            context! {title: "Invalid code", message: r#"The code did not match. Please <a href="/totp-setup">try again</a>."#, config, visitor},
         );
     }
 
-    db::join_group(dbh, gid, uid).await.unwrap();
-    db::audit(
-        dbh,
-        AuditType::JoinGroup,
-        json!({
-            "user": {
-                "id": uid,
-                "name": user.name,
-            },
-            "group": {
-                "id": gid,
-                "name": group.name,
-            }
-        }),
-    )
-    .await
-    .unwrap();
-    notify::owner_user_joined_group(dbh, myconfig, &user, &group).await;
+    let (recovery_codes, hashes) = totp::generate_recovery_codes();
+    db::enable_totp(dbh, user.uid, hashes, code).await.unwrap();
+
     Template::render(
-        "message",
-        context! {title: "Membership", message: format!(r#"User added to <a href="/group/{gid}">group</a>"#), config, visitor},
+        "totp_recovery",
+        context! {title: "Two-factor authentication enabled", recovery_codes, config, visitor},
     )
 }
 
-#[get("/leave-group?<gid>")]
-async fn leave_group_get(
+#[post("/totp-disable", data = "<input>")]
+async fn totp_disable_post(
     dbh: &State<Surreal<Client>>,
-    myconfig: &State<MyConfig>,
     visitor: LoggedIn,
-    gid: usize,
+    input: CsrfForm<TotpForm<'_>>,
 ) -> Template {
     let config = get_public_config();
+    let user = visitor.user.clone().unwrap();
 
-    let group = db::get_group_by_gid(dbh, gid).await.unwrap();
-    if group.is_none() {
+    let Some(totp) = db::get_totp(dbh, user.uid)
+        .await
+        .unwrap()
+        .filter(|totp| totp.enabled)
+    else {
         return Template::render(
             "message",
-            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+            context! {title: "Two-factor authentication is not enabled", message: r#"Two-factor authentication is not enabled. Check your <a href="/profile">profile</a>."#, config, visitor},
         );
-    }
-    let group = group.unwrap();
+    };
 
-    let user = visitor.user.clone().unwrap();
-    let uid = visitor.user.clone().unwrap().uid;
-    if uid == group.owner {
+    if !check_second_factor(dbh, &totp, &user.email, input.code).await {
         return Template::render(
             "message",
-            context! {title: "You are the owner of this group", message: "You cannot leave a group you own.", config, visitor},
+            context! {title: "Invalid code", message: r#"The code did not match. Check your <a href="/profile">profile</a>."#, config, visitor},
         );
     }
 
-    let member = db::get_membership(dbh, gid, uid).await.unwrap();
-    if member.is_none() {
-        return Template::render(
-            "message",
-            context! {title: "You are not a member of this group", message: "You cannot leave a group where you are not a member.", config, visitor},
-        );
-    }
-
-    db::leave_group(dbh, gid, uid).await.unwrap();
-    notify::owner_user_left_group(dbh, myconfig, &user, &group).await;
-    db::audit(
-        dbh,
-        AuditType::LeaveGroup,
-        json!({
-            "user": {
-                "id": uid,
-                "name": user.name,
-            },
-            "group": {
-                "id": gid,
-                "name": group.name,
-            },
-        }),
-    )
-    .await
-    .unwrap();
+    db::delete_totp(dbh, user.uid).await.unwrap();
 
     Template::render(
         "message",
-        context! {title: "Membership", message: format!(r#"User removed from <a href="/group/{gid}">group</a>"#), config, visitor},
+        context! {title: "Two-factor authentication disabled", message: r#"Two-factor authentication was disabled. Check your <a href="/profile">profile</a>."#, config, visitor},
     )
 }
 
-#[get("/rsvp-yes-event?<eid>")]
-async fn rsvp_yes_event_get(
-    dbh: &State<Surreal<Client>>,
-    myconfig: &State<MyConfig>,
-    visitor: LoggedIn,
-    eid: usize,
-) -> Template {
-    let config = get_public_config();
+fn login_failure_window(myconfig: &MyConfig) -> DateTime<Utc> {
+    #[expect(clippy::arithmetic_side_effects)]
+    let since = Utc::now() - Duration::minutes(myconfig.login_lockout_minutes);
+    since
+}
 
-    let Some(event) = db::get_event_by_eid(dbh, eid).await.unwrap() else {
-        return Template::render(
-            "message",
-            context! {title: "No such event", message: "No such event", config, visitor},
-        );
-    };
+async fn is_login_locked(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    email: &str,
+    ip: &str,
+) -> bool {
+    let since = login_failure_window(myconfig);
 
-    let gid = event.group_id;
-    let group = db::get_group_by_gid(dbh, gid).await.unwrap().unwrap();
+    let by_email = db::count_login_failures_by_email(dbh, email, since)
+        .await
+        .unwrap();
+    // The clients with an unknown address would share the limit, and one of them could lock out all the others
+    if ip.is_empty() {
+        return myconfig.login_max_failures <= by_email;
+    }
+    let by_ip = db::count_login_failures_by_ip(dbh, ip, since)
+        .await
+        .unwrap();
 
-    let user = visitor.user.clone().unwrap();
-    let uid = visitor.user.clone().unwrap().uid;
+    myconfig.login_max_failures <= by_email || myconfig.login_max_failures_per_ip <= by_ip
+}
 
-    if uid == group.owner {
-        return Template::render(
-            "message",
-            context! {title: "You are the owner of this group", message: "You cannot join an event in a group you own.", config, visitor},
-        );
-    }
+/// Record the failed login attempt and add an audit entry when it triggers a lockout.
+async fn login_failed(dbh: &Surreal<Client>, myconfig: &MyConfig, email: &str, ip: &str) {
+    let since = login_failure_window(myconfig);
+    db::delete_old_login_failures(dbh, since).await.unwrap();
+    db::add_login_failure(dbh, email, ip).await.unwrap();
 
-    // if user is not a member of the group join it
-    let member = db::get_membership(dbh, gid, uid).await.unwrap();
-    if member.is_none() {
-        db::join_group(dbh, gid, uid).await.unwrap();
+    let by_email = db::count_login_failures_by_email(dbh, email, since)
+        .await
+        .unwrap();
+    if by_email == myconfig.login_max_failures {
         db::audit(
             dbh,
-            AuditType::JoinGroup,
+            AuditType::AccountLocked,
             json!({
-                "user": {
-                    "id": uid,
-                    "name": user.name,
-                },
-                "group": {
-                    "id": gid,
-                    "name": group.name,
-                },
+                "email": email,
+                "ip": ip,
+                "failures": by_email,
             }),
         )
         .await
         .unwrap();
-        notify::owner_user_joined_group(dbh, myconfig, &user, &group).await;
     }
 
-    if let Some(rsvp) = db::get_rsvp(dbh, eid, uid).await.unwrap() {
-        if rsvp.status {
-            return Template::render(
-                "message",
-                context! {title: "You were already RSVPed", message: format!("You were already RSVPed"), config, visitor},
-            );
-        }
-        db::update_rsvp(dbh, eid, uid, true).await.unwrap();
-        db::audit(
-            dbh,
-            AuditType::RSVPYesAgain,
-            json!({
-                "user": {
-                    "id": uid,
-                    "name": user.name,
-                },
-                "event": {
-                    "id": eid,
-                    "title": event.title,
-                },
-            }),
-        )
+    if ip.is_empty() {
+        return;
+    }
+    let by_ip = db::count_login_failures_by_ip(dbh, ip, since)
         .await
         .unwrap();
-    } else {
-        db::new_rsvp(dbh, eid, uid, true).await.unwrap();
+    if by_ip == myconfig.login_max_failures_per_ip {
         db::audit(
             dbh,
-            AuditType::RSVPYes,
+            AuditType::IpLocked,
             json!({
-                "user": {
-                    "id": uid,
-                    "name": user.name,
-                },
-                "event": {
-                    "id": eid,
-                    "title": event.title,
-                },
+                "email": email,
+                "ip": ip,
+                "failures": by_ip,
             }),
         )
         .await
         .unwrap();
-        //notify::owner_user_rsvped_to_event(dbh, myconfig, &user, &group, &event).await;
     }
+}
+
+#[post("/logout", data = "<_input>")]
+async fn logout_post(
+    cookies: &CookieJar<'_>,
+    dbh: &State<Surreal<Client>>,
+    _visitor: LoggedIn,
+    _input: CsrfForm<CsrfOnlyForm<'_>>,
+) -> Template {
+    web::end_session(cookies, dbh).await;
+    let config = get_public_config();
+
+    let visitor = Visitor::new_after_logout(cookies);
 
     Template::render(
         "message",
-        context! {title: "RSVPed to event", message: format!(r#"User RSVPed to <a href="/event/{eid}">event</a>"#), config, visitor},
+        context! {title: "Logged out", message: "We have logged you out from the system", config, visitor},
     )
 }
 
-#[get("/rsvp-no-event?<eid>")]
-async fn rsvp_no_event_get(
+#[post("/logout-session", data = "<input>")]
+async fn logout_session_post(
+    cookies: &CookieJar<'_>,
     dbh: &State<Surreal<Client>>,
     visitor: LoggedIn,
-    eid: usize,
+    input: CsrfForm<RecordIdForm<'_>>,
 ) -> Template {
     let config = get_public_config();
+    let id = input.id;
+    let uid = visitor.user.clone().unwrap().uid;
 
-    let Some(event) = db::get_event_by_eid(dbh, eid).await.unwrap() else {
+    let Some(session) = db::delete_session_by_id(dbh, uid, id).await.unwrap() else {
         return Template::render(
             "message",
-            context! {title: "No such event", message: "No such event", config, visitor},
+            context! {title: "No such session", message: format!("There is no session <b>{id}</b>.", id = escape_html(id)), config, visitor},
         );
     };
 
-    let user = visitor.user.clone().unwrap();
-    let uid = user.uid;
-
-    let rsvp = db::get_rsvp(dbh, eid, uid).await.unwrap();
-    if rsvp.is_none() {
+    if web::get_session_id(cookies).as_deref() == Some(session.sid.as_str()) {
+        web::end_session(cookies, dbh).await;
+        #[expect(clippy::shadow_unrelated)]
+        let visitor = Visitor::new_after_logout(cookies);
         return Template::render(
             "message",
-            context! {title: "You were not registered to the event", message: format!(r#"You were not registered to the <a href="/event/{eid}">event</a>"#), config, visitor},
+            context! {title: "Logged out", message: "We have logged you out from the system", config, visitor},
         );
     }
-    db::update_rsvp(dbh, eid, uid, false).await.unwrap();
-    db::audit(
-        dbh,
-        AuditType::RSVPNo,
-        json!({
-            "user": {
-                "id": uid,
-                "name": user.name,
-            },
-            "event": {
-                "id": eid,
-                "title": event.title,
-            },
-        }),
-    )
-    .await
-    .unwrap();
-
-    // TODO audit
-    // TODO notify
 
     Template::render(
         "message",
-        context! {title: "Not attending", message: format!(r#"User not attending <a href="/event/{eid}">event</a>"#), config, visitor},
+        context! {title: "Session removed", message: r#"The device was logged out. Check your <a href="/profile">profile</a>."#, config, visitor},
     )
 }
 
-#[get("/profile")]
-async fn show_profile(dbh: &State<Surreal<Client>>, visitor: LoggedIn) -> Template {
+#[post("/logout-everywhere", data = "<_input>")]
+async fn logout_everywhere_post(
+    cookies: &CookieJar<'_>,
+    dbh: &State<Surreal<Client>>,
+    visitor: LoggedIn,
+    _input: CsrfForm<CsrfOnlyForm<'_>>,
+) -> Template {
     let config = get_public_config();
+    let uid = visitor.user.unwrap().uid;
 
-    let uid = visitor.user.clone().unwrap().uid;
-    let owned_groups = db::get_groups_by_owner_id(dbh, uid).await.unwrap();
-
-    let groups = db::get_groups_by_membership_id(dbh, uid).await.unwrap();
-    rocket::info!("groups: {groups:?}");
+    db::delete_sessions_of_user(dbh, uid, None).await.unwrap();
+    web::end_session(cookies, dbh).await;
 
-    let about = "";
-    // let about = visitor
-    //     .user
-    //     .clone()
-    //     .unwrap()
-    //     .about
-    //     .map(|text| markdown2html(&text).unwrap());
+    #[expect(clippy::shadow_unrelated)]
+    let visitor = Visitor::new_after_logout(cookies);
 
     Template::render(
-        "profile",
-        context! {title: "Profile", user: visitor.user.clone(), about, owned_groups, groups, config, visitor},
+        "message",
+        context! {title: "Logged out everywhere", message: "We have logged you out from all of your devices", config, visitor},
     )
 }
 
-#[get("/edit-profile")]
-fn edit_profile_get(visitor: LoggedIn) -> Template {
-    let config = get_public_config();
-
-    Template::render(
-        "edit_profile",
-        context! {title: "Edit Profile", user: visitor.user.clone(), config, visitor},
-    )
+enum TokenProblem {
+    Invalid,
+    Used,
+    Expired,
 }
 
-#[post("/edit-profile", data = "<input>")]
-async fn edit_profile_post(
-    dbh: &State<Surreal<Client>>,
-    input: Form<ProfileForm<'_>>,
-    visitor: LoggedIn,
-) -> Template {
-    let config = get_public_config();
+/// Create a new single-use token and return the code we need to send to the user.
+async fn create_token(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    uid: usize,
+    purpose: TokenPurpose,
+    email: Option<String>,
+    gid: Option<usize>,
+    owner: Option<usize>,
+) -> String {
+    let code = Uuid::new_v4().to_string();
+    let utc: DateTime<Utc> = Utc::now();
+    #[expect(clippy::arithmetic_side_effects)]
+    let token = Token {
+        id: Thing::from(("token", Id::ulid())),
+        uid,
+        purpose,
+        hash: hash_token(&code),
+        created: utc,
+        expires: utc + myconfig.token_lifetime(purpose),
+        used: None,
+        email,
+        gid,
+        owner,
+    };
+    db::add_token(dbh, &token).await.unwrap();
 
-    let re_github = Regex::new("^[a-zA-Z0-9]*$").unwrap();
-    let re_gitlab = Regex::new("^[a-zA-Z0-9]*$").unwrap();
-    let re_linkedin = Regex::new("^https://www.linkedin.com/in/[a-zA-Z0-9-]+/?$").unwrap();
+    code
+}
 
-    let uid = visitor.user.clone().unwrap().uid;
-    let name = input.name.trim();
-    let github = input.github.trim();
-    let gitlab = input.gitlab.trim();
-    let linkedin = input.linkedin.trim();
-    //let about = input.about;
-    let about = "";
+/// Check that the code belongs to this user, it was created for this purpose and it can still be used.
+async fn check_token(
+    dbh: &Surreal<Client>,
+    uid: usize,
+    code: &str,
+    purpose: TokenPurpose,
+) -> Result<Token, TokenProblem> {
+    let Some(token) = db::get_token(dbh, &hash_token(code)).await.unwrap() else {
+        return Err(TokenProblem::Invalid);
+    };
 
-    if !re_github.is_match(github) {
-        return Template::render(
-            "message",
-            context! {title: "Invalid GitHub username", message: format!("The GitHub username `{github}` is not valid."), config, visitor},
-        );
+    if token.uid != uid || token.purpose != purpose {
+        return Err(TokenProblem::Invalid);
     }
 
-    if !re_gitlab.is_match(gitlab) {
-        return Template::render(
-            "message",
-            context! {title: "Invalid GitLab username", message: format!("The GitLab username `{gitlab}` is not valid."), config, visitor},
-        );
+    if token.used.is_some() {
+        return Err(TokenProblem::Used);
     }
 
-    if !linkedin.is_empty() && !re_linkedin.is_match(linkedin) {
-        return Template::render(
-            "message",
-            context! {title: "Invalid LinkedIn profile link", message: format!("The LinkedIn profile link `{linkedin}` is not valid."), config, visitor},
-        );
+    if token.expires <= Utc::now() {
+        return Err(TokenProblem::Expired);
     }
 
-    if MAX_NAME_LEN < name.len() {
-        return Template::render(
-            "message",
-            context! {title: "Name is too long", message: format!("Name is too long. Max {MAX_NAME_LEN} while the current name is {} long. Please try again.", name.len()), config, visitor},
-        );
-    }
+    Ok(token)
+}
 
-    let re_name = get_re_name();
+/// `resend` is the page where the visitor can ask for a new link.
+fn token_problem_page(
+    problem: TokenProblem,
+    resend: &str,
+    config: PublicConfig,
+    visitor: Visitor,
+) -> Template {
+    let request_new = format!(r#"<a href="{resend}">request a new one</a>"#);
 
-    if !re_name.is_match(name) {
-        return Template::render(
+    match problem {
+        TokenProblem::Expired => Template::render(
             "message",
-            context! {title: "Invalid character", message: format!(r#"The name '{name}' contains a character that we currently don't accept. Use Latin letters for now and comment on <a href="https://github.com/szabgab/meet-os.rs/issues/38">this issue</a> where this topic is discussed."#), config, visitor},
-        );
+            context! {title: "Link expired", message: format!("This link has expired. Please {request_new}."), config, visitor},
+        ),
+        TokenProblem::Used => Template::render(
+            "message",
+            context! {title: "Link already used", message: format!("This link was already used. If you still need it, please {request_new}."), config, visitor},
+        ),
+        TokenProblem::Invalid => Template::render(
+            "message",
+            context! {title: "Invalid link", message: format!("This link is not valid. Please {request_new}."), config, visitor},
+        ),
     }
-
-    db::update_user(dbh, uid, name, github, gitlab, linkedin, about)
-        .await
-        .unwrap();
-
-    Template::render(
-        "message",
-        context! {title: "Profile updated", message: format!(r#"Check out the <a href="/profile">profile</a> and how others see it <a href="/user/{uid}">{name}</a>"#, ), config, visitor},
-    )
 }
 
-#[get("/event/<eid>")]
-async fn event_get(dbh: &State<Surreal<Client>>, visitor: Visitor, eid: usize) -> Template {
+#[get("/reset-password")]
+fn reset_password_get(visitor: Visitor) -> Template {
     let config = get_public_config();
 
-    let event = db::get_event_by_eid(dbh, eid).await.unwrap().unwrap();
-    let group = db::get_group_by_gid(dbh, event.group_id)
-        .await
-        .unwrap()
-        .unwrap();
-
-    let description = markdown2html(&event.description).unwrap();
-
-    let utc: DateTime<Utc> = Utc::now();
-    let editable = utc < event.date;
-
-    // has current user RSVP ed?
-    let rsvped = if visitor.logged_in {
-        let uid = visitor.clone().user.unwrap().uid;
-        match db::get_rsvp(dbh, eid, uid).await.unwrap() {
-            None => false,
-            Some(rsvp) => rsvp.status,
-        }
-    } else {
-        false
-    };
-
-    let people = db::get_all_rsvps_for_event(dbh, eid).await.unwrap();
-
     Template::render(
-        "event",
+        "reset_password",
         context! {
-            title: &event.title,
-            event: &event,
-            description,
-            group,
+            title: "Reset password",
             config,
             visitor,
-            editable,
-            rsvped,
-            people,
         },
     )
 }
 
-#[get("/group/<gid>")]
-async fn group_get(dbh: &State<Surreal<Client>>, visitor: Visitor, gid: usize) -> Template {
-    rocket::info!("group_get: {gid}");
-    let config = get_public_config();
+#[post("/reset-password", data = "<input>")]
+async fn reset_password_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: Visitor,
+    input: CsrfForm<ResetPasswordForm<'_>>,
+) -> Template {
+    rocket::info!("reset password for: {:?}", input.email);
+    let config = get_public_config();
+
+    let email = input.email.to_lowercase().trim().to_owned();
+
+    let Some(user) = db::get_user_by_email(dbh, &email).await.unwrap() else {
+        // TODO: we should probably limit the number of such request from the same visitor so a bot won't be able to try to guess email addresses
+        return Template::render(
+            "message",
+            context! {title: "No such user", message: format!("No user with address <b>{}</b>. Please try again", escape_html(input.email)), config, visitor},
+        );
+    };
+
+    let uid = user.uid;
+    let code = create_token(
+        dbh,
+        myconfig,
+        uid,
+        TokenPurpose::ResetPassword,
+        None,
+        None,
+        None,
+    )
+    .await;
+
+    let base_url = &myconfig.base_url;
+
+    let subject = "Reset your Meet-OS password!";
+    let text = format!(
+        r#"Hi,
+    <p>
+    Someone asked to reset the password on the Meet-OS web site connected to this email address.
+    If it was you, please <a href="{base_url}/save-password/{uid}/{code}">click on this link</a> to set your new password.
+    <p>
+    <p>
+    If it was not you, we would like to apologize. You don't need to do anything...
+    "#
+    );
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+    let to_address = &EmailAddress {
+        name: user.name.clone(),
+        email: user.email.clone(),
+    };
+
+    sendmail(myconfig, &from, to_address, subject, &text).await;
+    //notify::admin_user_asked_to_reset_password(myconfig, &user).await;
+
+    Template::render(
+        "message",
+        context! {title: "We sent you an email", message: format!("We sent you an email to <b>{}</b> Please click on the link to reset your password.", escape_html(&to_address.email)), config, visitor},
+    )
+}
+
+#[get("/save-password/<uid>/<code>")]
+async fn save_password_get(
+    dbh: &State<Surreal<Client>>,
+    visitor: Visitor,
+    uid: usize,
+    code: &str,
+) -> Template {
+    rocket::info!("save-password for uid={uid} with code: {code}");
+    let config = get_public_config();
+
+    let Some(user) = db::get_user_by_uid(dbh, uid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "Invalid id", message: format!("Invalid id <b>{uid}</b>"), config, visitor},
+        );
+    };
+
+    match check_token(dbh, user.uid, code, TokenPurpose::ResetPassword).await {
+        Ok(_token) => {}
+        Err(TokenProblem::Invalid) => {
+            return Template::render(
+                "message",
+                context! {title: "Invalid code", message: format!("Invalid code <b>{code}</b>", code = escape_html(code)), config, visitor},
+            );
+        }
+        Err(problem) => {
+            return token_problem_page(problem, "/reset-password", config, visitor);
+        }
+    }
+
+    Template::render(
+        "save_password",
+        context! {
+            title: "Type in your new password",
+            config,
+            visitor,
+            uid,
+            code,
+        },
+    )
+}
+
+#[post("/save-password", data = "<input>")]
+async fn save_password_post(
+    cookies: &CookieJar<'_>,
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: Visitor,
+    input: CsrfForm<SavePasswordForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+    let uid = input.uid;
+    let code = input.code;
+
+    let Some(user) = db::get_user_by_uid(dbh, uid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "Invalid userid", message: format!("Invalid userid <b>{uid}</b>."), config, visitor},
+        );
+    };
+
+    match check_token(dbh, user.uid, code, TokenPurpose::ResetPassword).await {
+        Ok(_token) => {}
+        Err(TokenProblem::Invalid) => {
+            rocket::warn!("Invalid code {code} for uid {uid}");
+            return Template::render(
+                "message",
+                context! {title: "Invalid code", message: format!("Invalid code <b>{code}</b>.", code = escape_html(code)), config, visitor},
+            );
+        }
+        Err(problem) => {
+            return token_problem_page(problem, "/reset-password", config, visitor);
+        }
+    }
+
+    let password = input.password.trim().as_bytes();
+    if password.len() < MIN_PASSWORD_LENGTH {
+        return Template::render(
+            "message",
+            context! {title: "Invalid password", message: format!("The password must be at least {MIN_PASSWORD_LENGTH} characters long."), config, visitor},
+        );
+    }
+
+    if db::use_token(dbh, &hash_token(code))
+        .await
+        .unwrap()
+        .is_none()
+    {
+        return token_problem_page(TokenProblem::Used, "/reset-password", config, visitor);
+    }
+
+    let hashed_password = password::hash(myconfig, password);
+
+    db::save_password(dbh, uid, &hashed_password).await.unwrap();
+
+    // The password was changed, all the other sessions of this user must log in again
+    let keep = if visitor.user.as_ref().is_some_and(|usr| usr.uid == uid) {
+        web::get_session_id(cookies)
+    } else {
+        None
+    };
+    db::delete_sessions_of_user(dbh, uid, keep.as_deref())
+        .await
+        .unwrap();
+
+    let base_url = &myconfig.base_url;
+
+    let subject = "Your Meet-OS password was reset!";
+    let text = format!(
+        r#"Hi,
+    <p>
+    The password of your <a href="{base_url}/">Meet-OS</a> account was reset. Please log in.
+    <p>
+    If it was not done by you, please <a href="{base_url}">reset your password</a> and contact us ASAP!
+    ";
+    "#
+    );
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+    let to_address = &EmailAddress {
+        name: user.name.clone(),
+        email: user.email.clone(),
+    };
+
+    sendmail(myconfig, &from, to_address, subject, &text).await;
+    //notify::admin_user_asked_to_reset_password(myconfig, &user).await;
+
+    Template::render(
+        "message",
+        context! {title: "Password updated", message: "Your password was updated.", config, visitor},
+    )
+}
+
+#[get("/register")]
+fn register_get(visitor: Visitor) -> Template {
+    let config = get_public_config();
+
+    Template::render(
+        "register",
+        context! {
+            title: "Register",
+            config,
+            visitor,
+            min_password_length: MIN_PASSWORD_LENGTH,
+        },
+    )
+}
+
+#[post("/register", data = "<input>")]
+async fn register_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: Visitor,
+    input: CsrfForm<RegistrationForm<'_>>,
+) -> Template {
+    rocket::info!("rocket input: {:?} {:?}", input.email, input.name);
+
+    let config = get_public_config();
+
+    let name = input.name.trim().to_owned();
+    if MAX_NAME_LEN < name.len() {
+        return Template::render(
+            "message",
+            context! {title: "Name is too long", message: format!("Name is too long. Max {MAX_NAME_LEN} while the current name is {} long. Please try again.", name.len()), config, visitor},
+        );
+    }
+
+    let re_name = get_re_name();
+
+    if !re_name.is_match(&name) {
+        return Template::render(
+            "message",
+            context! {title: "Invalid character", message: format!(r#"The name '{name}' contains a character that we currently don't accept. Use Latin letters for now and comment on <a href="https://github.com/szabgab/meet-os.rs/issues/38">this issue</a> where this topic is discussed."#, name = escape_html(&name)), config, visitor},
+        );
+    }
+
+    // email: lowerase, remove spaces from sides
+    // validate format @
+    let email = input.email.to_lowercase().trim().to_owned();
+    if !validator::validate_email(&email) {
+        return Template::render(
+            "message",
+            context! {title: "Invalid email address", message: format!("Invalid email address <b>{}</b> Please try again", escape_html(input.email)), config, visitor},
+        );
+    }
+
+    let password = input.password.trim().as_bytes();
+    if password.len() < MIN_PASSWORD_LENGTH {
+        return Template::render(
+            "message",
+            context! {title: "Invalid password", message: format!("The password must be at least {MIN_PASSWORD_LENGTH} characters long."), config, visitor},
+        );
+    }
+    let hashed_password = password::hash(myconfig, password);
+
+    let uid = db::increment(dbh, "user").await.unwrap();
+    let utc: DateTime<Utc> = Utc::now();
+    let id = Id::ulid();
+    let user = User {
+        id: Thing::from(("user", id.clone())),
+        uid,
+        name: name.clone(),
+        email: email.clone(),
+        password: hashed_password,
+        registration_date: utc,
+        verification_date: None,
+        verified: false,
+        github: None,
+        gitlab: None,
+        linkedin: None,
+        about: None,
+    };
+    match db::add_user(dbh, &user).await {
+        Ok(result) => result,
+        Err(err) => {
+            rocket::info!("Error while trying to add user {err}");
+            // TODO special reporting when the email is already in the system
+            return Template::render(
+                "message",
+                context! {title: "Registration failed", message: format!("Could not register <b>{}</b>.", escape_html(&user.email)), config, visitor},
+            );
+        }
+    }
+
+    let code = create_token(dbh, myconfig, uid, TokenPurpose::Register, None, None, None).await;
+
+    let base_url = &myconfig.base_url;
+    let subject = "Verify your Meet-OS registration!";
+    let text = format!(
+        r#"Hi,
+    <p>
+    Someone used your email to register on the Meet-OS web site.
+    If it was you, please <a href="{base_url}/verify-email/{id}/{code}">click on this link</a> to verify your email address.
+    <p>
+    <p>
+    If it was not you, we would like to apologize. You don't need to do anything. We'll discard your registration if it is not validated.
+    "#
+    );
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+    let to_address = &EmailAddress { name, email };
+
+    sendmail(myconfig, &from, to_address, subject, &text).await;
+    notify::admin_new_user_registered(myconfig, &user).await;
+
+    Template::render(
+        "message",
+        context! {title: "We sent you an email", message: format!("We sent you an email to <b>{}</b> Please check your inbox and verify your email address.", escape_html(&to_address.email)), config, visitor},
+    )
+}
+
+#[get("/verify-email/<uid>/<code>")]
+async fn verify_email(
+    cookies: &CookieJar<'_>,
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    client: ClientInfo,
+    visitor: Visitor,
+    uid: String,
+    code: &str,
+) -> Template {
+    rocket::info!("verify-email of uid='{uid}' using code='{code}'");
+
+    let config = get_public_config();
+
+    let Some(user) = db::get_user_by_id_str(dbh, &uid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "Invalid id", message: format!("Invalid id <b>{uid}</b>"), config, visitor},
+        );
+    };
+
+    match check_token(dbh, user.uid, code, TokenPurpose::Register).await {
+        Ok(_token) => {}
+        Err(TokenProblem::Invalid) => {
+            rocket::warn!("Received invalid code='{code}' for uid='{uid}'");
+            return Template::render(
+                "message",
+                context! {title: "Invalid code", message: format!("Invalid code <b>{code}</b>", code = escape_html(code)), config, visitor},
+            );
+        }
+        Err(problem) => {
+            return token_problem_page(problem, "/resend-email-verification-code", config, visitor);
+        }
+    }
+
+    if db::use_token(dbh, &hash_token(code))
+        .await
+        .unwrap()
+        .is_none()
+    {
+        return token_problem_page(
+            TokenProblem::Used,
+            "/resend-email-verification-code",
+            config,
+            visitor,
+        );
+    }
+
+    db::set_user_verified(dbh, user.uid).await.unwrap();
+
+    // Accept the invitations that brought the user here
+    for invitation in db::get_pending_invitations_by_email(dbh, &user.email)
+        .await
+        .unwrap()
+    {
+        if let Some(group) = db::get_group_by_gid(dbh, invitation.gid).await.unwrap() {
+            if group.archived.is_none() {
+                join_by_invitation(dbh, myconfig, &invitation, &user, &group).await;
+            }
+        }
+    }
+
+    rocket::info!("verified code for '{}'", user.email);
+    web::start_session(cookies, dbh, &client, &user).await;
+    notify::admin_new_user_verified(myconfig, &user).await;
+
+    // take into account the newly set cookie value
+    #[expect(clippy::shadow_unrelated)]
+    let visitor = Visitor::new_after_login(cookies, &user.email, dbh, myconfig).await;
+
+    Template::render(
+        "message",
+        context! {title: "Thank you for registering", message: "Your email was verified.", config, visitor},
+    )
+}
+
+#[post("/join-group", data = "<input>")]
+async fn join_group_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<JoinGroupForm>,
+) -> Template {
+    let config = get_public_config();
+    let gid = input.gid;
+
+    let group = db::get_group_by_gid(dbh, gid).await.unwrap();
+    if group.is_none() {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("There is not group with id <b>{gid}</b>"), config, visitor},
+        );
+    }
+    let group = group.unwrap();
+
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
+
+    let user = visitor.user.clone().unwrap();
+    let uid = visitor.user.clone().unwrap().uid;
+    if uid == group.owner {
+        return Template::render(
+            "message",
+            context! {title: "You are the owner of this group", message: "You cannot join a group you own.", config, visitor},
+        );
+    }
+
+    let member = db::get_membership(dbh, gid, uid).await.unwrap();
+    if member.is_some() {
+        return Template::render(
+            "message",
+            context! {title: "You are already a member of this group", message: format!(r#"You are already a member of the <a href="/group/{gid}">{}</a> group"#, escape_html(&group.name)), config, visitor},
+        );
+    }
+
+    if db::get_ban(dbh, gid, uid).await.unwrap().is_some() {
+        return banned_page(&group, config, visitor);
+    }
+
+    if group.requires_approval {
+        return ask_to_join_group(dbh, myconfig, visitor, &group, &input.answers).await;
+    }
+
+    db::join_group(dbh, gid, uid).await.unwrap();
+    db::audit(
+        dbh,
+        AuditType::JoinGroup,
+        json!({
+            "user": {
+                "id": uid,
+                "name": user.name,
+            },
+            "group": {
+                "id": gid,
+                "name": group.name,
+            }
+        }),
+    )
+    .await
+    .unwrap();
+    notify::owner_user_joined_group(dbh, myconfig, &user, &group).await;
+    Template::render(
+        "message",
+        context! {title: "Membership", message: format!(r#"User added to <a href="/group/{gid}">group</a>"#), config, visitor},
+    )
+}
+
+/// Banned users cannot join the group again, not even by registering to one of its events.
+fn banned_page<V: Serialize>(group: &Group, config: PublicConfig, visitor: V) -> Template {
+    Template::render(
+        "message",
+        context! {title: "Banned", message: format!(r#"You were banned from the <a href="/group/{}">{}</a> group."#, group.gid, escape_html(&group.name)), config, visitor},
+    )
+}
+
+/// Archived groups are kept for the history, but nothing can be changed in them.
+fn group_archived_page<V: Serialize>(group: &Group, config: PublicConfig, visitor: V) -> Template {
+    Template::render(
+        "message",
+        context! {title: "Group archived", message: format!(r#"The <a href="/group/{}">{}</a> group is archived."#, group.gid, escape_html(&group.name)), config, visitor},
+    )
+}
+
+/// Record the request to join a group that requires approval and let the owner know about it.
+async fn ask_to_join_group(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    visitor: LoggedIn,
+    group: &Group,
+    answers: &[String],
+) -> Template {
+    let config = get_public_config();
+    let user = visitor.user.clone().unwrap();
+    let gid = group.gid;
+
+    if db::get_join_request(dbh, gid, user.uid)
+        .await
+        .unwrap()
+        .is_some()
+    {
+        return Template::render(
+            "message",
+            context! {title: "Request pending", message: format!(r#"You already asked to join the <a href="/group/{gid}">{}</a> group. Please wait for the organizers to approve it."#, escape_html(&group.name)), config, visitor},
+        );
+    }
+
+    let answers = answers
+        .iter()
+        .map(|answer| answer.trim())
+        .collect::<Vec<_>>();
+    if answers.len() != group.questions.len() || answers.iter().any(|answer| answer.is_empty()) {
+        return Template::render(
+            "message",
+            context! {title: "Missing answer", message: "Please answer all the questions of the group.", config, visitor},
+        );
+    }
+
+    let request = JoinRequest {
+        id: Thing::from(("join_request", Id::ulid())),
+        gid,
+        uid: user.uid,
+        answers: group
+            .questions
+            .iter()
+            .cloned()
+            .zip(answers.into_iter().map(ToOwned::to_owned))
+            .collect(),
+        date: Utc::now(),
+    };
+    db::add_join_request(dbh, &request).await.unwrap();
+    db::audit(
+        dbh,
+        AuditType::JoinRequested,
+        json!({
+            "user": {
+                "id": user.uid,
+                "name": user.name,
+            },
+            "group": {
+                "id": gid,
+                "name": group.name,
+            }
+        }),
+    )
+    .await
+    .unwrap();
+    notify::owner_user_asked_to_join_group(dbh, myconfig, &user, group).await;
+
+    Template::render(
+        "message",
+        context! {title: "Request sent", message: format!(r#"Your request to join the <a href="/group/{gid}">{}</a> group was sent to the organizers."#, escape_html(&group.name)), config, visitor},
+    )
+}
+
+#[post("/approve-join-request", data = "<input>")]
+async fn approve_join_request_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<JoinRequestForm>,
+) -> Template {
+    decide_join_request(dbh, myconfig, visitor, &input, true).await
+}
+
+#[post("/reject-join-request", data = "<input>")]
+async fn reject_join_request_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<JoinRequestForm>,
+) -> Template {
+    decide_join_request(dbh, myconfig, visitor, &input, false).await
+}
+
+async fn decide_join_request(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    visitor: LoggedIn,
+    input: &JoinRequestForm,
+    approve: bool,
+) -> Template {
+    let config = get_public_config();
+    let gid = input.gid;
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        );
+    };
+
+    let organizer = visitor.user.clone().unwrap();
+    if !db::get_group_role(dbh, &group, organizer.uid)
+        .await
+        .unwrap()
+        .can_organize()
+    {
+        return Template::render(
+            "message",
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{gid}</b>"), config, visitor},
+        );
+    }
+
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
+
+    let Some(user) = db::get_user_by_uid(dbh, input.uid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such user", message: format!("The user ID <b>{}</b> does not exist.", input.uid), config, visitor},
+        );
+    };
+
+    if db::delete_join_request(dbh, gid, user.uid)
+        .await
+        .unwrap()
+        .is_none()
+    {
+        return Template::render(
+            "message",
+            context! {title: "No such request", message: format!("There is no pending request of <b>{}</b> to join this group.", escape_html(&user.name)), config, visitor},
+        );
+    }
+
+    if approve {
+        db::join_group(dbh, gid, user.uid).await.unwrap();
+    }
+
+    db::audit(
+        dbh,
+        if approve {
+            AuditType::JoinRequestApproved
+        } else {
+            AuditType::JoinRequestRejected
+        },
+        json!({
+            "user": {
+                "id": user.uid,
+                "name": user.name,
+            },
+            "group": {
+                "id": gid,
+                "name": group.name,
+            },
+            "organizer": {
+                "id": organizer.uid,
+                "name": organizer.name,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+    notify::member_join_request_decided(myconfig, &user, &group, approve).await;
+
+    if approve {
+        Template::render(
+            "message",
+            context! {title: "Join request approved", message: format!(r#"<b>{}</b> is now a member of the <a href="/group/{gid}">group</a>."#, escape_html(&user.name)), config, visitor},
+        )
+    } else {
+        Template::render(
+            "message",
+            context! {title: "Join request rejected", message: format!(r#"The request of <b>{}</b> to join the <a href="/group/{gid}">group</a> was rejected."#, escape_html(&user.name)), config, visitor},
+        )
+    }
+}
+
+#[get("/invite?<gid>")]
+async fn invite_get(dbh: &State<Surreal<Client>>, visitor: LoggedIn, gid: usize) -> Template {
+    let config = get_public_config();
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        );
+    };
+
+    let user = visitor.user.clone().unwrap();
+    if !db::get_group_role(dbh, &group, user.uid)
+        .await
+        .unwrap()
+        .can_organize()
+    {
+        return Template::render(
+            "message",
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{gid}</b>"), config, visitor},
+        );
+    }
+
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
+
+    let now = Utc::now();
+    let invitations = db::get_invitations_of_group(dbh, gid)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|invitation| {
+            let status = invitation.status(now);
+            (invitation.id.id.to_raw(), invitation, status)
+        })
+        .collect::<Vec<_>>();
+
+    Template::render(
+        "invite",
+        context! {title: "Invite people", group, invitations, config, visitor},
+    )
+}
+
+#[post("/invite", data = "<input>")]
+async fn invite_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<InviteForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+    let gid = input.gid;
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        );
+    };
+
+    let user = visitor.user.clone().unwrap();
+    if !db::get_group_role(dbh, &group, user.uid)
+        .await
+        .unwrap()
+        .can_organize()
+    {
+        return Template::render(
+            "message",
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{gid}</b>"), config, visitor},
+        );
+    }
+
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
+
+    let mut sent: Vec<String> = vec![];
+    let mut invalid: Vec<String> = vec![];
+    let mut members: Vec<String> = vec![];
+    for email in input
+        .emails
+        .split(|chr: char| chr.is_whitespace() || chr == ',' || chr == ';')
+        .filter(|email| !email.is_empty())
+        .map(str::to_lowercase)
+    {
+        if sent.contains(&email) {
+            continue;
+        }
+        if !validator::validate_email(&email) {
+            invalid.push(email);
+            continue;
+        }
+        if let Some(invitee) = db::get_user_by_email(dbh, &email).await.unwrap() {
+            if invitee.uid == group.owner
+                || db::get_membership(dbh, gid, invitee.uid)
+                    .await
+                    .unwrap()
+                    .is_some()
+            {
+                members.push(email);
+                continue;
+            }
+        }
+        send_invitation(dbh, myconfig, &user, &group, &email).await;
+        sent.push(email);
+    }
+
+    if sent.is_empty() && invalid.is_empty() && members.is_empty() {
+        return Template::render(
+            "message",
+            context! {title: "No email address", message: "Please provide at least one email address.", config, visitor},
+        );
+    }
+
+    let mut message = vec![format!("Invitations sent: {}.", sent.len())];
+    if !invalid.is_empty() {
+        message.push(format!(
+            "Invalid email addresses: <b>{}</b>.",
+            escape_html(&invalid.join(", "))
+        ));
+    }
+    if !members.is_empty() {
+        message.push(format!(
+            "Already members: <b>{}</b>.",
+            escape_html(&members.join(", "))
+        ));
+    }
+    message.push(format!(
+        r#"Check the <a href="/invite?gid={gid}">invitations</a>."#
+    ));
+
+    Template::render(
+        "message",
+        context! {title: "Invitations", message: message.join(" "), config, visitor},
+    )
+}
+
+#[post("/invite/resend", data = "<input>")]
+async fn invite_resend_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<InvitationForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+    let gid = input.gid;
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        );
+    };
+
+    let user = visitor.user.clone().unwrap();
+    if !db::get_group_role(dbh, &group, user.uid)
+        .await
+        .unwrap()
+        .can_organize()
+    {
+        return Template::render(
+            "message",
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{gid}</b>"), config, visitor},
+        );
+    }
+
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
+
+    let code = Uuid::new_v4().to_string();
+    #[expect(clippy::arithmetic_side_effects)]
+    let expires = Utc::now() + Duration::days(myconfig.invitation_days);
+    let Some(invitation) = db::renew_invitation(dbh, gid, input.id, &hash_token(&code), expires)
+        .await
+        .unwrap()
+    else {
+        return Template::render(
+            "message",
+            context! {title: "No such invitation", message: "This invitation does not exist or it was already accepted.", config, visitor},
+        );
+    };
+    notify::invitee_invited_to_group(myconfig, &user, &group, &invitation.email, &code).await;
+
+    Template::render(
+        "message",
+        context! {title: "Invitation sent", message: format!(r#"We sent a new invitation to <b>{}</b>. Check the <a href="/invite?gid={gid}">invitations</a>."#, escape_html(&invitation.email)), config, visitor},
+    )
+}
+
+#[post("/invite/revoke", data = "<input>")]
+async fn invite_revoke_post(
+    dbh: &State<Surreal<Client>>,
+    visitor: LoggedIn,
+    input: CsrfForm<InvitationForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+    let gid = input.gid;
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        );
+    };
+
+    let uid = visitor.user.clone().unwrap().uid;
+    if !db::get_group_role(dbh, &group, uid)
+        .await
+        .unwrap()
+        .can_organize()
+    {
+        return Template::render(
+            "message",
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{gid}</b>"), config, visitor},
+        );
+    }
+
+    let Some(invitation) = db::delete_invitation(dbh, gid, input.id).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such invitation", message: "This invitation does not exist.", config, visitor},
+        );
+    };
+
+    Template::render(
+        "message",
+        context! {title: "Invitation revoked", message: format!(r#"The invitation of <b>{}</b> was revoked. Check the <a href="/invite?gid={gid}">invitations</a>."#, escape_html(&invitation.email)), config, visitor},
+    )
+}
+
+#[get("/invitation/<code>")]
+async fn invitation_get(dbh: &State<Surreal<Client>>, visitor: Visitor, code: &str) -> Template {
+    let config = get_public_config();
+
+    let (invitation, group) =
+        match check_invitation(dbh, code, visitor.user.as_ref(), &visitor).await {
+            Ok(found) => found,
+            Err(page) => return *page,
+        };
+
+    if visitor.logged_in {
+        return Template::render(
+            "confirm",
+            context! {
+                title: "Invitation",
+                question: format!("Do you want to join the '{}' group?", group.name),
+                action: format!("/invitation/{code}"),
+                button: "Join",
+                config,
+                visitor,
+            },
+        );
+    }
+
+    // The same page whether the address has an account or not.
+    Template::render(
+        "register",
+        context! {
+            title: "Register",
+            config,
+            visitor,
+            min_password_length: MIN_PASSWORD_LENGTH,
+            email: invitation.email,
+            group,
+        },
+    )
+}
+
+#[post("/invitation/<code>", data = "<_input>")]
+async fn invitation_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    code: &str,
+    _input: CsrfForm<CsrfOnlyForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+    let user = visitor.user.clone().unwrap();
+
+    let (invitation, group) = match check_invitation(dbh, code, Some(&user), &visitor).await {
+        Ok(found) => found,
+        Err(page) => return *page,
+    };
+
+    join_by_invitation(dbh, myconfig, &invitation, &user, &group).await;
+    Template::render(
+        "message",
+        context! {title: "Invitation accepted", message: format!(r#"You are now a member of the <a href="/group/{}">{}</a> group."#, group.gid, escape_html(&group.name)), config, visitor},
+    )
+}
+
+/// The invitation can still be accepted and, if the visitor is logged in, it was sent to their address.
+/// Returns the invitation and the group or the error page.
+async fn check_invitation<V: Serialize>(
+    dbh: &Surreal<Client>,
+    code: &str,
+    user: Option<&User>,
+    visitor: &V,
+) -> Result<(Invitation, Group), Box<Template>> {
+    let config = get_public_config();
+
+    let Some(invitation) = db::get_invitation_by_hash(dbh, &hash_token(code))
+        .await
+        .unwrap()
+    else {
+        return Err(Box::new(Template::render(
+            "message",
+            context! {title: "Invalid invitation", message: "This invitation link is not valid.", config, visitor},
+        )));
+    };
+
+    let status = invitation.status(Utc::now());
+    if status == InvitationStatus::Accepted {
+        return Err(Box::new(Template::render(
+            "message",
+            context! {title: "Invitation already used", message: "This invitation was already accepted.", config, visitor},
+        )));
+    }
+    if status == InvitationStatus::Expired {
+        return Err(Box::new(Template::render(
+            "message",
+            context! {title: "Invitation expired", message: "This invitation has expired. Please ask the organizers of the group to send you a new one.", config, visitor},
+        )));
+    }
+
+    if let Some(user) = user {
+        if user.email.to_lowercase() != invitation.email {
+            return Err(Box::new(Template::render(
+                "message",
+                context! {title: "Wrong account", message: "This invitation was sent to another email address. Please login with the account of that address.", config, visitor},
+            )));
+        }
+    }
+
+    let gid = invitation.gid;
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Err(Box::new(Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        )));
+    };
+
+    if group.archived.is_some() {
+        return Err(Box::new(group_archived_page(&group, config, visitor)));
+    }
+
+    if let Some(user) = user {
+        if db::get_ban(dbh, gid, user.uid).await.unwrap().is_some() {
+            return Err(Box::new(banned_page(&group, config, visitor)));
+        }
+    }
+
+    Ok((invitation, group))
+}
+
+/// Store a new invitation and send the link to the address.
+async fn send_invitation(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    inviter: &User,
+    group: &Group,
+    email: &str,
+) {
+    let code = Uuid::new_v4().to_string();
+    let utc: DateTime<Utc> = Utc::now();
+    #[expect(clippy::arithmetic_side_effects)]
+    let invitation = Invitation {
+        id: Thing::from(("invitation", Id::ulid())),
+        gid: group.gid,
+        email: email.to_owned(),
+        hash: hash_token(&code),
+        invited_by: inviter.uid,
+        created: utc,
+        expires: utc + Duration::days(myconfig.invitation_days),
+        accepted: None,
+    };
+    db::add_invitation(dbh, &invitation).await.unwrap();
+    notify::invitee_invited_to_group(myconfig, inviter, group, email, &code).await;
+}
+
+/// Mark the invitation as accepted and add the user to the group unless they already belong to it.
+async fn join_by_invitation(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    invitation: &Invitation,
+    user: &User,
+    group: &Group,
+) {
+    if db::accept_invitation(dbh, &invitation.hash)
+        .await
+        .unwrap()
+        .is_none()
+    {
+        return;
+    }
+
+    let gid = group.gid;
+    if user.uid == group.owner
+        || db::get_membership(dbh, gid, user.uid)
+            .await
+            .unwrap()
+            .is_some()
+    {
+        return;
+    }
+
+    // Being invited replaces the approval of a pending join request
+    db::delete_join_request(dbh, gid, user.uid).await.unwrap();
+    db::join_group(dbh, gid, user.uid).await.unwrap();
+    db::audit(
+        dbh,
+        AuditType::JoinGroup,
+        json!({
+            "user": {
+                "id": user.uid,
+                "name": user.name,
+            },
+            "group": {
+                "id": gid,
+                "name": group.name,
+            },
+            "invited_by": {
+                "id": invitation.invited_by,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+    notify::owner_user_joined_group(dbh, myconfig, user, group).await;
+}
+
+#[post("/archive-group", data = "<input>")]
+async fn archive_group_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<GroupIdForm>,
+) -> Template {
+    set_group_archived(dbh, myconfig, visitor, input.gid, true).await
+}
+
+#[post("/unarchive-group", data = "<input>")]
+async fn unarchive_group_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<GroupIdForm>,
+) -> Template {
+    set_group_archived(dbh, myconfig, visitor, input.gid, false).await
+}
+
+/// The owner and the site admins can archive a group and reopen it.
+async fn set_group_archived(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    visitor: LoggedIn,
+    gid: usize,
+    archive: bool,
+) -> Template {
+    let config = get_public_config();
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        );
+    };
+
+    let user = visitor.user.clone().unwrap();
+    if group.owner != user.uid && !visitor.admin {
+        return Template::render(
+            "message",
+            context! {title: "Not the owner", message: format!("You are not the owner of the group <b>{gid}</b>"), config, visitor},
+        );
+    }
+
+    if group.archived.is_some() == archive {
+        return Template::render(
+            "message",
+            context! {title: "Nothing to do", message: format!(r#"The <a href="/group/{gid}">{}</a> group is {} archived."#, escape_html(&group.name), if archive { "already" } else { "not" }), config, visitor},
+        );
+    }
+
+    db::set_group_archived(dbh, gid, archive.then(Utc::now))
+        .await
+        .unwrap();
+    db::audit(
+        dbh,
+        if archive {
+            AuditType::GroupArchived
+        } else {
+            AuditType::GroupUnarchived
+        },
+        json!({
+            "group": {
+                "id": gid,
+                "name": group.name,
+            },
+            "user": {
+                "id": user.uid,
+                "name": user.name,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+
+    if archive {
+        notify::members_group_archived(dbh, myconfig, &group).await;
+        Template::render(
+            "message",
+            context! {title: "Group archived", message: format!(r#"The <a href="/group/{gid}">{}</a> group was archived."#, escape_html(&group.name)), config, visitor},
+        )
+    } else {
+        Template::render(
+            "message",
+            context! {title: "Group reopened", message: format!(r#"The <a href="/group/{gid}">{}</a> group was reopened."#, escape_html(&group.name)), config, visitor},
+        )
+    }
+}
+
+#[post("/leave-group", data = "<input>")]
+async fn leave_group_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<GroupIdForm>,
+) -> Template {
+    let config = get_public_config();
+    let gid = input.gid;
+
+    let group = db::get_group_by_gid(dbh, gid).await.unwrap();
+    if group.is_none() {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        );
+    }
+    let group = group.unwrap();
+
+    let user = visitor.user.clone().unwrap();
+    let uid = visitor.user.clone().unwrap().uid;
+    if uid == group.owner {
+        return Template::render(
+            "message",
+            context! {title: "You are the owner of this group", message: "You cannot leave a group you own.", config, visitor},
+        );
+    }
+
+    let member = db::get_membership(dbh, gid, uid).await.unwrap();
+    if member.is_none() {
+        return Template::render(
+            "message",
+            context! {title: "You are not a member of this group", message: "You cannot leave a group where you are not a member.", config, visitor},
+        );
+    }
+
+    db::leave_group(dbh, gid, uid).await.unwrap();
+    notify::owner_user_left_group(dbh, myconfig, &user, &group).await;
+    db::audit(
+        dbh,
+        AuditType::LeaveGroup,
+        json!({
+            "user": {
+                "id": uid,
+                "name": user.name,
+            },
+            "group": {
+                "id": gid,
+                "name": group.name,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+
+    Template::render(
+        "message",
+        context! {title: "Membership", message: format!(r#"User removed from <a href="/group/{gid}">group</a>"#), config, visitor},
+    )
+}
+
+#[get("/remove-member?<gid>&<uid>")]
+async fn remove_member_get(
+    dbh: &State<Surreal<Client>>,
+    visitor: LoggedIn,
+    gid: usize,
+    uid: usize,
+) -> Template {
+    let config = get_public_config();
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        );
+    };
+
+    let organizer = visitor.user.clone().unwrap();
+    if !db::get_group_role(dbh, &group, organizer.uid)
+        .await
+        .unwrap()
+        .can_organize()
+    {
+        return Template::render(
+            "message",
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{gid}</b>"), config, visitor},
+        );
+    }
+
+    let Some(user) = db::get_user_by_uid(dbh, uid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such user", message: format!("The user ID <b>{uid}</b> does not exist."), config, visitor},
+        );
+    };
+
+    Template::render(
+        "remove_member",
+        context! {title: "Remove member", group, user, config, visitor},
+    )
+}
+
+/// Organizers can remove a member and optionally ban them from joining again.
+/// Only the owner can remove a group admin.
+#[post("/remove-member", data = "<input>")]
+async fn remove_member_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<RemoveMemberForm>,
+) -> Template {
+    let config = get_public_config();
+    let gid = input.gid;
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        );
+    };
+
+    let organizer = visitor.user.clone().unwrap();
+    let role = db::get_group_role(dbh, &group, organizer.uid)
+        .await
+        .unwrap();
+    if !role.can_organize() {
+        return Template::render(
+            "message",
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{gid}</b>"), config, visitor},
+        );
+    }
+
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
+
+    let Some(user) = db::get_user_by_uid(dbh, input.uid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such user", message: format!("The user ID <b>{}</b> does not exist.", input.uid), config, visitor},
+        );
+    };
+
+    if user.uid == group.owner || user.uid == organizer.uid {
+        return Template::render(
+            "message",
+            context! {title: "Cannot remove", message: format!("<b>{}</b> cannot be removed from the group.", escape_html(&user.name)), config, visitor},
+        );
+    }
+
+    let membership = db::get_membership(dbh, gid, user.uid).await.unwrap();
+    if membership.is_none() && !input.ban {
+        return Template::render(
+            "message",
+            context! {title: "Not a member", message: format!("<b>{}</b> is not a member of this group.", escape_html(&user.name)), config, visitor},
+        );
+    }
+    if membership
+        .as_ref()
+        .is_some_and(|membership| membership.admin)
+        && !role.can_manage_admins()
+    {
+        return Template::render(
+            "message",
+            context! {title: "Not the owner", message: "Only the owner can remove a group admin.", config, visitor},
+        );
+    }
+    if input.ban && db::get_ban(dbh, gid, user.uid).await.unwrap().is_some() {
+        return Template::render(
+            "message",
+            context! {title: "Already banned", message: format!("<b>{}</b> is already banned from this group.", escape_html(&user.name)), config, visitor},
+        );
+    }
+
+    let reason = input.reason.trim();
+    remove_member(dbh, myconfig, &organizer, &user, &group, reason, input.ban).await;
+
+    if input.notify {
+        notify::member_removed_from_group(myconfig, &user, &group, reason, input.ban).await;
+    }
+
+    let banned = if input.ban {
+        " and banned from joining again"
+    } else {
+        ""
+    };
+    Template::render(
+        "message",
+        context! {title: "Member removed", message: format!(r#"<b>{}</b> was removed from the <a href="/group/{gid}">group</a>{banned}."#, escape_html(&user.name)), config, visitor},
+    )
+}
+
+/// Remove the membership, the pending join request and the RSVPs to future events and record it.
+/// The freed spots go to the people on the waitlists.
+async fn remove_member(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    organizer: &User,
+    user: &User,
+    group: &Group,
+    reason: &str,
+    ban: bool,
+) {
+    let gid = group.gid;
+    db::leave_group(dbh, gid, user.uid).await.unwrap();
+    db::delete_join_request(dbh, gid, user.uid).await.unwrap();
+    let cancelled = db::cancel_future_rsvps(dbh, gid, user.uid)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|rsvp| rsvp.eid)
+        .collect::<Vec<_>>();
+    if ban {
+        let entry = Ban {
+            id: Thing::from(("ban", Id::ulid())),
+            gid,
+            uid: user.uid,
+            reason: reason.to_owned(),
+            banned_by: organizer.uid,
+            date: Utc::now(),
+        };
+        db::add_ban(dbh, &entry).await.unwrap();
+    }
+
+    db::audit(
+        dbh,
+        AuditType::MemberRemoved,
+        json!({
+            "user": {
+                "id": user.uid,
+                "name": user.name,
+            },
+            "group": {
+                "id": gid,
+                "name": group.name,
+            },
+            "organizer": {
+                "id": organizer.uid,
+                "name": organizer.name,
+            },
+            "reason": reason,
+            "ban": ban,
+            "cancelled_rsvps": cancelled,
+        }),
+    )
+    .await
+    .unwrap();
+
+    for eid in cancelled {
+        if let Some(event) = db::get_event_by_eid(dbh, eid).await.unwrap() {
+            promote_from_waitlist(dbh, myconfig, &event).await;
+        }
+    }
+}
+
+#[post("/unban-member", data = "<input>")]
+async fn unban_member_post(
+    dbh: &State<Surreal<Client>>,
+    visitor: LoggedIn,
+    input: CsrfForm<JoinRequestForm>,
+) -> Template {
+    let config = get_public_config();
+    let gid = input.gid;
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        );
+    };
+
+    let organizer = visitor.user.clone().unwrap();
+    if !db::get_group_role(dbh, &group, organizer.uid)
+        .await
+        .unwrap()
+        .can_organize()
+    {
+        return Template::render(
+            "message",
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{gid}</b>"), config, visitor},
+        );
+    }
+
+    let Some(user) = db::get_user_by_uid(dbh, input.uid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such user", message: format!("The user ID <b>{}</b> does not exist.", input.uid), config, visitor},
+        );
+    };
+
+    if db::delete_ban(dbh, gid, user.uid).await.unwrap().is_none() {
+        return Template::render(
+            "message",
+            context! {title: "Not banned", message: format!("<b>{}</b> is not banned from this group.", escape_html(&user.name)), config, visitor},
+        );
+    }
+
+    db::audit(
+        dbh,
+        AuditType::MemberUnbanned,
+        json!({
+            "user": {
+                "id": user.uid,
+                "name": user.name,
+            },
+            "group": {
+                "id": gid,
+                "name": group.name,
+            },
+            "organizer": {
+                "id": organizer.uid,
+                "name": organizer.name,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+
+    Template::render(
+        "message",
+        context! {title: "Ban lifted", message: format!(r#"<b>{}</b> can join the <a href="/group/{gid}">group</a> again."#, escape_html(&user.name)), config, visitor},
+    )
+}
+
+#[post("/set-group-admin", data = "<input>")]
+async fn set_group_admin_post(
+    dbh: &State<Surreal<Client>>,
+    visitor: LoggedIn,
+    input: CsrfForm<SetGroupAdminForm>,
+) -> Template {
+    let config = get_public_config();
+    let gid = input.gid;
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        );
+    };
+
+    let uid = visitor.user.clone().unwrap().uid;
+    if !db::get_group_role(dbh, &group, uid)
+        .await
+        .unwrap()
+        .can_manage_admins()
+    {
+        return Template::render(
+            "message",
+            context! {title: "Not the owner", message: format!("You are not the owner of the group <b>{gid}</b>"), config, visitor},
+        );
+    }
+
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
+
+    let Some(member) = db::get_user_by_uid(dbh, input.uid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such user", message: format!("The user ID <b>{}</b> does not exist.", input.uid), config, visitor},
+        );
+    };
+
+    if db::set_group_admin(dbh, gid, member.uid, input.admin)
+        .await
+        .unwrap()
+        .is_none()
+    {
+        return Template::render(
+            "message",
+            context! {title: "Not a member", message: format!("<b>{}</b> is not a member of this group.", escape_html(&member.name)), config, visitor},
+        );
+    }
+
+    db::audit(
+        dbh,
+        if input.admin {
+            AuditType::GroupAdminAdded
+        } else {
+            AuditType::GroupAdminRemoved
+        },
+        json!({
+            "user": {
+                "id": member.uid,
+                "name": member.name,
+            },
+            "group": {
+                "id": gid,
+                "name": group.name,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+
+    let message = if input.admin {
+        format!(
+            r#"<b>{}</b> is now an admin of the <a href="/group/{gid}">group</a>."#,
+            escape_html(&member.name)
+        )
+    } else {
+        format!(
+            r#"<b>{}</b> is no longer an admin of the <a href="/group/{gid}">group</a>."#,
+            escape_html(&member.name)
+        )
+    };
+    Template::render(
+        "message",
+        context! {title: "Group admins", message, config, visitor},
+    )
+}
+
+#[get("/transfer-group?<gid>")]
+async fn transfer_group_get(
+    dbh: &State<Surreal<Client>>,
+    visitor: LoggedIn,
+    gid: usize,
+) -> Template {
+    let config = get_public_config();
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        );
+    };
+
+    let uid = visitor.user.clone().unwrap().uid;
+    if group.owner != uid && !visitor.admin {
+        return Template::render(
+            "message",
+            context! {title: "Not the owner", message: format!("You are not the owner of the group <b>{gid}</b>"), config, visitor},
+        );
+    }
+
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
+
+    let members = db::get_members_of_group(dbh, gid).await.unwrap();
+    if members.is_empty() {
+        return Template::render(
+            "message",
+            context! {title: "No members", message: "The group can only be transferred to one of its members.", config, visitor},
+        );
+    }
+
+    Template::render(
+        "transfer_group",
+        context! {title: "Transfer Group", group, members, config, visitor},
+    )
+}
+
+#[post("/transfer-group", data = "<input>")]
+async fn transfer_group_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<TransferGroupForm>,
+) -> Template {
+    let config = get_public_config();
+    let gid = input.gid;
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        );
+    };
+
+    let user = visitor.user.clone().unwrap();
+    if group.owner != user.uid && !visitor.admin {
+        return Template::render(
+            "message",
+            context! {title: "Not the owner", message: format!("You are not the owner of the group <b>{gid}</b>"), config, visitor},
+        );
+    }
+
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
+
+    let Some(member) = db::get_user_by_uid(dbh, input.uid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such user", message: format!("The user ID <b>{}</b> does not exist.", input.uid), config, visitor},
+        );
+    };
+
+    if db::get_membership(dbh, gid, member.uid)
+        .await
+        .unwrap()
+        .is_none()
+    {
+        return Template::render(
+            "message",
+            context! {title: "Not a member", message: format!("<b>{}</b> is not a member of this group.", escape_html(&member.name)), config, visitor},
+        );
+    }
+
+    let code = create_token(
+        dbh,
+        myconfig,
+        member.uid,
+        TokenPurpose::GroupTransfer,
+        None,
+        Some(gid),
+        Some(group.owner),
+    )
+    .await;
+    notify::member_group_transfer_offered(myconfig, &user, &member, &group, &code).await;
+
+    db::audit(
+        dbh,
+        AuditType::GroupTransferRequested,
+        json!({
+            "user": {
+                "id": member.uid,
+                "name": member.name,
+            },
+            "group": {
+                "id": gid,
+                "name": group.name,
+            },
+            "requested_by": {
+                "id": user.uid,
+                "name": user.name,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+
+    Template::render(
+        "message",
+        context! {title: "Transfer requested", message: format!("We sent an email to <b>{}</b>. The group will be transferred once they accept.", escape_html(&member.name)), config, visitor},
+    )
+}
+
+#[get("/accept-group/<uid>/<code>")]
+async fn accept_group_get(
+    dbh: &State<Surreal<Client>>,
+    visitor: LoggedIn,
+    uid: usize,
+    code: &str,
+) -> Template {
+    rocket::info!("accept-group for uid={uid}");
+    let config = get_public_config();
+
+    let group = match check_transfer_offer(dbh, uid, code, &visitor).await {
+        Ok((_, group)) => group,
+        Err(page) => return *page,
+    };
+
+    Template::render(
+        "confirm",
+        context! {
+            title: "Accept group",
+            question: format!("Do you want to become the owner of the '{}' group?", group.name),
+            action: format!("/accept-group/{uid}/{code}"),
+            button: "Accept",
+            config,
+            visitor,
+        },
+    )
+}
+
+#[post("/accept-group/<uid>/<code>", data = "<_input>")]
+async fn accept_group_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    uid: usize,
+    code: &str,
+    _input: CsrfForm<CsrfOnlyForm<'_>>,
+) -> Template {
+    rocket::info!("accept-group for uid={uid}");
+    let config = get_public_config();
+
+    let (user, group) = match check_transfer_offer(dbh, uid, code, &visitor).await {
+        Ok(offer) => offer,
+        Err(page) => return *page,
+    };
+    let gid = group.gid;
+
+    if db::use_token(dbh, &hash_token(code))
+        .await
+        .unwrap()
+        .is_none()
+    {
+        return token_problem_page(TokenProblem::Used, "/groups", config, visitor.into());
+    }
+
+    let old_owner = db::get_user_by_uid(dbh, group.owner)
+        .await
+        .unwrap()
+        .unwrap();
+    db::transfer_group(dbh, gid, old_owner.uid, uid)
+        .await
+        .unwrap();
+
+    db::audit(
+        dbh,
+        AuditType::GroupTransferred,
+        json!({
+            "group": {
+                "id": gid,
+                "name": group.name,
+            },
+            "old_owner": {
+                "id": old_owner.uid,
+                "name": old_owner.name,
+            },
+            "owner": {
+                "id": uid,
+                "name": user.name,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+    notify::group_transferred(dbh, myconfig, &old_owner, &user, &group).await;
+
+    Template::render(
+        "message",
+        context! {title: "Group transferred", message: format!(r#"You are now the owner of the <a href="/group/{gid}">{}</a> group."#, escape_html(&group.name)), config, visitor},
+    )
+}
+
+/// The offer can be accepted only by the user it was sent to. It is valid only as long as the group
+/// has the same owner and the user is still a member.
+/// Returns the user and the group or the error page.
+async fn check_transfer_offer(
+    dbh: &Surreal<Client>,
+    uid: usize,
+    code: &str,
+    visitor: &LoggedIn,
+) -> Result<(User, Group), Box<Template>> {
+    let config = get_public_config();
+
+    let user = visitor.user.clone().unwrap();
+    if user.uid != uid {
+        return Err(Box::new(Template::render(
+            "message",
+            context! {title: "Wrong account", message: "This offer was sent to another user. Please login with the account that received the email.", config, visitor},
+        )));
+    }
+
+    let token = match check_token(dbh, uid, code, TokenPurpose::GroupTransfer).await {
+        Ok(token) => token,
+        Err(TokenProblem::Invalid) => {
+            return Err(Box::new(Template::render(
+                "message",
+                context! {title: "Invalid code", message: "Invalid code", config, visitor},
+            )));
+        }
+        Err(problem) => {
+            return Err(Box::new(token_problem_page(
+                problem,
+                "/groups",
+                config,
+                visitor.clone().into(),
+            )));
+        }
+    };
+    let Some(gid) = token.gid else {
+        return Err(Box::new(Template::render(
+            "message",
+            context! {title: "Invalid code", message: "Invalid code", config, visitor},
+        )));
+    };
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Err(Box::new(Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group ID <b>{gid}</b> does not exist."), config, visitor},
+        )));
+    };
+
+    if group.archived.is_some() {
+        return Err(Box::new(group_archived_page(&group, config, visitor)));
+    }
+
+    if token.owner != Some(group.owner) {
+        return Err(Box::new(Template::render(
+            "message",
+            context! {title: "Offer withdrawn", message: format!(r#"The <a href="/group/{gid}">group</a> has a new owner since the offer was made."#), config, visitor},
+        )));
+    }
+
+    if db::get_membership(dbh, gid, uid).await.unwrap().is_none() {
+        return Err(Box::new(Template::render(
+            "message",
+            context! {title: "Not a member", message: format!(r#"You are no longer a member of the <a href="/group/{gid}">group</a>."#), config, visitor},
+        )));
+    }
+
+    Ok((user, group))
+}
+
+#[post("/rsvp-yes-event", data = "<input>")]
+async fn rsvp_yes_event_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<EventIdForm>,
+) -> Template {
+    let config = get_public_config();
+    let eid = input.eid;
+
+    let Some(event) = db::get_event_by_eid(dbh, eid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such event", message: "No such event", config, visitor},
+        );
+    };
+
+    if event.status != EventStatus::Published {
+        return rsvp_closed_page(&event, config, visitor);
+    }
+
+    let gid = event.group_id;
+    let group = db::get_group_by_gid(dbh, gid).await.unwrap().unwrap();
+
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
+
+    let user = visitor.user.clone().unwrap();
+    let uid = visitor.user.clone().unwrap().uid;
+
+    if uid == group.owner {
+        return Template::render(
+            "message",
+            context! {title: "You are the owner of this group", message: "You cannot join an event in a group you own.", config, visitor},
+        );
+    }
+
+    // if user is not a member of the group join it
+    let member = db::get_membership(dbh, gid, uid).await.unwrap();
+    if member.is_none() && db::get_ban(dbh, gid, uid).await.unwrap().is_some() {
+        return banned_page(&group, config, visitor);
+    }
+    if member.is_none() && group.requires_approval {
+        return Template::render(
+            "message",
+            context! {title: "Membership required", message: format!(r#"You need to be a member of the <a href="/group/{gid}">{}</a> group to register to its events. Please ask to join the group first."#, escape_html(&group.name)), config, visitor},
+        );
+    }
+    if member.is_none() {
+        join_group_of_event(dbh, myconfig, &user, &group).await;
+    }
+
+    let rsvp = db::get_rsvp(dbh, eid, uid).await.unwrap();
+    if rsvp.as_ref().is_some_and(|rsvp| rsvp.status) {
+        return Template::render(
+            "message",
+            context! {title: "You were already RSVPed", message: format!("You were already RSVPed"), config, visitor},
+        );
+    }
+
+    if let Some(position) = waitlist_position(dbh, eid, uid).await {
+        return Template::render(
+            "message",
+            context! {title: "You are on the waitlist", message: format!(r#"You are already #{position} on the waitlist of the <a href="/event/{eid}">event</a>."#), config, visitor},
+        );
+    }
+
+    if let Some(capacity) = event.capacity {
+        if !db::rsvp_yes_within_capacity(dbh, eid, uid, capacity)
+            .await
+            .unwrap()
+        {
+            return join_waitlist(dbh, visitor, &event).await;
+        }
+    } else if rsvp.is_some() {
+        db::update_rsvp(dbh, eid, uid, true).await.unwrap();
+    } else {
+        db::new_rsvp(dbh, eid, uid, true).await.unwrap();
+    }
+
+    db::audit(
+        dbh,
+        if rsvp.is_some() {
+            AuditType::RSVPYesAgain
+        } else {
+            AuditType::RSVPYes
+        },
+        json!({
+            "user": {
+                "id": uid,
+                "name": user.name,
+            },
+            "event": {
+                "id": eid,
+                "title": event.title,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+    //notify::owner_user_rsvped_to_event(dbh, myconfig, &user, &group, &event).await;
+
+    Template::render(
+        "message",
+        context! {title: "RSVPed to event", message: format!(r#"User RSVPed to <a href="/event/{eid}">event</a>"#), config, visitor},
+    )
+}
+
+/// The RSVPs of cancelled events are frozen. Drafts and hidden events cannot be found.
+fn rsvp_closed_page<V: Serialize>(event: &Event, config: PublicConfig, visitor: V) -> Template {
+    if event.status == EventStatus::Cancelled {
+        return Template::render(
+            "message",
+            context! {title: "Event cancelled", message: format!(r#"The <a href="/event/{}">event</a> was cancelled."#, event.eid), config, visitor},
+        );
+    }
+    Template::render(
+        "message",
+        context! {title: "No such event", message: "No such event", config, visitor},
+    )
+}
+
+/// RSVP-ing to an event of an open group makes the user a member of the group.
+async fn join_group_of_event(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    user: &User,
+    group: &Group,
+) {
+    db::join_group(dbh, group.gid, user.uid).await.unwrap();
+    db::audit(
+        dbh,
+        AuditType::JoinGroup,
+        json!({
+            "user": {
+                "id": user.uid,
+                "name": user.name,
+            },
+            "group": {
+                "id": group.gid,
+                "name": group.name,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+    notify::owner_user_joined_group(dbh, myconfig, user, group).await;
+}
+
+/// The event is full so the user is added to the end of the waitlist.
+async fn join_waitlist(dbh: &Surreal<Client>, visitor: LoggedIn, event: &Event) -> Template {
+    let config = get_public_config();
+    let user = visitor.user.clone().unwrap();
+    let eid = event.eid;
+
+    db::add_to_waitlist(dbh, eid, user.uid).await.unwrap();
+    db::audit(
+        dbh,
+        AuditType::Waitlisted,
+        json!({
+            "user": {
+                "id": user.uid,
+                "name": user.name,
+            },
+            "event": {
+                "id": eid,
+                "title": event.title,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+
+    let position = waitlist_position(dbh, eid, user.uid)
+        .await
+        .unwrap_or_default();
+    Template::render(
+        "message",
+        context! {title: "Event is full", message: format!(r#"The <a href="/event/{eid}">event</a> is full. You are #{position} on the waitlist. We will send you an email if a spot becomes available."#), config, visitor},
+    )
+}
+
+/// The place of the user on the waitlist of the event starting from 1.
+async fn waitlist_position(dbh: &Surreal<Client>, eid: usize, uid: usize) -> Option<usize> {
+    db::get_waitlist(dbh, eid)
+        .await
+        .unwrap()
+        .iter()
+        .position(|entry| entry.uid == uid)
+        .map(|index| index.saturating_add(1))
+}
+
+/// Give the free spots of a future event to the people on the waitlist, in the order they joined it.
+async fn promote_from_waitlist(dbh: &Surreal<Client>, myconfig: &MyConfig, event: &Event) {
+    let eid = event.eid;
+    if event.status != EventStatus::Published || event.date < Utc::now() {
+        return;
+    }
+
+    loop {
+        let Some(entry) = db::get_waitlist(dbh, eid).await.unwrap().into_iter().next() else {
+            return;
+        };
+
+        if let Some(capacity) = event.capacity {
+            if !db::rsvp_yes_within_capacity(dbh, eid, entry.uid, capacity)
+                .await
+                .unwrap()
+            {
+                return;
+            }
+        } else if db::get_rsvp(dbh, eid, entry.uid).await.unwrap().is_some() {
+            db::update_rsvp(dbh, eid, entry.uid, true).await.unwrap();
+        } else {
+            db::new_rsvp(dbh, eid, entry.uid, true).await.unwrap();
+        }
+        db::remove_from_waitlist(dbh, eid, entry.uid).await.unwrap();
+
+        // We assume that each uid will have a user
+        let user = db::get_user_by_uid(dbh, entry.uid).await.unwrap().unwrap();
+        db::audit(
+            dbh,
+            AuditType::WaitlistPromoted,
+            json!({
+                "user": {
+                    "id": user.uid,
+                    "name": user.name,
+                },
+                "event": {
+                    "id": eid,
+                    "title": event.title,
+                },
+            }),
+        )
+        .await
+        .unwrap();
+        notify::attendee_promoted_from_waitlist(myconfig, &user, event).await;
+    }
+}
+
+#[post("/rsvp-no-event", data = "<input>")]
+async fn rsvp_no_event_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<EventIdForm>,
+) -> Template {
+    let config = get_public_config();
+    let eid = input.eid;
+
+    let Some(event) = db::get_event_by_eid(dbh, eid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such event", message: "No such event", config, visitor},
+        );
+    };
+
+    if event.status != EventStatus::Published {
+        return rsvp_closed_page(&event, config, visitor);
+    }
+
+    let user = visitor.user.clone().unwrap();
+    let uid = user.uid;
+
+    if db::remove_from_waitlist(dbh, eid, uid)
+        .await
+        .unwrap()
+        .is_some()
+    {
+        db::audit(
+            dbh,
+            AuditType::WaitlistLeft,
+            json!({
+                "user": {
+                    "id": uid,
+                    "name": user.name,
+                },
+                "event": {
+                    "id": eid,
+                    "title": event.title,
+                },
+            }),
+        )
+        .await
+        .unwrap();
+        return Template::render(
+            "message",
+            context! {title: "Left the waitlist", message: format!(r#"You are not on the waitlist of the <a href="/event/{eid}">event</a> any more."#), config, visitor},
+        );
+    }
+
+    let Some(rsvp) = db::get_rsvp(dbh, eid, uid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "You were not registered to the event", message: format!(r#"You were not registered to the <a href="/event/{eid}">event</a>"#), config, visitor},
+        );
+    };
+    db::update_rsvp(dbh, eid, uid, false).await.unwrap();
+    db::audit(
+        dbh,
+        AuditType::RSVPNo,
+        json!({
+            "user": {
+                "id": uid,
+                "name": user.name,
+            },
+            "event": {
+                "id": eid,
+                "title": event.title,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+
+    if rsvp.status {
+        promote_from_waitlist(dbh, myconfig, &event).await;
+    }
+
+    Template::render(
+        "message",
+        context! {title: "Not attending", message: format!(r#"User not attending <a href="/event/{eid}">event</a>"#), config, visitor},
+    )
+}
+
+#[get("/profile")]
+async fn show_profile(
+    cookies: &CookieJar<'_>,
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+) -> Template {
+    let config = get_public_config();
+
+    let uid = visitor.user.clone().unwrap().uid;
+    let owned_groups = db::get_groups_by_owner_id(dbh, uid).await.unwrap();
+
+    let totp_enabled = db::get_totp(dbh, uid)
+        .await
+        .unwrap()
+        .is_some_and(|totp| totp.enabled);
+
+    let current_sid = web::get_session_id(cookies).unwrap_or_default();
+    let sessions = db::get_sessions_by_uid(dbh, uid)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|session| {
+            let current = session.sid == current_sid;
+            (session.id.id.to_raw(), session, current)
+        })
+        .collect::<Vec<_>>();
+
+    let identities = db::get_identities_by_uid(dbh, uid).await.unwrap();
+    let linked_accounts = myconfig
+        .oauth_providers
+        .iter()
+        .map(|provider| {
+            let identity = identities
+                .iter()
+                .find(|identity| identity.provider == provider.name);
+            (provider, identity)
+        })
+        .collect::<Vec<_>>();
+
+    let groups = db::get_groups_by_membership_id(dbh, uid).await.unwrap();
+    rocket::info!("groups: {groups:?}");
+
+    let mine = owned_groups
+        .iter()
+        .chain(groups.iter().map(|pair| &pair.0))
+        .collect::<Vec<_>>();
+    let suggested_groups = suggest_groups(
+        &mine,
+        db::get_groups(dbh).await.unwrap(),
+        MAX_SUGGESTED_GROUPS,
+    );
+
+    let api_tokens = db::get_api_tokens_by_uid(dbh, uid)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|token| {
+            let scopes = token
+                .scopes
+                .iter()
+                .map(|scope| scope.name())
+                .collect::<Vec<_>>()
+                .join(", ");
+            (token.id.id.to_raw(), token, scopes)
+        })
+        .collect::<Vec<_>>();
+    let api_scopes = ApiScope::ALL.map(ApiScope::name);
+    let calendar_token = db::get_calendar_token_by_uid(dbh, uid).await.unwrap();
+
+    let about = "";
+    // let about = visitor
+    //     .user
+    //     .clone()
+    //     .unwrap()
+    //     .about
+    //     .map(|text| markdown2html(&text).unwrap());
+
+    Template::render(
+        "profile",
+        context! {title: "Profile", user: visitor.user.clone(), about, owned_groups, groups, suggested_groups, sessions, totp_enabled, linked_accounts, api_tokens, api_scopes, calendar_token, config, visitor},
+    )
+}
+
+#[get("/edit-profile")]
+fn edit_profile_get(visitor: LoggedIn) -> Template {
+    let config = get_public_config();
+
+    Template::render(
+        "edit_profile",
+        context! {title: "Edit Profile", user: visitor.user.clone(), config, visitor},
+    )
+}
+
+#[post("/edit-profile", data = "<input>")]
+async fn edit_profile_post(
+    dbh: &State<Surreal<Client>>,
+    input: CsrfForm<ProfileForm<'_>>,
+    visitor: LoggedIn,
+) -> Template {
+    let config = get_public_config();
+
+    let re_github = Regex::new("^[a-zA-Z0-9]*$").unwrap();
+    let re_gitlab = Regex::new("^[a-zA-Z0-9]*$").unwrap();
+    let re_linkedin = Regex::new("^https://www.linkedin.com/in/[a-zA-Z0-9-]+/?$").unwrap();
+
+    let uid = visitor.user.clone().unwrap().uid;
+    let name = input.name.trim();
+    let github = input.github.trim();
+    let gitlab = input.gitlab.trim();
+    let linkedin = input.linkedin.trim();
+    //let about = input.about;
+    let about = "";
+
+    if !re_github.is_match(github) {
+        return Template::render(
+            "message",
+            context! {title: "Invalid GitHub username", message: format!("The GitHub username `{github}` is not valid.", github = escape_html(github)), config, visitor},
+        );
+    }
+
+    if !re_gitlab.is_match(gitlab) {
+        return Template::render(
+            "message",
+            context! {title: "Invalid GitLab username", message: format!("The GitLab username `{gitlab}` is not valid.", gitlab = escape_html(gitlab)), config, visitor},
+        );
+    }
+
+    if !linkedin.is_empty() && !re_linkedin.is_match(linkedin) {
+        return Template::render(
+            "message",
+            context! {title: "Invalid LinkedIn profile link", message: format!("The LinkedIn profile link `{linkedin}` is not valid.", linkedin = escape_html(linkedin)), config, visitor},
+        );
+    }
+
+    if MAX_NAME_LEN < name.len() {
+        return Template::render(
+            "message",
+            context! {title: "Name is too long", message: format!("Name is too long. Max {MAX_NAME_LEN} while the current name is {} long. Please try again.", name.len()), config, visitor},
+        );
+    }
+
+    let re_name = get_re_name();
+
+    if !re_name.is_match(name) {
+        return Template::render(
+            "message",
+            context! {title: "Invalid character", message: format!(r#"The name '{name}' contains a character that we currently don't accept. Use Latin letters for now and comment on <a href="https://github.com/szabgab/meet-os.rs/issues/38">this issue</a> where this topic is discussed."#, name = escape_html(name)), config, visitor},
+        );
+    }
+
+    db::update_user(dbh, uid, name, github, gitlab, linkedin, about)
+        .await
+        .unwrap();
+
+    Template::render(
+        "message",
+        context! {title: "Profile updated", message: format!(r#"Check out the <a href="/profile">profile</a> and how others see it <a href="/user/{uid}">{name}</a>"#, name = escape_html(name)), config, visitor},
+    )
+}
+
+#[post("/change-email", data = "<input>")]
+async fn change_email_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<ResetPasswordForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+    let user = visitor.user.clone().unwrap();
+
+    let email = input.email.to_lowercase().trim().to_owned();
+    if !validator::validate_email(&email) {
+        return Template::render(
+            "message",
+            context! {title: "Invalid email address", message: format!("Invalid email address <b>{}</b>. Please try again", escape_html(input.email)), config, visitor},
+        );
+    }
+
+    if email == user.email {
+        return Template::render(
+            "message",
+            context! {title: "Same email address", message: format!("<b>{email}</b> is already your email address.", email = escape_html(&email)), config, visitor},
+        );
+    }
+
+    if db::get_user_by_email(dbh, &email).await.unwrap().is_some() {
+        return Template::render(
+            "message",
+            context! {title: "Email address taken", message: format!("The email address <b>{email}</b> is already used by another account.", email = escape_html(&email)), config, visitor},
+        );
+    }
+
+    let uid = user.uid;
+    let code = create_token(
+        dbh,
+        myconfig,
+        uid,
+        TokenPurpose::EmailChange,
+        Some(email.clone()),
+        None,
+        None,
+    )
+    .await;
+
+    let base_url = &myconfig.base_url;
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+
+    let confirm_subject = "Confirm your new email address for Meet-OS";
+    let confirm_text = format!(
+        r#"Hi,
+    <p>
+    Someone asked to use this email address for their account on the Meet-OS web site.
+    If it was you, please <a href="{base_url}/change-email/{uid}/{code}">click on this link</a> to confirm the change.
+    <p>
+    <p>
+    If it was not you, we would like to apologize. You don't need to do anything...
+    "#
+    );
+    let to_address = &EmailAddress {
+        name: user.name.clone(),
+        email: email.clone(),
+    };
+    sendmail(myconfig, &from, to_address, confirm_subject, &confirm_text).await;
+
+    let notice_subject = "Your Meet-OS email address is about to change";
+    let notice_text = format!(
+        r#"Hi,
+    <p>
+    Someone asked to change the email address of your account on the Meet-OS web site to {email}.
+    We sent a confirmation link to the new address. The change will only happen after it is confirmed.
+    <p>
+    <p>
+    If it was not you, please <a href="{base_url}/reset-password">reset your password</a>.
+    "#
+    );
+    let old_address = &EmailAddress {
+        name: user.name.clone(),
+        email: user.email.clone(),
+    };
+    sendmail(myconfig, &from, old_address, notice_subject, &notice_text).await;
+
+    Template::render(
+        "message",
+        context! {title: "We sent you an email", message: format!("We sent you an email to <b>{email}</b> Please click on the link to confirm the change.", email = escape_html(&email)), config, visitor},
+    )
+}
+
+#[get("/change-email/<uid>/<code>")]
+async fn change_email_get(
+    dbh: &State<Surreal<Client>>,
+    visitor: Visitor,
+    uid: usize,
+    code: &str,
+) -> Result<Template, Status> {
+    rocket::info!("change-email for uid={uid}");
+    let config = get_public_config();
+
+    let Some(user) = db::get_user_by_uid(dbh, uid).await.unwrap() else {
+        return Ok(Template::render(
+            "message",
+            context! {title: "Invalid id", message: format!("Invalid id <b>{uid}</b>"), config, visitor},
+        ));
+    };
+
+    let token = match check_token(dbh, uid, code, TokenPurpose::EmailChange).await {
+        Ok(token) => token,
+        Err(TokenProblem::Invalid) => {
+            return Ok(Template::render(
+                "message",
+                context! {title: "Invalid code", message: "Invalid code", config, visitor},
+            ));
+        }
+        Err(problem) => {
+            return Ok(token_problem_page(
+                problem,
+                "/edit-profile",
+                config,
+                visitor,
+            ));
+        }
+    };
+    let email = token.email.unwrap();
+
+    // Use the token first so the same link cannot change the address twice.
+    if db::use_token(dbh, &hash_token(code))
+        .await
+        .unwrap()
+        .is_none()
+    {
+        return Ok(token_problem_page(
+            TokenProblem::Used,
+            "/edit-profile",
+            config,
+            visitor,
+        ));
+    }
+
+    match db::update_user_email(dbh, uid, &email).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(Status::NotFound),
+        Err(err) if db::is_duplicate(&err, "user_email") => {
+            return Ok(Template::render(
+                "message",
+                context! {title: "Email address taken", message: format!("The email address <b>{email}</b> is already used by another account.", email = escape_html(&email)), config, visitor},
+            ));
+        }
+        Err(err) => {
+            rocket::error!("Could not change the email address of user {uid}: {err}");
+            return Err(Status::InternalServerError);
+        }
+    }
+
+    db::audit(
+        dbh,
+        AuditType::EmailChanged,
+        json!({
+            "user": {
+                "id": uid,
+                "name": user.name,
+            },
+            "old_email": user.email,
+            "new_email": email,
+        }),
+    )
+    .await
+    .unwrap();
+
+    Ok(Template::render(
+        "message",
+        context! {title: "Email address changed", message: format!(r#"Your email address was changed to <b>{email}</b>. Check your <a href="/profile">profile</a>."#, email = escape_html(&email)), config, visitor},
+    ))
+}
+
+/// A page found by an old slug redirects to the current address.
+#[derive(Responder)]
+enum PageOrRedirect {
+    Page(Template),
+    Redirect(Redirect),
+}
+
+/// The slugs to keep redirecting after changing `current` to `new`.
+fn renamed_slugs(current: &str, old_slugs: &[String], new: &str) -> Vec<String> {
+    old_slugs
+        .iter()
+        .map(String::as_str)
+        .chain([current])
+        .filter(|slug| !slug.is_empty() && *slug != new)
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// The name of a group must be between 1 and `MAX_NAME_LEN` characters.
+fn check_group_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || MAX_NAME_LEN < name.len() {
+        return Err(format!(
+            "The name of the group must be between 1 and {MAX_NAME_LEN} characters."
+        ));
+    }
+    Ok(())
+}
+
+enum SlugProblem {
+    NotOwner,
+    Invalid,
+    Taken,
+}
+
+/// Move the group to the new address and keep redirecting from the old ones.
+/// Only the owner can do it. An empty `slug` keeps the current address.
+async fn change_group_slug(
+    dbh: &Surreal<Client>,
+    group: &Group,
+    role: GroupRole,
+    slug: &str,
+) -> Result<(), SlugProblem> {
+    if slug.is_empty() || slug == group.slug {
+        return Ok(());
+    }
+    if role != GroupRole::Owner {
+        return Err(SlugProblem::NotOwner);
+    }
+    if slugify(slug) != slug {
+        return Err(SlugProblem::Invalid);
+    }
+    if !db::is_group_slug_free(dbh, slug, group.gid).await.unwrap() {
+        return Err(SlugProblem::Taken);
+    }
+    let old_slugs = renamed_slugs(&group.slug, &group.old_slugs, slug);
+    db::set_group_slug(dbh, group.gid, slug, &old_slugs)
+        .await
+        .unwrap();
+
+    Ok(())
+}
+
+fn invalid_slug_page<V: Serialize>(slug: &str, config: PublicConfig, visitor: V) -> Template {
+    Template::render(
+        "message",
+        context! {title: "Invalid address", message: format!("The address <b>{slug}</b> can only contain lower case letters, digits and single dashes. e.g. <b>{}</b>", slugify(slug), slug = escape_html(slug)), config, visitor},
+    )
+}
+
+fn slug_taken_page<V: Serialize>(slug: &str, config: PublicConfig, visitor: V) -> Template {
+    Template::render(
+        "message",
+        context! {title: "Address taken", message: format!("The address <b>{slug}</b> is already in use.", slug = escape_html(slug)), config, visitor},
+    )
+}
+
+#[derive(Responder)]
+struct Attachment {
+    inner: (ContentType, String),
+    disposition: Header<'static>,
+}
+
+/// Does the audit entry refer to this user either by uid or by email address?
+fn audit_mentions_user(entry: &Audit, user: &User) -> bool {
+    let Ok(data) = serde_json::from_str::<serde_json::Value>(&entry.text) else {
+        return false;
+    };
+
+    [
+        "/user/id",
+        "/owner/id",
+        "/old_owner/id",
+        "/requested_by/id",
+        "/organizer/id",
+        "/invited_by/id",
+    ]
+    .iter()
+    .any(|pointer| data.pointer(pointer) == Some(&json!(user.uid)))
+        || ["/email", "/old_email", "/new_email"]
+            .iter()
+            .any(|pointer| data.pointer(pointer) == Some(&json!(user.email)))
+}
+
+#[get("/profile/export")]
+async fn profile_export(dbh: &State<Surreal<Client>>, visitor: LoggedIn) -> Attachment {
+    let user = visitor.user.unwrap();
+    let uid = user.uid;
+
+    let memberships = db::get_groups_by_membership_id(dbh, uid).await.unwrap();
+    let join_requests = db::get_join_requests_by_uid(dbh, uid).await.unwrap();
+    let rsvps = db::get_rsvps_by_uid(dbh, uid).await.unwrap();
+    let sessions = db::get_sessions_by_uid(dbh, uid).await.unwrap();
+    let identities = db::get_identities_by_uid(dbh, uid).await.unwrap();
+    let api_tokens = db::get_api_tokens_by_uid(dbh, uid).await.unwrap();
+    let audit = db::get_audit(dbh)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|entry| audit_mentions_user(entry, &user))
+        .collect::<Vec<_>>();
+
+    // We don't include the password hash, the two-factor secrets and the hashes of the API tokens.
+    let data = json!({
+        "user": {
+            "uid": uid,
+            "name": user.name,
+            "email": user.email,
+            "registration_date": user.registration_date,
+            "verification_date": user.verification_date,
+            "github": user.github,
+            "gitlab": user.gitlab,
+            "linkedin": user.linkedin,
+            "about": user.about,
+        },
+        "memberships": memberships.into_iter().map(|(group, membership)| json!({
+            "gid": group.gid,
+            "group": group.name,
+            "join_date": membership.join_date,
+            "admin": membership.admin,
+        })).collect::<Vec<_>>(),
+        "join_requests": join_requests.iter().map(|request| json!({
+            "gid": request.gid,
+            "date": request.date,
+            "answers": request.answers,
+        })).collect::<Vec<_>>(),
+        "rsvps": rsvps.iter().map(|rsvp| json!({
+            "eid": rsvp.eid,
+            "date": rsvp.date,
+            "status": rsvp.status,
+        })).collect::<Vec<_>>(),
+        "sessions": sessions.iter().map(|session| json!({
+            "created": session.created,
+            "last_seen": session.last_seen,
+            "user_agent": session.user_agent,
+            "ip": session.ip,
+        })).collect::<Vec<_>>(),
+        "identities": identities.iter().map(|identity| json!({
+            "provider": identity.provider,
+            "subject": identity.subject,
+            "username": identity.username,
+            "created": identity.created,
+        })).collect::<Vec<_>>(),
+        "api_tokens": api_tokens.iter().map(|token| json!({
+            "name": token.name,
+            "scopes": token.scopes,
+            "created": token.created,
+            "last_used": token.last_used,
+        })).collect::<Vec<_>>(),
+        "audit": audit.iter().map(|entry| json!({
+            "date": entry.date,
+            "type": entry.atype,
+            "text": entry.text,
+        })).collect::<Vec<_>>(),
+    });
+
+    Attachment {
+        inner: (
+            ContentType::JSON,
+            serde_json::to_string_pretty(&data).unwrap(),
+        ),
+        disposition: Header::new(
+            "Content-Disposition",
+            format!(r#"attachment; filename="meet-os-{uid}.json""#),
+        ),
+    }
+}
+
+#[post("/profile/api-tokens", data = "<input>")]
+async fn api_token_create(
+    dbh: &State<Surreal<Client>>,
+    visitor: LoggedIn,
+    input: CsrfForm<ApiTokenForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+    let uid = visitor.user.clone().unwrap().uid;
+
+    let name = input.name.trim().to_owned();
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Template::render(
+            "message",
+            context! {title: "Invalid name", message: format!("The name of the token must be between 1 and {MAX_NAME_LEN} characters."), config, visitor},
+        );
+    }
+
+    let Some(scopes) = input
+        .scopes
+        .iter()
+        .map(|scope| ApiScope::from_name(scope))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return Template::render(
+            "message",
+            context! {title: "Invalid scope", message: "Unknown scope.", config, visitor},
+        );
+    };
+    if scopes.is_empty() {
+        return Template::render(
+            "message",
+            context! {title: "No scope selected", message: r#"Please select at least one scope on your <a href="/profile">profile</a>."#, config, visitor},
+        );
+    }
+
+    let token = format!("meetos_{}", Uuid::new_v4().simple());
+    let api_token = ApiToken {
+        id: Thing::from(("api_token", Id::ulid())),
+        uid,
+        name,
+        hash: hash_token(&token),
+        scopes,
+        created: Utc::now(),
+        last_used: None,
+    };
+    db::add_api_token(dbh, &api_token).await.unwrap();
+
+    Template::render(
+        "message",
+        context! {title: "API token created", message: format!(r#"Your new token is <code id="api-token">{token}</code> Copy it now, we won't show it again. Check your <a href="/profile">profile</a>."#), config, visitor},
+    )
+}
+
+#[post("/profile/api-tokens/revoke", data = "<input>")]
+async fn api_token_revoke(
+    dbh: &State<Surreal<Client>>,
+    visitor: LoggedIn,
+    input: CsrfForm<RecordIdForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+    let uid = visitor.user.clone().unwrap().uid;
+
+    let Some(token) = db::delete_api_token(dbh, uid, input.id).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such token", message: format!("There is no API token <b>{}</b>.", escape_html(input.id)), config, visitor},
+        );
+    };
+
+    Template::render(
+        "message",
+        context! {title: "API token revoked", message: format!(r#"The token <b>{}</b> was revoked. Check your <a href="/profile">profile</a>."#, escape_html(&token.name)), config, visitor},
+    )
+}
+
+#[get("/profile/delete")]
+fn profile_delete_get(visitor: LoggedIn) -> Template {
+    let config = get_public_config();
+    let has_password = !visitor.user.as_ref().unwrap().password.is_empty();
+
+    Template::render(
+        "delete_account",
+        context! {title: "Delete account", has_password, config, visitor},
+    )
+}
+
+#[post("/profile/delete", data = "<input>")]
+async fn profile_delete_post(
+    cookies: &CookieJar<'_>,
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<DeleteAccountForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+    let user = visitor.user.clone().unwrap();
+
+    // Users without a password confirm the deletion by clicking on a link we send them.
+    if user.password.is_empty() {
+        if let Some(page) = owned_groups_page(dbh, user.uid, &config, &visitor).await {
+            return page;
+        }
+        send_delete_account_email(dbh, myconfig, &user).await;
+        return Template::render(
+            "message",
+            context! {title: "We sent you an email", message: "Please click on the link in the email to delete your account.", config, visitor},
+        );
+    }
+
+    if !password::verify(&user.password, input.password.trim().as_bytes()) {
+        return Template::render(
+            "message",
+            context! {title: "Invalid password", message: r#"The password was not correct. Please <a href="/profile/delete">try again</a>."#, config, visitor},
+        );
+    }
+
+    if let Some(page) = owned_groups_page(dbh, user.uid, &config, &visitor).await {
+        return page;
+    }
+
+    delete_account(cookies, dbh, myconfig, &user, config).await
+}
+
+#[get("/profile/delete/<uid>/<code>")]
+fn profile_delete_confirm_get(visitor: LoggedIn, uid: usize, code: &str) -> Template {
+    let config = get_public_config();
+
+    Template::render(
+        "confirm",
+        context! {
+            title: "Delete account",
+            question: "Do you want to delete your account and all the related data? It cannot be undone.",
+            action: format!("/profile/delete/{uid}/{code}"),
+            button: "Delete my account",
+            config,
+            visitor,
+        },
+    )
+}
+
+#[post("/profile/delete/<uid>/<code>", data = "<_input>")]
+async fn profile_delete_confirm_post(
+    cookies: &CookieJar<'_>,
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    uid: usize,
+    code: &str,
+    _input: CsrfForm<CsrfOnlyForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+    let user = visitor.user.clone().unwrap();
+
+    let checked = if user.uid == uid {
+        check_token(dbh, uid, code, TokenPurpose::DeleteAccount).await
+    } else {
+        Err(TokenProblem::Invalid)
+    };
+    if let Err(problem) = checked {
+        return token_problem_page(problem, "/profile/delete", config, visitor.into());
+    }
+
+    if let Some(page) = owned_groups_page(dbh, user.uid, &config, &visitor).await {
+        return page;
+    }
+
+    if db::use_token(dbh, &hash_token(code))
+        .await
+        .unwrap()
+        .is_none()
+    {
+        return token_problem_page(
+            TokenProblem::Used,
+            "/profile/delete",
+            config,
+            visitor.into(),
+        );
+    }
+
+    delete_account(cookies, dbh, myconfig, &user, config).await
+}
+
+/// The owners of groups must transfer them before they can delete their account.
+async fn owned_groups_page(
+    dbh: &Surreal<Client>,
+    uid: usize,
+    config: &PublicConfig,
+    visitor: &LoggedIn,
+) -> Option<Template> {
+    let owned_groups = db::get_groups_by_owner_id(dbh, uid).await.unwrap();
+    if owned_groups.is_empty() {
+        return None;
+    }
+
+    let groups = owned_groups
+        .iter()
+        .map(|group| {
+            format!(
+                r#"<a href="/group/{}">{}</a>"#,
+                group.gid,
+                escape_html(&group.name)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(Template::render(
+        "message",
+        context! {title: "You own groups", message: format!("You are the owner of the following groups: {groups}. Please transfer their ownership before deleting your account."), config, visitor},
+    ))
+}
+
+async fn send_delete_account_email(dbh: &Surreal<Client>, myconfig: &MyConfig, user: &User) {
+    let uid = user.uid;
+    let code = create_token(
+        dbh,
+        myconfig,
+        uid,
+        TokenPurpose::DeleteAccount,
+        None,
+        None,
+        None,
+    )
+    .await;
+
+    let base_url = &myconfig.base_url;
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+    let subject = "Delete your Meet-OS account";
+    let text = format!(
+        r#"Hi,
+    <p>
+    Someone asked to delete your account on the Meet-OS web site.
+    If it was you, please <a href="{base_url}/profile/delete/{uid}/{code}">click on this link</a> to confirm.
+    <p>
+    <p>
+    If it was not you, you don't need to do anything. Your account stays as it is.
+    "#
+    );
+    let to_address = &EmailAddress {
+        name: user.name.clone(),
+        email: user.email.clone(),
+    };
+    sendmail(myconfig, &from, to_address, subject, &text).await;
+}
+
+async fn delete_account(
+    cookies: &CookieJar<'_>,
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    user: &User,
+    config: PublicConfig,
+) -> Template {
+    // The spots of the user at the future events go to the people on the waitlists
+    let mut freed = vec![];
+    for rsvp in db::get_rsvps_by_uid(dbh, user.uid).await.unwrap() {
+        if !rsvp.status {
+            continue;
+        }
+        if let Some(event) = db::get_event_by_eid(dbh, rsvp.eid).await.unwrap() {
+            if event.capacity.is_some() && Utc::now() < event.date {
+                freed.push(event);
+            }
+        }
+    }
+
+    db::delete_user(dbh, user.uid).await.unwrap();
+    web::end_session(cookies, dbh).await;
+    for event in &freed {
+        promote_from_waitlist(dbh, myconfig, event).await;
+    }
+
+    db::audit(
+        dbh,
+        AuditType::UserDeleted,
+        json!({
+            "user": {
+                "id": user.uid,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+    notify::admin_user_deleted(myconfig, user).await;
+
+    let visitor = Visitor::new_after_logout(cookies);
+    Template::render(
+        "message",
+        context! {title: "Account deleted", message: "Your account and all the related data were deleted.", config, visitor},
+    )
+}
+
+#[get("/event/<eid>")]
+async fn event_get(dbh: &State<Surreal<Client>>, visitor: Visitor, eid: usize) -> Template {
+    let event = db::get_event_by_eid(dbh, eid).await.unwrap().unwrap();
+    let group = db::get_group_by_gid(dbh, event.group_id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    event_page(dbh, visitor, group, event).await
+}
+
+#[get("/g/<group_slug>/e/<event_slug>")]
+async fn event_slug_get(
+    dbh: &State<Surreal<Client>>,
+    visitor: Visitor,
+    group_slug: &str,
+    event_slug: &str,
+) -> PageOrRedirect {
+    let config = get_public_config();
+
+    let event = match db::get_group_by_slug(dbh, group_slug).await.unwrap() {
+        Some(group) => db::get_event_by_slug(dbh, group.gid, event_slug)
+            .await
+            .unwrap()
+            .map(|event| (group, event)),
+        None => None,
+    };
+    let Some((group, event)) = event else {
+        return PageOrRedirect::Page(Template::render(
+            "message",
+            context! {title: "No such event", message: format!("The event <b>{event_slug}</b> does not exist.", event_slug = escape_html(event_slug)), config, visitor},
+        ));
+    };
+
+    if group.slug != group_slug || event.slug != event_slug {
+        return PageOrRedirect::Redirect(Redirect::permanent(format!(
+            "/g/{}/e/{}",
+            group.slug, event.slug
+        )));
+    }
+
+    PageOrRedirect::Page(event_page(dbh, visitor, group, event).await)
+}
+
+async fn event_page(
+    dbh: &State<Surreal<Client>>,
+    visitor: Visitor,
+    group: Group,
+    event: Event,
+) -> Template {
+    let config = get_public_config();
+    let eid = event.eid;
+
+    let can_organize = match visitor.user.as_ref() {
+        Some(user) => db::get_group_role(dbh, &group, user.uid)
+            .await
+            .unwrap()
+            .can_organize(),
+        None => false,
+    };
+
+    if !event.is_public() && !can_organize {
+        return Template::render(
+            "message",
+            context! {title: "No such event", message: format!("The event <b>{eid}</b> does not exist."), config, visitor},
+        );
+    }
+
+    let description = markdown2html(&event.description).unwrap();
+
+    let utc: DateTime<Utc> = Utc::now();
+    let editable = utc < event.date;
+    let open = editable && event.status == EventStatus::Published;
+
+    // has current user RSVP ed?
+    let rsvped = if visitor.logged_in {
+        let uid = visitor.clone().user.unwrap().uid;
+        match db::get_rsvp(dbh, eid, uid).await.unwrap() {
+            None => false,
+            Some(rsvp) => rsvp.status,
+        }
+    } else {
+        false
+    };
+
+    let people = db::get_all_rsvps_for_event(dbh, eid).await.unwrap();
+    let attendees = people.iter().filter(|person| person.0.status).count();
+    let spots_left = event
+        .capacity
+        .map(|capacity| capacity.saturating_sub(attendees));
+    let waitlist_position = match visitor.user.as_ref() {
+        Some(user) => waitlist_position(dbh, eid, user.uid).await,
+        None => None,
+    };
+    let series = match event.occurrence.as_ref() {
+        Some(occurrence) => db::get_series_by_sid(dbh, occurrence.sid).await.unwrap(),
+        None => None,
+    };
+
+    Template::render(
+        "event",
+        context! {
+            title: &event.title,
+            event: &event,
+            description,
+            group,
+            config,
+            visitor,
+            editable,
+            open,
+            can_organize,
+            rsvped,
+            people,
+            spots_left,
+            waitlist_position,
+            series,
+            start_local: timezone::format_local(event.date, &event.timezone),
+            end_local: event.end.map(|end| timezone::format_local(end, &event.timezone)),
+        },
+    )
+}
+
+#[get("/group/<gid>")]
+async fn group_get(dbh: &State<Surreal<Client>>, visitor: Visitor, gid: usize) -> Template {
+    rocket::info!("group_get: {gid}");
+    let config = get_public_config();
+
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group <b>{gid}</b> does not exist."), config, visitor},
+        );
+    };
+
+    group_page(dbh, visitor, group).await
+}
+
+#[get("/g/<slug>")]
+async fn group_slug_get(
+    dbh: &State<Surreal<Client>>,
+    visitor: Visitor,
+    slug: &str,
+) -> PageOrRedirect {
+    let config = get_public_config();
+
+    let Some(group) = db::get_group_by_slug(dbh, slug).await.unwrap() else {
+        return PageOrRedirect::Page(Template::render(
+            "message",
+            context! {title: "No such group", message: format!("The group <b>{slug}</b> does not exist.", slug = escape_html(slug)), config, visitor},
+        ));
+    };
+
+    if group.slug != slug {
+        return PageOrRedirect::Redirect(Redirect::permanent(format!("/g/{}", group.slug)));
+    }
+
+    PageOrRedirect::Page(group_page(dbh, visitor, group).await)
+}
+
+async fn group_page(dbh: &State<Surreal<Client>>, visitor: Visitor, group: Group) -> Template {
+    let config = get_public_config();
+    let gid = group.gid;
+
+    let membership = if visitor.logged_in {
+        db::get_membership(dbh, gid, visitor.clone().user.unwrap().uid)
+            .await
+            .unwrap()
+    } else {
+        None
+    };
+
+    let role = match visitor.user.as_ref() {
+        Some(user) => GroupRole::new(&group, user.uid, membership.as_ref()),
+        None => GroupRole::Visitor,
+    };
+
+    let members = db::get_members_of_group(dbh, gid).await.unwrap();
+    let join_request = match visitor.user.as_ref() {
+        Some(user) => db::get_join_request(dbh, gid, user.uid).await.unwrap(),
+        None => None,
+    };
+    let join_requests = if role.can_organize() {
+        db::get_join_requests_of_group(dbh, gid).await.unwrap()
+    } else {
+        vec![]
+    };
+    let bans = if role.can_organize() {
+        db::get_bans_of_group(dbh, gid).await.unwrap()
+    } else {
+        vec![]
+    };
+
+    let events = db::get_events_by_group_id(dbh, gid)
+        .await
+        .into_iter()
+        .filter(|event| role.can_organize() || event.is_public())
+        .collect::<Vec<_>>();
+
+    let description = markdown2html(&group.description).unwrap();
+    let owner = db::get_user_by_uid(dbh, group.owner)
+        .await
+        .unwrap()
+        .unwrap();
+
+    Template::render(
+        "group",
+        context! {
+            title: &group.name,
+            group: &group,
+            description: description,
+            events: events,
+            config,
+            visitor,
+            owner,
+            members,
+            membership,
+            join_request,
+            join_requests,
+            bans,
+            can_organize: role.can_organize(),
+            can_manage_admins: role.can_manage_admins(),
+        },
+    )
+}
+
+#[get("/groups?<topic>&<location>")]
+async fn groups_get(
+    dbh: &State<Surreal<Client>>,
+    visitor: Visitor,
+    topic: Option<&str>,
+    location: Option<&str>,
+) -> Template {
+    let config = get_public_config();
+
+    let topic = topic.filter(|topic| !topic.is_empty());
+    let location = location
+        .map(str::trim)
+        .filter(|location| !location.is_empty());
+
+    let groups = match topic {
+        Some(topic) => db::get_groups_by_topic(dbh, topic).await.unwrap(),
+        None => db::get_groups(dbh).await.unwrap(),
+    };
+    let groups = groups
+        .into_iter()
+        .filter(|group| location.is_none_or(|location| location_matches(&group.location, location)))
+        .collect::<Vec<_>>();
+    let topics = db::get_topics(dbh).await.unwrap();
+
+    Template::render(
+        "groups",
+        context! {title: "Groups", groups: groups, topics, topic, location, config, visitor},
+    )
+}
+
+#[get("/start-group")]
+fn start_group_get(visitor: LoggedIn) -> Template {
+    let config = get_public_config();
+
+    Template::render(
+        "start_group",
+        context! {title: "Start a group", config, visitor},
+    )
+}
+
+/// Verified users can ask the site admins to create a group for them.
+#[post("/start-group", data = "<input>")]
+async fn start_group_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<StartGroupForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+    let user = visitor.user.clone().unwrap();
+
+    if !user.verified {
+        return Template::render(
+            "message",
+            context! {title: "Unverified email", message: "Please verify your email address before starting a group.", config, visitor},
+        );
+    }
+
+    let name = input.name.trim();
+    let why = input.why.trim();
+    if name.is_empty() || why.is_empty() {
+        return Template::render(
+            "message",
+            context! {title: "Missing field", message: "Please provide the name of the group and tell us why you would like to start it.", config, visitor},
+        );
+    }
+
+    let request = GroupRequest {
+        id: Thing::from(("group_request", Id::ulid())),
+        uid: user.uid,
+        name: name.to_owned(),
+        location: input.location.trim().to_owned(),
+        description: input.description.to_owned(),
+        why: why.to_owned(),
+        date: Utc::now(),
+    };
+    db::add_group_request(dbh, &request).await.unwrap();
+    db::audit(
+        dbh,
+        AuditType::GroupRequested,
+        json!({
+            "user": {
+                "id": user.uid,
+                "name": user.name,
+            },
+            "group": {
+                "name": request.name,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+    notify::admins_group_requested(myconfig, &user, &request).await;
+
+    Template::render(
+        "message",
+        context! {title: "Request sent", message: format!("Your request to start the <b>{name}</b> group was sent to the site admins. We will let you know when they decide.", name = escape_html(name)), config, visitor},
+    )
+}
+
+#[get("/topic/<name>")]
+async fn topic_get(dbh: &State<Surreal<Client>>, visitor: Visitor, name: &str) -> Template {
+    let config = get_public_config();
+
+    let Some(topic) = db::get_topic_by_name(dbh, name).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such topic", message: format!(r#"There is no topic <b>{name}</b>. Check the list of <a href="/groups">groups</a>."#, name = escape_html(name)), config, visitor},
+        );
+    };
+
+    let groups = db::get_groups_by_topic(dbh, name).await.unwrap();
+    let utc: DateTime<Utc> = Utc::now();
+    let mut events = db::get_events(dbh)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(Event::is_public)
+        .filter(|event| utc < event.date)
+        .filter(|event| groups.iter().any(|group| group.gid == event.group_id))
+        .collect::<Vec<_>>();
+    events.sort_by_key(|event| event.date);
+
+    Template::render(
+        "topic",
+        context! {title: format!("Topic: {}", topic.name), topic, groups, events, config, visitor},
+    )
+}
+
+#[get("/users")]
+async fn list_users(dbh: &State<Surreal<Client>>, visitor: Visitor) -> Template {
+    let config = get_public_config();
+
+    // TODO filtering  could be moved to the database call
+    let all_users = db::get_users(dbh).await.unwrap();
+    let users = id_user_pairs(all_users);
+
+    Template::render(
+        "users",
+        context! {
+            title: "List Users",
+            config ,
+            visitor,
+            users,
+        },
+    )
+}
+
+#[get("/user/<uid>")]
+async fn user(dbh: &State<Surreal<Client>>, visitor: Visitor, uid: usize) -> Template {
+    let config = get_public_config();
+
+    let Some(user) = db::get_user_by_uid(dbh, uid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "User not found", message: format!("There is no user with id <b>{uid}</b>."), config, visitor},
+        );
+    };
+
+    if !user.verified {
+        return Template::render(
+            "message",
+            context! {title: "Unverified user", message: format!("This user has not verified the email address yet."), config, visitor},
+        );
+    }
+
+    let about = "";
+    //let about = user.clone().about.map(|text| markdown2html(&text).unwrap());
+    let owned_groups = db::get_groups_by_owner_id(dbh, user.uid).await.unwrap();
+    let groups = db::get_groups_by_membership_id(dbh, user.uid)
+        .await
+        .unwrap();
+
+    Template::render(
+        "user",
+        context! {
+            title: user.name.clone(),
+            config ,
+            visitor,
+            user,
+            about,
+            groups,
+            owned_groups
+        },
+    )
+}
+
+#[get("/uid/<id>")]
+async fn user_by_id(dbh: &State<Surreal<Client>>, visitor: Visitor, id: &str) -> Template {
+    let config = get_public_config();
+
+    let Some(user) = db::get_user_by_id_str(dbh, id).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "User not found", message: format!("There is no user with id <b>{id}</b>.", id = escape_html(id)), config, visitor},
+        );
+    };
+
+    if !user.verified {
+        return Template::render(
+            "message",
+            context! {title: "Unverified user", message: format!("This user has not verified the email address yet."), config, visitor},
+        );
+    }
+
+    let about = "";
+    //let about = user.clone().about.map(|text| markdown2html(&text).unwrap());
+    let owned_groups = db::get_groups_by_owner_id(dbh, user.uid).await.unwrap();
+    let groups = db::get_groups_by_membership_id(dbh, user.uid)
+        .await
+        .unwrap();
+
+    Template::render(
+        "user",
+        context! {
+            title: user.name.clone(),
+            config ,
+            visitor,
+            user,
+            about,
+            groups,
+            owned_groups
+        },
+    )
+}
+
+#[get("/edit-group?<gid>")]
+async fn edit_group_get(dbh: &State<Surreal<Client>>, visitor: LoggedIn, gid: usize) -> Template {
+    let config = get_public_config();
 
+    let uid = visitor.user.clone().unwrap().uid;
     let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
         return Template::render(
             "message",
-            context! {title: "No such group", message: format!("The group <b>{gid}</b> does not exist."), config, visitor},
+            context! {title: "No such group", message: format!("Group <b>{gid}</b> does not exist"), config, visitor},
         );
     };
 
-    let membership = if visitor.logged_in {
-        db::get_membership(dbh, gid, visitor.clone().user.unwrap().uid)
+    let role = db::get_group_role(dbh, &group, uid).await.unwrap();
+    if !role.can_organize() {
+        return Template::render(
+            "message",
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{gid}</b>"), config, visitor},
+        );
+    }
+
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
+
+    let topics = db::get_topics(dbh).await.unwrap();
+
+    Template::render(
+        "edit_group",
+        context! {
+            title: "Edit Group",
+            config,
+            visitor,
+            gid,
+            questions: group.questions.join("\n"),
+            is_owner: role == GroupRole::Owner,
+            topics,
+            timezones: timezone::names(),
+            group
+        },
+    )
+}
+
+#[post("/edit-group", data = "<input>")]
+async fn edit_group_post(
+    dbh: &State<Surreal<Client>>,
+    visitor: LoggedIn,
+    input: CsrfForm<GroupForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+
+    let uid = visitor.user.clone().unwrap().uid;
+    let gid = input.gid;
+    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+        return Template::render(
+            "message",
+            context! {title: "No such group", message: format!("Group <b>{gid}</b> does not exist"), config, visitor},
+        );
+    };
+
+    let role = db::get_group_role(dbh, &group, uid).await.unwrap();
+    if !role.can_organize() {
+        return Template::render(
+            "message",
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{gid}</b>"), config, visitor},
+        );
+    }
+
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
+
+    let name = input.name.trim();
+    if let Err(message) = check_group_name(name) {
+        return Template::render(
+            "message",
+            context! {title: "Invalid name", message, config, visitor},
+        );
+    }
+
+    let slug = input.slug.trim();
+    match change_group_slug(dbh, &group, role, slug).await {
+        Ok(()) => {}
+        Err(SlugProblem::NotOwner) => {
+            return Template::render(
+                "message",
+                context! {title: "Not the owner", message: "Only the owner of the group can change its address.", config, visitor},
+            );
+        }
+        Err(SlugProblem::Invalid) => return invalid_slug_page(slug, config, visitor),
+        Err(SlugProblem::Taken) => return slug_taken_page(slug, config, visitor),
+    }
+
+    let topics = db::get_topics(dbh).await.unwrap();
+    if let Some(unknown) = input
+        .topics
+        .iter()
+        .find(|given| !topics.iter().any(|topic| topic.name == **given))
+    {
+        return Template::render(
+            "message",
+            context! {title: "No such topic", message: format!("There is no topic <b>{unknown}</b>.", unknown = escape_html(unknown)), config, visitor},
+        );
+    }
+
+    if !input.timezone.is_empty() && timezone::parse(input.timezone).is_none() {
+        return Template::render(
+            "message",
+            context! {title: "Invalid time zone", message: format!("Unknown time zone '{}'", escape_html(input.timezone)), config, visitor},
+        );
+    }
+
+    let location = input.location.trim();
+    let description = input.description;
+    db::update_group(dbh, gid, name, location, description)
+        .await
+        .unwrap();
+
+    let questions = input
+        .questions
+        .lines()
+        .map(str::trim)
+        .filter(|question| !question.is_empty())
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+    db::update_group_access(dbh, gid, input.requires_approval, questions)
+        .await
+        .unwrap();
+
+    // Keep the order of the list of topics
+    let group_topics = topics
+        .into_iter()
+        .map(|topic| topic.name)
+        .filter(|topic| input.topics.contains(&topic.as_str()))
+        .collect::<Vec<_>>();
+    db::set_group_topics(dbh, gid, group_topics).await.unwrap();
+    if !input.timezone.is_empty() {
+        db::set_group_timezone(dbh, gid, input.timezone)
             .await
-            .unwrap()
-    } else {
-        None
+            .unwrap();
+    }
+
+    Template::render(
+        "message",
+        context! {title: "Group updated", message: format!(r#"Check out the <a href="/group/{gid}">group</a>"#, ), config, visitor},
+    )
+}
+
+/// The start and the end of an event in UTC.
+type EventDates = (DateTime<Utc>, Option<DateTime<Utc>>);
+
+/// The zone picked in the form or the default one if none was picked.
+fn chosen_timezone(input: &str, default: &str) -> String {
+    if input.is_empty() { default } else { input }.to_owned()
+}
+
+/// The date entered in the form in the given time zone converted to UTC and the end of the event
+/// if the duration is given.
+/// Returns the title and the message of the error page.
+fn parse_event_date(
+    date_str: &str,
+    zone_name: &str,
+    duration: Option<i64>,
+) -> Result<EventDates, (&'static str, String)> {
+    let Some(zone) = timezone::parse(zone_name) else {
+        return Err((
+            "Invalid time zone",
+            format!("Unknown time zone '{}'", escape_html(zone_name)),
+        ));
+    };
+
+    let zone_name = escape_html(zone_name);
+    let date_str = date_str.trim();
+    let Ok(local) = NaiveDateTime::parse_from_str(date_str, timezone::INPUT_FORMAT) else {
+        return Err((
+            "Invalid date",
+            format!(
+                "Invalid date '{}' in time zone '{zone_name}'",
+                escape_html(date_str)
+            ),
+        ));
     };
+    // e.g. 02:30 on the day the clocks are moved forward
+    let Some(date) = timezone::to_utc(local, zone) else {
+        return Err((
+            "Invalid date",
+            format!(
+                "The time '{}' does not exist in time zone '{zone_name}'",
+                escape_html(date_str)
+            ),
+        ));
+    };
+
+    let end = duration
+        .map(|minutes| {
+            Duration::try_minutes(minutes)
+                .and_then(|length| date.checked_add_signed(length))
+                .ok_or(("Invalid duration", format!("Invalid duration '{minutes}'")))
+        })
+        .transpose()?;
+
+    Ok((date, end))
+}
+
+/// The checks of a new or edited event shared by the web forms and the API.
+/// The event must be in the future and it can last at most `MAX_EVENT_MINUTES`.
+/// Returns the title and the message of the error page.
+fn check_event(
+    title: &str,
+    date: DateTime<Utc>,
+    end: Option<DateTime<Utc>>,
+) -> Result<(), (&'static str, String)> {
+    if title.len() < MIN_TITLE_LEN {
+        return Err((
+            "Too short a title",
+            format!(
+                "Minimal title length {MIN_TITLE_LEN} Current title len: {}",
+                title.len()
+            ),
+        ));
+    }
+
+    if date < Utc::now() {
+        return Err((
+            "Can't schedule event to the past",
+            format!("Can't schedule event to the past '{date}'"),
+        ));
+    }
+
+    if let Some(end) = end {
+        let minutes = end.signed_duration_since(date).num_minutes();
+        if minutes <= 0 || MAX_EVENT_MINUTES < minutes {
+            return Err((
+                "Invalid duration",
+                format!("The duration must be between 1 and {MAX_EVENT_MINUTES} minutes, it was {minutes}"),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[post("/add-event", data = "<input>")]
+async fn add_event_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<AddEventForm<'_>>,
+) -> Template {
+    rocket::info!("input: gid: {:?} title: '{:?}'", input.gid, input.title);
 
-    let members = db::get_members_of_group(dbh, gid).await.unwrap();
+    let config = get_public_config();
 
-    let events = db::get_events_by_group_id(dbh, gid).await;
+    let uid = visitor.user.clone().unwrap().uid;
+    let group = db::get_group_by_gid(dbh, input.gid).await.unwrap().unwrap();
 
-    let description = markdown2html(&group.description).unwrap();
-    let owner = db::get_user_by_uid(dbh, group.owner)
+    if !db::get_group_role(dbh, &group, uid)
         .await
         .unwrap()
-        .unwrap();
+        .can_organize()
+    {
+        return Template::render(
+            "message",
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{}</b>", input.gid), config, visitor},
+        );
+    }
 
-    Template::render(
-        "group",
-        context! {
-            title: &group.name,
-            group: &group,
-            description: description,
-            events: events,
-            config,
-            visitor,
-            owner,
-            members,
-            membership,
-        },
-    )
-}
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
 
-#[get("/groups")]
-async fn groups_get(dbh: &State<Surreal<Client>>, visitor: Visitor) -> Template {
-    let config = get_public_config();
+    let title = input.title.trim().to_owned();
+    // TODO: no < in title
 
-    let groups = db::get_groups(dbh).await.unwrap();
-    Template::render(
-        "groups",
-        context! {title: "Groups", groups: groups, config, visitor},
-    )
-}
+    let description = input.description.to_owned();
+    // TODO validate the description - disable < character
 
-#[get("/users")]
-async fn list_users(dbh: &State<Surreal<Client>>, visitor: Visitor) -> Template {
-    let config = get_public_config();
+    let location = input.location.trim().to_owned();
 
-    // TODO filtering  could be moved to the database call
-    let all_users = db::get_users(dbh).await.unwrap();
-    let users = id_user_pairs(all_users);
+    let zone = chosen_timezone(input.timezone, &group.timezone);
+    let (date, end) = match parse_event_date(input.date, &zone, input.duration) {
+        Ok(dates) => dates,
+        Err((error, message)) => {
+            return Template::render("message", context! {title: error, message, config, visitor})
+        }
+    };
+    if let Err((error, message)) = check_event(&title, date, end) {
+        return Template::render("message", context! {title: error, message, config, visitor});
+    }
 
-    Template::render(
-        "users",
-        context! {
-            title: "List Users",
-            config ,
-            visitor,
-            users,
-        },
-    )
-}
+    if !input.rrule.trim().is_empty() {
+        return add_series(dbh, myconfig, &visitor, &group, &input, date, &zone).await;
+    }
 
-#[get("/user/<uid>")]
-async fn user(dbh: &State<Surreal<Client>>, visitor: Visitor, uid: usize) -> Template {
-    let config = get_public_config();
+    let eid = db::increment(dbh, "event").await.unwrap();
+    let slug = db::unique_event_slug(dbh, input.gid, &event_slug(&title, &date), eid)
+        .await
+        .unwrap();
 
-    let user = match db::get_user_by_uid(dbh, uid).await.unwrap() {
-        None => {
-            return Template::render(
-                "message",
-                context! {title: "User not found", message: format!("There is no user with id <b>{uid}</b>."), config, visitor},
-            )
-        }
-        Some(user) => user,
+    let event = Event {
+        id: Thing::from(("event", Id::ulid())),
+        eid,
+        title: title.clone(),
+        description,
+        date,
+        location,
+        group_id: input.gid,
+        status: if input.draft {
+            EventStatus::Draft
+        } else {
+            EventStatus::Published
+        },
+        slug,
+        old_slugs: vec![],
+        capacity: input.capacity,
+        cancel_reason: None,
+        occurrence: None,
+        sequence: 0,
+        timezone: zone,
+        end,
     };
+    db::add_event(dbh, &event).await.unwrap();
 
-    if !user.verified {
+    if input.draft {
         return Template::render(
             "message",
-            context! {title: "Unverified user", message: format!("This user has not verified the email address yet."), config, visitor},
+            context! {title: "Draft saved", message: format!(r#"Draft saved: <a href="/event/{eid}">{title}</a>. Only the organizers can see it until it is published."#, title = escape_html(&title)), config, visitor},
         );
     }
-
-    let about = "";
-    //let about = user.clone().about.map(|text| markdown2html(&text).unwrap());
-    let owned_groups = db::get_groups_by_owner_id(dbh, user.uid).await.unwrap();
-    let groups = db::get_groups_by_membership_id(dbh, user.uid)
-        .await
-        .unwrap();
+    notify::members_event_published(dbh, myconfig, &group, &event).await;
 
     Template::render(
-        "user",
-        context! {
-            title: user.name.clone(),
-            config ,
-            visitor,
-            user,
-            about,
-            groups,
-            owned_groups
-        },
+        "message",
+        context! {title: "Event added", message: format!(r#"Event added: <a href="/event/{}">{}</a>"#, eid, escape_html(&title)), config, visitor},
     )
 }
 
-#[get("/uid/<id>")]
-async fn user_by_id(dbh: &State<Surreal<Client>>, visitor: Visitor, id: &str) -> Template {
+#[get("/add-event?<gid>")]
+async fn add_event_get(dbh: &State<Surreal<Client>>, visitor: LoggedIn, gid: usize) -> Template {
+    rocket::info!("add-event to {gid}");
     let config = get_public_config();
 
-    let user = match db::get_user_by_id_str(dbh, id).await.unwrap() {
-        None => {
-            return Template::render(
-                "message",
-                context! {title: "User not found", message: format!("There is no user with id <b>{id}</b>."), config, visitor},
-            )
-        }
-        Some(user) => user,
-    };
+    let uid = visitor.user.clone().unwrap().uid;
+    let group = db::get_group_by_gid(dbh, gid).await.unwrap().unwrap();
 
-    if !user.verified {
+    if !db::get_group_role(dbh, &group, uid)
+        .await
+        .unwrap()
+        .can_organize()
+    {
         return Template::render(
             "message",
-            context! {title: "Unverified user", message: format!("This user has not verified the email address yet."), config, visitor},
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{gid}</b>"), config, visitor},
         );
     }
 
-    let about = "";
-    //let about = user.clone().about.map(|text| markdown2html(&text).unwrap());
-    let owned_groups = db::get_groups_by_owner_id(dbh, user.uid).await.unwrap();
-    let groups = db::get_groups_by_membership_id(dbh, user.uid)
-        .await
-        .unwrap();
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
 
     Template::render(
-        "user",
+        "add_event",
         context! {
-            title: user.name.clone(),
-            config ,
+            title: format!("Add event to the '{}' group", group.name),
+            config: get_public_config(),
             visitor,
-            user,
-            about,
-            groups,
-            owned_groups
+            gid,
+            timezones: timezone::names(),
+            group,
         },
     )
 }
 
-#[get("/edit-group?<gid>")]
-async fn edit_group_get(dbh: &State<Surreal<Client>>, visitor: LoggedIn, gid: usize) -> Template {
+#[get("/edit-event?<eid>")]
+async fn edit_event_get(dbh: &State<Surreal<Client>>, visitor: LoggedIn, eid: usize) -> Template {
     let config = get_public_config();
 
     let uid = visitor.user.clone().unwrap().uid;
-    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
-        return Template::render(
-            "message",
-            context! {title: "No such group", message: format!("Group <b>{gid}</b> does not exist"), config, visitor},
-        );
-    };
 
-    if group.owner != uid {
+    let event = db::get_event_by_eid(dbh, eid).await.unwrap().unwrap();
+
+    let group = db::get_group_by_gid(dbh, event.group_id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    if !db::get_group_role(dbh, &group, uid)
+        .await
+        .unwrap()
+        .can_organize()
+    {
         return Template::render(
             "message",
-            context! {title: "Not the owner", message: format!("You are not the owner of the group <b>{gid}</b>"), config, visitor},
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{}</b>", event.group_id), config, visitor},
         );
     }
 
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
+
     Template::render(
-        "edit_group",
+        "edit_event",
         context! {
-            title: "Edit Group",
-            config,
+            title: format!("Edit event in the '{}' group", group.name),
+            config: get_public_config(),
             visitor,
-            gid,
-            group
+            date: timezone::format_local(event.date, &event.timezone),
+            duration: event.end.map(|end| end.signed_duration_since(event.date).num_minutes()),
+            timezones: timezone::names(),
+            event,
+            group,
         },
     )
 }
 
-#[post("/edit-group", data = "<input>")]
-async fn edit_group_post(
+#[post("/edit-event", data = "<input>")]
+async fn edit_event_post(
     dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
     visitor: LoggedIn,
-    input: Form<GroupForm<'_>>,
+    input: CsrfForm<EditEventForm<'_>>,
 ) -> Template {
+    rocket::info!("input: eid: {:?} title: '{:?}'", input.eid, input.title);
+
     let config = get_public_config();
 
     let uid = visitor.user.clone().unwrap().uid;
-    let gid = input.gid;
-    let Some(group) = db::get_group_by_gid(dbh, gid).await.unwrap() else {
+    let Some(event) = db::get_event_by_eid(dbh, input.eid).await.unwrap() else {
         return Template::render(
             "message",
-            context! {title: "No such group", message: format!("Group <b>{gid}</b> does not exist"), config, visitor},
+            context! {title: "No such event", message: format!("The event id <b>{}</b> does not exist.", input.eid), config, visitor},
         );
     };
 
-    if group.owner != uid {
+    let group = db::get_group_by_gid(dbh, event.group_id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    if !db::get_group_role(dbh, &group, uid)
+        .await
+        .unwrap()
+        .can_organize()
+    {
         return Template::render(
             "message",
-            context! {title: "Not the owner", message: format!("You are not the owner of the group <b>{gid}</b>"), config, visitor},
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{}</b>", event.group_id), config, visitor},
         );
     }
 
-    let name = input.name.trim();
-    let location = input.location.trim();
-    let description = input.description;
-    db::update_group(dbh, gid, name, location, description)
-        .await
-        .unwrap();
+    if group.archived.is_some() {
+        return group_archived_page(&group, config, visitor);
+    }
+
+    let title = input.title.trim().to_owned();
+    // TODO: no < in title
+
+    let description = input.description.to_owned();
+    // TODO validate the description - disable < character
+
+    let location = input.location.trim().to_owned();
+
+    let zone = chosen_timezone(input.timezone, &event.timezone);
+    let (date, end) = match parse_event_date(input.date, &zone, input.duration) {
+        Ok(dates) => dates,
+        Err((error, message)) => {
+            return Template::render("message", context! {title: error, message, config, visitor})
+        }
+    };
+    if let Err((error, message)) = check_event(&title, date, end) {
+        return Template::render("message", context! {title: error, message, config, visitor});
+    }
+
+    let following = input.scope == "following" && event.occurrence.is_some();
+    if following && (zone != event.timezone || !timezone::same_day(date, event.date, &zone)) {
+        return Template::render(
+            "message",
+            context! {title: "Cannot move the series", message: "Only the time of the following events can be changed, not the day or the time zone.", config, visitor},
+        );
+    }
+
+    let slug = input.slug.trim();
+    if !slug.is_empty() && slug != event.slug {
+        if slugify(slug) != slug {
+            return invalid_slug_page(slug, config, visitor);
+        }
+        if !db::is_event_slug_free(dbh, event.group_id, slug, event.eid)
+            .await
+            .unwrap()
+        {
+            return slug_taken_page(slug, config, visitor);
+        }
+        let old_slugs = renamed_slugs(&event.slug, &event.old_slugs, slug);
+        db::set_event_slug(dbh, event.eid, slug, &old_slugs)
+            .await
+            .unwrap();
+    }
+
+    #[expect(clippy::arithmetic_side_effects)]
+    let shift = date - event.date;
+    let event = Event {
+        id: Thing::from(("event", Id::ulid())),
+        eid: input.eid,
+        title: title.clone(),
+        description,
+        date,
+        location,
+        group_id: event.group_id,
+        status: event.status,
+        slug: event.slug,
+        old_slugs: event.old_slugs,
+        capacity: input.capacity,
+        cancel_reason: event.cancel_reason,
+        occurrence: event.occurrence,
+        sequence: event.sequence,
+        timezone: zone,
+        end,
+    };
+    db::update_event(dbh, &event).await.unwrap();
+    // A larger capacity frees spots for the people on the waitlist
+    promote_from_waitlist(dbh, myconfig, &event).await;
+    if following {
+        update_following_events(dbh, myconfig, &visitor, &event, shift).await;
+    }
 
     Template::render(
         "message",
-        context! {title: "Group updated", message: format!(r#"Check out the <a href="/group/{gid}">group</a>"#, ), config, visitor},
+        context! {title: "Event updated", message: format!(r#"Event updated: <a href="/event/{}">{}</a>"#, input.eid, escape_html(&title)), config, visitor},
     )
 }
 
-#[post("/add-event", data = "<input>")]
-async fn add_event_post(
-    dbh: &State<Surreal<Client>>,
-    visitor: LoggedIn,
-    input: Form<AddEventForm<'_>>,
+/// Create a recurring series and its events within the horizon. The members are notified about the first one.
+async fn add_series(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    visitor: &LoggedIn,
+    group: &Group,
+    input: &AddEventForm<'_>,
+    start: DateTime<Utc>,
+    zone: &str,
 ) -> Template {
-    rocket::info!("input: gid: {:?} title: '{:?}'", input.gid, input.title);
-
     let config = get_public_config();
 
-    let uid = visitor.user.clone().unwrap().uid;
-    let group = db::get_group_by_gid(dbh, input.gid).await.unwrap().unwrap();
+    if input.draft {
+        return Template::render(
+            "message",
+            context! {title: "Cannot save as draft", message: "Recurring events cannot be saved as drafts.", config, visitor},
+        );
+    }
 
-    if group.owner != uid {
+    let rule = input.rrule.trim();
+    if let Err(err) = Rule::parse(rule) {
         return Template::render(
             "message",
-            context! {title: "Not the owner", message: format!("You are not the owner of the group <b>{}</b>", input.gid), config, visitor},
+            context! {title: "Invalid recurrence rule", message: format!("Invalid recurrence rule '{rule}': {err}", rule = escape_html(rule), err = escape_html(&err)), config, visitor},
         );
     }
 
-    let min_title_length = 10;
-    let title = input.title.trim().to_owned();
-    if title.len() < min_title_length {
+    let exceptions = match parse_exceptions(input.exceptions) {
+        Ok(exceptions) => exceptions,
+        Err(day) => {
+            return Template::render(
+                "message",
+                context! {title: "Invalid exception", message: format!("Invalid date '{day}'. Please use the YYYY-MM-DD format.", day = escape_html(&day)), config, visitor},
+            )
+        }
+    };
+
+    let sid = db::increment(dbh, "series").await.unwrap();
+    let series = EventSeries {
+        id: Thing::from(("series", Id::ulid())),
+        sid,
+        gid: group.gid,
+        title: input.title.trim().to_owned(),
+        description: input.description.to_owned(),
+        location: input.location.trim().to_owned(),
+        capacity: input.capacity,
+        rule: rule.to_owned(),
+        start,
+        exceptions,
+        timezone: zone.to_owned(),
+        duration: input.duration,
+    };
+    db::add_series(dbh, &series).await.unwrap();
+
+    let user = visitor.user.clone().unwrap();
+    db::audit(
+        dbh,
+        AuditType::SeriesCreated,
+        json!({
+            "user": {
+                "id": user.uid,
+                "name": user.name,
+            },
+            "group": {
+                "id": group.gid,
+                "name": group.name,
+            },
+            "series": {
+                "id": sid,
+                "rule": series.rule,
+            },
+        }),
+    )
+    .await
+    .unwrap();
+
+    let events = materialise_series(dbh, myconfig, &series).await;
+    let Some(first) = events.first() else {
         return Template::render(
             "message",
-            context! {title: "Too short a title", message: format!("Minimal title length {} Current title len: {}", min_title_length, title.len()), config, visitor},
+            context! {title: "Series created", message: format!("The recurring event <b>{}</b> has no dates in the next {} days.", escape_html(&series.title), myconfig.recurring_events_horizon_days), config, visitor},
         );
-    }
-    // TODO: no < in title
+    };
+    notify::members_event_published(dbh, myconfig, group, first).await;
 
-    let description = input.description.to_owned();
-    // TODO validate the description - disable < character
+    Template::render(
+        "message",
+        context! {title: "Series created", message: format!(r#"Recurring event added with {} events. The first one: <a href="/event/{}">{}</a>"#, events.len(), first.eid, escape_html(&first.title)), config, visitor},
+    )
+}
 
-    let location = input.location.trim().to_owned();
+/// Parse a comma separated list of days. Returns the first invalid one on error.
+fn parse_exceptions(text: &str) -> Result<Vec<NaiveDate>, String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|day| !day.is_empty())
+        .map(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|_err| day.to_owned()))
+        .collect()
+}
 
-    let date_str = input.date.trim().to_owned();
-    let offset = input.offset.to_owned();
-    let mydate = format!("{date_str}:00 +00:00");
-    let Ok(ts) = DateTime::parse_from_str(&mydate, "%Y-%m-%d %H:%M:%S %z") else {
-        return Template::render(
-            "message",
-            context! {title: "Invalid date", message: format!("Invalid date '{}' offset '{}'", date_str, offset), config, visitor},
-        );
+/// Create the events of the series up to the horizon that were not created yet.
+async fn materialise_series(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    series: &EventSeries,
+) -> Vec<Event> {
+    let Ok(rule) = Rule::parse(&series.rule) else {
+        rocket::warn!("Invalid rule '{}' in series {}", series.rule, series.sid);
+        return vec![];
     };
 
+    let now = Utc::now();
     #[expect(clippy::arithmetic_side_effects)]
-    let date = ts.to_utc() + Duration::minutes(offset);
+    let horizon = now + Duration::days(myconfig.recurring_events_horizon_days);
+    let existing = db::get_events_of_series(dbh, series.sid)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter_map(|event| event.occurrence.map(|occurrence| occurrence.date))
+        .collect::<Vec<_>>();
+
+    let zone = timezone::parse(&series.timezone).unwrap_or(Tz::UTC);
+    let start = series.start.with_timezone(&zone);
+    let mut events = vec![];
+    for date in rule.occurrences(&start, horizon, &series.exceptions) {
+        if date < now || existing.contains(&date) {
+            continue;
+        }
 
-    let utc: DateTime<Utc> = Utc::now();
-    if date < utc {
-        return Template::render(
-            "message",
-            context! {title: "Can't schedule event to the past", message: format!("Can't schedule event to the past '{}'", date), config, visitor},
-        );
+        let eid = db::increment(dbh, "event").await.unwrap();
+        let slug = db::unique_event_slug(dbh, series.gid, &event_slug(&series.title, &date), eid)
+            .await
+            .unwrap();
+        let event = Event {
+            id: Thing::from(("event", Id::ulid())),
+            eid,
+            title: series.title.clone(),
+            description: series.description.clone(),
+            date,
+            location: series.location.clone(),
+            group_id: series.gid,
+            status: EventStatus::Published,
+            slug,
+            old_slugs: vec![],
+            capacity: series.capacity,
+            cancel_reason: None,
+            occurrence: Some(Occurrence {
+                sid: series.sid,
+                date,
+            }),
+            sequence: 0,
+            timezone: series.timezone.clone(),
+            end: series
+                .duration
+                .and_then(|minutes| date.checked_add_signed(Duration::minutes(minutes))),
+        };
+        db::add_event(dbh, &event).await.unwrap();
+        events.push(event);
     }
 
-    let eid = db::increment(dbh, "event").await.unwrap();
-
-    let event = Event {
-        id: Thing::from(("event", Id::ulid())),
-        eid,
-        title: title.clone(),
-        description,
-        date,
-        location,
-        group_id: input.gid,
-        status: EventStatus::Published,
-    };
-    db::add_event(dbh, &event).await.unwrap();
+    events
+}
 
-    Template::render(
-        "message",
-        context! {title: "Event added", message: format!(r#"Event added: <a href="/event/{}">{}</a>"#, eid, title ), config, visitor},
-    )
+/// Create the upcoming events of every series in the groups that are not archived.
+async fn materialise_all_series(dbh: &Surreal<Client>, myconfig: &MyConfig) {
+    for series in db::get_all_series(dbh).await.unwrap() {
+        let Some(group) = db::get_group_by_gid(dbh, series.gid).await.unwrap() else {
+            continue;
+        };
+        if group.archived.is_some() {
+            continue;
+        }
+        materialise_series(dbh, myconfig, &series).await;
+    }
 }
 
-#[get("/add-event?<gid>")]
-async fn add_event_get(dbh: &State<Surreal<Client>>, visitor: LoggedIn, gid: usize) -> Template {
-    rocket::info!("add-event to {gid}");
-    let config = get_public_config();
+/// Keep the horizon of the recurring series filled. Runs at startup and then once a day.
+fn series_fairing() -> AdHoc {
+    AdHoc::on_liftoff("Recurring events", |rocket| {
+        Box::pin(async move {
+            let dbh = rocket.state::<Surreal<Client>>().unwrap().clone();
+            let myconfig = rocket.state::<MyConfig>().unwrap().clone();
+            spawn(async move {
+                let mut interval = interval(StdDuration::from_hours(24));
+                loop {
+                    interval.tick().await;
+                    materialise_all_series(&dbh, &myconfig).await;
+                }
+            });
+        })
+    })
+}
 
-    let uid = visitor.user.clone().unwrap().uid;
-    let group = db::get_group_by_gid(dbh, gid).await.unwrap().unwrap();
+/// "This and following": copy the changes of the event to the later events of its series
+/// and to the series itself so the events created later get them as well.
+async fn update_following_events(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    visitor: &LoggedIn,
+    event: &Event,
+    shift: Duration,
+) {
+    let Some(occurrence) = event.occurrence.as_ref() else {
+        return;
+    };
+    let Some(mut series) = db::get_series_by_sid(dbh, occurrence.sid).await.unwrap() else {
+        return;
+    };
 
-    if group.owner != uid {
-        return Template::render(
-            "message",
-            context! {title: "Not the owner", message: format!("You are not the owner of the group <b>{gid}</b>"), config, visitor},
-        );
+    series.title.clone_from(&event.title);
+    series.description.clone_from(&event.description);
+    series.location.clone_from(&event.location);
+    series.capacity = event.capacity;
+    series.start = timezone::shift_local(series.start, &series.timezone, shift);
+    series.duration = event
+        .end
+        .map(|end| end.signed_duration_since(event.date).num_minutes());
+    db::update_series(dbh, &series).await.unwrap();
+
+    // The dates of the occurrences follow the new time of the series so we don't create the same day twice.
+    for mut other in db::get_events_of_series(dbh, series.sid).await.unwrap() {
+        let Some(mut other_occurrence) = other.occurrence.clone() else {
+            continue;
+        };
+        if occurrence.date < other_occurrence.date {
+            other.title.clone_from(&event.title);
+            other.description.clone_from(&event.description);
+            other.location.clone_from(&event.location);
+            other.capacity = event.capacity;
+            other.date = timezone::shift_local(other.date, &other.timezone, shift);
+            other.end = series
+                .duration
+                .and_then(|minutes| other.date.checked_add_signed(Duration::minutes(minutes)));
+        }
+        other_occurrence.date =
+            timezone::shift_local(other_occurrence.date, &series.timezone, shift);
+        other.occurrence = Some(other_occurrence);
+        db::update_event(dbh, &other).await.unwrap();
+        promote_from_waitlist(dbh, myconfig, &other).await;
     }
 
-    Template::render(
-        "add_event",
-        context! {
-            title: format!("Add event to the '{}' group", group.name),
-            config: get_public_config(),
-            visitor,
-            gid,
-            group,
-        },
+    let user = visitor.user.clone().unwrap();
+    db::audit(
+        dbh,
+        AuditType::SeriesChanged,
+        json!({
+            "user": {
+                "id": user.uid,
+                "name": user.name,
+            },
+            "event": {
+                "id": event.eid,
+                "title": event.title,
+            },
+            "series": {
+                "id": series.sid,
+            },
+        }),
     )
+    .await
+    .unwrap();
 }
 
-#[get("/edit-event?<eid>")]
-async fn edit_event_get(dbh: &State<Surreal<Client>>, visitor: LoggedIn, eid: usize) -> Template {
+/// Load the event for one of its organizers or explain why it cannot be changed.
+async fn event_for_organizer(
+    dbh: &Surreal<Client>,
+    visitor: &LoggedIn,
+    eid: usize,
+) -> Result<(Event, Group), Template> {
     let config = get_public_config();
 
-    let uid = visitor.user.clone().unwrap().uid;
-
-    let event = db::get_event_by_eid(dbh, eid).await.unwrap().unwrap();
+    let Some(event) = db::get_event_by_eid(dbh, eid).await.unwrap() else {
+        return Err(Template::render(
+            "message",
+            context! {title: "No such event", message: format!("The event id <b>{eid}</b> does not exist."), config, visitor},
+        ));
+    };
 
     let group = db::get_group_by_gid(dbh, event.group_id)
         .await
         .unwrap()
         .unwrap();
 
-    if group.owner != uid {
-        return Template::render(
+    let uid = visitor.user.clone().unwrap().uid;
+    if !db::get_group_role(dbh, &group, uid)
+        .await
+        .unwrap()
+        .can_organize()
+    {
+        return Err(Template::render(
             "message",
-            context! {title: "Not the owner", message: format!("You are not the owner of the group <b>{}</b>", event.group_id), config, visitor},
-        );
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{}</b>", event.group_id), config, visitor},
+        ));
     }
 
-    Template::render(
-        "edit_event",
-        context! {
-            title: format!("Edit event in the '{}' group", group.name),
-            config: get_public_config(),
-            visitor,
-            event,
-            group,
-        },
+    if group.archived.is_some() {
+        return Err(group_archived_page(&group, config, visitor));
+    }
+
+    Ok((event, group))
+}
+
+async fn audit_event_status(
+    dbh: &Surreal<Client>,
+    atype: AuditType,
+    visitor: &LoggedIn,
+    event: &Event,
+) {
+    let user = visitor.user.clone().unwrap();
+    db::audit(
+        dbh,
+        atype,
+        json!({
+            "user": {
+                "id": user.uid,
+                "name": user.name,
+            },
+            "event": {
+                "id": event.eid,
+                "title": event.title,
+            },
+            "reason": event.cancel_reason,
+        }),
     )
+    .await
+    .unwrap();
 }
 
-#[post("/edit-event", data = "<input>")]
-async fn edit_event_post(
+/// Make a draft or a hidden event visible. The members are only notified about the first publication.
+#[post("/publish-event", data = "<input>")]
+async fn publish_event_post(
     dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
     visitor: LoggedIn,
-    input: Form<EditEventForm<'_>>,
+    input: CsrfForm<EventIdForm>,
 ) -> Template {
-    rocket::info!("input: eid: {:?} title: '{:?}'", input.eid, input.title);
-
     let config = get_public_config();
 
-    let uid = visitor.user.clone().unwrap().uid;
-    let Some(event) = db::get_event_by_eid(dbh, input.eid).await.unwrap() else {
+    let (event, group) = match event_for_organizer(dbh, &visitor, input.eid).await {
+        Ok(found) => found,
+        Err(page) => return page,
+    };
+
+    if event.status != EventStatus::Draft && event.status != EventStatus::Hidden {
         return Template::render(
             "message",
-            context! {title: "No such event", message: format!("The event id <b>{}</b> does not exist.", input.eid), config, visitor},
+            context! {title: "Cannot publish", message: format!(r#"Only drafts and hidden events can be published. <a href="/event/{}">{}</a>"#, event.eid, escape_html(&event.title)), config, visitor},
         );
-    };
+    }
 
-    let group = db::get_group_by_gid(dbh, event.group_id)
+    let announce = event.status == EventStatus::Draft;
+    let event = db::set_event_status(dbh, event.eid, EventStatus::Published, None)
         .await
         .unwrap()
         .unwrap();
+    audit_event_status(dbh, AuditType::EventPublished, &visitor, &event).await;
+    if announce {
+        notify::members_event_published(dbh, myconfig, &group, &event).await;
+    }
 
-    if group.owner != uid {
+    Template::render(
+        "message",
+        context! {title: "Event published", message: format!(r#"Event published: <a href="/event/{}">{}</a>"#, event.eid, escape_html(&event.title)), config, visitor},
+    )
+}
+
+/// The event stays visible with the reason. The RSVPs are frozen and the attendees get an email.
+#[post("/cancel-event", data = "<input>")]
+async fn cancel_event_post(
+    dbh: &State<Surreal<Client>>,
+    myconfig: &State<MyConfig>,
+    visitor: LoggedIn,
+    input: CsrfForm<CancelEventForm<'_>>,
+) -> Template {
+    let config = get_public_config();
+
+    let (event, group) = match event_for_organizer(dbh, &visitor, input.eid).await {
+        Ok(found) => found,
+        Err(page) => return page,
+    };
+
+    if event.status != EventStatus::Published {
         return Template::render(
             "message",
-            context! {title: "Not the owner", message: format!("You are not the owner of the group <b>{}</b>", event.group_id), config, visitor},
+            context! {title: "Cannot cancel", message: format!(r#"Only published events can be cancelled. <a href="/event/{}">{}</a>"#, event.eid, escape_html(&event.title)), config, visitor},
         );
     }
 
-    let min_title_length = 10;
-    let title = input.title.trim().to_owned();
-    if title.len() < min_title_length {
+    let reason = input.reason.trim();
+    if reason.is_empty() {
         return Template::render(
             "message",
-            context! {title: "Too short a title", message: format!("Minimal title length {} Current title len: {}", min_title_length, title.len()), config, visitor},
+            context! {title: "Missing reason", message: "Please tell the attendees why the event is cancelled.", config, visitor},
         );
     }
-    // TODO: no < in title
 
-    let description = input.description.to_owned();
-    // TODO validate the description - disable < character
+    let event = db::set_event_status(
+        dbh,
+        event.eid,
+        EventStatus::Cancelled,
+        Some(reason.to_owned()),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    audit_event_status(dbh, AuditType::EventCancelled, &visitor, &event).await;
+    notify::attendees_event_cancelled(dbh, myconfig, &group, &event).await;
 
-    let location = input.location.trim().to_owned();
+    Template::render(
+        "message",
+        context! {title: "Event cancelled", message: format!(r#"Event cancelled: <a href="/event/{}">{}</a>"#, event.eid, escape_html(&event.title)), config, visitor},
+    )
+}
 
-    let date_str = input.date.trim().to_owned();
-    let offset = input.offset.to_owned();
-    let mydate = format!("{date_str}:00 +00:00");
-    let Ok(ts) = DateTime::parse_from_str(&mydate, "%Y-%m-%d %H:%M:%S %z") else {
-        return Template::render(
-            "message",
-            context! {title: "Invalid date", message: format!("Invalid date '{}' offset '{}'", date_str, offset), config, visitor},
-        );
-    };
+/// Hidden events are only visible to the organizers, e.g. when an event was published by mistake.
+#[post("/hide-event", data = "<input>")]
+async fn hide_event_post(
+    dbh: &State<Surreal<Client>>,
+    visitor: LoggedIn,
+    input: CsrfForm<EventIdForm>,
+) -> Template {
+    let config = get_public_config();
 
-    #[expect(clippy::arithmetic_side_effects)]
-    let date = ts.to_utc() + Duration::minutes(offset);
+    let event = match event_for_organizer(dbh, &visitor, input.eid).await {
+        Ok((event, _group)) => event,
+        Err(page) => return page,
+    };
 
-    let utc: DateTime<Utc> = Utc::now();
-    if date < utc {
+    if event.status != EventStatus::Published {
         return Template::render(
             "message",
-            context! {title: "Can't schedule event to the past", message: format!("Can't schedule event to the past '{}'", date), config, visitor},
+            context! {title: "Cannot hide", message: format!(r#"Only published events can be hidden. <a href="/event/{}">{}</a>"#, event.eid, escape_html(&event.title)), config, visitor},
         );
     }
 
-    let event = Event {
-        id: Thing::from(("event", Id::ulid())),
-        eid: input.eid,
-        title: title.clone(),
-        description,
-        date,
-        location,
-        group_id: event.group_id,
-        status: EventStatus::Published,
-    };
-    db::update_event(dbh, &event).await.unwrap();
+    let event = db::set_event_status(dbh, event.eid, EventStatus::Hidden, None)
+        .await
+        .unwrap()
+        .unwrap();
+    audit_event_status(dbh, AuditType::EventHidden, &visitor, &event).await;
 
     Template::render(
         "message",
-        context! {title: "Event updated", message: format!(r#"Event updated: <a href="/event/{}">{}</a>"#, input.eid, title ), config, visitor},
+        context! {title: "Event hidden", message: format!(r#"Event hidden: <a href="/event/{}">{}</a>. Only the organizers can see it."#, event.eid, escape_html(&event.title)), config, visitor},
     )
 }
 
@@ -1491,10 +5869,14 @@ async fn contact_members_get(
         );
     };
 
-    if group.owner != uid {
+    if !db::get_group_role(dbh, &group, uid)
+        .await
+        .unwrap()
+        .can_organize()
+    {
         return Template::render(
             "message",
-            context! {title: "Not the owner", message: format!("You are not the owner of the group <b>{gid}</b>"), config, visitor},
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{gid}</b>"), config, visitor},
         );
     }
 
@@ -1515,17 +5897,21 @@ async fn contact_members_post(
     dbh: &State<Surreal<Client>>,
     myconfig: &State<MyConfig>,
     visitor: LoggedIn,
-    input: Form<ContactMembersForm<'_>>,
+    input: CsrfForm<ContactMembersForm<'_>>,
 ) -> Template {
     let config = get_public_config();
 
     let uid = visitor.user.clone().unwrap().uid;
     let group = db::get_group_by_gid(dbh, input.gid).await.unwrap().unwrap();
 
-    if group.owner != uid {
+    if !db::get_group_role(dbh, &group, uid)
+        .await
+        .unwrap()
+        .can_organize()
+    {
         return Template::render(
             "message",
-            context! {title: "Not the owner", message: format!("You are not the owner of the group <b>{}</b>", input.gid), config, visitor},
+            context! {title: "Not an organizer", message: format!("You are not an organizer of the group <b>{}</b>", input.gid), config, visitor},
         );
     }
 
@@ -1558,7 +5944,7 @@ fn get_resend_email_verification_code(visitor: Visitor) -> Template {
     if visitor.logged_in {
         return Template::render(
             "message",
-            context! {title: "Logged in", message: r#"Logged in users cannot access this page. Please, <a href="/logout">logout</a> and try again!"#, config, visitor},
+            context! {title: "Logged in", message: "Logged in users cannot access this page. Please, logout and try again!", config, visitor},
         );
     }
 
@@ -1577,7 +5963,7 @@ async fn post_resend_email_verification_code(
     dbh: &State<Surreal<Client>>,
     myconfig: &State<MyConfig>,
     visitor: Visitor,
-    input: Form<ResetPasswordForm<'_>>,
+    input: CsrfForm<ResetPasswordForm<'_>>,
 ) -> Template {
     rocket::info!("resend email for: {:?}", input.email);
     let config = get_public_config();
@@ -1585,7 +5971,7 @@ async fn post_resend_email_verification_code(
     if visitor.logged_in {
         return Template::render(
             "message",
-            context! {title: "Logged in", message: r#"Logged in users cannot access this page. Please, <a href="/logout">logout</a> and try again!"#, config, visitor},
+            context! {title: "Logged in", message: "Logged in users cannot access this page. Please, logout and try again!", config, visitor},
         );
     }
 
@@ -1595,7 +5981,7 @@ async fn post_resend_email_verification_code(
         // TODO: we should probably limit the number of such request from the same visitor so a bot won't be able to try to guess email addresses
         return Template::render(
             "message",
-            context! {title: "No such user", message: format!("No user with address <b>{}</b>. Please try again", input.email), config, visitor},
+            context! {title: "No such user", message: format!("No user with address <b>{}</b>. Please try again", escape_html(input.email)), config, visitor},
         );
     };
 
@@ -1606,15 +5992,19 @@ async fn post_resend_email_verification_code(
         );
     }
 
-    let process = "resetxxx";
-    let code = Uuid::new_v4();
+    let code = create_token(
+        dbh,
+        myconfig,
+        user.uid,
+        TokenPurpose::Register,
+        None,
+        None,
+        None,
+    )
+    .await;
     let user_id = user.id.to_string();
     let id = user_id.split(':').next_back().unwrap();
 
-    db::add_login_code_to_user(dbh, &email, process, code.to_string().as_str())
-        .await
-        .unwrap();
-
     let base_url = &myconfig.base_url;
 
     let subject = "Verify your email for Meet-OS!";
@@ -1643,7 +6033,7 @@ async fn post_resend_email_verification_code(
 
     Template::render(
         "message",
-        context! {title: "We sent you an email", message: format!("We sent you an email to <b>{}</b> Please click on the link to reset your password.", to_address.email), config, visitor},
+        context! {title: "We sent you an email", message: format!("We sent you an email to <b>{}</b> Please click on the link to reset your password.", escape_html(&to_address.email)), config, visitor},
     )
 }
 
@@ -1717,59 +6107,112 @@ async fn http_500(request: &Request<'_>) -> Template {
     )
 }
 
+fn routes() -> Vec<Route> {
+    routes![
+        add_event_get,
+        add_event_post,
+        contact_members_get,
+        contact_members_post,
+        edit_event_get,
+        edit_event_post,
+        publish_event_post,
+        cancel_event_post,
+        hide_event_post,
+        edit_group_get,
+        edit_group_post,
+        edit_profile_get,
+        edit_profile_post,
+        change_email_get,
+        change_email_post,
+        profile_export,
+        api_token_create,
+        api_token_revoke,
+        profile_delete_get,
+        profile_delete_post,
+        profile_delete_confirm_get,
+        profile_delete_confirm_post,
+        event_get,
+        event_slug_get,
+        events,
+        groups_get,
+        group_get,
+        group_slug_get,
+        topic_get,
+        start_group_get,
+        start_group_post,
+        index,
+        join_group_post,
+        leave_group_post,
+        remove_member_get,
+        remove_member_post,
+        unban_member_post,
+        list_users,
+        logout_post,
+        logout_everywhere_post,
+        logout_session_post,
+        login_get,
+        login_post,
+        login_totp_post,
+        login_by_email_get,
+        oauth_login,
+        oauth_callback,
+        oauth_unlink,
+        login_by_email_post,
+        login_by_email_confirm_post,
+        register_get,
+        register_post,
+        reset_password_get,
+        reset_password_post,
+        save_password_get,
+        save_password_post,
+        rsvp_yes_event_post,
+        rsvp_no_event_post,
+        set_group_admin_post,
+        approve_join_request_post,
+        archive_group_post,
+        unarchive_group_post,
+        invite_get,
+        invite_post,
+        invite_resend_post,
+        invite_revoke_post,
+        invitation_get,
+        invitation_post,
+        reject_join_request_post,
+        transfer_group_get,
+        transfer_group_post,
+        accept_group_get,
+        accept_group_post,
+        show_profile,
+        totp_disable_post,
+        totp_setup_get,
+        totp_setup_post,
+        totp_setup_start_post,
+        user,
+        user_by_id,
+        get_resend_email_verification_code,
+        post_resend_email_verification_code,
+        verify_email
+    ]
+}
+
 #[launch]
 fn rocket() -> _ {
     rocket::build()
         .mount("/admin", admin::routes())
+        .mount("/api", api::routes())
         .mount("/", public::routes())
-        .mount(
-            "/",
-            routes![
-                add_event_get,
-                add_event_post,
-                contact_members_get,
-                contact_members_post,
-                edit_event_get,
-                edit_event_post,
-                edit_group_get,
-                edit_group_post,
-                edit_profile_get,
-                edit_profile_post,
-                event_get,
-                events,
-                groups_get,
-                group_get,
-                index,
-                join_group_get,
-                leave_group_get,
-                list_users,
-                logout_get,
-                login_get,
-                login_post,
-                register_get,
-                register_post,
-                reset_password_get,
-                reset_password_post,
-                save_password_get,
-                save_password_post,
-                rsvp_yes_event_get,
-                rsvp_no_event_get,
-                show_profile,
-                user,
-                user_by_id,
-                get_resend_email_verification_code,
-                post_resend_email_verification_code,
-                verify_email
-            ],
-        )
+        .mount("/", calendar::routes())
+        .mount("/", routes())
         .mount("/", FileServer::from(relative!("static")))
         .attach(Template::fairing())
         .attach(AdHoc::config::<MyConfig>())
         .attach(db::fairing())
+        .attach(series_fairing())
         .register(
             "/",
             catchers![http_401, http_403, http_404, http_422, http_500],
         )
+        .register("/api", api::catchers())
 }
 
 #[cfg(test)]
@@ -1784,6 +6227,9 @@ mod test_db;
 #[cfg(test)]
 mod test_users;
 
+#[cfg(test)]
+mod test_waitlist;
+
 #[cfg(test)]
 mod test_admin;
 
@@ -1796,6 +6242,30 @@ mod test_static;
 #[cfg(test)]
 mod test_groups;
 
+#[cfg(test)]
+mod test_group_transfer;
+
+#[cfg(test)]
+mod test_private_groups;
+
+#[cfg(test)]
+mod test_invitations;
+
+#[cfg(test)]
+mod test_archive_groups;
+
+#[cfg(test)]
+mod test_slugs;
+
+#[cfg(test)]
+mod test_start_group;
+
+#[cfg(test)]
+mod test_remove_member;
+
+#[cfg(test)]
+mod test_topics;
+
 #[cfg(test)]
 mod test_complex;
 
@@ -1805,8 +6275,53 @@ mod test_reset_password;
 #[cfg(test)]
 mod test_events;
 
+#[cfg(test)]
+mod test_event_status;
+
+#[cfg(test)]
+mod test_rrule;
+
+#[cfg(test)]
+mod test_series;
+
+#[cfg(test)]
+mod test_ical;
+
+#[cfg(test)]
+mod test_calendar;
+
+#[cfg(test)]
+mod test_timezones;
+
 #[cfg(test)]
 mod test_contact_members;
 
 #[cfg(test)]
 mod test_resend_email_verification;
+
+#[cfg(test)]
+mod test_sessions;
+
+#[cfg(test)]
+mod test_api;
+
+#[cfg(test)]
+mod test_change_email;
+
+#[cfg(test)]
+mod test_csrf;
+
+#[cfg(test)]
+mod test_magic_link;
+
+#[cfg(test)]
+mod test_oauth;
+
+#[cfg(test)]
+mod test_password;
+
+#[cfg(test)]
+mod test_personal_data;
+
+#[cfg(test)]
+mod test_totp;
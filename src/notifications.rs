@@ -14,7 +14,7 @@ use std::path::Path;
 // TODO display some error if the email sending failed
 /// # Panics
 ///
-/// Panics when there is an error
+/// Panics when there is an error.
 pub async fn sendmail(
     myconfig: &MyConfig,
     from: &EmailAddress,
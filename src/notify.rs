@@ -1,7 +1,9 @@
 use surrealdb::engine::remote::ws::Client;
 use surrealdb::Surreal;
 
-use meetings::{db, sendmail, EmailAddress, Group, MyConfig, User};
+use meetings::{
+    db, escape_html, sendmail, EmailAddress, Event, Group, GroupRequest, MyConfig, User,
+};
 
 pub async fn admin_new_user_registered(myconfig: &MyConfig, user: &User) {
     let base_url = &myconfig.base_url;
@@ -72,6 +74,37 @@ pub async fn admin_new_user_verified(myconfig: &MyConfig, user: &User) {
     }
 }
 
+pub async fn admin_user_deleted(myconfig: &MyConfig, user: &User) {
+    let base_url = &myconfig.base_url;
+    let subject = "Meet-OS user deleted their account";
+    let name = &user.name;
+    let uid = &user.uid;
+    let email = &user.email;
+    let text = format!(
+        "Hi,
+
+        User deleted their account: {name} {email}
+        uid: {uid}
+    <p>
+    Sent from {base_url}
+    "
+    );
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+
+    for admin_email in &myconfig.admins {
+        let to_address = &EmailAddress {
+            name: String::new(),
+            email: admin_email.clone(),
+        };
+
+        sendmail(myconfig, &from, to_address, subject, &text).await;
+    }
+}
+
 pub async fn owner_group_was_created(
     dbh: &Surreal<Client>,
     myconfig: &MyConfig,
@@ -101,6 +134,78 @@ pub async fn owner_group_was_created(
     send_to_admins(dbh, myconfig, &from, &subject, &text).await;
 }
 
+pub async fn admins_group_requested(myconfig: &MyConfig, user: &User, request: &GroupRequest) {
+    let base_url = &myconfig.base_url;
+    let subject = format!(
+        "Meet-OS: user '{}' would like to start the group '{}'",
+        user.name, request.name
+    );
+    let text = format!(
+        r#"Hi,
+    <p>
+    User <a href="{base_url}/user/{}">{}</a> would like to start the group <b>{}</b> in {}.
+    <p>
+    {}
+    <p>
+    Approve or reject it on the <a href="{base_url}/admin">admin page</a>.
+    <p>
+    Sent from {base_url}
+    "#,
+        user.uid,
+        escape_html(&user.name),
+        escape_html(&request.name),
+        escape_html(&request.location),
+        escape_html(&request.why)
+    );
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+
+    for admin_email in &myconfig.admins {
+        let to_address = &EmailAddress {
+            name: String::new(),
+            email: admin_email.clone(),
+        };
+        sendmail(myconfig, &from, to_address, &subject, &text).await;
+    }
+}
+
+pub async fn requester_group_request_rejected(
+    myconfig: &MyConfig,
+    user: &User,
+    request: &GroupRequest,
+    reason: &str,
+) {
+    let base_url = &myconfig.base_url;
+    let subject = format!("Meet-OS: your request to start '{}'", request.name);
+    let text = format!(
+        "Hi {},
+    <p>
+    Your request to start the Meet-OS group <b>{}</b> was not approved.
+    <p>
+    {}
+    <p>
+    Sent from {base_url}
+    ",
+        escape_html(&user.name),
+        escape_html(&request.name),
+        escape_html(reason)
+    );
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+
+    let to_address = &EmailAddress {
+        name: user.name.clone(),
+        email: user.email.clone(),
+    };
+    sendmail(myconfig, &from, to_address, &subject, &text).await;
+}
+
 pub async fn owner_user_joined_group(
     dbh: &Surreal<Client>,
     myconfig: &MyConfig,
@@ -230,3 +335,366 @@ async fn send_to_admins(
         sendmail(myconfig, from, to_address, subject, text).await;
     }
 }
+
+pub async fn member_group_transfer_offered(
+    myconfig: &MyConfig,
+    owner: &User,
+    member: &User,
+    group: &Group,
+    code: &str,
+) {
+    let base_url = &myconfig.base_url;
+    let subject = format!(
+        "Meet-OS: you were offered the ownership of the group '{}'",
+        group.name
+    );
+    let text = format!(
+        r#"Hi {},
+    <p>
+    {} would like you to become the owner of the Meet-OS group <a href="{base_url}/group/{}">{}</a>.
+    If you accept, please <a href="{base_url}/accept-group/{}/{code}">click on this link</a>.
+    <p>
+    If you don't want to take over the group you don't need to do anything.
+    <p>
+    Sent from {base_url}
+    "#,
+        member.name, owner.name, group.gid, group.name, member.uid
+    );
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+
+    let to_address = &EmailAddress {
+        name: member.name.clone(),
+        email: member.email.clone(),
+    };
+    sendmail(myconfig, &from, to_address, &subject, &text).await;
+}
+
+pub async fn group_transferred(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    old_owner: &User,
+    new_owner: &User,
+    group: &Group,
+) {
+    let base_url = &myconfig.base_url;
+    let subject = format!("Meet-OS: group '{}' has a new owner", group.name);
+    let text = format!(
+        r#"Hi,
+
+        The Meet-OS group <a href="{base_url}/group/{}">{}</a> was transferred
+        from <a href="{base_url}/user/{}">{}</a> to <a href="{base_url}/user/{}">{}</a>.
+    <p>
+    Sent from {base_url}
+    "#,
+        group.gid, group.name, old_owner.uid, old_owner.name, new_owner.uid, new_owner.name
+    );
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+
+    for user in [old_owner, new_owner] {
+        let to_address = &EmailAddress {
+            name: user.name.clone(),
+            email: user.email.clone(),
+        };
+        sendmail(myconfig, &from, to_address, &subject, &text).await;
+    }
+    send_to_admins(dbh, myconfig, &from, &subject, &text).await;
+}
+
+pub async fn owner_user_asked_to_join_group(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    user: &User,
+    group: &Group,
+) {
+    let base_url = &myconfig.base_url;
+    let subject = format!(
+        "Meet-OS: user '{}' asked to join group '{}'",
+        user.name, group.name
+    );
+    let text = format!(
+        r#"Hi,
+
+        User <a href="{base_url}/user/{}">{}</a> asked to join the Meet-OS group <a href="{base_url}/group/{}">{}</a>
+        You can approve or reject the request on the page of the group.
+    <p>
+    Sent from {base_url}
+    "#,
+        user.uid, user.name, group.gid, group.name
+    );
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+
+    send_to_group_owner(dbh, myconfig, &from, group, &subject, &text).await;
+}
+
+pub async fn member_join_request_decided(
+    myconfig: &MyConfig,
+    user: &User,
+    group: &Group,
+    approved: bool,
+) {
+    let base_url = &myconfig.base_url;
+    let (subject, decision) = if approved {
+        (
+            format!("Meet-OS: welcome to the group '{}'", group.name),
+            "was approved. You are now a member of the group.",
+        )
+    } else {
+        (
+            format!("Meet-OS: your request to join '{}'", group.name),
+            "was not approved.",
+        )
+    };
+    let text = format!(
+        r#"Hi {},
+    <p>
+    Your request to join the Meet-OS group <a href="{base_url}/group/{}">{}</a> {decision}
+    <p>
+    Sent from {base_url}
+    "#,
+        user.name, group.gid, group.name
+    );
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+
+    let to_address = &EmailAddress {
+        name: user.name.clone(),
+        email: user.email.clone(),
+    };
+    sendmail(myconfig, &from, to_address, &subject, &text).await;
+}
+
+pub async fn member_removed_from_group(
+    myconfig: &MyConfig,
+    user: &User,
+    group: &Group,
+    reason: &str,
+    banned: bool,
+) {
+    let base_url = &myconfig.base_url;
+    let subject = format!("Meet-OS: you were removed from the group '{}'", group.name);
+    let ban = if banned {
+        "You cannot join the group again."
+    } else {
+        ""
+    };
+    let reason = if reason.is_empty() {
+        String::new()
+    } else {
+        format!("Reason: {}", escape_html(reason))
+    };
+    let text = format!(
+        r#"Hi {},
+    <p>
+    The organizers of the Meet-OS group <a href="{base_url}/group/{}">{}</a> removed you from the group. {ban}
+    <p>
+    {reason}
+    <p>
+    Sent from {base_url}
+    "#,
+        escape_html(&user.name),
+        group.gid,
+        escape_html(&group.name)
+    );
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+
+    let to_address = &EmailAddress {
+        name: user.name.clone(),
+        email: user.email.clone(),
+    };
+    sendmail(myconfig, &from, to_address, &subject, &text).await;
+}
+
+pub async fn attendee_promoted_from_waitlist(myconfig: &MyConfig, user: &User, event: &Event) {
+    let base_url = &myconfig.base_url;
+    let subject = format!("Meet-OS: you are in! '{}'", event.title);
+    let text = format!(
+        r#"Hi {},
+    <p>
+    A spot became available at the event <a href="{base_url}/event/{}">{}</a> and it is yours.
+    If you cannot make it, please unregister so the next person on the waitlist can come.
+    <p>
+    Sent from {base_url}
+    "#,
+        user.name, event.eid, event.title
+    );
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+
+    let to_address = &EmailAddress {
+        name: user.name.clone(),
+        email: user.email.clone(),
+    };
+    sendmail(myconfig, &from, to_address, &subject, &text).await;
+}
+
+pub async fn invitee_invited_to_group(
+    myconfig: &MyConfig,
+    inviter: &User,
+    group: &Group,
+    email: &str,
+    code: &str,
+) {
+    let base_url = &myconfig.base_url;
+    let subject = format!(
+        "Meet-OS: {} invited you to the group '{}'",
+        inviter.name, group.name
+    );
+    let text = format!(
+        r#"Hi,
+    <p>
+    {} invited you to join the Meet-OS group <a href="{base_url}/group/{}">{}</a>.
+    If you would like to join, please <a href="{base_url}/invitation/{code}">click on this link</a>.
+    If you don't have an account yet you will be able to register.
+    <p>
+    If you are not interested you don't need to do anything.
+    <p>
+    Sent from {base_url}
+    "#,
+        inviter.name, group.gid, group.name
+    );
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+
+    let to_address = &EmailAddress {
+        name: String::new(),
+        email: email.to_owned(),
+    };
+    sendmail(myconfig, &from, to_address, &subject, &text).await;
+}
+
+pub async fn members_group_archived(dbh: &Surreal<Client>, myconfig: &MyConfig, group: &Group) {
+    let base_url = &myconfig.base_url;
+    let subject = format!("Meet-OS: the group '{}' was archived", group.name);
+    let text = format!(
+        r#"Hi,
+
+        The Meet-OS group <a href="{base_url}/group/{}">{}</a> was archived.
+        You can still see its past events, but there won't be new ones.
+    <p>
+    Sent from {base_url}
+    "#,
+        group.gid, group.name
+    );
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+
+    send_to_group_owner(dbh, myconfig, &from, group, &subject, &text).await;
+    group_members(dbh, myconfig, &subject, &text, group.gid).await;
+}
+
+/// Must be called before the group is deleted as we need the list of the members.
+pub async fn members_group_deleted(dbh: &Surreal<Client>, myconfig: &MyConfig, group: &Group) {
+    let base_url = &myconfig.base_url;
+    let subject = format!("Meet-OS: the group '{}' was deleted", group.name);
+    let text = format!(
+        "Hi,
+
+        The Meet-OS group {} and all of its events were deleted.
+    <p>
+    Sent from {base_url}
+    ",
+        group.name
+    );
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+
+    send_to_group_owner(dbh, myconfig, &from, group, &subject, &text).await;
+    group_members(dbh, myconfig, &subject, &text, group.gid).await;
+}
+
+pub async fn members_event_published(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    group: &Group,
+    event: &Event,
+) {
+    let base_url = &myconfig.base_url;
+    let subject = format!("Meet-OS: new event in '{}': {}", group.name, event.title);
+    let text = format!(
+        r#"Hi,
+    <p>
+    There is a new event in the <a href="{base_url}/group/{}">{}</a> group:
+    <a href="{base_url}/event/{}">{}</a> on {} at {}.
+    <p>
+    Sent from {base_url}
+    "#,
+        group.gid, group.name, event.eid, event.title, event.date, event.location
+    );
+
+    group_members(dbh, myconfig, &subject, &text, group.gid).await;
+}
+
+pub async fn attendees_event_cancelled(
+    dbh: &Surreal<Client>,
+    myconfig: &MyConfig,
+    group: &Group,
+    event: &Event,
+) {
+    let base_url = &myconfig.base_url;
+    let subject = format!("Meet-OS: cancelled '{}'", event.title);
+    let reason = escape_html(&event.cancel_reason.clone().unwrap_or_default());
+
+    let from = EmailAddress {
+        name: myconfig.from_name.clone(),
+        email: myconfig.from_email.clone(),
+    };
+
+    let people = db::get_all_rsvps_for_event(dbh, event.eid).await.unwrap();
+    for (rsvp, user) in people {
+        if !rsvp.status {
+            continue;
+        }
+        let text = format!(
+            r#"Hi {},
+    <p>
+    The organizers of the <a href="{base_url}/group/{}">{}</a> group cancelled the event
+    <a href="{base_url}/event/{}">{}</a>.
+    <p>
+    Reason: {reason}
+    <p>
+    Sent from {base_url}
+    "#,
+            escape_html(&user.name),
+            group.gid,
+            escape_html(&group.name),
+            event.eid,
+            escape_html(&event.title)
+        );
+        let to_address = &EmailAddress {
+            name: user.name,
+            email: user.email,
+        };
+        sendmail(myconfig, &from, to_address, &subject, &text).await;
+    }
+}
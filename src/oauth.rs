@@ -0,0 +1,162 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest as _, Sha256};
+use uuid::Uuid;
+
+/// The format of the user information returned by the provider.
+#[non_exhaustive]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Kind {
+    GitHub,
+    GitLab,
+    Oidc,
+}
+
+/// An `OAuth2` provider configured in the `oauth_providers` section of `Rocket.toml`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Provider {
+    /// Used in the URLs e.g. `/oauth/github/login`.
+    pub name: String,
+    pub display_name: String,
+    pub kind: Kind,
+    pub client_id: String,
+    pub client_secret: String,
+    pub authorize_url: String,
+    pub token_url: String,
+    pub userinfo_url: String,
+    pub scope: String,
+}
+
+/// What we learned about the user from the provider.
+#[derive(Debug, PartialEq, Eq)]
+pub struct UserInfo {
+    pub subject: String,
+    pub username: Option<String>,
+    pub name: Option<String>,
+    /// Only set if the provider says the address was verified.
+    pub email: Option<String>,
+}
+
+/// A random PKCE code verifier, 64 characters long.
+#[must_use]
+pub fn generate_verifier() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// The S256 code challenge of the verifier.
+#[must_use]
+pub fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// # Panics
+///
+/// Panics if the `authorize_url` in the config is not a valid URL.
+#[must_use]
+pub fn authorize_url(
+    provider: &Provider,
+    redirect_uri: &str,
+    state: &str,
+    verifier: &str,
+) -> String {
+    Url::parse_with_params(
+        &provider.authorize_url,
+        &[
+            ("response_type", "code"),
+            ("client_id", &provider.client_id),
+            ("redirect_uri", redirect_uri),
+            ("scope", &provider.scope),
+            ("state", state),
+            ("code_challenge", &code_challenge(verifier)),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .unwrap()
+    .to_string()
+}
+
+/// Exchange the authorization code for an access token.
+pub async fn exchange_code(
+    provider: &Provider,
+    redirect_uri: &str,
+    code: &str,
+    verifier: &str,
+) -> Result<String, String> {
+    let response = reqwest::Client::new()
+        .post(&provider.token_url)
+        .header("Accept", "application/json")
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("client_id", &provider.client_id),
+            ("client_secret", &provider.client_secret),
+            ("code_verifier", verifier),
+        ])
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let data: Value = serde_json::from_str(&response.text().await.map_err(|err| err.to_string())?)
+        .map_err(|err| err.to_string())?;
+
+    data.get("access_token")
+        .and_then(Value::as_str)
+        .map(ToOwned::to_owned)
+        .ok_or_else(|| format!("No access_token in response: {data}"))
+}
+
+pub async fn fetch_userinfo(provider: &Provider, access_token: &str) -> Result<UserInfo, String> {
+    let response = reqwest::Client::new()
+        .get(&provider.userinfo_url)
+        .header("Accept", "application/json")
+        .header("User-Agent", "Meet-OS")
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let data: Value = serde_json::from_str(&response.text().await.map_err(|err| err.to_string())?)
+        .map_err(|err| err.to_string())?;
+
+    parse_userinfo(provider.kind, &data).ok_or_else(|| format!("Invalid user info: {data}"))
+}
+
+fn get_string(data: &Value, field: &str) -> Option<String> {
+    data.get(field)
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+        .map(ToOwned::to_owned)
+}
+
+/// GitHub and GitLab only return addresses that were verified, OIDC tells us explicitly.
+#[must_use]
+pub fn parse_userinfo(kind: Kind, data: &Value) -> Option<UserInfo> {
+    match kind {
+        Kind::GitHub => Some(UserInfo {
+            subject: data.get("id")?.as_u64()?.to_string(),
+            username: get_string(data, "login"),
+            name: get_string(data, "name"),
+            email: get_string(data, "email"),
+        }),
+        Kind::GitLab => Some(UserInfo {
+            subject: data.get("id")?.as_u64()?.to_string(),
+            username: get_string(data, "username"),
+            name: get_string(data, "name"),
+            email: get_string(data, "email"),
+        }),
+        Kind::Oidc => Some(UserInfo {
+            subject: get_string(data, "sub")?,
+            username: get_string(data, "preferred_username"),
+            name: get_string(data, "name"),
+            email: get_string(data, "email").filter(|_| {
+                data.get("email_verified")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false)
+            }),
+        }),
+    }
+}
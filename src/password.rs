@@ -0,0 +1,88 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use pbkdf2::password_hash::{
+    rand_core::OsRng, PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString,
+};
+use pbkdf2::Pbkdf2;
+
+use core::hint::black_box;
+use std::sync::OnceLock;
+
+use crate::MyConfig;
+
+fn argon2(myconfig: &MyConfig) -> Argon2<'static> {
+    let params = Params::new(
+        myconfig.argon2_memory_kib,
+        myconfig.argon2_iterations,
+        myconfig.argon2_parallelism,
+        None,
+    )
+    .unwrap();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+}
+
+/// Hash a new password with Argon2id using the parameters from the config.
+///
+/// # Panics
+///
+/// Panics if the Argon2 parameters in the config are invalid.
+#[must_use]
+pub fn hash(myconfig: &MyConfig, password: &[u8]) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    argon2(myconfig)
+        .hash_password(password, &salt)
+        .unwrap()
+        .to_string()
+}
+
+/// Check a password against a stored PHC string. Accepts both Argon2 and the old PBKDF2 hashes.
+#[must_use]
+pub fn verify(hashed_password: &str, password: &[u8]) -> bool {
+    let Ok(parsed_hash) = PasswordHash::new(hashed_password) else {
+        return false;
+    };
+
+    if parsed_hash.algorithm == Algorithm::Argon2id.ident() {
+        return Argon2::default()
+            .verify_password(password, &parsed_hash)
+            .is_ok();
+    }
+
+    Pbkdf2.verify_password(password, &parsed_hash).is_ok()
+}
+
+/// Check the password against a hash of a random password. Always false.
+/// Used when there is no such user so the login takes as long as for a registered one.
+///
+/// # Panics
+///
+/// Panics if the Argon2 parameters in the config are invalid.
+#[must_use]
+pub fn verify_dummy(myconfig: &MyConfig, password: &[u8]) -> bool {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    let hashed_password = DUMMY_HASH.get_or_init(|| {
+        let salt = SaltString::generate(&mut OsRng);
+        hash(myconfig, salt.as_str().as_bytes())
+    });
+
+    black_box(verify(hashed_password, password));
+    false
+}
+
+/// Hashes created with another algorithm or with other parameters than the current config
+/// should be replaced after a successful login.
+#[must_use]
+pub fn needs_rehash(myconfig: &MyConfig, hashed_password: &str) -> bool {
+    let Ok(parsed_hash) = PasswordHash::new(hashed_password) else {
+        return true;
+    };
+
+    if parsed_hash.algorithm != Algorithm::Argon2id.ident() {
+        return true;
+    }
+
+    Params::try_from(&parsed_hash).map_or(true, |params| {
+        params.m_cost() != myconfig.argon2_memory_kib
+            || params.t_cost() != myconfig.argon2_iterations
+            || params.p_cost() != myconfig.argon2_parallelism
+    })
+}
@@ -0,0 +1,279 @@
+//! The subset of the RFC 5545 recurrence rules we support for event series:
+//! `FREQ=WEEKLY` and `FREQ=MONTHLY` with `INTERVAL`, `BYDAY`, `UNTIL` and `COUNT`.
+
+use chrono::{
+    DateTime, Datelike as _, Days, Months, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday,
+};
+
+/// Never materialise more than this many events of a single series in one go.
+const MAX_OCCURRENCES: usize = 500;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum Frequency {
+    Weekly,
+    Monthly,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Rule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    /// The days of the week. For monthly rules each day has its position in the month,
+    /// e.g. 2 for the second Tuesday and -1 for the last Friday. Weekly rules use 0.
+    pub by_day: Vec<(i8, Weekday)>,
+    pub until: Option<DateTime<Utc>>,
+    pub count: Option<usize>,
+}
+
+impl Rule {
+    /// Parse a rule such as `FREQ=WEEKLY;INTERVAL=2;BYDAY=TU` or `FREQ=MONTHLY;BYDAY=-1FR`.
+    /// The `RRULE:` prefix is optional.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let text = text.strip_prefix("RRULE:").unwrap_or(text);
+
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut by_day = vec![];
+        let mut until = None;
+        let mut count = None;
+
+        for part in text.split(';').filter(|part| !part.is_empty()) {
+            let Some((key, value)) = part.split_once('=') else {
+                return Err(format!("Invalid part '{part}'"));
+            };
+            match key.to_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_uppercase().as_str() {
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        _ => {
+                            return Err(format!(
+                                "Unsupported frequency '{value}'. Use WEEKLY or MONTHLY."
+                            ))
+                        }
+                    });
+                }
+                "INTERVAL" => {
+                    interval = match value.parse::<u32>() {
+                        Ok(number) if number > 0 => number,
+                        _ => return Err(format!("Invalid interval '{value}'")),
+                    };
+                }
+                "BYDAY" => {
+                    by_day = value
+                        .split(',')
+                        .map(parse_day)
+                        .collect::<Result<Vec<_>, _>>()?;
+                }
+                "UNTIL" => until = Some(parse_until(value)?),
+                "COUNT" => {
+                    count = match value.parse::<usize>() {
+                        Ok(number) if number > 0 => Some(number),
+                        _ => return Err(format!("Invalid count '{value}'")),
+                    };
+                }
+                _ => return Err(format!("Unsupported part '{key}'")),
+            }
+        }
+
+        let Some(frequency) = frequency else {
+            return Err(String::from("Missing FREQ"));
+        };
+
+        match frequency {
+            Frequency::Weekly => {
+                if by_day.iter().any(|day| day.0 != 0) {
+                    return Err(String::from("Weekly rules cannot have a position in BYDAY"));
+                }
+            }
+            Frequency::Monthly => {
+                if by_day.iter().any(|day| day.0 == 0 || day.0.abs() > 5) {
+                    return Err(String::from(
+                        "Monthly rules need a position between 1 and 5 or -1 and -5 in BYDAY, e.g. 2TU",
+                    ));
+                }
+            }
+        }
+
+        Ok(Self {
+            frequency,
+            interval,
+            by_day,
+            until,
+            count,
+        })
+    }
+
+    /// The dates of the series starting at `start` up to `end`, leaving out the days in `exceptions`.
+    /// Every occurrence is at the local time of the day of `start` in its zone, so a meeting at 18:00
+    /// stays at 18:00 when the clocks change. Local times skipped by the change are left out.
+    #[must_use]
+    pub fn occurrences<Tz: TimeZone>(
+        &self,
+        start: &DateTime<Tz>,
+        end: DateTime<Utc>,
+        exceptions: &[NaiveDate],
+    ) -> Vec<DateTime<Utc>> {
+        let zone = start.timezone();
+        let local_start = start.naive_local();
+        let first_date = start.with_timezone(&Utc);
+        let mut dates = vec![];
+        let mut found: usize = 0;
+
+        let mut period = match self.frequency {
+            Frequency::Weekly => local_start.date().checked_sub_days(Days::new(
+                local_start.weekday().num_days_from_monday().into(),
+            )),
+            Frequency::Monthly => local_start.date().with_day(1),
+        };
+
+        while let Some(first_day) = period {
+            for day in self.days_of_period(first_day, local_start.date()) {
+                let Some(date) = zone
+                    .from_local_datetime(&day.and_time(local_start.time()))
+                    .earliest()
+                    .map(|date| date.with_timezone(&Utc))
+                else {
+                    continue;
+                };
+                if date < first_date {
+                    continue;
+                }
+                if date > end
+                    || self.until.is_some_and(|until| until < date)
+                    || self.count.is_some_and(|count| count <= found)
+                    || MAX_OCCURRENCES <= dates.len()
+                {
+                    return dates;
+                }
+                found = found.saturating_add(1);
+                if !exceptions.contains(&day) {
+                    dates.push(date);
+                }
+            }
+
+            if end.date_naive() < first_day {
+                break;
+            }
+            period = match self.frequency {
+                Frequency::Weekly => first_day
+                    .checked_add_days(Days::new(u64::from(self.interval).saturating_mul(7))),
+                Frequency::Monthly => first_day.checked_add_months(Months::new(self.interval)),
+            };
+        }
+
+        dates
+    }
+
+    /// The days of the week or the month starting at `first_day` in chronological order.
+    fn days_of_period(&self, first_day: NaiveDate, start: NaiveDate) -> Vec<NaiveDate> {
+        let mut days = match self.frequency {
+            Frequency::Weekly => {
+                let weekdays = if self.by_day.is_empty() {
+                    vec![start.weekday()]
+                } else {
+                    self.by_day.iter().map(|day| day.1).collect()
+                };
+                weekdays
+                    .into_iter()
+                    .filter_map(|weekday| {
+                        first_day.checked_add_days(Days::new(weekday.num_days_from_monday().into()))
+                    })
+                    .collect::<Vec<_>>()
+            }
+            Frequency::Monthly => {
+                let by_day = if self.by_day.is_empty() {
+                    vec![(position_in_month(start), start.weekday())]
+                } else {
+                    self.by_day.clone()
+                };
+                by_day
+                    .into_iter()
+                    .filter_map(|(position, weekday)| nth_weekday(first_day, position, weekday))
+                    .collect::<Vec<_>>()
+            }
+        };
+        days.sort_unstable();
+        days.dedup();
+        days
+    }
+}
+
+/// 1 for the first Tuesday of the month, 2 for the second etc.
+fn position_in_month(day: NaiveDate) -> i8 {
+    let week = day.day0().div_euclid(7).saturating_add(1);
+    i8::try_from(week).unwrap_or(1)
+}
+
+/// The `position`-th `weekday` of the month starting at `first_day`. Negative positions count from the end of the month.
+fn nth_weekday(first_day: NaiveDate, position: i8, weekday: Weekday) -> Option<NaiveDate> {
+    if 0 < position {
+        let position = u8::try_from(position).ok()?;
+        return NaiveDate::from_weekday_of_month_opt(
+            first_day.year(),
+            first_day.month(),
+            weekday,
+            position,
+        );
+    }
+
+    let last_day = first_day
+        .checked_add_months(Months::new(1))?
+        .checked_sub_days(Days::new(1))?;
+    let back = (7_u32
+        .saturating_add(last_day.weekday().num_days_from_monday())
+        .saturating_sub(weekday.num_days_from_monday()))
+    .rem_euclid(7);
+    let weeks = u32::from(position.unsigned_abs().saturating_sub(1));
+    let day = last_day.checked_sub_days(Days::new(
+        u64::from(back).saturating_add(u64::from(weeks).saturating_mul(7)),
+    ))?;
+    (day.month() == first_day.month()).then_some(day)
+}
+
+fn parse_day(text: &str) -> Result<(i8, Weekday), String> {
+    let text = text.trim().to_uppercase();
+    let Some(split) = text.len().checked_sub(2) else {
+        return Err(format!("Invalid day '{text}'"));
+    };
+    let (position, name) = text
+        .split_at_checked(split)
+        .ok_or_else(|| format!("Invalid day '{text}'"))?;
+
+    let weekday = match name {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return Err(format!("Invalid day '{text}'")),
+    };
+    let position = if position.is_empty() {
+        0
+    } else {
+        position
+            .trim_start_matches('+')
+            .parse::<i8>()
+            .map_err(|_err| format!("Invalid day '{text}'"))?
+    };
+
+    Ok((position, weekday))
+}
+
+fn parse_until(text: &str) -> Result<DateTime<Utc>, String> {
+    let text = text.trim().trim_end_matches('Z');
+    // A date without time includes the whole day
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y%m%d") {
+        return date
+            .and_hms_opt(23, 59, 59)
+            .map(|date| date.and_utc())
+            .ok_or_else(|| format!("Invalid UNTIL '{text}'"));
+    }
+    NaiveDateTime::parse_from_str(text, "%Y%m%dT%H%M%S")
+        .map(|date| date.and_utc())
+        .map_err(|_err| format!("Invalid UNTIL '{text}'"))
+}
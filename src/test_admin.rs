@@ -96,7 +96,10 @@ fn admin_search_post_as_admin() {
         .client
         .post("/admin/search")
         .header(ContentType::Form)
-        .body(params!([("query", "admin"),]))
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("query", "admin"),
+        ]))
         .dispatch();
     assert_eq!(res.status(), Status::UnprocessableEntity);
 
@@ -104,7 +107,11 @@ fn admin_search_post_as_admin() {
         .client
         .post("/admin/search")
         .header(ContentType::Form)
-        .body(params!([("query", "admin"), ("table", "user")]))
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("query", "admin"),
+            ("table", "user")
+        ]))
         .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
@@ -0,0 +1,408 @@
+use crate::test_lib::{check_html, check_message, params, TestRunner, OWNER_EMAIL, OWNER_NAME};
+use meetings::ApiScope;
+use regex::Regex;
+use rocket::http::{ContentType, Header, Status};
+use serde_json::{json, Value};
+
+fn create_token(tr: &TestRunner, name: &str, scopes: &[&str]) -> String {
+    let csrf_token = tr.csrf_token();
+    let mut fields = vec![("csrf_token", csrf_token.as_str()), ("name", name)];
+    fields.extend(scopes.iter().map(|scope| ("scopes", *scope)));
+
+    let res = tr
+        .client
+        .post("/profile/api-tokens")
+        .header(ContentType::Form)
+        .body(params!(fields))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "API token created");
+
+    let re = Regex::new(r#"<code id="api-token">(meetos_[a-f0-9]+)</code>"#).unwrap();
+    re.captures(&html).unwrap()[1].to_owned()
+}
+
+fn bearer(token: &str) -> Header<'static> {
+    Header::new("Authorization", format!("Bearer {token}"))
+}
+
+#[test]
+fn api_scope_names() {
+    for scope in ApiScope::ALL {
+        assert_eq!(ApiScope::from_name(scope.name()), Some(scope));
+    }
+    assert_eq!(
+        ApiScope::from_name("events:write"),
+        Some(ApiScope::EventsWrite)
+    );
+    assert_eq!(ApiScope::from_name("admin"), None);
+}
+
+#[test]
+fn api_without_token() {
+    let tr = TestRunner::new();
+
+    let res = tr.client.get("/api/me").dispatch();
+    assert_eq!(res.status(), Status::Unauthorized);
+    let data: Value = serde_json::from_str(&res.into_string().unwrap()).unwrap();
+    assert_eq!(data, json!({"error": "Missing or invalid API token"}));
+
+    let res = tr
+        .client
+        .get("/api/me")
+        .header(bearer("meetos_123"))
+        .dispatch();
+    assert_eq!(res.status(), Status::Unauthorized);
+}
+
+#[test]
+fn api_token_lifecycle() {
+    let tr = TestRunner::new();
+    tr.setup_owner();
+
+    let token = create_token(&tr, "Laptop script", &["read"]);
+
+    let res = tr.client.get("/profile").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("<td>Laptop script</td>"));
+    assert!(html.contains("<td>read</td>"));
+    assert!(html.contains("<td>Never</td>"));
+    assert!(!html.contains(&token));
+
+    let res = tr.client.get("/api/me").header(bearer(&token)).dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let data: Value = serde_json::from_str(&res.into_string().unwrap()).unwrap();
+    assert_eq!(data["name"], OWNER_NAME);
+    assert_eq!(data["email"], OWNER_EMAIL);
+    assert_eq!(data["scopes"], json!(["read"]));
+
+    let res = tr.client.get("/profile").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(!html.contains("<td>Never</td>"));
+
+    let re = Regex::new(
+        r#"<input type="hidden" name="id" value="([^"]+)">\s*<input type="submit" value="Revoke""#,
+    )
+    .unwrap();
+    let id = re.captures(&html).unwrap()[1].to_owned();
+    let res = tr
+        .client
+        .post("/profile/api-tokens/revoke")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("id", id.as_str())
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "API token revoked",
+        r#"The token <b>Laptop script</b> was revoked. Check your <a href="/profile">profile</a>."#
+    );
+
+    let res = tr.client.get("/api/me").header(bearer(&token)).dispatch();
+    assert_eq!(res.status(), Status::Unauthorized);
+}
+
+#[test]
+fn api_token_needs_scope() {
+    let tr = TestRunner::new();
+    tr.setup_owner();
+
+    let res = tr
+        .client
+        .post("/profile/api-tokens")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("name", "Nothing")
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "No scope selected");
+
+    let res = tr
+        .client
+        .post("/profile/api-tokens")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("name", "Everything"),
+            ("scopes", "admin")
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(&html, "Invalid scope", "Unknown scope.");
+
+    let token = create_token(&tr, "Events only", &["events:write"]);
+    let res = tr.client.get("/api/me").header(bearer(&token)).dispatch();
+    assert_eq!(res.status(), Status::Forbidden);
+    let data: Value = serde_json::from_str(&res.into_string().unwrap()).unwrap();
+    assert_eq!(
+        data,
+        json!({"error": "The token does not have the 'read' scope"})
+    );
+}
+
+#[test]
+fn api_add_event_and_update_group() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+
+    tr.login_owner();
+    let token = create_token(&tr, "Rust-Maven", &["read", "events:write", "groups:write"]);
+    tr.logout();
+
+    let res = tr
+        .client
+        .post("/api/events")
+        .header(bearer(&token))
+        .header(ContentType::JSON)
+        .body(
+            json!({
+                "gid": 1,
+                "title": "Rust-Maven live coding",
+                "date": "2030-01-01T10:00:00Z",
+                "location": "Virtual",
+                "description": "Let's write some code",
+            })
+            .to_string(),
+        )
+        .dispatch();
+    assert_eq!(res.status(), Status::Created);
+    let data: Value = serde_json::from_str(&res.into_string().unwrap()).unwrap();
+    assert_eq!(data["eid"], 1);
+
+    let res = tr
+        .client
+        .get("/api/groups/1/events")
+        .header(bearer(&token))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let data: Value = serde_json::from_str(&res.into_string().unwrap()).unwrap();
+    assert_eq!(data["events"][0]["title"], "Rust-Maven live coding");
+
+    let res = tr
+        .client
+        .post("/api/groups/1")
+        .header(bearer(&token))
+        .header(ContentType::JSON)
+        .body(
+            json!({"name": "Rust Maven", "location": "Online", "description": "Rust"}).to_string(),
+        )
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "h1", "Rust Maven");
+
+    // Events in the past are rejected
+    let res = tr
+        .client
+        .post("/api/events")
+        .header(bearer(&token))
+        .header(ContentType::JSON)
+        .body(
+            json!({
+                "gid": 1,
+                "title": "Rust-Maven live coding",
+                "date": "2020-01-01T10:00:00Z",
+                "location": "Virtual",
+                "description": "",
+            })
+            .to_string(),
+        )
+        .dispatch();
+    assert_eq!(res.status(), Status::UnprocessableEntity);
+}
+
+#[test]
+fn api_only_organizer_can_add_event() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+
+    tr.login_user();
+    let token = create_token(&tr, "Member", &["events:write", "messages:send"]);
+
+    let res = tr
+        .client
+        .post("/api/events")
+        .header(bearer(&token))
+        .header(ContentType::JSON)
+        .body(
+            json!({
+                "gid": 1,
+                "title": "Not my group event",
+                "date": "2030-01-01T10:00:00Z",
+                "location": "",
+                "description": "",
+            })
+            .to_string(),
+        )
+        .dispatch();
+    assert_eq!(res.status(), Status::Forbidden);
+    let data: Value = serde_json::from_str(&res.into_string().unwrap()).unwrap();
+    assert_eq!(
+        data,
+        json!({"error": "You are not an organizer of the group 1"})
+    );
+
+    let res = tr
+        .client
+        .post("/api/groups/1/messages")
+        .header(bearer(&token))
+        .header(ContentType::JSON)
+        .body(json!({"subject": "Hello everyone", "content": "Hi"}).to_string())
+        .dispatch();
+    assert_eq!(res.status(), Status::Forbidden);
+}
+
+#[test]
+fn api_add_event_notifies_members() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+
+    tr.login_user();
+    let res = tr
+        .client
+        .post("/join-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1")
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    tr.logout();
+
+    tr.login_owner();
+    let token = create_token(&tr, "Rust-Maven", &["events:write"]);
+    tr.logout();
+    tr.clean_emails();
+
+    let res = tr
+        .client
+        .post("/api/events")
+        .header(bearer(&token))
+        .header(ContentType::JSON)
+        .body(
+            json!({
+                "gid": 1,
+                "title": "Rust-Maven live coding",
+                "date": "2030-01-01T10:00:00Z",
+                "location": "Virtual",
+                "description": "",
+            })
+            .to_string(),
+        )
+        .dispatch();
+    assert_eq!(res.status(), Status::Created);
+
+    let email = std::fs::read_to_string(tr.email_folder.join("0.txt")).unwrap();
+    assert!(email.contains("Meet-OS: new event in 'First Group': Rust-Maven live coding"));
+}
+
+#[test]
+fn api_add_event_checks_like_the_web() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+
+    tr.login_owner();
+    let token = create_token(&tr, "Rust-Maven", &["events:write"]);
+    tr.logout();
+
+    for (title, end, error) in [
+        (
+            "Short",
+            None,
+            "Minimal title length 10 Current title len: 5",
+        ),
+        (
+            "Rust-Maven live coding",
+            Some("2030-01-01T09:00:00Z"),
+            "The duration must be between 1 and 10080 minutes, it was -60",
+        ),
+        (
+            "Rust-Maven live coding",
+            Some("2030-02-01T10:00:00Z"),
+            "The duration must be between 1 and 10080 minutes, it was 44640",
+        ),
+    ] {
+        let res = tr
+            .client
+            .post("/api/events")
+            .header(bearer(&token))
+            .header(ContentType::JSON)
+            .body(
+                json!({
+                    "gid": 1,
+                    "title": title,
+                    "date": "2030-01-01T10:00:00Z",
+                    "end": end,
+                    "location": "",
+                    "description": "",
+                })
+                .to_string(),
+            )
+            .dispatch();
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        let data: Value = serde_json::from_str(&res.into_string().unwrap()).unwrap();
+        assert_eq!(data, json!({"error": error}));
+    }
+}
+
+#[test]
+fn api_update_group_name_and_slug() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+
+    tr.login_owner();
+    let token = create_token(&tr, "Rust-Maven", &["groups:write"]);
+    tr.logout();
+
+    let update = |data: Value| {
+        let res = tr
+            .client
+            .post("/api/groups/1")
+            .header(bearer(&token))
+            .header(ContentType::JSON)
+            .body(data.to_string())
+            .dispatch();
+        let status = res.status();
+        let data: Value = serde_json::from_str(&res.into_string().unwrap()).unwrap();
+        (status, data)
+    };
+
+    let (status, data) = update(json!({"name": "  ", "location": "", "description": ""}));
+    assert_eq!(status, Status::UnprocessableEntity);
+    assert_eq!(
+        data,
+        json!({"error": "The name of the group must be between 1 and 50 characters."})
+    );
+
+    let (status, _) = update(json!({"name": "x".repeat(51), "location": "", "description": ""}));
+    assert_eq!(status, Status::UnprocessableEntity);
+
+    let (status, data) = update(
+        json!({"name": "Rust Maven", "location": "", "description": "", "slug": "Rust Maven"}),
+    );
+    assert_eq!(status, Status::UnprocessableEntity);
+    assert_eq!(
+        data,
+        json!({"error": "The address 'Rust Maven' can only contain lower case letters, digits and single dashes"})
+    );
+
+    let (status, data) = update(
+        json!({"name": "Rust Maven", "location": "", "description": "", "slug": "rust-maven"}),
+    );
+    assert_eq!(status, Status::Ok);
+    assert_eq!(data, json!({"gid": 1, "slug": "rust-maven"}));
+
+    // The old address redirects to the new one
+    let res = tr.client.get("/g/first-group").dispatch();
+    assert_eq!(res.status(), Status::PermanentRedirect);
+    assert_eq!(res.headers().get_one("Location"), Some("/g/rust-maven"));
+}
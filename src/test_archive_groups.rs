@@ -0,0 +1,147 @@
+use crate::test_lib::{check_html, check_message, check_unauthorized, params, TestRunner};
+use rocket::http::{ContentType, Status};
+
+fn post_gid(tr: &TestRunner, path: &str) -> String {
+    let res = tr
+        .client
+        .post(path)
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1")
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+#[test]
+fn archive_and_reopen_group() {
+    let tr = TestRunner::new();
+    tr.setup_for_events();
+
+    tr.login_owner();
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<form method="POST" action="/archive-group">"#));
+
+    let html = post_gid(&tr, "/archive-group");
+    check_message!(
+        &html,
+        "Group archived",
+        r#"The <a href="/group/1">First Group</a> group was archived."#
+    );
+
+    let res = tr.client.get("/groups").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(!html.contains("First Group"));
+
+    // The group and its events are kept, but they are read-only
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("This group was archived on"));
+    assert!(html.contains(r#"<a href="/event/1">"#));
+    assert!(!html.contains(r#"<a href="/add-event?gid=1">"#));
+
+    let res = tr.client.get("/edit-group?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Group archived",
+        r#"The <a href="/group/1">First Group</a> group is archived."#
+    );
+
+    let html = post_gid(&tr, "/archive-group");
+    check_html!(&html, "title", "Nothing to do");
+    tr.logout();
+
+    tr.login_user();
+    let html = post_gid(&tr, "/join-group");
+    check_html!(&html, "title", "Group archived");
+
+    let res = tr
+        .client
+        .post("/rsvp-yes-event")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("eid", "1")
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Group archived");
+
+    let html = post_gid(&tr, "/unarchive-group");
+    check_html!(&html, "title", "Not the owner");
+    tr.logout();
+
+    // Site admins can also reopen the group
+    tr.login_admin();
+    let html = post_gid(&tr, "/unarchive-group");
+    check_message!(
+        &html,
+        "Group reopened",
+        r#"The <a href="/group/1">First Group</a> group was reopened."#
+    );
+
+    let res = tr.client.get("/groups").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("First Group"));
+}
+
+#[test]
+fn delete_group_by_admin() {
+    let tr = TestRunner::new();
+    tr.setup_for_events();
+
+    tr.login_user();
+    let res = tr
+        .client
+        .post("/rsvp-yes-event")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("eid", "1")
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+
+    let res = tr
+        .client
+        .post("/admin/delete-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1")
+        ]))
+        .dispatch();
+    check_unauthorized!(res);
+    tr.logout();
+
+    tr.login_admin();
+    tr.clean_emails();
+    let html = post_gid(&tr, "/admin/delete-group");
+    check_message!(
+        &html,
+        "Group deleted",
+        "The group <b>First Group</b> was deleted together with its 1 events."
+    );
+
+    // The owner, the member and the site admin are notified
+    let email = std::fs::read_to_string(tr.email_folder.join("0.txt")).unwrap();
+    assert!(email.contains("The Meet-OS group First Group and all of its events were deleted."));
+    assert!(tr.email_folder.join("2.txt").exists());
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "No such group");
+
+    let res = tr.client.get("/events").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    assert!(!html.contains(r#"<a href="/event/1">"#));
+
+    let res = tr.client.get("/admin/audit").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("GroupDeleted"));
+}
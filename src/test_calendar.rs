@@ -0,0 +1,99 @@
+use crate::test_lib::{check_html, TestRunner};
+use regex::Regex;
+use rocket::http::{ContentType, Status};
+
+fn get_ics(tr: &TestRunner, path: &str) -> String {
+    let res = tr.client.get(path).dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    assert_eq!(res.content_type(), Some(ContentType::Calendar));
+    res.into_string().unwrap()
+}
+
+#[test]
+fn event_and_group_calendars() {
+    let tr = TestRunner::new();
+    tr.setup_for_events();
+
+    let res = tr.client.get("/event/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<a href="/event/1.ics" id="add-to-calendar">Add to calendar</a>"#));
+
+    let ics = get_ics(&tr, "/event/1.ics");
+    assert!(ics.contains("SUMMARY:First event\r\n"));
+    assert!(ics.contains("DTSTART:20300101T071000Z\r\n"));
+    assert!(ics.contains("SEQUENCE:0\r\n"));
+    assert!(ics.contains("STATUS:CONFIRMED\r\n"));
+
+    let res = tr.client.get("/event/2.ics").dispatch();
+    assert_eq!(res.status(), Status::NotFound);
+
+    // Every change is a new revision for the calendar applications
+    tr.login_owner();
+    let html = tr.post(
+        "/edit-event",
+        &[
+            ("eid", "1"),
+            ("timezone", "UTC"),
+            ("title", "First event renamed"),
+            ("location", "Virtual"),
+            ("description", ""),
+            ("date", "2030-01-01 10:10"),
+        ],
+    );
+    check_html!(&html, "title", "Event updated");
+    let html = tr.post("/cancel-event", &[("eid", "1"), ("reason", "Sick")]);
+    check_html!(&html, "title", "Event cancelled");
+    tr.logout();
+
+    let ics = get_ics(&tr, "/group/1/events.ics");
+    assert!(ics.contains("X-WR-CALNAME:First Group\r\n"));
+    assert!(ics.contains("SUMMARY:First event renamed\r\n"));
+    assert!(ics.contains("SEQUENCE:2\r\n"));
+    assert!(ics.contains("STATUS:CANCELLED\r\n"));
+
+    let res = tr.client.get("/group/2/events.ics").dispatch();
+    assert_eq!(res.status(), Status::NotFound);
+}
+
+#[test]
+fn personal_calendar() {
+    let tr = TestRunner::new();
+    tr.setup_for_events();
+    tr.setup_event(2);
+    tr.logout();
+
+    tr.login_user();
+    tr.post("/rsvp-yes-event", &[("eid", "1")]);
+
+    let res = tr.client.get("/profile").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"value="Create calendar link""#));
+
+    let html = tr.post("/profile/calendar-token", &[]);
+    check_html!(&html, "title", "Calendar feed created");
+    let re = Regex::new(
+        r#"<code id="calendar-url">https?://[^/]+(/calendar/[0-9a-f]+/events.ics)</code>"#,
+    )
+    .unwrap();
+    let path = re.captures(&html).unwrap()[1].to_owned();
+
+    let res = tr.client.get("/profile").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"id="calendar-feed""#));
+    tr.logout();
+
+    // The feed works without login
+    let ics = get_ics(&tr, &path);
+    assert!(ics.contains("SUMMARY:First event\r\n"));
+    assert!(!ics.contains("Second event"));
+
+    tr.login_user();
+    let html = tr.post("/profile/calendar-token/revoke", &[]);
+    check_html!(&html, "title", "Calendar feed disabled");
+    let html = tr.post("/profile/calendar-token/revoke", &[]);
+    check_html!(&html, "title", "No calendar feed");
+    tr.logout();
+
+    let res = tr.client.get(path).dispatch();
+    assert_eq!(res.status(), Status::NotFound);
+}
@@ -0,0 +1,174 @@
+use crate::test_lib::{
+    check_guest_menu, check_html, check_message, check_not_logged_in, params, TestRunner,
+    ADMIN_EMAIL, OWNER_EMAIL, OWNER_PW,
+};
+use rocket::http::{ContentType, Status};
+
+const NEW_EMAIL: &str = "new-owner@meet-os.com";
+
+fn request_change(tr: &TestRunner, email: &str) -> String {
+    let res = tr
+        .client
+        .post("/change-email")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", email)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+#[test]
+fn change_email() {
+    let tr = TestRunner::new();
+
+    tr.setup_owner();
+    tr.clean_emails();
+
+    let res = tr.client.get("/edit-profile").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<form method="POST" action="/change-email">"#));
+
+    let html = request_change(&tr, NEW_EMAIL);
+    check_message!(
+        &html,
+        "We sent you an email",
+        "We sent you an email to <b>new-owner@meet-os.com</b> Please click on the link to confirm the change."
+    );
+
+    let notice = std::fs::read_to_string(tr.email_folder.join("1.txt")).unwrap();
+    assert!(notice.contains(&format!(
+        "Someone asked to change the email address of your account on the Meet-OS web site to {NEW_EMAIL}."
+    )));
+
+    // Until the new address is confirmed the old one is in use
+    let res = tr.client.get("/profile").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(OWNER_EMAIL));
+
+    let (uid, code) = tr.read_code_from_email("0.txt", "change-email");
+    let res = tr
+        .client
+        .get(format!("/change-email/{uid}/{code}"))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Email address changed",
+        r#"Your email address was changed to <b>new-owner@meet-os.com</b>. Check your <a href="/profile">profile</a>."#
+    );
+
+    let res = tr
+        .client
+        .get(format!("/change-email/{uid}/{code}"))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Link already used");
+
+    tr.logout();
+    let res = tr
+        .client
+        .post("/login")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", OWNER_EMAIL),
+            ("password", OWNER_PW)
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Invalid email or password");
+
+    let res = tr
+        .client
+        .post("/login")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", NEW_EMAIL),
+            ("password", OWNER_PW)
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Welcome back");
+}
+
+#[test]
+fn change_email_to_taken_address() {
+    let tr = TestRunner::new();
+
+    tr.setup_admin();
+    tr.setup_owner();
+
+    let html = request_change(&tr, ADMIN_EMAIL);
+    check_message!(
+        &html,
+        "Email address taken",
+        "The email address <b>admin@meet-os.com</b> is already used by another account."
+    );
+}
+
+#[test]
+fn change_email_to_same_address() {
+    let tr = TestRunner::new();
+
+    tr.setup_owner();
+
+    let html = request_change(&tr, OWNER_EMAIL);
+    check_message!(
+        &html,
+        "Same email address",
+        "<b>owner@meet-os.com</b> is already your email address."
+    );
+}
+
+#[test]
+fn change_email_taken_before_confirmation() {
+    let tr = TestRunner::new();
+
+    tr.setup_owner();
+    tr.clean_emails();
+    request_change(&tr, NEW_EMAIL);
+    let (uid, code) = tr.read_code_from_email("0.txt", "change-email");
+    tr.logout();
+
+    tr.register_and_verify_user("Someone Else", NEW_EMAIL, "password2");
+
+    let res = tr
+        .client
+        .get(format!("/change-email/{uid}/{code}"))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Email address taken",
+        "The email address <b>new-owner@meet-os.com</b> is already used by another account."
+    );
+
+    // The link cannot be tried again
+    let res = tr
+        .client
+        .get(format!("/change-email/{uid}/{code}"))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Link already used");
+}
+
+#[test]
+fn change_email_guest() {
+    let tr = TestRunner::new();
+
+    let res = tr
+        .client
+        .post("/change-email")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", NEW_EMAIL)
+        ]))
+        .dispatch();
+    check_not_logged_in!(res);
+}
@@ -23,6 +23,7 @@ fn test_complex() {
         .post("/admin/create-group")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("name", group_name),
             ("location", "Virtual"),
             ("description", ""),
@@ -53,8 +54,9 @@ fn test_complex() {
         .post("/add-event")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("gid", "1"),
-            ("offset", "-180"),
+            ("timezone", "Europe/Istanbul"),
             ("title", first_event_title),
             ("location", "Virtual"),
             ("description", ""),
@@ -105,8 +107,9 @@ fn test_complex() {
         .post("/add-event")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("gid", "1"),
-            ("offset", "-180"),
+            ("timezone", "Europe/Istanbul"),
             ("title", second_event_title),
             ("location", "Jerusalem"),
             ("description", ""),
@@ -128,8 +131,9 @@ fn test_complex() {
         .post("/add-event")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("gid", "1"),
-            ("offset", "-180"),
+            ("timezone", "Europe/Istanbul"),
             ("title", third_event_title),
             ("location", "Tel Aviv"),
             ("description", ""),
@@ -170,8 +174,9 @@ fn test_complex() {
         .post("/edit-event")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("eid", "2"),
-            ("offset", "-180"),
+            ("timezone", "Europe/Istanbul"),
             ("title", second_event_title_2),
             ("location", "Ramat Gan"),
             ("description", "We need new description"),
@@ -1,5 +1,5 @@
 use crate::test_lib::{
-    check_html, check_message, check_not_the_owner, check_unprocessable, params, TestRunner,
+    check_html, check_message, check_not_an_organizer, check_unprocessable, params, TestRunner,
 };
 use rocket::http::{ContentType, Status};
 
@@ -57,7 +57,7 @@ fn contact_members_get_user_not_owner() {
     tr.login_user();
 
     let res = tr.client.get("/contact-members?gid=1").dispatch();
-    check_not_the_owner!(res);
+    check_not_an_organizer!(res);
 }
 
 // TODO contact_members_get_user_with_gid() {
@@ -89,6 +89,7 @@ fn contact_members_post_user_with_all() {
         .client
         .post("/contact-members")
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("gid", "1"),
             ("subject", "Test subject line"),
             ("content", "Test content"),
@@ -115,6 +116,7 @@ fn contact_members_post_user_subject_too_short() {
         .client
         .post("/contact-members")
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("gid", "1"),
             ("subject", "Test"),
             ("content", "Test content"),
@@ -142,11 +144,12 @@ fn contact_members_post_user_who_is_not_the_owner() {
         .client
         .post("/contact-members")
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("gid", "1"),
             ("subject", "Test subject line"),
             ("content", "Test content"),
         ]))
         .header(ContentType::Form)
         .dispatch();
-    check_not_the_owner!(res);
+    check_not_an_organizer!(res);
 }
@@ -0,0 +1,122 @@
+use crate::test_lib::{check_html, check_message, check_unauthorized, params, TestRunner};
+use rocket::http::{ContentType, Status};
+
+#[test]
+fn post_without_csrf_token() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+    tr.login_user();
+
+    let res = tr
+        .client
+        .post("/join-group")
+        .header(ContentType::Form)
+        .body(params!([("gid", "1")]))
+        .dispatch();
+    check_unauthorized!(res);
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<form method="POST" action="/join-group">"#));
+}
+
+#[test]
+fn post_with_wrong_csrf_token() {
+    let tr = TestRunner::new();
+    tr.setup_owner();
+
+    let res = tr
+        .client
+        .post("/edit-profile")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", "0123456789abcdef0123456789abcdef"),
+            ("name", "Mallory"),
+            ("github", ""),
+            ("gitlab", ""),
+            ("linkedin", ""),
+        ]))
+        .dispatch();
+    check_unauthorized!(res);
+
+    // Logout is protected as well, so we are still logged in
+    let res = tr
+        .client
+        .post("/logout")
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", "abc")]))
+        .dispatch();
+    check_unauthorized!(res);
+
+    let res = tr.client.get("/profile").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_html!(&html, "h1", crate::test_lib::OWNER_NAME);
+}
+
+#[test]
+fn state_changes_are_not_allowed_with_get() {
+    let tr = TestRunner::new();
+    tr.setup_for_events();
+    tr.login_user();
+
+    for path in [
+        "/join-group?gid=1",
+        "/leave-group?gid=1",
+        "/rsvp-yes-event?eid=1",
+        "/rsvp-no-event?eid=1",
+        "/logout",
+        "/logout-everywhere",
+    ] {
+        let res = tr.client.get(path).dispatch();
+        assert_eq!(res.status(), Status::NotFound, "{path}");
+        let html = res.into_string().unwrap();
+        check_message!(&html, "404 Not Found", "404 Not Found");
+    }
+}
+
+#[test]
+fn csrf_token_is_in_every_form() {
+    let tr = TestRunner::new();
+
+    let res = tr.client.get("/login").dispatch();
+    let html = res.into_string().unwrap();
+    let expected = format!(
+        r#"<input type="hidden" name="csrf_token" value="{}">"#,
+        tr.csrf_token()
+    );
+    assert_eq!(
+        html.matches(&expected).count(),
+        html.matches(r#"<form method="POST""#).count()
+    );
+}
+
+#[test]
+fn csrf_token_is_replaced_at_login_and_logout() {
+    let tr = TestRunner::new();
+    tr.setup_owner();
+    tr.logout();
+
+    let before_login = tr.csrf_token();
+    tr.login_owner();
+    let after_login = tr.csrf_token();
+    assert_ne!(before_login, after_login);
+
+    // A form loaded before the login cannot be submitted any more
+    let res = tr
+        .client
+        .post("/edit-profile")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", before_login.as_str()),
+            ("name", "Mallory"),
+            ("github", ""),
+            ("gitlab", ""),
+            ("linkedin", ""),
+        ]))
+        .dispatch();
+    check_unauthorized!(res);
+
+    tr.logout();
+    assert_ne!(after_login, tr.csrf_token());
+}
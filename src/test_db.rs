@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde_json::json;
 
 use surrealdb::engine::remote::ws::Client;
@@ -6,7 +6,10 @@ use surrealdb::sql::{Id, Thing};
 use surrealdb::Surreal;
 
 use crate::db;
-use meetings::{db::get_user_by_uid, AuditType, Event, EventStatus, Group, Membership, User, RSVP};
+use meetings::{
+    db::get_user_by_uid, hash_token, AuditType, Event, EventStatus, Group, Membership, Token,
+    TokenPurpose, User, RSVP,
+};
 
 use crate::test_lib::{ADMIN_EMAIL, ADMIN_NAME, OWNER_EMAIL, OWNER_NAME, USER_EMAIL, USER_NAME};
 
@@ -36,12 +39,9 @@ async fn add_admin_helper(dbh: &Surreal<Client>) {
         name: ADMIN_NAME.to_owned(),
         email: ADMIN_EMAIL.to_owned(),
         password: String::from("should be hashed password"),
-        code: String::from("generated code"),
-        process: String::from("register"),
         verified: false,
         registration_date: utc,
         verification_date: None,
-        code_generated_date: Some(utc),
         github: None,
         gitlab: None,
         linkedin: None,
@@ -61,12 +61,9 @@ async fn add_owner_helper(dbh: &Surreal<Client>) {
         name: OWNER_NAME.to_owned(),
         email: OWNER_EMAIL.to_owned(),
         password: String::from("should be hashed password"),
-        code: String::from("generated code"),
-        process: String::from("register"),
         verified: false,
         registration_date: utc,
         verification_date: None,
-        code_generated_date: Some(utc),
 
         github: None,
         gitlab: None,
@@ -87,12 +84,9 @@ async fn add_user_helper(dbh: &Surreal<Client>) {
         name: USER_NAME.to_owned(),
         email: USER_EMAIL.to_owned(),
         password: String::from("should be hashed password"),
-        code: String::from("generated code"),
-        process: String::from("register"),
         verified: false,
         registration_date: utc,
         verification_date: None,
-        code_generated_date: Some(utc),
 
         github: None,
         gitlab: None,
@@ -115,6 +109,13 @@ async fn add_groups_helper(dbh: &Surreal<Client>) {
         location: String::new(),
         description: String::new(),
         creation_date: utc,
+        requires_approval: false,
+        questions: vec![],
+        archived: None,
+        slug: String::from("rust-maven"),
+        old_slugs: vec![],
+        topics: vec![],
+        timezone: String::from("UTC"),
     };
     let res = db::add_group(&dbh, &rust_maven).await.unwrap();
     assert_eq!(res, ());
@@ -129,6 +130,13 @@ async fn add_groups_helper(dbh: &Surreal<Client>) {
         location: String::new(),
         description: String::new(),
         creation_date: utc,
+        requires_approval: false,
+        questions: vec![],
+        archived: None,
+        slug: String::from("python-maven"),
+        old_slugs: vec![],
+        topics: vec![],
+        timezone: String::from("UTC"),
     };
     let res = db::add_group(&dbh, &python_maven).await.unwrap();
     assert_eq!(res, ());
@@ -143,6 +151,13 @@ async fn add_groups_helper(dbh: &Surreal<Client>) {
         location: String::new(),
         description: String::new(),
         creation_date: utc,
+        requires_approval: false,
+        questions: vec![],
+        archived: None,
+        slug: String::from("guest-maven"),
+        old_slugs: vec![],
+        topics: vec![],
+        timezone: String::from("UTC"),
     };
     let res = db::add_group(&dbh, &guest_maven).await.unwrap();
     assert_eq!(res, ());
@@ -165,6 +180,14 @@ async fn add_events_helper(dbh: &Surreal<Client>) {
         location: location.to_owned(),
         group_id: gid,
         status: EventStatus::Published,
+        slug: String::from("first-conference"),
+        old_slugs: vec![],
+        capacity: None,
+        cancel_reason: None,
+        occurrence: None,
+        sequence: 0,
+        timezone: String::from("UTC"),
+        end: None,
     };
 
     db::add_event(&dbh, &event).await.unwrap();
@@ -179,6 +202,14 @@ async fn add_events_helper(dbh: &Surreal<Client>) {
         location: location.to_owned(),
         group_id: gid,
         status: EventStatus::Published,
+        slug: String::from("second-conf"),
+        old_slugs: vec![],
+        capacity: None,
+        cancel_reason: None,
+        occurrence: None,
+        sequence: 0,
+        timezone: String::from("UTC"),
+        end: None,
     };
 
     db::add_event(&dbh, &event).await.unwrap();
@@ -237,12 +268,9 @@ async fn test_db_user() {
         name: String::from("Foo Bar"),
         email: String::from("foo@meet-os.com"),
         password: String::from("should be hashed password"),
-        code: String::from("generated code"),
-        process: String::from("register"),
         verified: false,
         registration_date: utc,
         verification_date: None,
-        code_generated_date: Some(utc),
         github: None,
         gitlab: None,
         linkedin: None,
@@ -260,12 +288,13 @@ async fn test_db_user() {
     let id2 = Id::ulid();
     let other_user = User {
         id: Thing::from(("user", id2.clone())),
-        code: String::from("other code"),
         uid: 2,
         ..user_foo.clone()
     };
     let res = db::add_user(&dbh, &other_user).await;
     assert!(res.is_err());
+    assert!(db::is_duplicate(res.as_ref().unwrap_err(), "user_email"));
+    assert!(!db::is_duplicate(res.as_ref().unwrap_err(), "user_uid"));
     let err = res.err().unwrap().to_string();
     //println!("{err}");
     assert!(err.contains("There was a problem with the database: Database index `user_email` already contains 'foo@meet-os.com'"));
@@ -273,7 +302,6 @@ async fn test_db_user() {
     let id3 = Id::ulid();
     let other_user = User {
         id: Thing::from(("user", id3.clone())),
-        code: String::from("other code"),
         email: String::from("peti@meet-os.com"),
         ..user_foo.clone()
     };
@@ -289,28 +317,12 @@ async fn test_db_user() {
     let user1 = db::get_user_by_id(&dbh, id1).await.unwrap().unwrap();
     assert_eq!(user1.email, "foo@meet-os.com");
 
-    // TODO make sure we don't accidentally use the same code twice
-    // let other_user = User {
-    //     uid: 2,
-    //     email: String::from("peti@meet-os.com"),
-    //     ..user_foo.clone()
-    // };
-
-    // let res = db::add_user(&dbh, &other_user).await;
-    // assert!(res.is_err(), "Should not be able to use the same code twice");
-    // let err = res.err().unwrap().to_string();
-    // //assert_eq!(err, "");
-    // assert!(err.contains(
-    //     "There was a problem with the database: Database index `user_code` already contains 'generated code'"
-    // ));
-
     let id = Id::ulid();
     let user_peti = User {
         id: Thing::from(("user", id)),
         uid: 2,
         name: String::from("Peti Bar"),
         email: String::from("peti@meet-os.com"),
-        code: String::from("some other code"),
         ..user_foo.clone()
     };
     let res = db::add_user(&dbh, &user_peti).await.unwrap();
@@ -342,6 +354,13 @@ async fn test_db_user() {
         location: String::new(),
         description: String::new(),
         creation_date: utc,
+        requires_approval: false,
+        questions: vec![],
+        archived: None,
+        slug: String::from("rust-maven"),
+        old_slugs: vec![],
+        topics: vec![],
+        timezone: String::from("UTC"),
     };
     let res = db::add_group(&dbh, &rust_maven).await.unwrap();
     assert_eq!(res, ());
@@ -354,6 +373,7 @@ async fn test_db_user() {
     let id = Id::ulid();
     let other_group = Group {
         id: Thing::from(("group", id)),
+        slug: String::from("other-group"),
         ..rust_maven.clone()
     };
     let res = db::add_group(&dbh, &other_group).await;
@@ -384,6 +404,13 @@ async fn test_db_groups() {
         location: String::new(),
         description: String::new(),
         creation_date: utc,
+        requires_approval: false,
+        questions: vec![],
+        archived: None,
+        slug: String::from("rust-maven"),
+        old_slugs: vec![],
+        topics: vec![],
+        timezone: String::from("UTC"),
     };
     let res = db::add_group(&dbh, &rust_maven).await.unwrap();
     assert_eq!(res, ());
@@ -398,6 +425,13 @@ async fn test_db_groups() {
         location: String::new(),
         description: String::new(),
         creation_date: utc,
+        requires_approval: false,
+        questions: vec![],
+        archived: None,
+        slug: String::from("python-maven"),
+        old_slugs: vec![],
+        topics: vec![],
+        timezone: String::from("UTC"),
     };
     let res = db::add_group(&dbh, &python_maven).await.unwrap();
     assert_eq!(res, ());
@@ -412,6 +446,13 @@ async fn test_db_groups() {
         location: String::new(),
         description: String::new(),
         creation_date: utc,
+        requires_approval: false,
+        questions: vec![],
+        archived: None,
+        slug: String::from("guest-maven"),
+        old_slugs: vec![],
+        topics: vec![],
+        timezone: String::from("UTC"),
     };
     let res = db::add_group(&dbh, &guest_maven).await.unwrap();
     assert_eq!(res, ());
@@ -534,6 +575,14 @@ async fn test_db_events() {
         location: location.to_owned(),
         group_id: gid,
         status: EventStatus::Published,
+        slug: String::from("first-conference"),
+        old_slugs: vec![],
+        capacity: None,
+        cancel_reason: None,
+        occurrence: None,
+        sequence: 0,
+        timezone: String::from("UTC"),
+        end: None,
     };
 
     db::add_event(&dbh, &event).await.unwrap();
@@ -622,6 +671,43 @@ async fn test_db_rsvp() {
     teardown(dbh, db_name).await;
 }
 
+#[async_test]
+async fn test_db_rsvp_last_spot() {
+    let (dbh, db_name) = setup().await;
+
+    add_admin_helper(&dbh).await;
+    add_owner_helper(&dbh).await;
+    add_user_helper(&dbh).await;
+    add_groups_helper(&dbh).await;
+    add_events_helper(&dbh).await;
+
+    let eid = 1;
+    db::new_rsvp(&dbh, eid, 1, true).await.unwrap();
+
+    // two users try to take the last spot at the same time
+    let (first, second) = rocket::tokio::join!(
+        db::rsvp_yes_within_capacity(&dbh, eid, 2, 2),
+        db::rsvp_yes_within_capacity(&dbh, eid, 3, 2),
+    );
+    let (first, second) = (first.unwrap(), second.unwrap());
+    assert!(first != second);
+    assert_eq!(db::count_attendees(&dbh, eid).await.unwrap(), 2);
+
+    // the winner says no and then yes again, the other one still cannot get in
+    let (uid, other) = if first { (2, 3) } else { (3, 2) };
+    db::update_rsvp(&dbh, eid, uid, false).await.unwrap();
+    assert!(db::rsvp_yes_within_capacity(&dbh, eid, uid, 2)
+        .await
+        .unwrap());
+    assert!(!db::rsvp_yes_within_capacity(&dbh, eid, other, 2)
+        .await
+        .unwrap());
+    let rsvps = db::get_all_rsvps_for_event(&dbh, eid).await.unwrap();
+    assert_eq!(rsvps.len(), 2);
+
+    teardown(dbh, db_name).await;
+}
+
 #[async_test]
 async fn test_db_increment() {
     let (dbh, db_name) = setup().await;
@@ -698,7 +784,7 @@ async fn test_db_audit() {
 }
 
 #[async_test]
-async fn test_db_code() {
+async fn test_db_verify_user() {
     let (dbh, db_name) = setup().await;
 
     add_admin_helper(&dbh).await;
@@ -707,7 +793,6 @@ async fn test_db_code() {
 
     let user = db::get_user_by_uid(&dbh, 3).await.unwrap().unwrap();
     assert_eq!(user.name, USER_NAME);
-    assert_eq!(user.code, "generated code");
     assert_eq!(user.verified, false);
     assert!(user.verification_date.is_none());
 
@@ -715,31 +800,93 @@ async fn test_db_code() {
 
     let user = db::get_user_by_uid(&dbh, 3).await.unwrap().unwrap();
     assert_eq!(user.name, USER_NAME);
-    assert_eq!(user.code, "");
     assert_eq!(user.verified, true);
     assert!(user.verification_date.is_some());
 
     let user = db::get_user_by_uid(&dbh, 2).await.unwrap().unwrap();
     assert_eq!(user.name, OWNER_NAME);
-    assert_eq!(user.code, "generated code");
     assert_eq!(user.verified, false);
     assert!(user.verification_date.is_none());
 
-    db::remove_code(&dbh, 2).await.unwrap();
-    let user = db::get_user_by_uid(&dbh, 2).await.unwrap().unwrap();
-    assert_eq!(user.name, OWNER_NAME);
-    assert_eq!(user.code, "");
-    assert_eq!(user.verified, false);
-    assert!(user.verification_date.is_none());
+    teardown(dbh, db_name).await;
+}
+
+fn token_helper(uid: usize, purpose: TokenPurpose, code: &str) -> Token {
+    let utc: DateTime<Utc> = Utc::now();
+    Token {
+        id: Thing::from(("token", Id::ulid())),
+        uid,
+        purpose,
+        hash: hash_token(code),
+        created: utc,
+        expires: utc + Duration::hours(1),
+        used: None,
+        email: None,
+        gid: None,
+        owner: None,
+    }
+}
+
+#[async_test]
+async fn test_db_token() {
+    let (dbh, db_name) = setup().await;
 
-    db::add_login_code_to_user(&dbh, OWNER_EMAIL, "qqrq", "new code")
+    add_owner_helper(&dbh).await;
+
+    let token = token_helper(2, TokenPurpose::Register, "first code");
+    db::add_token(&dbh, &token).await.unwrap();
+
+    let stored = db::get_token(&dbh, &hash_token("first code"))
         .await
+        .unwrap()
         .unwrap();
-    let user = db::get_user_by_uid(&dbh, 2).await.unwrap().unwrap();
-    assert_eq!(user.name, OWNER_NAME);
-    assert_eq!(user.code, "new code");
-    assert_eq!(user.verified, false);
-    assert!(user.verification_date.is_none());
+    assert_eq!(stored, token);
+    assert!(db::get_token(&dbh, &hash_token("other code"))
+        .await
+        .unwrap()
+        .is_none());
+
+    // a new token for the same purpose replaces the unused one
+    let token = token_helper(2, TokenPurpose::Register, "second code");
+    db::add_token(&dbh, &token).await.unwrap();
+    assert!(db::get_token(&dbh, &hash_token("first code"))
+        .await
+        .unwrap()
+        .is_none());
+
+    // but not the one with a different purpose
+    let reset = token_helper(2, TokenPurpose::ResetPassword, "reset code");
+    db::add_token(&dbh, &reset).await.unwrap();
+    assert!(db::get_token(&dbh, &hash_token("second code"))
+        .await
+        .unwrap()
+        .is_some());
+
+    // the transfer offers of different groups are kept
+    let first_group = Token {
+        gid: Some(1),
+        ..token_helper(2, TokenPurpose::GroupTransfer, "first group")
+    };
+    db::add_token(&dbh, &first_group).await.unwrap();
+    let second_group = Token {
+        gid: Some(2),
+        ..token_helper(2, TokenPurpose::GroupTransfer, "second group")
+    };
+    db::add_token(&dbh, &second_group).await.unwrap();
+    assert!(db::get_token(&dbh, &hash_token("first group"))
+        .await
+        .unwrap()
+        .is_some());
+
+    let used = db::use_token(&dbh, &hash_token("second code"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(used.used.is_some());
+    assert!(db::use_token(&dbh, &hash_token("second code"))
+        .await
+        .unwrap()
+        .is_none());
 
     teardown(dbh, db_name).await;
 }
@@ -759,12 +906,9 @@ async fn test_db_update_user() {
             email: ADMIN_EMAIL.to_string(),
             password: String::from("should be hashed password"),
             name: ADMIN_NAME.to_string(),
-            code: String::from("generated code"),
-            process: String::from("register"),
             verified: false,
             registration_date: admin.registration_date,
             verification_date: None,
-            code_generated_date: admin.code_generated_date,
             github: None,
             gitlab: None,
             linkedin: None,
@@ -793,12 +937,9 @@ async fn test_db_update_user() {
             email: ADMIN_EMAIL.to_string(),
             password: String::from("should be hashed password"),
             name: "New Name".to_string(),
-            code: String::from("generated code"),
-            process: String::from("register"),
             verified: false,
             registration_date: admin.registration_date,
             verification_date: None,
-            code_generated_date: admin.code_generated_date,
             github: Some(String::from("foogh")),
             gitlab: Some(String::from("foogl")),
             linkedin: Some(String::from("https://linkedin.com/")),
@@ -816,12 +957,9 @@ async fn test_db_update_user() {
             email: ADMIN_EMAIL.to_string(),
             password: String::from("new password"),
             name: "New Name".to_string(),
-            code: String::from("generated code"),
-            process: String::from("register"),
             verified: false,
             registration_date: admin.registration_date,
             verification_date: None,
-            code_generated_date: admin.code_generated_date,
             github: Some(String::from("foogh")),
             gitlab: Some(String::from("foogl")),
             linkedin: Some(String::from("https://linkedin.com/")),
@@ -851,6 +989,13 @@ async fn test_db_update_group() {
             description: String::new(),
             owner: 2,
             creation_date: group.creation_date,
+            requires_approval: false,
+            questions: vec![],
+            archived: None,
+            slug: String::from("rust-maven"),
+            old_slugs: vec![],
+            topics: vec![],
+            timezone: String::from("UTC"),
         }
     );
 
@@ -870,6 +1015,13 @@ async fn test_db_update_group() {
             description: String::from("New Description"),
             owner: 2,
             creation_date: group.creation_date,
+            requires_approval: false,
+            questions: vec![],
+            archived: None,
+            slug: String::from("rust-maven"),
+            old_slugs: vec![],
+            topics: vec![],
+            timezone: String::from("UTC"),
         }
     );
 
@@ -899,6 +1051,14 @@ async fn test_db_update_event() {
             location: String::new(),
             group_id: 1,
             status: EventStatus::Published,
+            slug: String::from("first-conference"),
+            old_slugs: vec![],
+            capacity: None,
+            cancel_reason: None,
+            occurrence: None,
+            sequence: 0,
+            timezone: String::from("UTC"),
+            end: None,
         }
     );
 
@@ -921,6 +1081,14 @@ async fn test_db_update_event() {
             location: String::from("New Location"),
             group_id: 1,
             status: EventStatus::Published,
+            slug: String::from("first-conference"),
+            old_slugs: vec![],
+            capacity: None,
+            cancel_reason: None,
+            occurrence: None,
+            sequence: 0,
+            timezone: String::from("UTC"),
+            end: None,
         }
     );
 
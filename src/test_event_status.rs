@@ -0,0 +1,136 @@
+use crate::test_lib::{check_html, check_message, TestRunner};
+use rocket::http::Status;
+
+fn get(tr: &TestRunner, path: &str) -> String {
+    let res = tr.client.get(path).dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+#[test]
+fn draft_and_publish_event() {
+    let tr = TestRunner::new();
+    tr.setup_many_users();
+    tr.create_group_helper("First Group", 2);
+
+    tr.login_user();
+    tr.post("/join-group", &[("gid", "1")]);
+    tr.logout();
+
+    tr.login_owner();
+    tr.clean_emails();
+    let html = tr.post(
+        "/add-event",
+        &[
+            ("gid", "1"),
+            ("timezone", "UTC"),
+            ("title", "Secret planning"),
+            ("location", "Meeting room"),
+            ("description", ""),
+            ("date", "2030-01-01 10:10"),
+            ("draft", "true"),
+        ],
+    );
+    check_message!(
+        &html,
+        "Draft saved",
+        r#"Draft saved: <a href="/event/1">Secret planning</a>. Only the organizers can see it until it is published."#
+    );
+    assert!(!tr.email_folder.join("0.txt").exists());
+
+    let html = get(&tr, "/event/1");
+    check_html!(&html, "title", "Secret planning");
+    assert!(html.contains("This is a draft. Only the organizers can see it."));
+    let html = get(&tr, "/group/1");
+    assert!(html.contains(r#"Secret planning</a> <span class="tag">Draft</span>"#));
+    tr.logout();
+
+    // Members and visitors cannot see the draft
+    tr.login_user();
+    let html = get(&tr, "/event/1");
+    check_html!(&html, "title", "No such event");
+    let html = get(&tr, "/group/1");
+    assert!(!html.contains("Secret planning"));
+    let html = get(&tr, "/events");
+    assert!(!html.contains("Secret planning"));
+    let html = tr.post("/rsvp-yes-event", &[("eid", "1")]);
+    check_html!(&html, "title", "No such event");
+    let html = tr.post("/publish-event", &[("eid", "1")]);
+    check_html!(&html, "title", "Not an organizer");
+    tr.logout();
+
+    tr.login_owner();
+    let html = tr.post("/publish-event", &[("eid", "1")]);
+    check_message!(
+        &html,
+        "Event published",
+        r#"Event published: <a href="/event/1">Secret planning</a>"#
+    );
+    let email = std::fs::read_to_string(tr.email_folder.join("0.txt")).unwrap();
+    assert!(email.contains(r#"/event/1">Secret planning</a> on 2030-01-01 10:10:00 UTC"#));
+
+    let html = tr.post("/publish-event", &[("eid", "1")]);
+    check_html!(&html, "title", "Cannot publish");
+
+    // Hidden again and published again without a second announcement
+    let html = tr.post("/hide-event", &[("eid", "1")]);
+    check_html!(&html, "title", "Event hidden");
+    tr.clean_emails();
+    let html = tr.post("/publish-event", &[("eid", "1")]);
+    check_html!(&html, "title", "Event published");
+    assert!(!tr.email_folder.join("0.txt").exists());
+    tr.logout();
+
+    let html = get(&tr, "/events");
+    assert!(html.contains("Secret planning"));
+}
+
+#[test]
+fn cancel_event() {
+    let tr = TestRunner::new();
+    tr.setup_for_events();
+
+    tr.login_user();
+    let html = tr.post("/rsvp-yes-event", &[("eid", "1")]);
+    check_html!(&html, "title", "RSVPed to event");
+    tr.logout();
+
+    tr.login_owner();
+    let html = tr.post("/cancel-event", &[("eid", "1"), ("reason", " ")]);
+    check_message!(
+        &html,
+        "Missing reason",
+        "Please tell the attendees why the event is cancelled."
+    );
+
+    tr.clean_emails();
+    let html = tr.post(
+        "/cancel-event",
+        &[("eid", "1"), ("reason", "The speaker is <b>sick</b>")],
+    );
+    check_html!(&html, "title", "Event cancelled");
+    let email = std::fs::read_to_string(tr.email_folder.join("0.txt")).unwrap();
+    assert!(email.contains("Hi Sancho Panza,"));
+    assert!(email.contains("Reason: The speaker is &lt;b&gt;sick&lt;/b&gt;"));
+
+    let html = tr.post("/hide-event", &[("eid", "1")]);
+    check_html!(&html, "title", "Cannot hide");
+    tr.logout();
+
+    // The event stays visible with the reason, but the RSVPs are frozen
+    let html = get(&tr, "/event/1");
+    assert!(html
+        .contains("This event was cancelled. Reason: The speaker is &lt;b&gt;sick&lt;&#x2F;b&gt;"));
+    assert!(!html.contains("RSVP to the event"));
+
+    tr.login_user();
+    let html = tr.post("/rsvp-no-event", &[("eid", "1")]);
+    check_message!(
+        &html,
+        "Event cancelled",
+        r#"The <a href="/event/1">event</a> was cancelled."#
+    );
+    let html = get(&tr, "/event/1");
+    assert!(html.contains("Sancho Panza"));
+    assert!(!html.contains("Unregister from the event"));
+}
@@ -1,5 +1,5 @@
 use crate::test_lib::{
-    check_html, check_message, check_not_the_owner, check_unprocessable, params, TestRunner,
+    check_html, check_message, check_not_an_organizer, check_unprocessable, params, TestRunner,
     USER_NAME,
 };
 use rocket::http::{ContentType, Status};
@@ -18,7 +18,15 @@ fn leave_event_before_joining_it() {
     tr.setup_for_events();
     tr.login_user();
 
-    let res = tr.client.get("/rsvp-no-event?eid=1").dispatch();
+    let res = tr
+        .client
+        .post("/rsvp-no-event")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("eid", "1")
+        ]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
     check_message!(
@@ -61,7 +69,15 @@ fn join_event() {
     assert!(!html.contains(&expected_user_name_listed_as_participant));
 
     // RSVP to event
-    let res = tr.client.get("/rsvp-yes-event?eid=1").dispatch();
+    let res = tr
+        .client
+        .post("/rsvp-yes-event")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("eid", "1")
+        ]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
 
     let html = res.into_string().unwrap();
@@ -95,7 +111,15 @@ fn join_event() {
     assert!(html.contains(r#"Unregister from the event"#));
 
     // leave event
-    let res = tr.client.get("/rsvp-no-event?eid=1").dispatch();
+    let res = tr
+        .client
+        .post("/rsvp-no-event")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("eid", "1")
+        ]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
 
     let html = res.into_string().unwrap();
@@ -130,7 +154,15 @@ fn join_event() {
     assert!(html.contains(&expected));
 
     // join event again
-    let res = tr.client.get("/rsvp-yes-event?eid=1").dispatch();
+    let res = tr
+        .client
+        .post("/rsvp-yes-event")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("eid", "1")
+        ]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
     check_message!(
@@ -154,7 +186,15 @@ fn join_event() {
     assert!(html.contains(r#"Unregister from the event"#));
 
     // join event again while already joined
-    let res = tr.client.get("/rsvp-yes-event?eid=1").dispatch();
+    let res = tr
+        .client
+        .post("/rsvp-yes-event")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("eid", "1")
+        ]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
 
     let html = res.into_string().unwrap();
@@ -168,7 +208,15 @@ fn join_not_existing_event() {
     tr.setup_for_events();
     tr.login_user();
 
-    let res = tr.client.get("/rsvp-yes-event?eid=10").dispatch();
+    let res = tr
+        .client
+        .post("/rsvp-yes-event")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("eid", "10")
+        ]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
 
     let html = res.into_string().unwrap();
@@ -181,7 +229,15 @@ fn leave_not_existing_event() {
     tr.setup_for_events();
     tr.login_user();
 
-    let res = tr.client.get("/rsvp-no-event?eid=10").dispatch();
+    let res = tr
+        .client
+        .post("/rsvp-no-event")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("eid", "10")
+        ]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
     check_message!(&html, "No such event", "No such event");
@@ -194,7 +250,15 @@ fn join_event_by_group_owner() {
     tr.setup_for_events();
     tr.login_owner();
 
-    let res = tr.client.get("/rsvp-yes-event?eid=1").dispatch();
+    let res = tr
+        .client
+        .post("/rsvp-yes-event")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("eid", "1")
+        ]))
+        .dispatch();
 
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
@@ -231,11 +295,12 @@ fn post_edit_event_user_no_such_event() {
         .post("/edit-event")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("title", "New title"),
             ("date", "2030-10-10 08:00"),
             ("location", "Virtual"),
             ("description", ""),
-            ("offset", "-180"),
+            ("timezone", "Europe/Istanbul"),
             ("eid", "1"),
         ]))
         .dispatch();
@@ -262,16 +327,17 @@ fn post_edit_event_user_not_the_owner() {
         .post("/edit-event")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("title", "The new title"),
             ("date", "2030-10-10 08:00"),
             ("location", "In a pub"),
             ("description", "This is the explanation"),
-            ("offset", "-180"),
+            ("timezone", "Europe/Istanbul"),
             ("eid", "1"),
         ]))
         .dispatch();
 
-    check_not_the_owner!(res);
+    check_not_an_organizer!(res);
 }
 
 #[test]
@@ -287,11 +353,12 @@ fn post_edit_event_owner_title_too_short() {
         .post("/edit-event")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("title", "The"),
             ("date", "2030-10-10 08:00"),
             ("location", "In a pub"),
             ("description", "This is the explanation"),
-            ("offset", "-180"),
+            ("timezone", "Europe/Istanbul"),
             ("eid", "1"),
         ]))
         .dispatch();
@@ -319,11 +386,12 @@ fn post_edit_event_owner_invalid_date() {
         .post("/edit-event")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("title", "The title is good"),
             ("date", "2030-13-10 08:00"),
             ("location", "In a pub"),
             ("description", "This is the explanation"),
-            ("offset", "-180"),
+            ("timezone", "Europe/Istanbul"),
             ("eid", "1"),
         ]))
         .dispatch();
@@ -334,7 +402,7 @@ fn post_edit_event_owner_invalid_date() {
     check_message!(
         &html,
         "Invalid date",
-        r#"Invalid date '2030-13-10 08:00' offset '-180'"#
+        r#"Invalid date '2030-13-10 08:00' in time zone 'Europe/Istanbul'"#
     );
 }
 
@@ -351,11 +419,12 @@ fn post_edit_event_owner_date_in_the_past() {
         .post("/edit-event")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("title", "The title is good"),
             ("date", "2020-10-10 08:00"),
             ("location", "In a pub"),
             ("description", "This is the explanation"),
-            ("offset", "-180"),
+            ("timezone", "Europe/Istanbul"),
             ("eid", "1"),
         ]))
         .dispatch();
@@ -393,11 +462,12 @@ fn post_edit_event_owner() {
         .post("/edit-event")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("title", "The new title"),
             ("date", "2030-10-10 08:00"),
             ("location", "In a pub"),
             ("description", "This is the explanation"),
-            ("offset", "-180"),
+            ("timezone", "Europe/Istanbul"),
             ("eid", "1"),
         ]))
         .dispatch();
@@ -447,7 +517,7 @@ fn get_add_event_user_not_the_owner() {
     tr.login_user();
 
     let res = tr.client.get("/add-event?gid=1").dispatch();
-    check_not_the_owner!(res);
+    check_not_an_organizer!(res);
 }
 
 #[test]
@@ -466,7 +536,7 @@ fn get_add_event_user_is_owner() {
     check_html!(&html, "h1", "Add event to the 'First Group' group");
     assert!(html.contains(r#"<form method="POST" action="/add-event" id="add-event">"#));
     assert!(html.contains(r#"<input type="hidden" name="gid" value="1">"#));
-    assert!(html.contains(r#"<input type="hidden" name="offset" id="offset">"#));
+    assert!(html.contains(r#"<option value="UTC" selected>UTC</option>"#));
     // TODO the rest of the form
 }
 
@@ -482,15 +552,16 @@ fn post_add_event_user_not_owner() {
         .post("/add-event")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("title", "Event title"),
             ("date", "2030-10-10 08:00"),
             ("location", "Virtual"),
             ("description", ""),
-            ("offset", "-180"),
+            ("timezone", "Europe/Istanbul"),
             ("gid", "1"),
         ]))
         .dispatch();
-    check_not_the_owner!(res);
+    check_not_an_organizer!(res);
 }
 
 #[test]
@@ -509,11 +580,12 @@ fn post_add_event_owner_title_too_short() {
         .post("/add-event")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("title", "OK"),
             ("date", "2030-10-10 08:00"),
             ("location", "Virtual"),
             ("description", ""),
-            ("offset", "-180"),
+            ("timezone", "Europe/Istanbul"),
             ("gid", "1"),
         ]))
         .dispatch();
@@ -544,11 +616,12 @@ fn post_add_event_owner_invalid_date() {
         .post("/add-event")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("title", "Event title"),
             ("date", "2030-02-30 08:00"),
             ("location", "Virtual"),
             ("description", ""),
-            ("offset", "-180"),
+            ("timezone", "Europe/Istanbul"),
             ("gid", "1"),
         ]))
         .dispatch();
@@ -559,7 +632,7 @@ fn post_add_event_owner_invalid_date() {
     check_message!(
         &html,
         "Invalid date",
-        r#"Invalid date '2030-02-30 08:00' offset '-180'"#
+        r#"Invalid date '2030-02-30 08:00' in time zone 'Europe/Istanbul'"#
     );
 }
 
@@ -578,11 +651,12 @@ fn post_add_event_owner_event_in_the_past() {
         .post("/add-event")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("title", "Event title"),
             ("date", "2020-02-10 08:00"),
             ("location", "Virtual"),
             ("description", ""),
-            ("offset", "-180"),
+            ("timezone", "Europe/Istanbul"),
             ("gid", "1"),
         ]))
         .dispatch();
@@ -613,11 +687,12 @@ fn post_add_event_owner() {
         .post("/add-event")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("title", "Event title"),
             ("date", "2030-10-10 08:00"),
             ("location", "Virtual"),
             ("description", ""),
-            ("offset", "-180"),
+            ("timezone", "Europe/Istanbul"),
             ("gid", "1"),
         ]))
         .dispatch();
@@ -669,7 +744,7 @@ fn get_edit_event_as_user_but_not_owner() {
     tr.login_user();
 
     let res = tr.client.get("/edit-event?eid=1").dispatch();
-    check_not_the_owner!(res);
+    check_not_an_organizer!(res);
 }
 #[test]
 fn get_edit_event_as_owner_with_eid() {
@@ -687,11 +762,11 @@ fn get_edit_event_as_owner_with_eid() {
     check_html!(&html, "h1", "Edit event in the 'First Group' group");
     assert!(html.contains(r#"<form method="POST" action="/edit-event" id="edit-event">"#));
     assert!(html.contains(r#"<input type="hidden" name="eid" value="1">"#));
-    assert!(html.contains(r#"<input type="hidden" name="offset" id="offset">"#));
+    assert!(html.contains(r#"<option value="Europe/Istanbul" selected>Europe/Istanbul</option>"#));
     assert!(
         html.contains(r#"Title: <input name="title" id="title" type="text" value="First event">"#)
     );
-    assert!(html.contains(r#"Date: <input placeholder="YYYY-MM-DD HH::MM" name="date" id="date" type="text" original-value="2030-01-01T07:10:00Z">"#));
+    assert!(html.contains(r#"Date: <input placeholder="YYYY-MM-DD HH:MM" name="date" id="date" type="text" value="2030-01-01 10:10">"#));
     assert!(html.contains(
         r#"Location: <input name="location" id="location" type="text" value="Virtual">"#
     ));
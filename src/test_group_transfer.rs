@@ -0,0 +1,279 @@
+use crate::test_lib::{
+    check_guest_menu, check_html, check_message, check_not_logged_in, params, TestRunner,
+    OWNER_NAME, USER_NAME,
+};
+use rocket::http::{ContentType, Status};
+
+fn join_group(tr: &TestRunner, gid: &str) {
+    tr.client
+        .post("/join-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", gid)
+        ]))
+        .dispatch();
+}
+
+fn join_group_as_user(tr: &TestRunner) {
+    tr.login_user();
+    join_group(tr, "1");
+    tr.logout();
+}
+
+fn request_transfer(tr: &TestRunner, uid: &str) -> String {
+    request_group_transfer(tr, "1", uid)
+}
+
+fn request_group_transfer(tr: &TestRunner, gid: &str, uid: &str) -> String {
+    let res = tr
+        .client
+        .post("/transfer-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", gid),
+            ("uid", uid)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+/// Opening the link only asks for confirmation, the transfer happens when it is accepted.
+fn accept_offer(tr: &TestRunner, uid: &str, code: &str) -> String {
+    let res = tr
+        .client
+        .post(format!("/accept-group/{uid}/{code}"))
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token().as_str())]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+#[test]
+fn transfer_group_to_member() {
+    let tr = TestRunner::new();
+
+    tr.setup_for_groups();
+    join_group_as_user(&tr);
+    tr.clean_emails();
+
+    tr.login_owner();
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<a href="/transfer-group?gid=1">"#));
+
+    let res = tr.client.get("/transfer-group?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Transfer Group");
+    assert!(html.contains(&format!(
+        r#"<input type="radio" name="uid" value="3"> {USER_NAME}"#
+    )));
+
+    let html = request_transfer(&tr, "3");
+    check_message!(
+        &html,
+        "Transfer requested",
+        "We sent an email to <b>Sancho Panza</b>. The group will be transferred once they accept."
+    );
+
+    // Until the member accepts the owner does not change
+    let res = tr.client.get("/edit-group?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Edit Group");
+    tr.logout();
+
+    let (uid, code) = tr.read_code_from_email("0.txt", "accept-group");
+    assert_eq!(uid, "3");
+    let res = tr
+        .client
+        .get(format!("/accept-group/{uid}/{code}"))
+        .dispatch();
+    check_not_logged_in!(res);
+
+    // Only the member who got the offer can accept it
+    tr.login_admin();
+    let html = accept_offer(&tr, &uid, &code);
+    check_message!(
+        &html,
+        "Wrong account",
+        "This offer was sent to another user. Please login with the account that received the email."
+    );
+    tr.logout();
+
+    tr.login_user();
+    let res = tr
+        .client
+        .get(format!("/accept-group/{uid}/{code}"))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Accept group");
+    check_html!(
+        &html,
+        "#question",
+        "Do you want to become the owner of the 'First Group' group?"
+    );
+    assert!(html.contains(&format!(
+        r#"<form method="POST" action="/accept-group/{uid}/{code}" id="confirm">"#
+    )));
+
+    let html = accept_offer(&tr, &uid, &code);
+    check_message!(
+        &html,
+        "Group transferred",
+        r#"You are now the owner of the <a href="/group/1">First Group</a> group."#
+    );
+
+    // The old owner, the new owner and the site admin are notified
+    let notice = std::fs::read_to_string(tr.email_folder.join("1.txt")).unwrap();
+    assert!(notice.contains(">First Group</a> was transferred"));
+    assert!(tr.email_folder.join("3.txt").exists());
+
+    let html = accept_offer(&tr, &uid, &code);
+    check_html!(&html, "title", "Link already used");
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(&format!(r#"Owner: <a href="/user/3">{USER_NAME}</a>"#)));
+    assert!(html.contains(&format!(r#"<td><a href="/user/2">{OWNER_NAME}</a></td>"#)));
+    assert!(!html.contains(&format!(r#"<td><a href="/user/3">{USER_NAME}</a></td>"#)));
+
+    // The old owner is a regular member now
+    tr.logout();
+    tr.login_owner();
+    let res = tr.client.get("/edit-group?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Not an organizer");
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<form method="POST" action="/leave-group">"#));
+}
+
+#[test]
+fn transfer_group_not_owner() {
+    let tr = TestRunner::new();
+
+    tr.setup_for_groups();
+    join_group_as_user(&tr);
+
+    tr.login_user();
+    let res = tr.client.get("/transfer-group?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Not the owner",
+        "You are not the owner of the group <b>1</b>"
+    );
+
+    let html = request_transfer(&tr, "3");
+    check_html!(&html, "title", "Not the owner");
+}
+
+#[test]
+fn transfer_group_to_non_member() {
+    let tr = TestRunner::new();
+
+    tr.setup_for_groups();
+
+    tr.login_owner();
+    let res = tr.client.get("/transfer-group?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "No members",
+        "The group can only be transferred to one of its members."
+    );
+
+    let html = request_transfer(&tr, "3");
+    check_message!(
+        &html,
+        "Not a member",
+        "<b>Sancho Panza</b> is not a member of this group."
+    );
+}
+
+#[test]
+fn transfer_group_by_site_admin() {
+    let tr = TestRunner::new();
+
+    tr.setup_for_groups();
+    join_group_as_user(&tr);
+    tr.clean_emails();
+
+    tr.login_admin();
+    let html = request_transfer(&tr, "3");
+    check_html!(&html, "title", "Transfer requested");
+    tr.logout();
+
+    let (uid, code) = tr.read_code_from_email("0.txt", "accept-group");
+    tr.login_user();
+    let html = accept_offer(&tr, &uid, &code);
+    check_html!(&html, "title", "Group transferred");
+}
+
+#[test]
+fn transfer_group_other_offers_are_void() {
+    let tr = TestRunner::new();
+
+    tr.setup_for_groups();
+    join_group_as_user(&tr);
+    tr.login_admin();
+    join_group(&tr, "1");
+    tr.logout();
+    tr.clean_emails();
+
+    tr.login_owner();
+    request_transfer(&tr, "3");
+    request_transfer(&tr, "1");
+    tr.logout();
+
+    let (user_uid, user_code) = tr.read_code_from_email("0.txt", "accept-group");
+    let (admin_uid, admin_code) = tr.read_code_from_email("1.txt", "accept-group");
+
+    tr.login_admin();
+    let html = accept_offer(&tr, &admin_uid, &admin_code);
+    check_html!(&html, "title", "Group transferred");
+    tr.logout();
+
+    // The offer of the old owner cannot be used any more
+    tr.login_user();
+    let html = accept_offer(&tr, &user_uid, &user_code);
+    check_message!(&html, "Invalid code", "Invalid code");
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"Owner: <a href="/user/1">"#));
+}
+
+#[test]
+fn transfer_offers_of_several_groups() {
+    let tr = TestRunner::new();
+
+    tr.setup_for_groups();
+    tr.create_group_helper("Second Group", 2);
+    tr.logout();
+    tr.login_user();
+    join_group(&tr, "1");
+    join_group(&tr, "2");
+    tr.logout();
+    tr.clean_emails();
+
+    tr.login_owner();
+    request_group_transfer(&tr, "1", "3");
+    request_group_transfer(&tr, "2", "3");
+    tr.logout();
+
+    let (first_uid, first_code) = tr.read_code_from_email("0.txt", "accept-group");
+    let (second_uid, second_code) = tr.read_code_from_email("1.txt", "accept-group");
+
+    tr.login_user();
+    let html = accept_offer(&tr, &first_uid, &first_code);
+    check_html!(&html, "title", "Group transferred");
+    let html = accept_offer(&tr, &second_uid, &second_code);
+    check_message!(
+        &html,
+        "Group transferred",
+        r#"You are now the owner of the <a href="/group/2">Second Group</a> group."#
+    );
+}
@@ -1,5 +1,5 @@
 use crate::test_lib::{
-    check_html, check_message, check_not_the_owner, check_unauthorized, check_unprocessable,
+    check_html, check_message, check_not_an_organizer, check_unauthorized, check_unprocessable,
     params, TestRunner, USER_NAME,
 };
 use rocket::http::{ContentType, Status};
@@ -38,6 +38,7 @@ fn create_group_by_admin() {
         .post("/admin/create-group")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("name", "Rust Maven"),
             ("location", "Virtual"),
             (
@@ -69,6 +70,7 @@ fn create_group_by_admin() {
         .post("/admin/create-group")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("name", "Python Maven"),
             ("location", "Other"),
             ("description", "Text with [link](https://code-maven.com/)"),
@@ -104,6 +106,7 @@ fn create_group_unauthorized() {
         .client
         .post("/admin/create-group")
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("name", "Rust Maven"),
             ("location", "Virtual"),
             ("description", "nope"),
@@ -140,7 +143,15 @@ fn get_join_group_not_existing_group_as_user() {
     tr.setup_owner();
     tr.login_owner();
 
-    let res = tr.client.get("/join-group?gid=20").dispatch();
+    let res = tr
+        .client
+        .post("/join-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "20")
+        ]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
 
@@ -163,7 +174,15 @@ fn get_join_group_as_user() {
     tr.login_user();
 
     // user joins group
-    let res = tr.client.get("/join-group?gid=1").dispatch();
+    let res = tr
+        .client
+        .post("/join-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1")
+        ]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
 
@@ -193,12 +212,19 @@ fn get_join_group_as_user() {
     let expected = format!(r#"<a href="/user/3">{USER_NAME}</a>"#);
     assert!(html.contains(&expected));
     assert!(html.contains(r#"You are a member."#));
-    assert!(html.contains(
-        r#"<a href="/leave-group?gid=1"><button class="button is-link">leave group</button></a>"#
-    ));
+    assert!(html.contains(r#"<form method="POST" action="/leave-group">"#));
+    assert!(html.contains(r#"<input type="hidden" name="gid" value="1">"#));
 
     // try to join the same group again - should fail
-    let res = tr.client.get("/join-group?gid=1").dispatch();
+    let res = tr
+        .client
+        .post("/join-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1")
+        ]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
 
@@ -208,7 +234,15 @@ fn get_join_group_as_user() {
         .contains(r#"You are already a member of the <a href="/group/1">First Group</a> group"#));
 
     // leave group
-    let res = tr.client.get("/leave-group?gid=1").dispatch();
+    let res = tr
+        .client
+        .post("/leave-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1")
+        ]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
 
@@ -239,7 +273,15 @@ fn get_join_group_as_owner() {
     tr.create_group_helper("First Group", 2);
     tr.login_owner();
 
-    let res = tr.client.get("/join-group?gid=1").dispatch();
+    let res = tr
+        .client
+        .post("/join-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1")
+        ]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
     check_message!(
@@ -256,7 +298,15 @@ fn get_leave_not_existing_group() {
     tr.setup_owner();
     tr.login_owner();
 
-    let res = tr.client.get("/leave-group?gid=20").dispatch();
+    let res = tr
+        .client
+        .post("/leave-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "20")
+        ]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
 
@@ -276,7 +326,15 @@ fn get_leave_group_as_owner() {
     tr.create_group_helper("First Group", 2);
     tr.login_owner();
 
-    let res = tr.client.get("/leave-group?gid=1").dispatch();
+    let res = tr
+        .client
+        .post("/leave-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1")
+        ]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
 
@@ -295,7 +353,15 @@ fn get_leave_group_user_does_not_belong_to() {
     tr.create_group_helper("First Group", 2);
     tr.login_user();
 
-    let res = tr.client.get("/leave-group?gid=1").dispatch();
+    let res = tr
+        .client
+        .post("/leave-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1")
+        ]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
 
@@ -328,7 +394,7 @@ fn get_edit_group_user_is_not_the_owner() {
     tr.login_user();
 
     let res = tr.client.get("/edit-group?gid=1").dispatch();
-    check_not_the_owner!(res);
+    check_not_an_organizer!(res);
 }
 
 #[test]
@@ -396,6 +462,7 @@ fn post_edit_group_user_no_such_group() {
         .post("/edit-group")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("gid", "1"),
             ("name", "Update"),
             ("location", "Virtual"),
@@ -422,6 +489,7 @@ fn post_edit_group_by_user_not_owner() {
         .post("/edit-group")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("gid", "1"),
             ("name", "Updated name"),
             ("location", "Local"),
@@ -429,7 +497,7 @@ fn post_edit_group_by_user_not_owner() {
         ]))
         .dispatch();
 
-    check_not_the_owner!(res);
+    check_not_an_organizer!(res);
 }
 
 #[test]
@@ -444,13 +512,14 @@ fn post_edit_group_user_not_owner() {
         .post("/edit-group")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("gid", "1"),
             ("name", "Updated name"),
             ("location", "Local"),
             ("description", "Some group"),
         ]))
         .dispatch();
-    check_not_the_owner!(res);
+    check_not_an_organizer!(res);
 }
 
 #[test]
@@ -468,6 +537,7 @@ fn post_edit_group_owner() {
         .post("/edit-group")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("gid", "1"),
             ("name", "Updated name"),
             ("location", "Local"),
@@ -494,3 +564,184 @@ fn post_edit_group_owner() {
     assert!(html.contains(r#"<p>Some group</p>"#));
     assert!(html.contains(r#"<b>Location</b>: Local"#));
 }
+
+#[test]
+fn post_edit_group_owner_empty_name() {
+    let tr = TestRunner::new();
+
+    tr.setup_admin();
+    tr.setup_owner();
+    tr.create_group_helper("First Group", 2);
+    tr.logout();
+    tr.login_owner();
+
+    let res = tr
+        .client
+        .post("/edit-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1"),
+            ("name", "  "),
+            ("location", "Local"),
+            ("description", "Some group"),
+        ]))
+        .dispatch();
+
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Invalid name",
+        "The name of the group must be between 1 and 50 characters."
+    );
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "h1", "First Group");
+}
+
+fn set_group_admin(tr: &TestRunner, uid: &str, admin: &str) -> String {
+    let res = tr
+        .client
+        .post("/set-group-admin")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1"),
+            ("uid", uid),
+            ("admin", admin),
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+#[test]
+fn group_admin_can_organize() {
+    let tr = TestRunner::new();
+
+    tr.setup_for_groups();
+    tr.login_user();
+    tr.client
+        .post("/join-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1")
+        ]))
+        .dispatch();
+    tr.logout();
+
+    // the owner promotes the member
+    tr.login_owner();
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<input type="hidden" name="uid" value="3">"#));
+    assert!(html.contains("Make admin"));
+
+    let html = set_group_admin(&tr, "3", "true");
+    check_message!(
+        &html,
+        "Group admins",
+        r#"<b>Sancho Panza</b> is now an admin of the <a href="/group/1">group</a>."#
+    );
+    tr.logout();
+
+    // the admin can edit the group and contact the members
+    tr.login_user();
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("you are an admin of this group"));
+    assert!(html.contains(r#"<a href="/add-event?gid=1">"#));
+    assert!(html.contains(r#"<form method="POST" action="/leave-group">"#));
+    assert!(!html.contains("Make admin"));
+
+    let res = tr.client.get("/edit-group?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Edit Group");
+
+    let res = tr
+        .client
+        .post("/edit-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1"),
+            ("name", "First Group"),
+            ("location", "Local"),
+            ("description", "Updated by an admin"),
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Group updated");
+
+    let res = tr
+        .client
+        .post("/contact-members")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1"),
+            ("subject", "Test subject line"),
+            ("content", "Test content"),
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(&html, "Message sent", "Message sent");
+
+    let res = tr
+        .client
+        .post("/add-event")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1"),
+            ("timezone", "Europe/Istanbul"),
+            ("title", "Event by the admin"),
+            ("location", "Virtual"),
+            ("description", ""),
+            ("date", "2030-01-01 10:10"),
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Event added");
+
+    // but cannot manage the admins
+    let html = set_group_admin(&tr, "3", "false");
+    check_message!(
+        &html,
+        "Not the owner",
+        "You are not the owner of the group <b>1</b>"
+    );
+    tr.logout();
+
+    // the owner demotes the admin
+    tr.login_owner();
+    let html = set_group_admin(&tr, "3", "false");
+    check_message!(
+        &html,
+        "Group admins",
+        r#"<b>Sancho Panza</b> is no longer an admin of the <a href="/group/1">group</a>."#
+    );
+    tr.logout();
+
+    tr.login_user();
+    let res = tr.client.get("/edit-group?gid=1").dispatch();
+    check_not_an_organizer!(res);
+}
+
+#[test]
+fn set_group_admin_not_a_member() {
+    let tr = TestRunner::new();
+
+    tr.setup_for_groups();
+    tr.login_owner();
+
+    let html = set_group_admin(&tr, "3", "true");
+    check_message!(
+        &html,
+        "Not a member",
+        "<b>Sancho Panza</b> is not a member of this group."
+    );
+}
@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use meetings::{ical, Event, EventStatus};
+use surrealdb::sql::{Id, Thing};
+
+fn event(eid: usize, title: &str, status: EventStatus) -> Event {
+    Event {
+        id: Thing::from(("event", Id::ulid())),
+        eid,
+        title: title.to_owned(),
+        description: String::from("Bring your laptop,\nand a friend; or two"),
+        date: DateTime::parse_from_rfc3339("2030-01-01T18:00:00Z")
+            .unwrap()
+            .to_utc(),
+        location: String::from("Budapest"),
+        group_id: 1,
+        status,
+        slug: String::new(),
+        old_slugs: vec![],
+        capacity: None,
+        cancel_reason: None,
+        occurrence: None,
+        sequence: 2,
+        timezone: String::from("Europe/Budapest"),
+        end: Some(
+            DateTime::parse_from_rfc3339("2030-01-01T20:00:00Z")
+                .unwrap()
+                .to_utc(),
+        ),
+    }
+}
+
+#[test]
+fn calendar_of_events() {
+    let now: DateTime<Utc> = DateTime::parse_from_rfc3339("2029-12-01T10:00:00Z")
+        .unwrap()
+        .to_utc();
+    let events = [
+        event(1, "Rust meetup", EventStatus::Published),
+        event(2, "Cancelled meetup", EventStatus::Cancelled),
+        event(3, "Draft meetup", EventStatus::Draft),
+    ];
+    let ics = ical::calendar("First Group", "https://meet-os.com", &events, now);
+
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    assert!(ics.contains("X-WR-CALNAME:First Group\r\n"));
+    assert!(ics.contains("UID:event-1@meet-os.com\r\n"));
+    assert!(ics.contains("DTSTAMP:20291201T100000Z\r\n"));
+    assert!(ics.contains("DTSTART:20300101T180000Z\r\n"));
+    assert!(ics.contains("DTEND:20300101T200000Z\r\n"));
+    assert!(ics.contains("SEQUENCE:2\r\n"));
+    assert!(ics.contains("SUMMARY:Rust meetup\r\n"));
+    assert!(ics.contains("DESCRIPTION:Bring your laptop\\,\\nand a friend\\; or two\r\n"));
+    assert!(ics.contains("URL:https://meet-os.com/event/1\r\n"));
+    assert!(ics.contains("STATUS:CONFIRMED\r\n"));
+
+    assert!(ics.contains("UID:event-2@meet-os.com\r\n"));
+    assert!(ics.contains("STATUS:CANCELLED\r\n"));
+
+    assert!(!ics.contains("Draft meetup"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+}
+
+#[test]
+fn long_lines_are_folded() {
+    let now = Utc::now();
+    let title = "Árvíztűrő tükörfúrógép ".repeat(5);
+    let ics = ical::calendar(
+        "Group",
+        "https://meet-os.com",
+        &[event(1, &title, EventStatus::Published)],
+        now,
+    );
+
+    for line in ics.split("\r\n") {
+        assert!(line.len() <= 75, "{line}");
+    }
+    let unfolded = ics.replace("\r\n ", "");
+    assert!(unfolded.contains(&format!("SUMMARY:{title}\r\n")));
+}
@@ -0,0 +1,280 @@
+use crate::test_lib::{
+    check_html, check_message, params, TestRunner, OTHER_EMAIL, OTHER_NAME, OTHER_PW, USER_EMAIL,
+    USER_NAME,
+};
+use regex::Regex;
+use rocket::http::{ContentType, Status};
+use serde_json::json;
+
+fn invite(tr: &TestRunner, emails: &str) -> String {
+    let res = tr
+        .client
+        .post("/invite")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1"),
+            ("emails", emails)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+fn read_invitation_code(tr: &TestRunner, filename: &str) -> String {
+    let email = std::fs::read_to_string(tr.email_folder.join(filename)).unwrap();
+    let re = Regex::new("http://localhost:[0-9]+/invitation/([a-z0-9-]+)").unwrap();
+    re.captures(&email).unwrap()[1].to_owned()
+}
+
+fn accept_invitation(tr: &TestRunner, code: &str) -> String {
+    let res = tr
+        .client
+        .post(format!("/invitation/{code}"))
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token().as_str())]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+fn invitation_id(html: &str) -> String {
+    let re = Regex::new(r#"<input type="hidden" name="id" value="([^"]+)">"#).unwrap();
+    re.captures(html).unwrap()[1].to_owned()
+}
+
+#[test]
+fn invite_existing_user() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+    tr.login_owner();
+    tr.clean_emails();
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<a href="/invite?gid=1">"#));
+
+    let html = invite(
+        &tr,
+        &format!("{USER_EMAIL}, not-an-email\nowner@meet-os.com {USER_EMAIL}"),
+    );
+    check_message!(
+        &html,
+        "Invitations",
+        r#"Invitations sent: 1. Invalid email addresses: <b>not-an-email</b>. Already members: <b>owner@meet-os.com</b>. Check the <a href="/invite?gid=1">invitations</a>."#
+    );
+
+    let res = tr.client.get("/invite?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Invite people");
+    assert!(html.contains(&format!("<td>{USER_EMAIL}</td>")));
+    assert!(html.contains("<td>Sent</td>"));
+    tr.logout();
+
+    // The guest sees the same page as people without an account
+    let code = read_invitation_code(&tr, "0.txt");
+    let res = tr.client.get(format!("/invitation/{code}")).dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Register");
+    assert!(html.contains(r#"If you already have an account, please <a href="/login">login</a> and open the invitation link again."#));
+
+    tr.login_user();
+    let res = tr.client.get(format!("/invitation/{code}")).dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Invitation");
+    check_html!(
+        &html,
+        "#question",
+        "Do you want to join the 'First Group' group?"
+    );
+    assert!(html.contains(&format!(
+        r#"<form method="POST" action="/invitation/{code}" id="confirm">"#
+    )));
+
+    let html = accept_invitation(&tr, &code);
+    check_message!(
+        &html,
+        "Invitation accepted",
+        r#"You are now a member of the <a href="/group/1">First Group</a> group."#
+    );
+
+    let html = accept_invitation(&tr, &code);
+    check_html!(&html, "title", "Invitation already used");
+    let res = tr.client.get(format!("/invitation/{code}")).dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Invitation already used");
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(&format!(r#"<td><a href="/user/3">{USER_NAME}</a></td>"#)));
+    tr.logout();
+
+    tr.login_owner();
+    let res = tr.client.get("/invite?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("<td>Accepted</td>"));
+}
+
+#[test]
+fn invitation_of_someone_else() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+    tr.login_owner();
+    tr.clean_emails();
+
+    invite(&tr, USER_EMAIL);
+    tr.logout();
+    let code = read_invitation_code(&tr, "0.txt");
+
+    tr.login_admin();
+    let res = tr.client.get(format!("/invitation/{code}")).dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Wrong account",
+        "This invitation was sent to another email address. Please login with the account of that address."
+    );
+
+    let html = accept_invitation(&tr, &code);
+    check_html!(&html, "title", "Wrong account");
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("No members in this group."));
+}
+
+#[test]
+fn invite_new_person() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+    tr.login_owner();
+    tr.clean_emails();
+
+    let html = invite(&tr, OTHER_EMAIL);
+    check_html!(&html, "title", "Invitations");
+    tr.logout();
+
+    let code = read_invitation_code(&tr, "0.txt");
+    let res = tr.client.get(format!("/invitation/{code}")).dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Register");
+    assert!(html.contains(&format!(r#"value="{OTHER_EMAIL}">"#)));
+    assert!(html.contains("You were invited to the <b>First Group</b> group."));
+
+    tr.register_and_verify_user(OTHER_NAME, OTHER_EMAIL, OTHER_PW);
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(&format!("{OTHER_NAME}</a></td>")));
+}
+
+#[test]
+fn invite_resend_and_revoke() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+    tr.login_owner();
+    tr.clean_emails();
+
+    invite(&tr, OTHER_EMAIL);
+    let first_code = read_invitation_code(&tr, "0.txt");
+
+    let res = tr.client.get("/invite?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    let id = invitation_id(&html);
+
+    let res = tr
+        .client
+        .post("/invite/resend")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1"),
+            ("id", id.as_str())
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Invitation sent",
+        r#"We sent a new invitation to <b>foo-alpha@meet-os.com</b>. Check the <a href="/invite?gid=1">invitations</a>."#
+    );
+    let second_code = read_invitation_code(&tr, "1.txt");
+    assert_ne!(first_code, second_code);
+
+    // The old link does not work any more
+    let res = tr
+        .client
+        .get(format!("/invitation/{first_code}"))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Invalid invitation");
+
+    let res = tr
+        .client
+        .post("/invite/revoke")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1"),
+            ("id", id.as_str())
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Invitation revoked",
+        r#"The invitation of <b>foo-alpha@meet-os.com</b> was revoked. Check the <a href="/invite?gid=1">invitations</a>."#
+    );
+
+    let res = tr
+        .client
+        .get(format!("/invitation/{second_code}"))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Invalid invitation");
+
+    let res = tr.client.get("/invite?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("No invitations were sent yet."));
+}
+
+#[test]
+fn invitation_expired() {
+    let tr = TestRunner::with_settings(json!({"invitation_days": 0}));
+    tr.setup_for_groups();
+    tr.login_owner();
+    tr.clean_emails();
+
+    invite(&tr, OTHER_EMAIL);
+    let res = tr.client.get("/invite?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("<td>Expired</td>"));
+    tr.logout();
+
+    let code = read_invitation_code(&tr, "0.txt");
+    let res = tr.client.get(format!("/invitation/{code}")).dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Invitation expired",
+        "This invitation has expired. Please ask the organizers of the group to send you a new one."
+    );
+}
+
+#[test]
+fn invite_not_organizer() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+    tr.login_user();
+
+    let res = tr.client.get("/invite?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Not an organizer",
+        "You are not an organizer of the group <b>1</b>"
+    );
+
+    let html = invite(&tr, OTHER_EMAIL);
+    check_html!(&html, "title", "Not an organizer");
+}
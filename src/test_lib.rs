@@ -37,6 +37,7 @@ pub struct TestRunner {
     user_pw: String,
     tmp_dir: tempfile::TempDir,
     pub email_folder: PathBuf,
+    settings: serde_json::Value,
     pub client: Client,
 }
 
@@ -45,9 +46,16 @@ impl TestRunner {
         Self::from("")
     }
 
+    /// Override some of the values in the configuration e.g. `json!({"login_max_failures": 2})`.
+    pub fn with_settings(settings: serde_json::Value) -> Self {
+        Self::create("", settings)
+    }
+
     pub fn from(filename: &str) -> Self {
-        use rocket::config::Config;
+        Self::create(filename, serde_json::json!({}))
+    }
 
+    fn create(filename: &str, settings: serde_json::Value) -> Self {
         let tmp_dir = tempfile::tempdir().unwrap();
         println!("tmp_dir: {:?}", tmp_dir);
         let email_folder = tmp_dir.path().join("emails");
@@ -85,15 +93,9 @@ impl TestRunner {
             assert_eq!(result.status, ExitStatus::default(), "Importing test data");
         }
 
-        let provider = Config::figment()
-            .merge(("database_namespace", &db_namespace))
-            .merge(("database_name", &db_name))
-            .merge(("email", "Folder"))
-            .merge(("email_folder", email_folder.to_str().unwrap()))
-            .merge(("admins", [ADMIN_EMAIL]));
-
-        let app = super::rocket().configure(provider);
-        let client = Client::tracked(app).unwrap();
+        let client = Self::create_client(&db_namespace, &db_name, &email_folder, &settings);
+        // The first visit sets the CSRF cookie
+        csrf_token(&client);
 
         Self {
             db_name,
@@ -102,10 +104,42 @@ impl TestRunner {
             user_pw,
             tmp_dir,
             email_folder,
+            settings,
             client,
         }
     }
 
+    fn create_client(
+        db_namespace: &str,
+        db_name: &str,
+        email_folder: &PathBuf,
+        settings: &serde_json::Value,
+    ) -> Client {
+        use rocket::config::Config;
+        use rocket::figment::providers::Serialized;
+
+        let provider = Config::figment()
+            .merge(("database_namespace", db_namespace))
+            .merge(("database_name", db_name))
+            .merge(("email", "Folder"))
+            .merge(("email_folder", email_folder.to_str().unwrap()))
+            .merge(("admins", [ADMIN_EMAIL]))
+            .merge(Serialized::globals(settings));
+
+        let app = super::rocket().configure(provider);
+        Client::tracked(app).unwrap()
+    }
+
+    /// An additional client (e.g. a second browser) using the same database.
+    pub fn new_client(&self) -> Client {
+        Self::create_client(
+            &self.db_namespace,
+            &self.db_name,
+            &self.email_folder,
+            &self.settings,
+        )
+    }
+
     pub fn setup_for_groups(&self) {
         self.setup_admin();
         self.setup_owner();
@@ -123,8 +157,37 @@ impl TestRunner {
         self.logout();
     }
 
+    /// The CSRF token of `client`, needed in every form we submit. It changes at login and logout.
+    pub fn csrf_token(&self) -> String {
+        self.client
+            .cookies()
+            .get_private("meet-os-csrf")
+            .unwrap()
+            .value()
+            .to_owned()
+    }
+
     pub fn logout(&self) {
-        self.client.get(format!("/logout")).dispatch();
+        self.client
+            .post("/logout")
+            .header(ContentType::Form)
+            .body(params!([("csrf_token", self.csrf_token().as_str())]))
+            .dispatch();
+    }
+
+    /// Submit a form with the CSRF token added to the fields and return the page.
+    pub fn post(&self, path: &str, fields: &[(&str, &str)]) -> String {
+        let csrf_token = self.csrf_token();
+        let mut fields = fields.to_vec();
+        fields.push(("csrf_token", csrf_token.as_str()));
+        let res = self
+            .client
+            .post(path)
+            .header(ContentType::Form)
+            .body(params!(fields))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        res.into_string().unwrap()
     }
 
     pub fn login_admin(&self) {
@@ -144,7 +207,11 @@ impl TestRunner {
             .client
             .post("/login")
             .header(ContentType::Form)
-            .body(params!([("email", email), ("password", password)]))
+            .body(params!([
+                ("csrf_token", self.csrf_token().as_str()),
+                ("email", email),
+                ("password", password)
+            ]))
             .dispatch();
         assert_eq!(res.status(), Status::Ok);
     }
@@ -164,7 +231,7 @@ impl TestRunner {
         self.setup_event(3);
 
         // Make sure the client is not logged in after the setup
-        let _res = &self.client.get(format!("/logout")).dispatch();
+        self.logout();
         // The setup_many_users logged the user out already so the above might return an error
         // That's why we don't check if it is Status::Ok
         //assert_eq!(res.status(), Status::Ok);
@@ -197,7 +264,7 @@ impl TestRunner {
         );
 
         // Make sure the client is not logged in after the setup
-        let _res = &self.client.get(format!("/logout")).dispatch();
+        self.logout();
         //assert_eq!(res.status(), Status::Ok);
         rocket::info!("--------------- finished setup_many_users ----------------");
         ids
@@ -221,6 +288,7 @@ impl TestRunner {
             .post("/admin/create-group")
             .header(ContentType::Form)
             .body(params!([
+                ("csrf_token", self.csrf_token().as_str()),
                 ("name", name),
                 ("location", ""),
                 ("description", "",),
@@ -251,8 +319,9 @@ impl TestRunner {
             .post("/add-event")
             .header(ContentType::Form)
             .body(params!([
+                ("csrf_token", self.csrf_token().as_str()),
                 ("gid", gid),
-                ("offset", "-180"),
+                ("timezone", "Europe/Istanbul"),
                 ("title", title),
                 ("location", "Virtual"),
                 ("description", ""),
@@ -293,6 +362,7 @@ impl TestRunner {
             .post(format!("/register"))
             .header(ContentType::Form)
             .body(params!([
+                ("csrf_token", self.csrf_token().as_str()),
                 ("name", name),
                 ("email", email),
                 ("password", password)
@@ -364,6 +434,83 @@ impl Drop for TestRunner {
     }
 }
 
+/// A minimal `OAuth2` provider listening on a random port. The token endpoint accepts any code,
+/// the user endpoint returns the given JSON. Returns the base URL of the server.
+pub fn start_mock_oauth_provider(userinfo: serde_json::Value) -> String {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+            let content = match path {
+                "/token" => {
+                    serde_json::json!({"access_token": "mock-token", "token_type": "bearer"})
+                }
+                "/user" => userinfo.clone(),
+                _ => serde_json::json!({"error": "not found"}),
+            }
+            .to_string();
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{content}",
+                content.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    format!("http://127.0.0.1:{port}")
+}
+
+/// Settings for a GitHub-like provider called `github` served by `start_mock_oauth_provider`.
+pub fn mock_oauth_settings(base_url: &str) -> serde_json::Value {
+    serde_json::json!({
+        "oauth_providers": [{
+            "name": "github",
+            "display_name": "GitHub",
+            "kind": "GitHub",
+            "client_id": "client-id",
+            "client_secret": "client-secret",
+            "authorize_url": format!("{base_url}/authorize"),
+            "token_url": format!("{base_url}/token"),
+            "userinfo_url": format!("{base_url}/user"),
+            "scope": "read:user user:email",
+        }]
+    })
+}
+
+/// Visit a page and take the CSRF token from the header of the page.
+/// It stays the same for the whole life of the client.
+pub fn csrf_token(client: &Client) -> String {
+    let html = client.get("/about").dispatch().into_string().unwrap();
+    let re = Regex::new(r#"<meta name="csrf-token" content="([a-z0-9]+)">"#).unwrap();
+    re.captures(&html).unwrap()[1].to_owned()
+}
+
 macro_rules! params {
     ($params:expr) => {
         $params
@@ -404,7 +551,7 @@ macro_rules! check_guest_menu {
         assert!($html.contains(r#"<a href="/login" class="navbar-item">Login</a>"#));
 
         assert!(!$html.contains(r#"<a href="/profile" class="navbar-item">Profile"#));
-        assert!(!$html.contains(r#"<a href="/logout" class="navbar-item">Logout</a>"#));
+        assert!(!$html.contains(r#"<form method="POST" action="/logout" class="navbar-item">"#));
     }};
 }
 pub(crate) use check_guest_menu;
@@ -415,7 +562,7 @@ macro_rules! check_logged_in_menu {
         assert!(!$html.contains(r#"<a href="/login" class="navbar-item">Login</a>"#));
 
         assert!($html.contains(r#"<a href="/profile" class="navbar-item">Profile"#));
-        assert!($html.contains(r#"<a href="/logout" class="navbar-item">Logout</a>"#));
+        assert!($html.contains(r#"<form method="POST" action="/logout" class="navbar-item">"#));
     }};
 }
 pub(crate) use check_logged_in_menu;
@@ -495,24 +642,28 @@ macro_rules! check_unprocessable {
 }
 pub(crate) use check_unprocessable;
 
-macro_rules! check_not_the_owner {
+macro_rules! check_not_an_organizer {
     ($res: expr) => {{
         assert_eq!($res.status(), Status::Ok);
         let html = $res.into_string().unwrap();
         check_message!(
             &html,
-            "Not the owner",
-            r#"You are not the owner of the group <b>1</b>"#
+            "Not an organizer",
+            r#"You are not an organizer of the group <b>1</b>"#
         );
     }};
 }
-pub(crate) use check_not_the_owner;
+pub(crate) use check_not_an_organizer;
 
 macro_rules! check_only_guest {
     ($res: expr) => {{
         assert_eq!($res.status(), Status::Ok);
         let html = $res.into_string().unwrap();
-        check_message!(&html, "Logged in", r#"Logged in users cannot access this page. Please, <a href="/logout">logout</a> and try again!"#);
+        check_message!(
+            &html,
+            "Logged in",
+            "Logged in users cannot access this page. Please, logout and try again!"
+        );
         check_user_menu!(&html);
     }};
 }
@@ -0,0 +1,226 @@
+use crate::test_lib::{
+    check_guest_menu, check_html, check_message, check_user_menu, params, TestRunner, OWNER_EMAIL,
+    OWNER_PW,
+};
+use rocket::http::{ContentType, Status};
+use serde_json::json;
+
+fn request_link(tr: &TestRunner, email: &str) -> String {
+    let res = tr
+        .client
+        .post("/login-by-email")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", email)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+/// Opening the link only shows a button, pressing it logs in.
+fn open_link(tr: &TestRunner, uid: &str, code: &str) -> String {
+    let res = tr
+        .client
+        .get(format!("/login-by-email/{uid}/{code}"))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Login");
+    assert!(html.contains(&format!(
+        r#"<form method="POST" action="/login-by-email/{uid}/{code}" id="confirm">"#
+    )));
+    check_guest_menu!(&html);
+
+    let res = tr
+        .client
+        .post(format!("/login-by-email/{uid}/{code}"))
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token().as_str())]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+#[test]
+fn login_page_has_magic_link_form() {
+    let tr = TestRunner::new();
+
+    let res = tr.client.get("/login").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<form method="POST" action="/login-by-email">"#));
+}
+
+#[test]
+fn login_by_email() {
+    let tr = TestRunner::new();
+
+    tr.setup_owner();
+    tr.logout();
+    tr.clean_emails();
+
+    let res = tr
+        .client
+        .post("/login-by-email")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", OWNER_EMAIL)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "We sent you an email",
+        "If the address belongs to a verified account we sent an email to it. Please click on the link in the email to login."
+    );
+    check_guest_menu!(&html);
+
+    let (uid, code) = tr.read_code_from_email("0.txt", "login-by-email");
+
+    // Opening the link several times does not use it up
+    let res = tr
+        .client
+        .get(format!("/login-by-email/{uid}/{code}"))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = open_link(&tr, &uid, &code);
+    check_html!(&html, "title", "Welcome back");
+    check_user_menu!(&html);
+
+    tr.logout();
+
+    let html = open_link(&tr, &uid, &code);
+    check_message!(
+        &html,
+        "Link already used",
+        r#"This link was already used. If you still need it, please <a href="/login">request a new one</a>."#
+    );
+    check_guest_menu!(&html);
+}
+
+#[test]
+fn login_by_email_expired() {
+    let tr = TestRunner::with_settings(json!({"magic_link_token_minutes": 0}));
+
+    tr.setup_owner();
+    tr.logout();
+    tr.clean_emails();
+
+    let res = tr
+        .client
+        .post("/login-by-email")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", OWNER_EMAIL)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let (uid, code) = tr.read_code_from_email("0.txt", "login-by-email");
+
+    let html = open_link(&tr, &uid, &code);
+    check_message!(
+        &html,
+        "Link expired",
+        r#"This link has expired. Please <a href="/login">request a new one</a>."#
+    );
+    check_guest_menu!(&html);
+}
+
+#[test]
+fn login_by_email_unknown_user() {
+    let tr = TestRunner::new();
+    tr.setup_owner();
+    tr.logout();
+    tr.clean_emails();
+
+    // The same answer as for a registered address
+    let html = request_link(&tr, "peter@meet-os.com");
+    check_message!(
+        &html,
+        "We sent you an email",
+        "If the address belongs to a verified account we sent an email to it. Please click on the link in the email to login."
+    );
+    assert_eq!(tr.email_folder.read_dir().unwrap().count(), 0);
+}
+
+#[test]
+fn login_by_email_is_throttled() {
+    let tr =
+        TestRunner::with_settings(json!({"magic_link_max_requests": 2, "login_max_failures": 2}));
+    tr.setup_owner();
+    tr.logout();
+    tr.clean_emails();
+
+    for _ in 0..2 {
+        let html = request_link(&tr, OWNER_EMAIL);
+        check_html!(&html, "title", "We sent you an email");
+    }
+    let html = request_link(&tr, OWNER_EMAIL);
+    check_message!(
+        &html,
+        "Too many requests",
+        "Too many login links were requested for this address. Please try again in 15 minutes."
+    );
+    assert_eq!(tr.email_folder.read_dir().unwrap().count(), 2);
+
+    // Unknown addresses are counted the same way
+    for _ in 0..2 {
+        let html = request_link(&tr, "unknown@meet-os.com");
+        check_html!(&html, "title", "We sent you an email");
+    }
+    let html = request_link(&tr, "unknown@meet-os.com");
+    check_html!(&html, "title", "Too many requests");
+
+    // The requests do not lock the password login
+    let res = tr
+        .client
+        .post("/login")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", OWNER_EMAIL),
+            ("password", OWNER_PW)
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Welcome back");
+}
+
+#[test]
+fn login_by_email_disabled() {
+    let tr = TestRunner::with_settings(json!({"magic_link_login": false}));
+
+    tr.setup_owner();
+    tr.logout();
+
+    let res = tr.client.get("/login").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    assert!(!html.contains("/login-by-email"));
+
+    let expected =
+        r#"Login by email is not available. Please <a href="/login">login</a> with your password."#;
+
+    let res = tr
+        .client
+        .post("/login-by-email")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", OWNER_EMAIL)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(&html, "Not available", expected);
+
+    let res = tr.client.get("/login-by-email/1/abc").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(&html, "Not available", expected);
+}
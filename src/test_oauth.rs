@@ -0,0 +1,383 @@
+use crate::test_lib::{
+    check_guest_menu, check_html, check_message, check_user_menu, mock_oauth_settings, params,
+    start_mock_oauth_provider, TestRunner, OWNER_EMAIL, OWNER_NAME,
+};
+use meetings::oauth::{parse_userinfo, Kind, UserInfo};
+use regex::Regex;
+use rocket::http::{ContentType, SameSite, Status};
+use serde_json::json;
+
+fn start_login(tr: &TestRunner) -> String {
+    let res = tr.client.get("/oauth/github/login").dispatch();
+    assert_eq!(res.status(), Status::SeeOther);
+    // The browser has to send it back when the provider redirects to us
+    let cookie = res.cookies().get("meet-os-oauth").unwrap();
+    assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+    let location = res.headers().get_one("Location").unwrap();
+    assert!(location.contains("code_challenge_method=S256"));
+    assert!(location.contains("client_id=client-id"));
+
+    let re = Regex::new("[?&]state=([a-z0-9]+)").unwrap();
+    re.captures(location).unwrap()[1].to_owned()
+}
+
+fn github_user(email: &str) -> serde_json::Value {
+    json!({"id": 4242, "login": "quijote", "name": "Alonso Quijano", "email": email})
+}
+
+#[test]
+fn parse_github_userinfo() {
+    let info = parse_userinfo(Kind::GitHub, &github_user("alonso@meet-os.com")).unwrap();
+    assert_eq!(
+        info,
+        UserInfo {
+            subject: String::from("4242"),
+            username: Some(String::from("quijote")),
+            name: Some(String::from("Alonso Quijano")),
+            email: Some(String::from("alonso@meet-os.com")),
+        }
+    );
+
+    assert!(parse_userinfo(Kind::GitHub, &json!({"login": "quijote"})).is_none());
+}
+
+#[test]
+fn parse_oidc_userinfo_unverified_email() {
+    let info = parse_userinfo(
+        Kind::Oidc,
+        &json!({"sub": "abc", "email": "alonso@meet-os.com", "email_verified": false}),
+    )
+    .unwrap();
+    assert_eq!(info.subject, "abc");
+    assert_eq!(info.email, None);
+
+    let info = parse_userinfo(
+        Kind::Oidc,
+        &json!({"sub": "abc", "email": "alonso@meet-os.com", "email_verified": true}),
+    )
+    .unwrap();
+    assert_eq!(info.email, Some(String::from("alonso@meet-os.com")));
+}
+
+#[test]
+fn login_page_shows_providers() {
+    let base_url = start_mock_oauth_provider(github_user("alonso@meet-os.com"));
+    let tr = TestRunner::with_settings(mock_oauth_settings(&base_url));
+
+    let res = tr.client.get("/login").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    assert!(
+        html.contains(r#"<a href="/oauth/github/login" class="button">Sign in with GitHub</a>"#)
+    );
+}
+
+#[test]
+fn unknown_provider() {
+    let tr = TestRunner::new();
+
+    let res = tr.client.get("/oauth/github/login").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "No such provider",
+        "We don't support login with this provider."
+    );
+}
+
+#[test]
+fn oauth_register_new_user() {
+    let base_url = start_mock_oauth_provider(github_user("alonso@meet-os.com"));
+    let tr = TestRunner::with_settings(mock_oauth_settings(&base_url));
+
+    let state = start_login(&tr);
+    let res = tr
+        .client
+        .get(format!("/oauth/github/callback?code=abc&state={state}"))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let cookie = res.cookies().get("meet-os").unwrap();
+    assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+    assert_eq!(cookie.http_only(), Some(true));
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Welcome back");
+    check_user_menu!(&html);
+
+    let res = tr.client.get("/profile").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "h1", "Alonso Quijano");
+    assert!(html.contains(r#"<a href="https://github.com/quijote">"#));
+    assert!(html.contains("<td>quijote</td>"));
+
+    // The second time we log in to the same account
+    tr.logout();
+    let state = start_login(&tr);
+    let res = tr
+        .client
+        .get(format!("/oauth/github/callback?code=abc&state={state}"))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Welcome back");
+
+    let res = tr.client.get("/profile").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "h1", "Alonso Quijano");
+}
+
+#[test]
+fn oauth_state_mismatch() {
+    let base_url = start_mock_oauth_provider(github_user("alonso@meet-os.com"));
+    let tr = TestRunner::with_settings(mock_oauth_settings(&base_url));
+
+    let _state = start_login(&tr);
+    let res = tr
+        .client
+        .get("/oauth/github/callback?code=abc&state=forged")
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Login expired",
+        r#"Please <a href="/login">login</a> again."#
+    );
+    check_guest_menu!(&html);
+
+    // Without starting the login process
+    let res = tr
+        .client
+        .get("/oauth/github/callback?code=abc&state=forged")
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Login expired");
+}
+
+#[test]
+fn oauth_cancelled() {
+    let base_url = start_mock_oauth_provider(github_user("alonso@meet-os.com"));
+    let tr = TestRunner::with_settings(mock_oauth_settings(&base_url));
+
+    let state = start_login(&tr);
+    let res = tr
+        .client
+        .get(format!(
+            "/oauth/github/callback?error=access_denied&state={state}"
+        ))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Login cancelled",
+        r#"The login with GitHub was cancelled. Please <a href="/login">login</a> again."#
+    );
+}
+
+#[test]
+fn oauth_email_already_registered() {
+    let base_url = start_mock_oauth_provider(github_user(OWNER_EMAIL));
+    let tr = TestRunner::with_settings(mock_oauth_settings(&base_url));
+    tr.setup_owner();
+    tr.logout();
+
+    let state = start_login(&tr);
+    let res = tr
+        .client
+        .get(format!("/oauth/github/callback?code=abc&state={state}"))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Account exists",
+        r#"There is already an account with the email address <b>owner@meet-os.com</b>. Please <a href="/login">login</a> and link your GitHub account from your profile."#
+    );
+    check_guest_menu!(&html);
+}
+
+#[test]
+fn oauth_link_login_and_unlink() {
+    let base_url = start_mock_oauth_provider(github_user("other@example.com"));
+    let tr = TestRunner::with_settings(mock_oauth_settings(&base_url));
+    tr.setup_owner();
+
+    let res = tr.client.get("/profile").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<a href="/oauth/github/login">Link GitHub account</a>"#));
+
+    let state = start_login(&tr);
+    let res = tr
+        .client
+        .get(format!("/oauth/github/callback?code=abc&state={state}"))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Account linked",
+        r#"Your GitHub account was linked. Check your <a href="/profile">profile</a>."#
+    );
+
+    let state = start_login(&tr);
+    let res = tr
+        .client
+        .get(format!("/oauth/github/callback?code=abc&state={state}"))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Already linked",
+        "Your GitHub account is already linked."
+    );
+
+    // Login with the linked account
+    tr.logout();
+    let state = start_login(&tr);
+    let res = tr
+        .client
+        .get(format!("/oauth/github/callback?code=abc&state={state}"))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Welcome back");
+    let res = tr.client.get("/profile").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "h1", OWNER_NAME);
+
+    let res = tr
+        .client
+        .post("/oauth/github/unlink")
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token().as_str())]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Account unlinked",
+        r#"The <b>github</b> account was unlinked. Check your <a href="/profile">profile</a>."#
+    );
+
+    // After unlinking the GitHub account leads to a new registration
+    tr.logout();
+    let state = start_login(&tr);
+    let res = tr
+        .client
+        .get(format!("/oauth/github/callback?code=abc&state={state}"))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Welcome back");
+    let res = tr.client.get("/profile").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "h1", "Alonso Quijano");
+}
+
+#[test]
+fn oauth_cannot_unlink_only_login_method() {
+    let base_url = start_mock_oauth_provider(github_user("alonso@meet-os.com"));
+    let tr = TestRunner::with_settings(mock_oauth_settings(&base_url));
+
+    let state = start_login(&tr);
+    tr.client
+        .get(format!("/oauth/github/callback?code=abc&state={state}"))
+        .dispatch();
+
+    let res = tr
+        .client
+        .post("/oauth/github/unlink")
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token().as_str())]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Cannot unlink",
+        r#"This is the only way you can login. Please <a href="/reset-password">set a password</a> first."#
+    );
+    check_user_menu!(&html);
+}
+
+#[test]
+fn oauth_user_deletes_account_by_email() {
+    let base_url = start_mock_oauth_provider(github_user("alonso@meet-os.com"));
+    let tr = TestRunner::with_settings(mock_oauth_settings(&base_url));
+
+    let state = start_login(&tr);
+    tr.client
+        .get(format!("/oauth/github/callback?code=abc&state={state}"))
+        .dispatch();
+    tr.clean_emails();
+
+    let res = tr.client.get("/profile/delete").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Delete account");
+    assert!(!html.contains(r#"name="password""#));
+
+    let res = tr
+        .client
+        .post("/profile/delete")
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token().as_str())]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "We sent you an email",
+        "Please click on the link in the email to delete your account."
+    );
+
+    // Opening the link only asks for confirmation
+    let (uid, code) = tr.read_code_from_email("0.txt", "profile/delete");
+    let res = tr
+        .client
+        .get(format!("/profile/delete/{uid}/{code}"))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Delete account");
+    assert!(html.contains(&format!(
+        r#"<form method="POST" action="/profile/delete/{uid}/{code}" id="confirm">"#
+    )));
+    check_user_menu!(&html);
+
+    let res = tr
+        .client
+        .post(format!("/profile/delete/{uid}/{code}"))
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token().as_str())]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Account deleted",
+        "Your account and all the related data were deleted."
+    );
+    check_guest_menu!(&html);
+}
+
+#[test]
+fn delete_account_link_of_other_user() {
+    let base_url = start_mock_oauth_provider(github_user("alonso@meet-os.com"));
+    let tr = TestRunner::with_settings(mock_oauth_settings(&base_url));
+
+    let state = start_login(&tr);
+    tr.client
+        .get(format!("/oauth/github/callback?code=abc&state={state}"))
+        .dispatch();
+    tr.clean_emails();
+    tr.client
+        .post("/profile/delete")
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token().as_str())]))
+        .dispatch();
+    let (uid, code) = tr.read_code_from_email("0.txt", "profile/delete");
+
+    tr.logout();
+    tr.setup_owner();
+    tr.login_owner();
+
+    let res = tr
+        .client
+        .post(format!("/profile/delete/{uid}/{code}"))
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token().as_str())]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Invalid link");
+    check_user_menu!(&html);
+}
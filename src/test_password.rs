@@ -0,0 +1,49 @@
+use meetings::{password, MyConfig};
+use pbkdf2::password_hash::{rand_core::OsRng, PasswordHasher as _, SaltString};
+use pbkdf2::Pbkdf2;
+use serde_json::json;
+
+fn myconfig(memory: u32) -> MyConfig {
+    serde_json::from_value(json!({
+        "base_url": "http://localhost:8001",
+        "email": "Folder",
+        "admins": [],
+        "from_name": "Meet-OS",
+        "from_email": "no-reply@meet-os.com",
+        "database_username": "root",
+        "database_password": "root",
+        "database_namespace": "ns",
+        "database_name": "db",
+        "argon2_memory_kib": memory,
+    }))
+    .unwrap()
+}
+
+#[test]
+fn argon2_hash() {
+    let myconfig = myconfig(8192);
+    let hashed = password::hash(&myconfig, b"123456");
+    assert!(hashed.starts_with("$argon2id$v=19$m=8192,t=2,p=1$"));
+    assert!(password::verify(&hashed, b"123456"));
+    assert!(!password::verify(&hashed, b"1234567"));
+    assert!(!password::needs_rehash(&myconfig, &hashed));
+
+    // changing the parameters in the config requires a new hash
+    assert!(password::needs_rehash(&self::myconfig(4096), &hashed));
+}
+
+#[test]
+fn pbkdf2_hash_is_accepted_and_upgraded() {
+    let myconfig = myconfig(8192);
+    let salt = SaltString::generate(&mut OsRng);
+    let hashed = Pbkdf2.hash_password(b"123456", &salt).unwrap().to_string();
+    assert!(password::verify(&hashed, b"123456"));
+    assert!(!password::verify(&hashed, b"1234567"));
+    assert!(password::needs_rehash(&myconfig, &hashed));
+}
+
+#[test]
+fn invalid_hash() {
+    assert!(!password::verify("", b"123456"));
+    assert!(!password::verify("hello", b"123456"));
+}
@@ -0,0 +1,190 @@
+use crate::test_lib::{
+    check_guest_menu, check_html, check_message, check_not_logged_in, params, TestRunner, OWNER_PW,
+    USER_EMAIL, USER_NAME, USER_PW,
+};
+use rocket::http::{ContentType, Status};
+
+#[test]
+fn export_guest() {
+    let tr = TestRunner::new();
+
+    let res = tr.client.get("/profile/export").dispatch();
+    check_not_logged_in!(res);
+}
+
+#[test]
+fn export_user_data() {
+    let tr = TestRunner::new();
+
+    tr.setup_for_events();
+    tr.login_user();
+
+    let res = tr
+        .client
+        .post("/rsvp-yes-event")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("eid", "1")
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+
+    let res = tr.client.get("/profile/export").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    assert_eq!(res.content_type(), Some(ContentType::JSON));
+    assert_eq!(
+        res.headers().get_one("Content-Disposition"),
+        Some(r#"attachment; filename="meet-os-3.json""#)
+    );
+
+    let data: serde_json::Value = serde_json::from_str(&res.into_string().unwrap()).unwrap();
+    assert_eq!(data["user"]["uid"], 3);
+    assert_eq!(data["user"]["name"], USER_NAME);
+    assert_eq!(data["user"]["email"], USER_EMAIL);
+    assert!(data["user"].get("password").is_none());
+
+    let memberships = data["memberships"].as_array().unwrap();
+    assert_eq!(memberships.len(), 1);
+    assert_eq!(memberships[0]["gid"], 1);
+
+    let rsvps = data["rsvps"].as_array().unwrap();
+    assert_eq!(rsvps.len(), 1);
+    assert_eq!(rsvps[0]["eid"], 1);
+    assert_eq!(rsvps[0]["status"], true);
+
+    assert!(!data["sessions"].as_array().unwrap().is_empty());
+
+    let audit = data["audit"].as_array().unwrap();
+    assert!(audit.iter().any(|entry| entry["type"] == "RSVPYes"));
+    assert!(!audit.iter().any(|entry| entry["type"] == "GroupCreated"));
+}
+
+#[test]
+fn delete_account_with_wrong_password() {
+    let tr = TestRunner::new();
+
+    tr.setup_user();
+
+    let res = tr.client.get("/profile/delete").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Delete account");
+    assert!(html.contains(r#"<form method="POST" action="/profile/delete">"#));
+
+    let res = tr
+        .client
+        .post("/profile/delete")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("password", "wrong password")
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Invalid password",
+        r#"The password was not correct. Please <a href="/profile/delete">try again</a>."#
+    );
+
+    let res = tr.client.get("/profile").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+}
+
+#[test]
+fn delete_account() {
+    let tr = TestRunner::new();
+
+    tr.setup_for_events();
+    tr.login_user();
+
+    let res = tr
+        .client
+        .post("/rsvp-yes-event")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("eid", "1")
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    tr.clean_emails();
+
+    let res = tr
+        .client
+        .post("/profile/delete")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("password", USER_PW)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Account deleted",
+        "Your account and all the related data were deleted."
+    );
+    check_guest_menu!(&html);
+
+    // the admin was notified
+    let email = std::fs::read_to_string(tr.email_folder.join("0.txt")).unwrap();
+    assert!(email.contains(&format!(
+        "User deleted their account: {USER_NAME} {USER_EMAIL}"
+    )));
+
+    let res = tr.client.get("/profile").dispatch();
+    check_not_logged_in!(res);
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(!html.contains(USER_NAME));
+
+    let res = tr.client.get("/event/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(!html.contains(USER_NAME));
+
+    let res = tr
+        .client
+        .post("/login")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", USER_EMAIL),
+            ("password", USER_PW)
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Invalid email or password");
+}
+
+#[test]
+fn delete_account_of_group_owner() {
+    let tr = TestRunner::new();
+
+    tr.setup_for_groups();
+    tr.login_owner();
+
+    let res = tr
+        .client
+        .post("/profile/delete")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("password", OWNER_PW)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "You own groups",
+        r#"You are the owner of the following groups: <a href="/group/1">First Group</a>. Please transfer their ownership before deleting your account."#
+    );
+
+    let res = tr.client.get("/profile").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+}
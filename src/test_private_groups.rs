@@ -0,0 +1,192 @@
+use crate::test_lib::{check_html, check_message, params, TestRunner, USER_NAME};
+use rocket::http::{ContentType, Status};
+
+fn make_group_private(tr: &TestRunner) {
+    tr.login_owner();
+    let res = tr
+        .client
+        .post("/edit-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1"),
+            ("name", "First Group"),
+            ("location", "Local"),
+            ("description", "Internal study group"),
+            ("requires_approval", "true"),
+            (
+                "questions",
+                "Which team are you in?\n\nWhy do you want to join?\n"
+            ),
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Group updated");
+    tr.logout();
+}
+
+fn ask_to_join(tr: &TestRunner, answers: &[&str]) -> String {
+    let csrf_token = tr.csrf_token();
+    let mut fields = vec![("csrf_token", csrf_token.as_str()), ("gid", "1")];
+    fields.extend(answers.iter().map(|answer| ("answers", *answer)));
+
+    let res = tr
+        .client
+        .post("/join-group")
+        .header(ContentType::Form)
+        .body(params!(fields))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+fn decide(tr: &TestRunner, action: &str) -> String {
+    let res = tr
+        .client
+        .post(format!("/{action}-join-request"))
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1"),
+            ("uid", "3")
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+#[test]
+fn join_private_group_approved() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+    make_group_private(&tr);
+
+    tr.login_owner();
+    let res = tr.client.get("/edit-group?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"value="true" checked> New members need approval"#));
+    assert!(html.contains("Which team are you in?\nWhy do you want to join?</textarea>"));
+    tr.logout();
+
+    tr.login_user();
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("ask to join this group"));
+    assert!(
+        html.contains(r#"<div>Which team are you in? <input name="answers" type="text"></div>"#)
+    );
+
+    let html = ask_to_join(&tr, &["Backend"]);
+    check_message!(
+        &html,
+        "Missing answer",
+        "Please answer all the questions of the group."
+    );
+
+    tr.clean_emails();
+    let html = ask_to_join(&tr, &["Backend", "To learn Rust"]);
+    check_message!(
+        &html,
+        "Request sent",
+        r#"Your request to join the <a href="/group/1">First Group</a> group was sent to the organizers."#
+    );
+    let email = std::fs::read_to_string(tr.email_folder.join("0.txt")).unwrap();
+    assert!(email.contains(">Sancho Panza</a> asked to join the Meet-OS group"));
+
+    let html = ask_to_join(&tr, &["Backend", "To learn Rust"]);
+    check_html!(&html, "title", "Request pending");
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("You asked to join this group."));
+    assert!(html.contains("No members in this group."));
+
+    // The applicant cannot approve their own request
+    let html = decide(&tr, "approve");
+    check_html!(&html, "title", "Not an organizer");
+    tr.logout();
+
+    tr.login_owner();
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<h2 class="title is-4">Join requests</h2>"#));
+    assert!(html.contains("<div><b>Why do you want to join?</b> To learn Rust</div>"));
+
+    tr.clean_emails();
+    let html = decide(&tr, "approve");
+    check_message!(
+        &html,
+        "Join request approved",
+        r#"<b>Sancho Panza</b> is now a member of the <a href="/group/1">group</a>."#
+    );
+    let email = std::fs::read_to_string(tr.email_folder.join("0.txt")).unwrap();
+    assert!(email.contains("was approved. You are now a member of the group."));
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(!html.contains("Join requests"));
+    assert!(html.contains(&format!(r#"<td><a href="/user/3">{USER_NAME}</a></td>"#)));
+
+    let html = decide(&tr, "approve");
+    check_html!(&html, "title", "No such request");
+}
+
+#[test]
+fn join_private_group_rejected() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+    make_group_private(&tr);
+
+    tr.login_user();
+    ask_to_join(&tr, &["Backend", "To learn Rust"]);
+    tr.logout();
+
+    tr.login_owner();
+    tr.clean_emails();
+    let html = decide(&tr, "reject");
+    check_message!(
+        &html,
+        "Join request rejected",
+        r#"The request of <b>Sancho Panza</b> to join the <a href="/group/1">group</a> was rejected."#
+    );
+    let email = std::fs::read_to_string(tr.email_folder.join("0.txt")).unwrap();
+    assert!(email.contains("was not approved."));
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("No members in this group."));
+    tr.logout();
+
+    // After the rejection the user can ask again
+    tr.login_user();
+    let html = ask_to_join(&tr, &["Frontend", "Changed my mind"]);
+    check_html!(&html, "title", "Request sent");
+}
+
+#[test]
+fn rsvp_in_private_group_needs_membership() {
+    let tr = TestRunner::new();
+    tr.setup_for_events();
+    make_group_private(&tr);
+
+    tr.login_user();
+    let res = tr
+        .client
+        .post("/rsvp-yes-event")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("eid", "1")
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Membership required",
+        r#"You need to be a member of the <a href="/group/1">First Group</a> group to register to its events. Please ask to join the group first."#
+    );
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("No members in this group."));
+}
@@ -0,0 +1,125 @@
+use crate::test_lib::{check_html, check_message, TestRunner};
+
+#[test]
+fn remove_and_ban_member() {
+    let tr = TestRunner::new();
+    tr.setup_for_events();
+
+    // RSVP-ing to the event makes the user a member of the group
+    tr.login_user();
+    let html = tr.post("/rsvp-yes-event", &[("eid", "1")]);
+    check_html!(&html, "title", "RSVPed to event");
+
+    // A member cannot remove other people
+    let html = tr.post("/remove-member", &[("gid", "1"), ("uid", "2")]);
+    check_html!(&html, "title", "Not an organizer");
+    tr.logout();
+
+    tr.login_owner();
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(
+        r#"<a href="/remove-member?gid=1&uid=3" class="button is-small is-danger">Remove</a>"#
+    ));
+
+    let res = tr.client.get("/remove-member?gid=1&uid=3").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Remove member");
+
+    let html = tr.post("/remove-member", &[("gid", "1"), ("uid", "2")]);
+    check_message!(
+        &html,
+        "Cannot remove",
+        "<b>Don Quijote de la Mancha</b> cannot be removed from the group."
+    );
+
+    tr.clean_emails();
+    let html = tr.post(
+        "/remove-member",
+        &[
+            ("gid", "1"),
+            ("uid", "3"),
+            ("reason", "Spamming <b>links</b>"),
+            ("ban", "true"),
+            ("notify", "true"),
+        ],
+    );
+    check_message!(
+        &html,
+        "Member removed",
+        r#"<b>Sancho Panza</b> was removed from the <a href="/group/1">group</a> and banned from joining again."#
+    );
+    let email = std::fs::read_to_string(tr.email_folder.join("0.txt")).unwrap();
+    assert!(email.contains("Hi Sancho Panza,"));
+    assert!(email.contains(">First Group</a> removed you from the group."));
+    assert!(email.contains("You cannot join the group again."));
+    assert!(email.contains("Reason: Spamming &lt;b&gt;links&lt;/b&gt;"));
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("No members in this group."));
+    assert!(html.contains(r#"<h2 class="title is-4">Banned users</h2>"#));
+    assert!(html.contains("<td>Spamming &lt;b&gt;links&lt;&#x2F;b&gt;</td>"));
+    tr.logout();
+
+    // The banned user can neither join nor get in through an event
+    tr.login_user();
+    let html = tr.post("/join-group", &[("gid", "1")]);
+    check_message!(
+        &html,
+        "Banned",
+        r#"You were banned from the <a href="/group/1">First Group</a> group."#
+    );
+    let html = tr.post("/rsvp-yes-event", &[("eid", "1")]);
+    check_html!(&html, "title", "Banned");
+    tr.logout();
+
+    tr.login_owner();
+    let html = tr.post("/unban-member", &[("gid", "1"), ("uid", "3")]);
+    check_message!(
+        &html,
+        "Ban lifted",
+        r#"<b>Sancho Panza</b> can join the <a href="/group/1">group</a> again."#
+    );
+    let html = tr.post("/unban-member", &[("gid", "1"), ("uid", "3")]);
+    check_html!(&html, "title", "Not banned");
+    tr.logout();
+
+    tr.login_user();
+    let html = tr.post("/join-group", &[("gid", "1")]);
+    check_html!(&html, "title", "Membership");
+}
+
+#[test]
+fn remove_member_without_ban() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+
+    tr.login_user();
+    tr.post("/join-group", &[("gid", "1")]);
+    tr.logout();
+
+    tr.login_owner();
+    tr.clean_emails();
+    let html = tr.post("/remove-member", &[("gid", "1"), ("uid", "3")]);
+    check_message!(
+        &html,
+        "Member removed",
+        r#"<b>Sancho Panza</b> was removed from the <a href="/group/1">group</a>."#
+    );
+    // No notification was requested
+    assert!(!tr.email_folder.join("0.txt").exists());
+
+    let html = tr.post("/remove-member", &[("gid", "1"), ("uid", "3")]);
+    check_message!(
+        &html,
+        "Not a member",
+        "<b>Sancho Panza</b> is not a member of this group."
+    );
+    tr.logout();
+
+    // Without a ban the user can join again
+    tr.login_user();
+    let html = tr.post("/join-group", &[("gid", "1")]);
+    check_html!(&html, "title", "Membership");
+}
@@ -39,7 +39,10 @@ fn post_resend_email_verification_logged_in_user() {
         .client
         .post("/resend-email-verification-code")
         .header(ContentType::Form)
-        .body(params!([("email", "any@meet-os.com"),]))
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", "any@meet-os.com"),
+        ]))
         .dispatch();
     check_only_guest!(res);
 }
@@ -55,7 +58,10 @@ fn post_resend_email_verification_verified_email() {
         .client
         .post("/resend-email-verification-code")
         .header(ContentType::Form)
-        .body(params!([("email", USER_EMAIL),]))
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", USER_EMAIL),
+        ]))
         .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
@@ -78,7 +84,10 @@ fn post_resend_email_verification_unverified_email() {
         .client
         .post("/resend-email-verification-code")
         .header(ContentType::Form)
-        .body(params!([("email", UNVERIFIED_EMAIL)]))
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", UNVERIFIED_EMAIL)
+        ]))
         .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
@@ -109,3 +118,36 @@ fn post_resend_email_verification_unverified_email() {
 
     check_profile_by_user!(&tr.client, UNVERIFIED_NAME);
 }
+
+#[test]
+fn post_resend_email_verification_invalidates_old_code() {
+    let tr = TestRunner::new();
+
+    tr.setup_unverified_user();
+    tr.logout();
+    let (uid, old_code) = tr.read_code_from_email("0.txt", "verify-email");
+    tr.clean_emails();
+
+    let res = tr
+        .client
+        .post("/resend-email-verification-code")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", UNVERIFIED_EMAIL)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+
+    let res = tr
+        .client
+        .get(format!("/verify-email/{uid}/{old_code}"))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Invalid code",
+        &format!("Invalid code <b>{old_code}</b>")
+    );
+}
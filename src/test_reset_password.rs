@@ -4,6 +4,7 @@ use crate::test_lib::{
 };
 
 use rocket::http::{ContentType, Status};
+use serde_json::json;
 
 #[test]
 fn reset_password_full() {
@@ -21,7 +22,12 @@ fn reset_password_full() {
     let html = res.into_string().unwrap();
     check_user_menu!(&html);
 
-    let res = tr.client.get("/logout").dispatch();
+    let res = tr
+        .client
+        .post("/logout")
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token().as_str())]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
     check_guest_menu!(&html);
@@ -41,7 +47,10 @@ fn reset_password_full() {
         .client
         .post("/reset-password")
         .header(ContentType::Form)
-        .body(params!([("email", "peter@meet-os.com"),]))
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", "peter@meet-os.com"),
+        ]))
         .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
@@ -58,7 +67,10 @@ fn reset_password_full() {
         .client
         .post("/reset-password")
         .header(ContentType::Form)
-        .body(params!([("email", OWNER_EMAIL),]))
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", OWNER_EMAIL),
+        ]))
         .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
@@ -90,6 +102,7 @@ fn reset_password_full() {
         .post("/save-password")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token()),
             ("uid", uid.to_string()),
             ("code", code.clone()),
             ("password", String::from("abc"))
@@ -110,6 +123,7 @@ fn reset_password_full() {
         .post("/save-password")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token()),
             ("uid", uid.to_string()),
             ("code", code),
             ("password", new_password.clone())
@@ -126,6 +140,7 @@ fn reset_password_full() {
         .post("/login")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("email", OWNER_EMAIL),
             ("password", &new_password)
         ]))
@@ -173,6 +188,7 @@ fn save_password_post_invalid_uid() {
         .post("/save-password")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("uid", "42"),
             ("code", "abc"),
             ("password", "new_password")
@@ -197,6 +213,7 @@ fn save_password_post_invalid_code() {
         .post("/save-password")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("uid", "2"),
             ("code", "abc"),
             ("password", "new_password")
@@ -207,3 +224,115 @@ fn save_password_post_invalid_code() {
     let html = res.into_string().unwrap();
     check_message!(&html, "Invalid code", "Invalid code <b>abc</b>.");
 }
+
+#[test]
+fn save_password_with_used_code() {
+    let tr = TestRunner::new();
+
+    tr.setup_owner();
+    tr.logout();
+    tr.clean_emails();
+
+    let res = tr
+        .client
+        .post("/reset-password")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", OWNER_EMAIL)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let (uid, code) = tr.read_code_from_email("0.txt", "save-password");
+
+    let res = tr
+        .client
+        .post("/save-password")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token()),
+            ("uid", uid.clone()),
+            ("code", code.clone()),
+            ("password", String::from("new password"))
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(&html, "Password updated", "Your password was updated.");
+
+    let expected = r#"This link was already used. If you still need it, please <a href="/reset-password">request a new one</a>."#;
+
+    let res = tr
+        .client
+        .get(format!("/save-password/{uid}/{code}"))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(&html, "Link already used", expected);
+
+    let res = tr
+        .client
+        .post("/save-password")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token()),
+            ("uid", uid),
+            ("code", code),
+            ("password", String::from("other password"))
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(&html, "Link already used", expected);
+}
+
+#[test]
+fn save_password_with_expired_code() {
+    let tr = TestRunner::with_settings(json!({"reset_password_token_hours": 0}));
+
+    tr.setup_owner();
+    tr.logout();
+    tr.clean_emails();
+
+    let res = tr
+        .client
+        .post("/reset-password")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", OWNER_EMAIL)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let (uid, code) = tr.read_code_from_email("0.txt", "save-password");
+
+    let res = tr
+        .client
+        .get(format!("/save-password/{uid}/{code}"))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Link expired",
+        r#"This link has expired. Please <a href="/reset-password">request a new one</a>."#
+    );
+}
+
+#[test]
+fn save_password_with_verification_code() {
+    let tr = TestRunner::new();
+
+    tr.register_user_helper("Foo Bar", OWNER_EMAIL, "123456");
+    let (_id, code) = tr.read_code_from_email("0.txt", "verify-email");
+
+    // The code sent for email verification cannot be used to reset the password
+    let res = tr.client.get(format!("/save-password/1/{code}")).dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Invalid code",
+        &format!("Invalid code <b>{code}</b>")
+    );
+}
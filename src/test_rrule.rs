@@ -0,0 +1,123 @@
+use chrono::{DateTime, NaiveDate, TimeZone as _, Utc, Weekday};
+use chrono_tz::Europe::Budapest;
+use meetings::rrule::{Frequency, Rule};
+
+fn date(text: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(text).unwrap().to_utc()
+}
+
+fn days(dates: &[DateTime<Utc>]) -> Vec<String> {
+    dates
+        .iter()
+        .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+        .collect()
+}
+
+#[test]
+fn parse_rules() {
+    let rule = Rule::parse("RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=TU,TH").unwrap();
+    assert_eq!(rule.frequency, Frequency::Weekly);
+    assert_eq!(rule.interval, 2);
+    assert_eq!(rule.by_day, vec![(0, Weekday::Tue), (0, Weekday::Thu)]);
+
+    let rule = Rule::parse("FREQ=MONTHLY;BYDAY=-1FR;COUNT=3").unwrap();
+    assert_eq!(rule.by_day, vec![(-1, Weekday::Fri)]);
+    assert_eq!(rule.count, Some(3));
+
+    let rule = Rule::parse("FREQ=WEEKLY;UNTIL=20300131").unwrap();
+    assert_eq!(rule.until, Some(date("2030-01-31T23:59:59Z")));
+
+    assert!(Rule::parse("").is_err());
+    assert!(Rule::parse("FREQ=DAILY").is_err());
+    assert!(Rule::parse("FREQ=WEEKLY;INTERVAL=0").is_err());
+    assert!(Rule::parse("FREQ=WEEKLY;BYDAY=2TU").is_err());
+    assert!(Rule::parse("FREQ=MONTHLY;BYDAY=TU").is_err());
+    assert!(Rule::parse("FREQ=MONTHLY;BYDAY=2XX").is_err());
+    assert!(Rule::parse("FREQ=WEEKLY;BYSETPOS=1").is_err());
+}
+
+#[test]
+fn every_second_tuesday() {
+    let rule = Rule::parse("FREQ=WEEKLY;INTERVAL=2;BYDAY=TU").unwrap();
+    // 2030-01-01 is a Tuesday
+    let dates = rule.occurrences(
+        &date("2030-01-01T18:00:00Z"),
+        date("2030-02-15T00:00:00Z"),
+        &[NaiveDate::from_ymd_opt(2030, 1, 15).unwrap()],
+    );
+    assert_eq!(
+        days(&dates),
+        vec!["2030-01-01 18:00", "2030-01-29 18:00", "2030-02-12 18:00"]
+    );
+}
+
+#[test]
+fn weekly_on_several_days_with_count() {
+    let rule = Rule::parse("FREQ=WEEKLY;BYDAY=MO,WE;COUNT=3").unwrap();
+    // Starting on a Tuesday skips the Monday of the first week
+    let dates = rule.occurrences(
+        &date("2030-01-01T10:00:00Z"),
+        date("2030-12-31T00:00:00Z"),
+        &[],
+    );
+    assert_eq!(
+        days(&dates),
+        vec!["2030-01-02 10:00", "2030-01-07 10:00", "2030-01-09 10:00"]
+    );
+}
+
+#[test]
+fn monthly_by_weekday() {
+    let rule = Rule::parse("FREQ=MONTHLY;BYDAY=2TU").unwrap();
+    let dates = rule.occurrences(
+        &date("2030-01-01T18:00:00Z"),
+        date("2030-04-30T00:00:00Z"),
+        &[],
+    );
+    assert_eq!(
+        days(&dates),
+        vec![
+            "2030-01-08 18:00",
+            "2030-02-12 18:00",
+            "2030-03-12 18:00",
+            "2030-04-09 18:00"
+        ]
+    );
+
+    let rule = Rule::parse("FREQ=MONTHLY;BYDAY=-1FR;UNTIL=20300331").unwrap();
+    let dates = rule.occurrences(
+        &date("2030-01-01T18:00:00Z"),
+        date("2030-12-31T00:00:00Z"),
+        &[],
+    );
+    assert_eq!(
+        days(&dates),
+        vec!["2030-01-25 18:00", "2030-02-22 18:00", "2030-03-29 18:00"]
+    );
+
+    // Without BYDAY the position of the first date is used: 2030-01-15 is the third Tuesday
+    let rule = Rule::parse("FREQ=MONTHLY").unwrap();
+    let dates = rule.occurrences(
+        &date("2030-01-15T18:00:00Z"),
+        date("2030-02-28T00:00:00Z"),
+        &[],
+    );
+    assert_eq!(days(&dates), vec!["2030-01-15 18:00", "2030-02-19 18:00"]);
+}
+
+#[test]
+fn keeps_the_local_time_when_the_clocks_change() {
+    let rule = Rule::parse("FREQ=WEEKLY;BYDAY=TU").unwrap();
+    // Summer time starts on 2030-03-31 in Budapest
+    let start = Budapest.with_ymd_and_hms(2030, 3, 19, 18, 0, 0).unwrap();
+    let dates = rule.occurrences(&start, date("2030-04-10T00:00:00Z"), &[]);
+    assert_eq!(
+        days(&dates),
+        vec![
+            "2030-03-19 17:00",
+            "2030-03-26 17:00",
+            "2030-04-02 16:00",
+            "2030-04-09 16:00"
+        ]
+    );
+}
@@ -0,0 +1,101 @@
+use crate::test_lib::{check_html, check_message, TestRunner};
+
+fn add_series(tr: &TestRunner, rrule: &str, exceptions: &str) -> String {
+    tr.post(
+        "/add-event",
+        &[
+            ("gid", "1"),
+            ("timezone", "UTC"),
+            ("title", "Rust Tuesday"),
+            ("location", "Library"),
+            ("description", ""),
+            ("date", "2030-01-01 18:00"),
+            ("rrule", rrule),
+            ("exceptions", exceptions),
+        ],
+    )
+}
+
+fn edit_event(tr: &TestRunner, eid: &str, title: &str, date: &str, scope: &str) -> String {
+    tr.post(
+        "/edit-event",
+        &[
+            ("eid", eid),
+            ("timezone", "UTC"),
+            ("title", title),
+            ("location", "Library"),
+            ("description", ""),
+            ("date", date),
+            ("scope", scope),
+        ],
+    )
+}
+
+#[test]
+fn create_recurring_event() {
+    // The default horizon would not reach 2030
+    let tr = TestRunner::with_settings(serde_json::json!({"recurring_events_horizon_days": 3650}));
+    tr.setup_many_users();
+    tr.create_group_helper("First Group", 2);
+
+    tr.login_owner();
+    let html = add_series(&tr, "FREQ=DAILY", "");
+    check_html!(&html, "title", "Invalid recurrence rule");
+    let html = add_series(&tr, "FREQ=WEEKLY", "next week");
+    check_message!(
+        &html,
+        "Invalid exception",
+        "Invalid date 'next week'. Please use the YYYY-MM-DD format."
+    );
+
+    let html = add_series(&tr, "FREQ=WEEKLY;INTERVAL=2;BYDAY=TU;COUNT=4", "2030-01-15");
+    check_message!(
+        &html,
+        "Series created",
+        r#"Recurring event added with 3 events. The first one: <a href="/event/1">Rust Tuesday</a>"#
+    );
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("2030-01-01T18:00:00Z - Rust Tuesday"));
+    assert!(!html.contains("2030-01-15T18:00:00Z - Rust Tuesday"));
+    assert!(html.contains("2030-01-29T18:00:00Z - Rust Tuesday"));
+    assert!(html.contains("2030-02-12T18:00:00Z - Rust Tuesday"));
+
+    let res = tr.client.get("/event/2").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("Recurring event: FREQ=WEEKLY;INTERVAL=2;BYDAY=TU;COUNT=4"));
+
+    // This event only
+    let html = edit_event(&tr, "2", "Rust Tuesday special", "2030-01-30 18:00", "this");
+    check_html!(&html, "title", "Event updated");
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("2030-01-30T18:00:00Z - Rust Tuesday special"));
+    assert!(html.contains("2030-02-12T18:00:00Z - Rust Tuesday</a>"));
+
+    // This and following events can only change the time
+    let html = edit_event(
+        &tr,
+        "1",
+        "Rust Tuesday evening",
+        "2030-01-02 19:00",
+        "following",
+    );
+    check_html!(&html, "title", "Cannot move the series");
+
+    let html = edit_event(
+        &tr,
+        "1",
+        "Rust Tuesday evening",
+        "2030-01-01 19:00",
+        "following",
+    );
+    check_html!(&html, "title", "Event updated");
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("2030-01-01T19:00:00Z - Rust Tuesday evening"));
+    assert!(html.contains("2030-01-30T19:00:00Z - Rust Tuesday evening"));
+    assert!(html.contains("2030-02-12T19:00:00Z - Rust Tuesday evening"));
+    assert!(!html.contains("T18:00:00Z"));
+}
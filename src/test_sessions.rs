@@ -0,0 +1,167 @@
+use crate::test_lib::{
+    check_guest_menu, check_html, check_message, check_not_logged_in, check_user_menu, csrf_token,
+    params, TestRunner, OWNER_EMAIL, OWNER_PW,
+};
+use regex::Regex;
+use rocket::http::{ContentType, Status};
+
+#[test]
+fn profile_lists_current_session() {
+    let tr = TestRunner::new();
+
+    tr.setup_owner();
+
+    let res = tr.client.get("/profile").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<h2 class="title is-4">Active Sessions</h2>"#));
+    assert!(html.contains("<td>This device</td>"));
+    assert!(!html.contains("Log out this device"));
+    assert!(html.contains(r#"<form method="POST" action="/logout-everywhere">"#));
+}
+
+#[test]
+fn logout_other_device() {
+    let tr = TestRunner::new();
+
+    tr.setup_owner();
+
+    let other = tr.new_client();
+    let other_csrf_token = csrf_token(&other);
+    let res = other
+        .post("/login")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", other_csrf_token.as_str()),
+            ("email", OWNER_EMAIL),
+            ("password", OWNER_PW)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+
+    let res = tr.client.get("/profile").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    let re = Regex::new(r#"<input type="hidden" name="id" value="([^"]+)">"#).unwrap();
+    let id = re.captures(&html).unwrap()[1].to_owned();
+
+    let res = tr
+        .client
+        .post("/logout-session")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("id", id.as_str())
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Session removed",
+        r#"The device was logged out. Check your <a href="/profile">profile</a>."#
+    );
+    check_user_menu!(&html);
+
+    let res = other.get("/profile").dispatch();
+    check_not_logged_in!(res);
+
+    let res = tr.client.get("/profile").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+}
+
+#[test]
+fn logout_session_with_invalid_id() {
+    let tr = TestRunner::new();
+
+    tr.setup_owner();
+
+    let res = tr
+        .client
+        .post("/logout-session")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("id", "abc")
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(&html, "No such session", "There is no session <b>abc</b>.");
+}
+
+#[test]
+fn logout_everywhere() {
+    let tr = TestRunner::new();
+
+    tr.setup_owner();
+
+    let other = tr.new_client();
+    let other_csrf_token = csrf_token(&other);
+    let res = other
+        .post("/login")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", other_csrf_token.as_str()),
+            ("email", OWNER_EMAIL),
+            ("password", OWNER_PW)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+
+    let res = tr
+        .client
+        .post("/logout-everywhere")
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token().as_str())]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Logged out everywhere",
+        "We have logged you out from all of your devices"
+    );
+    check_guest_menu!(&html);
+
+    let res = tr.client.get("/profile").dispatch();
+    check_not_logged_in!(res);
+
+    let res = other.get("/profile").dispatch();
+    check_not_logged_in!(res);
+}
+
+#[test]
+fn reset_password_logs_out_other_sessions() {
+    let tr = TestRunner::new();
+
+    tr.setup_owner();
+
+    let other = tr.new_client();
+    tr.clean_emails();
+    let res = other
+        .post("/reset-password")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", OWNER_EMAIL)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+
+    let (uid, code) = tr.read_code_from_email("0.txt", "save-password");
+    let res = other
+        .post("/save-password")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token()),
+            ("uid", uid),
+            ("code", code),
+            ("password", String::from("new password"))
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+
+    let res = tr.client.get("/profile").dispatch();
+    check_not_logged_in!(res);
+}
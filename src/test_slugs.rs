@@ -0,0 +1,248 @@
+use crate::test_lib::{check_html, check_message, params, TestRunner};
+use chrono::{TimeZone, Utc};
+use meetings::{event_slug, slugify};
+use rocket::http::{ContentType, Status};
+
+#[test]
+fn test_slugify() {
+    assert_eq!(slugify("Rust Maven"), "rust-maven");
+    assert_eq!(slugify("  Rust -- Maven!  "), "rust-maven");
+    assert_eq!(slugify("C++ & Rust in 2026"), "c-rust-in-2026");
+    assert_eq!(slugify("Ünïcode"), "n-code");
+    assert_eq!(slugify("!!!"), "");
+
+    let date = Utc.with_ymd_and_hms(2026, 11, 3, 18, 0, 0).unwrap();
+    assert_eq!(event_slug("Async Rust", &date), "2026-11-async-rust");
+    assert_eq!(event_slug("???", &date), "2026-11-event");
+}
+
+fn edit_group(tr: &TestRunner, slug: &str) -> String {
+    let res = tr
+        .client
+        .post("/edit-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1"),
+            ("name", "First Group"),
+            ("location", ""),
+            ("description", ""),
+            ("slug", slug),
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+#[test]
+fn group_slug() {
+    let tr = TestRunner::new();
+    tr.setup_for_events();
+
+    let res = tr.client.get("/g/first-group").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "First Group");
+    assert!(html.contains(r#"<a href="/g/first-group" id="permalink">"#));
+
+    let res = tr.client.get("/g/no-such-group").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "No such group");
+
+    // A second group with the same name gets a numbered slug
+    tr.create_group_helper("First Group", 2);
+    tr.logout();
+    let res = tr.client.get("/group/2").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<a href="/g/first-group-2" id="permalink">"#));
+
+    tr.login_owner();
+    let res = tr.client.get("/edit-group?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<input name="slug" id="slug" type="text" value="first-group">"#));
+
+    let html = edit_group(&tr, "Rust Maven");
+    check_message!(
+        &html,
+        "Invalid address",
+        "The address <b>Rust Maven</b> can only contain lower case letters, digits and single dashes. e.g. <b>rust-maven</b>"
+    );
+
+    let html = edit_group(&tr, "first-group-2");
+    check_message!(
+        &html,
+        "Address taken",
+        "The address <b>first-group-2</b> is already in use."
+    );
+
+    let html = edit_group(&tr, "rust-maven");
+    check_html!(&html, "title", "Group updated");
+    tr.logout();
+
+    let res = tr.client.get("/g/rust-maven").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+
+    // The old slug redirects and the numeric route keeps working
+    let res = tr.client.get("/g/first-group").dispatch();
+    assert_eq!(res.status(), Status::PermanentRedirect);
+    assert_eq!(res.headers().get_one("Location").unwrap(), "/g/rust-maven");
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<a href="/g/rust-maven" id="permalink">"#));
+
+    // Old slugs are not given to other groups
+    tr.login_owner();
+    let res = tr
+        .client
+        .post("/edit-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "2"),
+            ("name", "First Group"),
+            ("location", ""),
+            ("description", ""),
+            ("slug", "first-group"),
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Address taken");
+
+    // but the group can go back to its own old slug
+    let html = edit_group(&tr, "first-group");
+    check_html!(&html, "title", "Group updated");
+    tr.logout();
+
+    let res = tr.client.get("/g/rust-maven").dispatch();
+    assert_eq!(res.status(), Status::PermanentRedirect);
+    assert_eq!(res.headers().get_one("Location").unwrap(), "/g/first-group");
+}
+
+#[test]
+fn only_owner_changes_group_slug() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+
+    tr.login_user();
+    let res = tr
+        .client
+        .post("/join-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1")
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    tr.logout();
+
+    tr.login_owner();
+    let res = tr
+        .client
+        .post("/set-group-admin")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1"),
+            ("uid", "3"),
+            ("admin", "true"),
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    tr.logout();
+
+    tr.login_user();
+    let res = tr.client.get("/edit-group?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(!html.contains(r#"name="slug""#));
+
+    let html = edit_group(&tr, "my-group");
+    check_message!(
+        &html,
+        "Not the owner",
+        "Only the owner of the group can change its address."
+    );
+    tr.logout();
+
+    let res = tr.client.get("/g/my-group").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "No such group");
+}
+
+fn edit_event(tr: &TestRunner, slug: &str) -> String {
+    let res = tr
+        .client
+        .post("/edit-event")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("eid", "1"),
+            ("title", "First event"),
+            ("date", "2030-01-01 10:10"),
+            ("location", ""),
+            ("description", ""),
+            ("timezone", "UTC"),
+            ("slug", slug),
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+#[test]
+fn event_slug_routes() {
+    let tr = TestRunner::new();
+    tr.setup_for_events();
+    tr.setup_event(2);
+    tr.logout();
+
+    let res = tr
+        .client
+        .get("/g/first-group/e/2030-01-first-event")
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "First event");
+    assert!(html.contains(r#"<a href="/g/first-group/e/2030-01-first-event" id="permalink">"#));
+
+    let res = tr.client.get("/g/first-group/e/no-such-event").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "No such event");
+
+    tr.login_owner();
+    let html = edit_event(&tr, "2030-01-second-event");
+    check_message!(
+        &html,
+        "Address taken",
+        "The address <b>2030-01-second-event</b> is already in use."
+    );
+
+    let html = edit_event(&tr, "new-year-meetup");
+    check_html!(&html, "title", "Event updated");
+
+    let html = edit_group(&tr, "rust-maven");
+    check_html!(&html, "title", "Group updated");
+    tr.logout();
+
+    let res = tr.client.get("/g/rust-maven/e/new-year-meetup").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+
+    // Any combination of old slugs redirects to the current address
+    for path in [
+        "/g/first-group/e/2030-01-first-event",
+        "/g/first-group/e/new-year-meetup",
+        "/g/rust-maven/e/2030-01-first-event",
+    ] {
+        let res = tr.client.get(path).dispatch();
+        assert_eq!(res.status(), Status::PermanentRedirect);
+        assert_eq!(
+            res.headers().get_one("Location").unwrap(),
+            "/g/rust-maven/e/new-year-meetup"
+        );
+    }
+
+    let res = tr.client.get("/event/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<a href="/g/rust-maven/e/new-year-meetup" id="permalink">"#));
+}
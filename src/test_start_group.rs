@@ -0,0 +1,145 @@
+use crate::test_lib::{
+    check_guest_menu, check_html, check_message, check_not_logged_in, TestRunner,
+};
+use regex::Regex;
+use rocket::http::Status;
+
+fn request_group(tr: &TestRunner, name: &str) -> String {
+    tr.post(
+        "/start-group",
+        &[
+            ("name", name),
+            ("location", "Budapest"),
+            ("description", "Rustaceans of Budapest"),
+            ("why", "There is no <b>Rust</b> group in the city"),
+        ],
+    )
+}
+
+/// The ID of the first pending request as listed on the admin page.
+fn first_request_id(tr: &TestRunner) -> String {
+    let res = tr.client.get("/admin").dispatch();
+    let html = res.into_string().unwrap();
+    let re = Regex::new(r#"<input type="hidden" name="id" value="([A-Z0-9]+)">"#).unwrap();
+    re.captures(&html).unwrap()[1].to_owned()
+}
+
+#[test]
+fn start_group_approved() {
+    let tr = TestRunner::new();
+    tr.setup_admin();
+    tr.setup_user();
+    tr.logout();
+
+    let res = tr.client.get("/start-group").dispatch();
+    check_not_logged_in!(res);
+
+    tr.login_user();
+    let res = tr.client.get("/start-group").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Start a group");
+
+    let html = tr.post(
+        "/start-group",
+        &[
+            ("name", "Rust Budapest"),
+            ("location", ""),
+            ("description", ""),
+            ("why", " "),
+        ],
+    );
+    check_html!(&html, "title", "Missing field");
+
+    tr.clean_emails();
+    let html = request_group(&tr, "Rust Budapest");
+    check_message!(
+        &html,
+        "Request sent",
+        "Your request to start the <b>Rust Budapest</b> group was sent to the site admins. We will let you know when they decide."
+    );
+    let email = std::fs::read_to_string(tr.email_folder.join("0.txt")).unwrap();
+    assert!(email.contains(
+        ">Sancho Panza</a> would like to start the group <b>Rust Budapest</b> in Budapest."
+    ));
+    assert!(email.contains("There is no &lt;b&gt;Rust&lt;/b&gt; group in the city"));
+
+    let res = tr.client.get("/admin").dispatch();
+    assert_eq!(res.status(), Status::Forbidden);
+    tr.logout();
+
+    tr.login_admin();
+    let res = tr.client.get("/admin").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("<div><b>Rust Budapest</b> (Budapest)</div>"));
+    assert!(html.contains(
+        "<div><b>Why</b>: There is no &lt;b&gt;Rust&lt;&#x2F;b&gt; group in the city</div>"
+    ));
+
+    let id = first_request_id(&tr);
+    tr.clean_emails();
+    let html = tr.post("/admin/approve-group-request", &[("id", &id)]);
+    check_message!(
+        &html,
+        "Group created",
+        r#"Group <b><a href="/group/1">Rust Budapest</a></b> created"#
+    );
+    let email = std::fs::read_to_string(tr.email_folder.join("0.txt")).unwrap();
+    assert!(email.contains("A new group was created for you."));
+
+    let res = tr.client.get("/admin").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("No pending group requests."));
+
+    let html = tr.post("/admin/approve-group-request", &[("id", &id)]);
+    check_html!(&html, "title", "No such request");
+    tr.logout();
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Rust Budapest");
+    assert!(html.contains("Sancho Panza"));
+}
+
+#[test]
+fn start_group_rejected() {
+    let tr = TestRunner::new();
+    tr.setup_admin();
+    tr.setup_user();
+    tr.logout();
+
+    tr.login_user();
+    request_group(&tr, "Rust Budapest");
+    tr.logout();
+
+    tr.login_admin();
+    let id = first_request_id(&tr);
+    let html = tr.post("/admin/reject-group-request", &[("id", &id)]);
+    check_message!(
+        &html,
+        "Missing reason",
+        "Please explain why the group is not approved."
+    );
+
+    tr.clean_emails();
+    let html = tr.post(
+        "/admin/reject-group-request",
+        &[
+            ("id", &id),
+            ("reason", "There is already <b>such</b> a group"),
+        ],
+    );
+    check_message!(
+        &html,
+        "Group request rejected",
+        "The request of <b>Sancho Panza</b> to start <b>Rust Budapest</b> was rejected."
+    );
+    let email = std::fs::read_to_string(tr.email_folder.join("0.txt")).unwrap();
+    assert!(email.contains(
+        "Your request to start the Meet-OS group <b>Rust Budapest</b> was not approved."
+    ));
+    assert!(email.contains("There is already &lt;b&gt;such&lt;/b&gt; a group"));
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "No such group");
+}
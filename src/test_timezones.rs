@@ -0,0 +1,116 @@
+use crate::test_lib::{check_html, check_message, TestRunner};
+
+fn set_group_timezone(tr: &TestRunner, timezone: &str) -> String {
+    tr.post(
+        "/edit-group",
+        &[
+            ("gid", "1"),
+            ("name", "First Group"),
+            ("location", ""),
+            ("description", ""),
+            ("timezone", timezone),
+        ],
+    )
+}
+
+fn add_event(tr: &TestRunner, date: &str, timezone: &str, duration: &str) -> String {
+    tr.post(
+        "/add-event",
+        &[
+            ("gid", "1"),
+            ("title", "Rust in the evening"),
+            ("location", "Library"),
+            ("description", ""),
+            ("date", date),
+            ("timezone", timezone),
+            ("duration", duration),
+        ],
+    )
+}
+
+#[test]
+fn events_in_the_time_zone_of_the_group() {
+    let tr = TestRunner::with_settings(serde_json::json!({"recurring_events_horizon_days": 3650}));
+    tr.setup_for_groups();
+    tr.login_owner();
+
+    let html = set_group_timezone(&tr, "Mars/Olympus");
+    check_message!(
+        &html,
+        "Invalid time zone",
+        "Unknown time zone 'Mars/Olympus'"
+    );
+    let html = set_group_timezone(&tr, "Europe/Budapest");
+    check_html!(&html, "title", "Group updated");
+
+    let res = tr.client.get("/add-event?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<option value="Europe/Budapest" selected>Europe/Budapest</option>"#));
+
+    let html = add_event(&tr, "2030-07-01 18:00", "Mars/Olympus", "");
+    check_message!(
+        &html,
+        "Invalid time zone",
+        "Unknown time zone 'Mars/Olympus'"
+    );
+    let html = add_event(&tr, "2030-07-01 18:00", "<b>Mars</b>", "");
+    check_message!(
+        &html,
+        "Invalid time zone",
+        "Unknown time zone '&lt;b&gt;Mars&lt;/b&gt;'"
+    );
+    let html = add_event(&tr, "2030-03-31 02:30", "", "");
+    check_message!(
+        &html,
+        "Invalid date",
+        "The time '2030-03-31 02:30' does not exist in time zone 'Europe/Budapest'"
+    );
+    let html = add_event(&tr, "2030-07-01 18:00", "", "0");
+    check_html!(&html, "title", "Invalid duration");
+
+    // Summer time, the zone of the group is used
+    let html = add_event(&tr, "2030-07-01 18:00", "", "90");
+    check_html!(&html, "title", "Event added");
+    let res = tr.client.get("/event/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(
+        r#"<span id="event-time">2030-07-01 18:00 - 2030-07-01 19:30 (Europe/Budapest)</span>"#
+    ));
+    assert!(html.contains(r#"<span class="datetime" value="2030-07-01T16:00:00Z"></span>"#));
+
+    let res = tr.client.get("/event/1.ics").dispatch();
+    let ics = res.into_string().unwrap();
+    assert!(ics.contains("DTSTART:20300701T160000Z\r\n"));
+    assert!(ics.contains("DTEND:20300701T173000Z\r\n"));
+
+    let res = tr.client.get("/edit-event?eid=1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"name="date" id="date" type="text" value="2030-07-01 18:00">"#));
+    assert!(html.contains(r#"name="duration" id="duration" type="text" value="90">"#));
+
+    // An event in another zone
+    let html = add_event(&tr, "2030-07-01 18:00", "America/New_York", "");
+    check_html!(&html, "title", "Event added");
+    let res = tr.client.get("/event/2").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<span id="event-time">2030-07-01 18:00 (America/New_York)</span>"#));
+    assert!(html.contains(r#"value="2030-07-01T22:00:00Z""#));
+
+    // A weekly series keeps the local time when the clocks change on 2030-03-31
+    let html = tr.post(
+        "/add-event",
+        &[
+            ("gid", "1"),
+            ("title", "Rust Tuesday"),
+            ("location", "Library"),
+            ("description", ""),
+            ("date", "2030-03-26 18:00"),
+            ("rrule", "FREQ=WEEKLY;COUNT=2"),
+        ],
+    );
+    check_html!(&html, "title", "Series created");
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("2030-03-26T17:00:00Z - Rust Tuesday"));
+    assert!(html.contains("2030-04-02T16:00:00Z - Rust Tuesday"));
+}
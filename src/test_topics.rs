@@ -0,0 +1,227 @@
+use crate::test_lib::{check_html, check_message, check_unauthorized, params, TestRunner};
+use chrono::Utc;
+use meetings::{suggest_groups, Group};
+use rocket::http::{ContentType, Status};
+use surrealdb::sql::{Id, Thing};
+
+fn add_topic(tr: &TestRunner, name: &str) -> String {
+    let res = tr
+        .client
+        .post("/admin/add-topic")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("name", name),
+            ("description", "Everything about it"),
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+fn tag_group(tr: &TestRunner, gid: &str, name: &str, location: &str, topics: &[&str]) -> String {
+    let csrf_token = tr.csrf_token();
+    let mut fields = vec![
+        ("csrf_token", csrf_token.as_str()),
+        ("gid", gid),
+        ("name", name),
+        ("location", location),
+        ("description", ""),
+    ];
+    for topic in topics {
+        fields.push(("topics", *topic));
+    }
+    let res = tr
+        .client
+        .post("/edit-group")
+        .header(ContentType::Form)
+        .body(params!(fields))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+#[test]
+fn manage_topics() {
+    let tr = TestRunner::new();
+    tr.setup_for_groups();
+
+    tr.login_user();
+    let res = tr.client.get("/admin/topics").dispatch();
+    check_unauthorized!(res);
+    tr.logout();
+
+    tr.login_admin();
+    let html = add_topic(&tr, "Python+Web");
+    check_message!(
+        &html,
+        "Invalid topic",
+        "The name of the topic <b>Python Web</b> can only contain lower case letters, digits and single dashes. e.g. <b>python-web</b>"
+    );
+
+    let html = add_topic(&tr, "python");
+    check_html!(&html, "title", "Topic added");
+
+    let html = add_topic(&tr, "python");
+    check_message!(
+        &html,
+        "Topic exists",
+        "The topic <b>python</b> already exists."
+    );
+
+    let html = add_topic(&tr, "rust");
+    check_html!(&html, "title", "Topic added");
+
+    let res = tr.client.get("/admin/topics").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<a href="/topic/python">python</a>"#));
+    assert!(html.contains(r#"<a href="/topic/rust">rust</a>"#));
+    tr.logout();
+
+    tr.login_owner();
+    let html = tag_group(&tr, "1", "First Group", "Tel Aviv", &["rust", "perl"]);
+    check_message!(&html, "No such topic", "There is no topic <b>perl</b>.");
+
+    let html = tag_group(&tr, "1", "First Group", "Tel Aviv", &["rust", "python"]);
+    check_html!(&html, "title", "Group updated");
+
+    let res = tr.client.get("/edit-group?gid=1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<input name="topics" type="checkbox" value="rust" checked>"#));
+    tr.logout();
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<a href="/topic/python">python</a> <a href="/topic/rust">rust</a>"#));
+
+    // Deleting a topic removes it from the groups
+    tr.login_admin();
+    let res = tr
+        .client
+        .post("/admin/delete-topic")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("name", "python"),
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Topic deleted");
+    tr.logout();
+
+    let res = tr.client.get("/group/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(!html.contains(r#"<a href="/topic/python">"#));
+    assert!(html.contains(r#"<a href="/topic/rust">rust</a>"#));
+
+    let res = tr.client.get("/topic/python").dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "No such topic");
+}
+
+#[test]
+fn browse_by_topic() {
+    let tr = TestRunner::new();
+    tr.setup_for_events();
+    tr.create_group_helper("Second Group", 2);
+    tr.add_event_helper("Second event", "2030-01-02 10:10", "2");
+    add_topic(&tr, "python");
+    add_topic(&tr, "rust");
+    tr.logout();
+
+    tr.login_owner();
+    tag_group(&tr, "1", "First Group", "Tel Aviv", &["rust"]);
+    tag_group(&tr, "2", "Second Group", "Budapest", &["python", "rust"]);
+    tr.logout();
+
+    let res = tr.client.get("/topic/python").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Topic: python");
+    assert!(html.contains(r#"<a href="/group/2">Second Group</a>"#));
+    assert!(!html.contains(r#"<a href="/group/1">"#));
+    assert!(html.contains(r#"<a href="/event/2">Second event</a>"#));
+    assert!(!html.contains(r#"<a href="/event/1">"#));
+
+    let res = tr
+        .client
+        .get("/groups?topic=rust&location=tel+aviv")
+        .dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<a href="/group/1">First Group</a>"#));
+    assert!(!html.contains(r#"<a href="/group/2">"#));
+
+    let res = tr.client.get("/groups?topic=python&location=").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(!html.contains(r#"<a href="/group/1">"#));
+    assert!(html.contains(r#"<a href="/group/2">Second Group</a>"#));
+
+    let res = tr.client.get("/events?topic=python").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(!html.contains(r#"<a href="/event/1">"#));
+    assert!(html.contains(r#"<a href="/event/2">Second event</a>"#));
+
+    // The user is a member of the first group so the second one is suggested
+    tr.login_user();
+    let res = tr
+        .client
+        .post("/join-group")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("gid", "1")
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+
+    let res = tr.client.get("/profile").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("Groups you might like"));
+    assert!(
+        html.contains(r#"<tr><td><a href="/group/2">Second Group</a></td><td>Budapest</td></tr>"#)
+    );
+}
+
+fn group(gid: usize, topics: &[&str]) -> Group {
+    Group {
+        id: Thing::from(("group", Id::Number(gid.try_into().unwrap()))),
+        gid,
+        name: format!("Group {gid}"),
+        location: String::new(),
+        description: String::new(),
+        owner: 1,
+        creation_date: Utc::now(),
+        requires_approval: false,
+        questions: vec![],
+        archived: None,
+        slug: format!("group-{gid}"),
+        old_slugs: vec![],
+        topics: topics.iter().map(ToString::to_string).collect(),
+        timezone: String::from("UTC"),
+    }
+}
+
+#[test]
+fn test_suggest_groups() {
+    let mine = group(1, &["rust", "python"]);
+    let groups = vec![
+        mine.clone(),
+        group(2, &["perl"]),
+        group(3, &["python"]),
+        group(4, &["python", "rust"]),
+        group(5, &[]),
+    ];
+
+    let suggested = suggest_groups(&[&mine], groups.clone(), 5);
+    assert_eq!(
+        suggested.iter().map(|group| group.gid).collect::<Vec<_>>(),
+        vec![4, 3]
+    );
+
+    let suggested = suggest_groups(&[&mine], groups.clone(), 1);
+    assert_eq!(suggested.len(), 1);
+
+    let suggested = suggest_groups(&[], groups, 5);
+    assert!(suggested.is_empty());
+}
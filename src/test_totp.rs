@@ -0,0 +1,256 @@
+use crate::test_lib::{
+    check_guest_menu, check_html, check_message, check_not_logged_in, check_user_menu, params,
+    TestRunner, OWNER_EMAIL, OWNER_PW,
+};
+use meetings::totp;
+use regex::Regex;
+use rocket::http::{ContentType, Status};
+
+fn start_totp_setup(tr: &TestRunner) -> String {
+    // Opening the page does not create a secret
+    let res = tr.client.get("/totp-setup").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Enable two-factor authentication");
+    assert!(html.contains(r#"<form method="POST" action="/totp-setup/start">"#));
+    assert!(!html.contains("<svg"));
+
+    let res = tr
+        .client
+        .post("/totp-setup/start")
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token().as_str())]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Enable two-factor authentication");
+    assert!(html.contains("<svg"));
+    html
+}
+
+fn enable_totp(tr: &TestRunner) -> (String, Vec<String>) {
+    let html = start_totp_setup(tr);
+
+    let re = Regex::new(r#"<code id="secret">([A-Z2-7]+)</code>"#).unwrap();
+    let secret = re.captures(&html).unwrap()[1].to_owned();
+    let code = totp::totp(&secret, OWNER_EMAIL).generate_current().unwrap();
+
+    let res = tr
+        .client
+        .post("/totp-setup")
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token()), ("code", code)]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Two-factor authentication enabled");
+
+    let re = Regex::new(r"<li><code>([a-z0-9]{5}-[a-z0-9]{5})</code></li>").unwrap();
+    let recovery_codes = re
+        .captures_iter(&html)
+        .map(|cap| cap[1].to_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(recovery_codes.len(), 10);
+
+    (secret, recovery_codes)
+}
+
+fn login_with_password(tr: &TestRunner) {
+    let res = tr
+        .client
+        .post("/login")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", OWNER_EMAIL),
+            ("password", OWNER_PW)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Two-factor authentication");
+    check_guest_menu!(&html);
+}
+
+#[test]
+fn totp_setup_with_invalid_code() {
+    let tr = TestRunner::new();
+    tr.setup_owner();
+
+    // Without starting the setup first
+    let res = tr
+        .client
+        .post("/totp-setup")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("code", "000000")
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "No pending setup");
+
+    start_totp_setup(&tr);
+    let res = tr
+        .client
+        .post("/totp-setup")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("code", "000000")
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Invalid code",
+        r#"The code did not match. Please <a href="/totp-setup">try again</a>."#
+    );
+
+    let res = tr.client.get("/profile").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("Two-factor authentication is not enabled."));
+}
+
+#[test]
+fn login_with_totp() {
+    let tr = TestRunner::new();
+    tr.setup_owner();
+    let (_secret, recovery_codes) = enable_totp(&tr);
+
+    let res = tr.client.get("/profile").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("Two-factor authentication is enabled."));
+
+    tr.logout();
+    login_with_password(&tr);
+
+    // The password alone is not enough
+    let res = tr.client.get("/profile").dispatch();
+    check_not_logged_in!(res);
+
+    let res = tr
+        .client
+        .post("/login-totp")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("code", "000000")
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_html!(&html, "#error", "Invalid code. Please try again");
+    check_guest_menu!(&html);
+
+    let res = tr
+        .client
+        .post("/login-totp")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("code", &recovery_codes[0])
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Welcome back");
+    check_user_menu!(&html);
+
+    // A recovery code can be used only once
+    tr.logout();
+    login_with_password(&tr);
+    let res = tr
+        .client
+        .post("/login-totp")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("code", &recovery_codes[0])
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "#error", "Invalid code. Please try again");
+}
+
+#[test]
+fn login_totp_without_password() {
+    let tr = TestRunner::new();
+
+    let res = tr
+        .client
+        .post("/login-totp")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("code", "123456")
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Login expired",
+        r#"Please <a href="/login">login</a> again."#
+    );
+}
+
+#[test]
+fn disable_totp() {
+    let tr = TestRunner::new();
+    tr.setup_owner();
+    let (_secret, recovery_codes) = enable_totp(&tr);
+
+    let res = tr
+        .client
+        .post("/totp-disable")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("code", &recovery_codes[1])
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Two-factor authentication disabled",
+        r#"Two-factor authentication was disabled. Check your <a href="/profile">profile</a>."#
+    );
+
+    tr.logout();
+    let res = tr
+        .client
+        .post("/login")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", OWNER_EMAIL),
+            ("password", OWNER_PW)
+        ]))
+        .dispatch();
+    let html = res.into_string().unwrap();
+    check_html!(&html, "title", "Welcome back");
+}
+
+#[test]
+fn admins_are_sent_to_enable_totp() {
+    let tr = TestRunner::with_settings(serde_json::json!({"admins_require_2fa": true}));
+    tr.setup_admin();
+    tr.login_admin();
+
+    let res = tr.client.get("/admin").dispatch();
+    assert_eq!(res.status(), Status::SeeOther);
+    assert_eq!(res.headers().get_one("Location"), Some("/totp-setup"));
+
+    let res = tr.client.get("/admin/users").dispatch();
+    assert_eq!(res.status(), Status::SeeOther);
+    assert_eq!(res.headers().get_one("Location"), Some("/totp-setup"));
+
+    // Other users still get 403
+    tr.setup_user();
+    tr.login_user();
+    let res = tr.client.get("/admin").dispatch();
+    assert_eq!(res.status(), Status::Forbidden);
+}
@@ -2,9 +2,10 @@ use crate::test_lib::{
     check_admin_menu, check_guest_menu, check_html, check_message, check_not_logged_in,
     check_profile_by_guest, check_profile_by_user, check_user_menu, params, TestRunner,
     ADMIN_EMAIL, ADMIN_NAME, ADMIN_PW, OTHER_NAME, OWNER_EMAIL, OWNER_NAME, OWNER_PW,
-    UNVERIFIED_NAME, USER_EMAIL, USER_NAME,
+    UNVERIFIED_NAME, USER_EMAIL, USER_NAME, USER_PW,
 };
-use rocket::http::{ContentType, Status};
+use rocket::http::{ContentType, Header, Status};
+use serde_json::json;
 
 #[test]
 fn protected_pages_as_guest() {
@@ -16,14 +17,10 @@ fn protected_pages_as_guest() {
         "/admin/create-group?uid=1",
         "/admin/search",
         "/admin/users",
-        "/join-group?gid=1",
-        "/leave-group?gid=1",
         "/edit-group",
         "/contact-members",
         "/add-event",
         "/edit-event",
-        "/rsvp-yes-event?eid=1",
-        "/rsvp-no-event?eid=1",
         "/edit-profile",
         "/profile",
     ] {
@@ -43,6 +40,13 @@ fn protected_post_requests_as_guest() {
         "/edit-group",
         "/admin/search",
         "/admin/create-group",
+        "/join-group",
+        "/leave-group",
+        "/rsvp-yes-event",
+        "/rsvp-no-event",
+        "/logout",
+        "/logout-session",
+        "/logout-everywhere",
     ] {
         let res = tr.client.post(path).header(ContentType::Form).dispatch();
 
@@ -54,6 +58,7 @@ fn protected_post_requests_as_guest() {
         .client
         .post("/admin/create-group")
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("name", "Rust Maven"),
             ("location", ""),
             ("description", ""),
@@ -72,6 +77,7 @@ fn register_user() {
         .post(format!("/register"))
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("name", OWNER_NAME),
             ("email", OWNER_EMAIL),
             ("password", OWNER_PW),
@@ -124,6 +130,7 @@ fn get_verify_email_with_bad_code() {
         .post(format!("/register"))
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("name", OWNER_NAME),
             ("email", OWNER_EMAIL),
             ("password", OWNER_PW),
@@ -147,6 +154,7 @@ fn post_register_duplicate_email() {
         .post(format!("/register"))
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("name", OWNER_NAME),
             ("email", OWNER_EMAIL),
             ("password", OWNER_PW),
@@ -165,6 +173,7 @@ fn post_register_duplicate_email() {
         .post(format!("/register"))
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("name", OWNER_NAME),
             ("email", OWNER_EMAIL),
             ("password", OWNER_PW),
@@ -193,7 +202,11 @@ fn post_login_regular_user() {
         .client
         .post("/login")
         .header(ContentType::Form)
-        .body(params!([("email", OWNER_EMAIL), ("password", OWNER_PW)]))
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", OWNER_EMAIL),
+            ("password", OWNER_PW)
+        ]))
         .dispatch();
     assert_eq!(res.status(), Status::Ok);
 
@@ -204,7 +217,12 @@ fn post_login_regular_user() {
     check_profile_by_user!(&tr.client, OWNER_NAME);
 
     // logout
-    let res = tr.client.get("/logout").dispatch();
+    let res = tr
+        .client
+        .post("/logout")
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token().as_str())]))
+        .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
 
@@ -230,7 +248,11 @@ fn post_login_admin() {
         .client
         .post("/login")
         .header(ContentType::Form)
-        .body(params!([("email", ADMIN_EMAIL), ("password", ADMIN_PW)]))
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", ADMIN_EMAIL),
+            ("password", ADMIN_PW)
+        ]))
         .dispatch();
     assert_eq!(res.status(), Status::Ok);
 
@@ -241,7 +263,12 @@ fn post_login_admin() {
     check_profile_by_user!(&tr.client, ADMIN_NAME);
 
     // logout
-    let res = tr.client.get("/logout").dispatch();
+    let res = tr
+        .client
+        .post("/logout")
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token().as_str())]))
+        .dispatch();
 
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
@@ -260,6 +287,7 @@ fn post_register_with_invalid_email_address() {
         .post("/register")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("name", "Foo Bar"),
             ("email", "meet-os.com"),
             ("password", "123456"),
@@ -281,7 +309,7 @@ fn post_register_with_too_long_username() {
     let res = tr.client
             .post("/register")
             .header(ContentType::Form)
-            .body("name=QWERTYUIOPASDFGHJKLZXCVBNM QWERTYUIOPASDFGHJKLZXCVBNM&email=long@meet-os.com&password=123456")
+            .body(format!("csrf_token={}&name=QWERTYUIOPASDFGHJKLZXCVBNM QWERTYUIOPASDFGHJKLZXCVBNM&email=long@meet-os.com&password=123456", tr.csrf_token()))
             .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
@@ -302,6 +330,7 @@ fn post_register_with_bad_email_address() {
         .post("/register")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("name", "Foo Bar"),
             ("email", "meet-os.com"),
             ("password", "123456"),
@@ -329,6 +358,7 @@ fn post_login_with_unregistered_email() {
         .post("/login")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("email", "other@meet-os.com"),
             ("password", "123456")
         ]))
@@ -338,8 +368,8 @@ fn post_login_with_unregistered_email() {
     let html = res.into_string().unwrap();
     check_message!(
         &html,
-        "No such user",
-        "No user with address <b>other@meet-os.com</b>. Please try again"
+        "Invalid email or password",
+        "Invalid email or password. Please try again"
     );
     check_guest_menu!(&html);
 }
@@ -355,16 +385,121 @@ fn post_login_with_bad_password() {
         .client
         .post("/login")
         .header(ContentType::Form)
-        .body(params!([("email", USER_EMAIL), ("password", OWNER_PW)]))
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", USER_EMAIL),
+            ("password", OWNER_PW)
+        ]))
         .dispatch();
 
     assert_eq!(res.status(), Status::Ok);
     assert!(res.headers().get_one("set-cookie").is_none());
     let html = res.into_string().unwrap();
 
-    check_html!(&html, "title", "Invalid password");
-    check_html!(&html, "h1", "Invalid password");
+    check_message!(
+        &html,
+        "Invalid email or password",
+        "Invalid email or password. Please try again"
+    );
+    check_guest_menu!(&html);
+}
+
+#[test]
+fn post_login_locked_after_too_many_failures() {
+    let tr = TestRunner::new();
+
+    tr.setup_admin();
+    tr.setup_user();
+    tr.logout();
+
+    for _ in 0..5 {
+        let res = tr
+            .client
+            .post("/login")
+            .header(ContentType::Form)
+            .body(params!([
+                ("csrf_token", tr.csrf_token().as_str()),
+                ("email", USER_EMAIL),
+                ("password", OWNER_PW)
+            ]))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let html = res.into_string().unwrap();
+        check_html!(&html, "title", "Invalid email or password");
+    }
+
+    // Even the correct password is rejected now
+    let res = tr
+        .client
+        .post("/login")
+        .header(ContentType::Form)
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", USER_EMAIL),
+            ("password", USER_PW)
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    assert!(res.headers().get_one("set-cookie").is_none());
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Too many failed attempts",
+        "Too many failed login attempts. Please try again in 15 minutes."
+    );
     check_guest_menu!(&html);
+
+    // The lockout is visible to the site admin
+    tr.login_admin();
+    let res = tr.client.get("/admin/audit").dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    assert!(html.contains("AccountLocked"));
+    assert!(html.contains(USER_EMAIL));
+}
+
+fn failed_login_from(tr: &TestRunner, ip: Option<&str>, email: &str) -> String {
+    let mut req = tr.client.post("/login").header(ContentType::Form);
+    if let Some(ip) = ip {
+        req = req.header(Header::new("X-Real-IP", ip.to_owned()));
+    }
+    let res = req
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", email),
+            ("password", "wrong password")
+        ]))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    res.into_string().unwrap()
+}
+
+#[test]
+fn post_login_locked_per_ip() {
+    let tr = TestRunner::with_settings(json!({"login_max_failures_per_ip": 2}));
+
+    tr.setup_admin();
+    tr.setup_user();
+    tr.logout();
+
+    // Without a known address the failures only count for the account
+    for email in [ADMIN_EMAIL, OWNER_EMAIL] {
+        let html = failed_login_from(&tr, None, email);
+        check_html!(&html, "title", "Invalid email or password");
+    }
+    let html = failed_login_from(&tr, None, USER_EMAIL);
+    check_html!(&html, "title", "Invalid email or password");
+
+    for email in [ADMIN_EMAIL, OWNER_EMAIL] {
+        let html = failed_login_from(&tr, Some("10.0.0.1"), email);
+        check_html!(&html, "title", "Invalid email or password");
+    }
+    let html = failed_login_from(&tr, Some("10.0.0.1"), USER_EMAIL);
+    check_html!(&html, "title", "Too many failed attempts");
+
+    // Other addresses are not affected
+    let html = failed_login_from(&tr, Some("10.0.0.2"), USER_EMAIL);
+    check_html!(&html, "title", "Invalid email or password");
 }
 
 #[test]
@@ -375,6 +510,7 @@ fn post_login_with_unverified_email() {
         .post("/register")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("name", "Foo Bar"),
             ("email", "foo@meet-os.com"),
             ("password", "123456"),
@@ -393,6 +529,7 @@ fn post_login_with_unverified_email() {
         .post("/login")
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("email", "foo@meet-os.com"),
             ("password", "123456")
         ]))
@@ -418,7 +555,11 @@ fn post_login_with_invalid_email() {
         .client
         .post("/login")
         .header(ContentType::Form)
-        .body(params!([("email", "meet-os.com"), ("password", "123456")]))
+        .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
+            ("email", "meet-os.com"),
+            ("password", "123456")
+        ]))
         .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
@@ -436,6 +577,7 @@ fn post_register_with_short_password() {
         .post(format!("/register"))
         .header(ContentType::Form)
         .body(params!([
+            ("csrf_token", tr.csrf_token().as_str()),
             ("name", "Foo Bar"),
             ("email", OWNER_EMAIL),
             ("password", "123"),
@@ -633,7 +775,10 @@ fn post_edit_profile_failures() {
         .client
         .post("/edit-profile")
         .header(ContentType::Form)
-        .body("name=XX&github=szabgab*&gitlab=&linkedin")
+        .body(format!(
+            "csrf_token={}&name=XX&github=szabgab*&gitlab=&linkedin",
+            tr.csrf_token()
+        ))
         .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
@@ -648,7 +793,10 @@ fn post_edit_profile_failures() {
         .client
         .post("/edit-profile")
         .header(ContentType::Form)
-        .body("name=XX&github=&gitlab=foo*bar&linkedin=")
+        .body(format!(
+            "csrf_token={}&name=XX&github=&gitlab=foo*bar&linkedin=",
+            tr.csrf_token()
+        ))
         .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
@@ -663,7 +811,10 @@ fn post_edit_profile_failures() {
         .client
         .post("/edit-profile")
         .header(ContentType::Form)
-        .body("name=XX&github=&gitlab=&linkedin=szabgab")
+        .body(format!(
+            "csrf_token={}&name=XX&github=&gitlab=&linkedin=szabgab",
+            tr.csrf_token()
+        ))
         .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
@@ -678,9 +829,10 @@ fn post_edit_profile_failures() {
         .client
         .post("/edit-profile")
         .header(ContentType::Form)
-        .body(
-            "name=QWERTYUIOPASDFGHJKLZXCVBNM QWERTYUIOPASDFGHJKLZXCVBNM&github=&gitlab=&linkedin=",
-        )
+        .body(format!(
+            "csrf_token={}&name=QWERTYUIOPASDFGHJKLZXCVBNM QWERTYUIOPASDFGHJKLZXCVBNM&github=&gitlab=&linkedin=",
+            tr.csrf_token()
+        ))
         .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
@@ -695,7 +847,10 @@ fn post_edit_profile_failures() {
         .client
         .post("/edit-profile")
         .header(ContentType::Form)
-        .body("name=é&github=&gitlab=&linkedin=")
+        .body(format!(
+            "csrf_token={}&name=é&github=&gitlab=&linkedin=",
+            tr.csrf_token()
+        ))
         .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
@@ -718,7 +873,7 @@ fn post_edit_profile_works() {
     let res = tr.client
             .post("/edit-profile")
             .header(ContentType::Form)
-            .body("name= Lord Voldemort &github= alfa &gitlab= beta &linkedin=  https://www.linkedin.com/in/szabgab/  ")
+            .body(format!("csrf_token={}&name= Lord Voldemort &github= alfa &gitlab= beta &linkedin=  https://www.linkedin.com/in/szabgab/  ", tr.csrf_token()))
             .dispatch();
     // &about=* text\n* more\n* [link](https://meet-os.com/)\n* <b>bold</b>\n* <a href=\"https://meet-os.com/\">bad link</a>\n"
 
@@ -765,7 +920,10 @@ fn post_register_with_invalid_username() {
         .client
         .post("/register")
         .header(ContentType::Form)
-        .body("name=é&email=long@meet-os.com&password=123456")
+        .body(format!(
+            "csrf_token={}&name=é&email=long@meet-os.com&password=123456",
+            tr.csrf_token()
+        ))
         .dispatch();
     assert_eq!(res.status(), Status::Ok);
     let html = res.into_string().unwrap();
@@ -780,6 +938,60 @@ fn post_register_with_invalid_username() {
 fn logout_by_guest() {
     let tr = TestRunner::new();
     // logout requires a logged in user
-    let res = tr.client.get("/logout").dispatch();
+    let res = tr
+        .client
+        .post("/logout")
+        .header(ContentType::Form)
+        .body(params!([("csrf_token", tr.csrf_token().as_str())]))
+        .dispatch();
     check_not_logged_in!(res);
 }
+
+#[test]
+fn get_verify_email_twice() {
+    let tr = TestRunner::new();
+
+    tr.register_user_helper(OWNER_NAME, OWNER_EMAIL, OWNER_PW);
+    let (uid, code) = tr.read_code_from_email("0.txt", "verify-email");
+
+    let res = tr
+        .client
+        .get(format!("/verify-email/{uid}/{code}"))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    tr.logout();
+
+    let res = tr
+        .client
+        .get(format!("/verify-email/{uid}/{code}"))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Link already used",
+        r#"This link was already used. If you still need it, please <a href="/resend-email-verification-code">request a new one</a>."#
+    );
+    check_guest_menu!(&html);
+}
+
+#[test]
+fn get_verify_email_expired() {
+    let tr = TestRunner::with_settings(json!({"verify_email_token_hours": 0}));
+
+    tr.register_user_helper(OWNER_NAME, OWNER_EMAIL, OWNER_PW);
+    let (uid, code) = tr.read_code_from_email("0.txt", "verify-email");
+
+    let res = tr
+        .client
+        .get(format!("/verify-email/{uid}/{code}"))
+        .dispatch();
+    assert_eq!(res.status(), Status::Ok);
+    let html = res.into_string().unwrap();
+    check_message!(
+        &html,
+        "Link expired",
+        r#"This link has expired. Please <a href="/resend-email-verification-code">request a new one</a>."#
+    );
+    check_guest_menu!(&html);
+}
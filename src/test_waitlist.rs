@@ -0,0 +1,135 @@
+use crate::test_lib::{check_html, check_message, TestRunner, OTHER_EMAIL, OTHER_PW, USER_PW};
+
+fn login_other(tr: &TestRunner) {
+    tr.post("/login", &[("email", OTHER_EMAIL), ("password", OTHER_PW)]);
+}
+
+#[test]
+fn event_with_waitlist() {
+    let tr = TestRunner::new();
+    tr.setup_many_users();
+    tr.create_group_helper("First Group", 2);
+
+    tr.login_owner();
+    let html = tr.post(
+        "/add-event",
+        &[
+            ("gid", "1"),
+            ("timezone", "UTC"),
+            ("title", "Small meetup"),
+            ("location", "Meeting room"),
+            ("description", ""),
+            ("date", "2030-01-01 10:10"),
+            ("capacity", "1"),
+        ],
+    );
+    check_html!(&html, "title", "Event added");
+    tr.logout();
+
+    let res = tr.client.get("/event/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("1 spot left."));
+
+    tr.login_user();
+    let html = tr.post("/rsvp-yes-event", &[("eid", "1")]);
+    check_html!(&html, "title", "RSVPed to event");
+    tr.logout();
+
+    login_other(&tr);
+    let res = tr.client.get("/event/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("The event is full."));
+    assert!(html.contains("Join the waitlist"));
+
+    let html = tr.post("/rsvp-yes-event", &[("eid", "1")]);
+    check_message!(
+        &html,
+        "Event is full",
+        r#"The <a href="/event/1">event</a> is full. You are #1 on the waitlist. We will send you an email if a spot becomes available."#
+    );
+    let html = tr.post("/rsvp-yes-event", &[("eid", "1")]);
+    check_message!(
+        &html,
+        "You are on the waitlist",
+        r#"You are already #1 on the waitlist of the <a href="/event/1">event</a>."#
+    );
+
+    let res = tr.client.get("/event/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains(r#"<p class="is-size-6" id="waitlist">You are #1 on the waitlist.</p>"#));
+    tr.logout();
+
+    // The attendee cancels and the first person on the waitlist gets the spot
+    tr.login_user();
+    tr.clean_emails();
+    let html = tr.post("/rsvp-no-event", &[("eid", "1")]);
+    check_html!(&html, "title", "Not attending");
+    let email = std::fs::read_to_string(tr.email_folder.join("0.txt")).unwrap();
+    assert!(email.contains("Hi Foo Alpha,"));
+    assert!(email.contains("A spot became available at the event"));
+    assert!(email.contains(r#"/event/1">Small meetup</a> and it is yours."#));
+
+    // Now the original attendee is on the waitlist and can leave it
+    let html = tr.post("/rsvp-yes-event", &[("eid", "1")]);
+    check_html!(&html, "title", "Event is full");
+    let html = tr.post("/rsvp-no-event", &[("eid", "1")]);
+    check_message!(
+        &html,
+        "Left the waitlist",
+        r#"You are not on the waitlist of the <a href="/event/1">event</a> any more."#
+    );
+    tr.logout();
+
+    login_other(&tr);
+    let res = tr.client.get("/event/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("Unregister from the event"));
+    assert!(!html.contains(r#"id="waitlist""#));
+}
+
+#[test]
+fn deleted_attendee_frees_the_spot() {
+    let tr = TestRunner::new();
+    tr.setup_many_users();
+    tr.create_group_helper("First Group", 2);
+
+    tr.login_owner();
+    let html = tr.post(
+        "/add-event",
+        &[
+            ("gid", "1"),
+            ("timezone", "UTC"),
+            ("title", "Small meetup"),
+            ("location", "Meeting room"),
+            ("description", ""),
+            ("date", "2030-01-01 10:10"),
+            ("capacity", "1"),
+        ],
+    );
+    check_html!(&html, "title", "Event added");
+    tr.logout();
+
+    tr.login_user();
+    let html = tr.post("/rsvp-yes-event", &[("eid", "1")]);
+    check_html!(&html, "title", "RSVPed to event");
+    tr.logout();
+
+    login_other(&tr);
+    let html = tr.post("/rsvp-yes-event", &[("eid", "1")]);
+    check_html!(&html, "title", "Event is full");
+    tr.logout();
+
+    tr.login_user();
+    tr.clean_emails();
+    let html = tr.post("/profile/delete", &[("password", USER_PW)]);
+    check_html!(&html, "title", "Account deleted");
+    let email = std::fs::read_to_string(tr.email_folder.join("0.txt")).unwrap();
+    assert!(email.contains("Hi Foo Alpha,"));
+    assert!(email.contains(r#"/event/1">Small meetup</a> and it is yours."#));
+
+    login_other(&tr);
+    let res = tr.client.get("/event/1").dispatch();
+    let html = res.into_string().unwrap();
+    assert!(html.contains("Unregister from the event"));
+    assert!(!html.contains(r#"id="waitlist""#));
+}
@@ -0,0 +1,65 @@
+//! IANA time zones of the groups and the events, e.g. `Europe/Budapest`.
+//! The dates are stored in UTC, the zone is used to enter and to show them in local time.
+
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone as _, Utc};
+use chrono_tz::{Tz, TZ_VARIANTS};
+
+pub const DEFAULT_TIMEZONE: &str = "UTC";
+
+/// The format of the date and time in the forms.
+pub const INPUT_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+#[must_use]
+pub fn parse(name: &str) -> Option<Tz> {
+    name.parse::<Tz>().ok()
+}
+
+/// The names of all the zones for the select box of the forms.
+#[must_use]
+pub fn names() -> Vec<&'static str> {
+    TZ_VARIANTS.iter().map(|zone| zone.name()).collect()
+}
+
+/// The moment of a local time in the given zone.
+/// None for the times skipped when the clocks are moved forward.
+/// For the times repeated when the clocks are moved back the earlier one is used.
+#[must_use]
+pub fn to_utc(local: NaiveDateTime, zone: Tz) -> Option<DateTime<Utc>> {
+    zone.from_local_datetime(&local)
+        .earliest()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+/// The local time of the date in the given zone. Falls back to UTC for unknown zones.
+#[must_use]
+pub fn to_local(date: DateTime<Utc>, name: &str) -> NaiveDateTime {
+    parse(name).map_or_else(
+        || date.naive_utc(),
+        |zone| date.with_timezone(&zone).naive_local(),
+    )
+}
+
+/// The two dates fall on the same day in the given zone.
+#[must_use]
+pub fn same_day(first: DateTime<Utc>, second: DateTime<Utc>, name: &str) -> bool {
+    to_local(first, name).date() == to_local(second, name).date()
+}
+
+/// The date in the given zone in the format of the forms.
+#[must_use]
+pub fn format_local(date: DateTime<Utc>, name: &str) -> String {
+    to_local(date, name).format(INPUT_FORMAT).to_string()
+}
+
+/// Move the date by `shift` in local time, so it stays at the same time of the day
+/// on the other side of a daylight saving change.
+#[must_use]
+pub fn shift_local(date: DateTime<Utc>, name: &str, shift: Duration) -> DateTime<Utc> {
+    parse(name)
+        .and_then(|zone| {
+            let local = to_local(date, name).checked_add_signed(shift)?;
+            to_utc(local, zone)
+        })
+        .or_else(|| date.checked_add_signed(shift))
+        .unwrap_or(date)
+}
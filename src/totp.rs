@@ -0,0 +1,74 @@
+use core::iter::repeat_with;
+
+use qrcode::render::svg;
+use qrcode::QrCode;
+use sha2::{Digest as _, Sha256};
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+const ISSUER: &str = "Meet-OS";
+const RECOVERY_CODES: usize = 10;
+
+/// A new random secret, base32 encoded as the authenticator apps expect it.
+#[must_use]
+pub fn generate_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+/// # Panics
+///
+/// Panics if the secret is not a valid base32 string of at least 128 bits.
+#[must_use]
+pub fn totp(secret: &str, email: &str) -> TOTP {
+    let bytes = Secret::Encoded(secret.to_owned()).to_bytes().unwrap();
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        bytes,
+        Some(ISSUER.to_owned()),
+        email.to_owned(),
+    )
+    .unwrap()
+}
+
+/// # Panics
+///
+/// Panics if the system clock is before the UNIX epoch.
+#[must_use]
+pub fn check_code(secret: &str, email: &str, code: &str) -> bool {
+    totp(secret, email).check_current(code.trim()).unwrap()
+}
+
+/// The otpauth:// URL of the secret rendered as an SVG QR code.
+///
+/// # Panics
+///
+/// Panics if the URL does not fit in a QR code.
+#[must_use]
+pub fn qr_code_svg(secret: &str, email: &str) -> String {
+    let url = totp(secret, email).get_url();
+    let code = QrCode::new(url.as_bytes()).unwrap();
+    code.render::<svg::Color>().min_dimensions(200, 200).build()
+}
+
+/// Returns the recovery codes to show to the user and their hashes to store in the database.
+#[must_use]
+pub fn generate_recovery_codes() -> (Vec<String>, Vec<String>) {
+    let codes = repeat_with(|| {
+        let raw = Uuid::new_v4().simple().to_string();
+        let (first, rest) = raw.split_at(5);
+        format!("{first}-{}", rest.split_at(5).0)
+    })
+    .take(RECOVERY_CODES)
+    .collect::<Vec<_>>();
+    let hashes = codes.iter().map(|code| hash_code(code)).collect::<Vec<_>>();
+
+    (codes, hashes)
+}
+
+#[must_use]
+pub fn hash_code(code: &str) -> String {
+    hex::encode(Sha256::digest(code.trim().to_lowercase().as_bytes()))
+}
@@ -1,21 +1,33 @@
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 
-use rocket::http::CookieJar;
+use core::ops::Deref;
+
+use rocket::data::{self, Data, FromData, Limits};
+use rocket::form::{Form, FromForm};
 use rocket::http::Status;
+use rocket::http::{Cookie, CookieJar, SameSite};
 use rocket::outcome::Outcome;
 use rocket::request::{self, FromRequest, Request};
+use rocket::time;
 use rocket::State;
 
 use surrealdb::engine::remote::ws::Client;
+use surrealdb::sql::{Id, Thing};
 use surrealdb::Surreal;
 
+use rocket::serde::uuid::Uuid;
+
 use crate::db;
-use meetings::{MyConfig, User};
+use meetings::{hash_token, ApiScope, MyConfig, Session, User};
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct CookieUser {
-    email: String,
-}
+const SESSION_COOKIE: &str = "meet-os";
+const SECOND_FACTOR_COOKIE: &str = "meet-os-2fa";
+const OAUTH_COOKIE: &str = "meet-os-oauth";
+const CSRF_COOKIE: &str = "meet-os-csrf";
+
+/// How long the user has to type in the second factor after the password.
+const SECOND_FACTOR_MINUTES: i64 = 5;
 
 #[expect(clippy::struct_field_names)]
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -23,6 +35,7 @@ pub struct LoggedIn {
     pub logged_in: bool,
     pub admin: bool,
     pub user: Option<User>,
+    pub csrf_token: String,
 }
 
 #[rocket::async_trait]
@@ -39,6 +52,7 @@ impl<'r> FromRequest<'r> for LoggedIn {
                         logged_in: visitor.logged_in,
                         admin: visitor.admin,
                         user: visitor.user,
+                        csrf_token: visitor.csrf_token,
                     };
                     Outcome::Success(user)
                 } else {
@@ -58,6 +72,7 @@ pub struct AdminUser {
     pub logged_in: bool,
     pub admin: bool,
     pub user: Option<User>,
+    pub csrf_token: String,
 }
 
 #[rocket::async_trait]
@@ -72,8 +87,12 @@ impl<'r> FromRequest<'r> for AdminUser {
                         logged_in: visitor.logged_in,
                         admin: visitor.admin,
                         user: visitor.user,
+                        csrf_token: visitor.csrf_token,
                     };
                     Outcome::Success(user)
+                } else if must_enable_second_factor(request, visitor.user.as_ref()) {
+                    // Let the route sending them to the setup page handle it
+                    Outcome::Forward(Status::Forbidden)
                 } else {
                     Outcome::Error((Status::Forbidden, ()))
                 }
@@ -85,11 +104,32 @@ impl<'r> FromRequest<'r> for AdminUser {
     }
 }
 
+/// A site admin who only gets their rights after enabling two-factor authentication.
+fn must_enable_second_factor(request: &Request<'_>, user: Option<&User>) -> bool {
+    let Some(myconfig) = request.rocket().state::<MyConfig>() else {
+        return false;
+    };
+    myconfig.admins_require_2fa && user.is_some_and(|user| myconfig.admins.contains(&user.email))
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Visitor {
     pub logged_in: bool,
     pub admin: bool,
     pub user: Option<User>,
+    /// Included in every form as the `csrf_token` field.
+    pub csrf_token: String,
+}
+
+impl From<LoggedIn> for Visitor {
+    fn from(visitor: LoggedIn) -> Self {
+        Self {
+            logged_in: visitor.logged_in,
+            admin: visitor.admin,
+            user: visitor.user,
+            csrf_token: visitor.csrf_token,
+        }
+    }
 }
 
 impl Visitor {
@@ -98,24 +138,33 @@ impl Visitor {
             logged_in: false,
             admin: false,
             user: None,
+            csrf_token: csrf_token(cookies),
         };
 
-        if let Some(cookie_user) = get_logged_in(cookies) {
-            rocket::info!("Email from cookie: {}", &cookie_user.email);
-            if let Some(user) = db::get_user_by_email(dbh, &cookie_user.email)
-                .await
-                .unwrap()
-            {
+        if let Some(sid) = get_session_id(cookies) {
+            let Some(session) = db::get_session_by_sid(dbh, &sid).await.unwrap() else {
+                rocket::warn!("Could not find session in the database");
+                return me;
+            };
+
+            let utc: DateTime<Utc> = Utc::now();
+            #[expect(clippy::arithmetic_side_effects)]
+            let expires = session.last_seen + Duration::days(myconfig.session_timeout_days);
+            if expires < utc {
+                rocket::info!("Session of uid {} expired", session.uid);
+                db::delete_session(dbh, &sid).await.unwrap();
+                return me;
+            }
+
+            if let Some(user) = db::get_user_by_uid(dbh, session.uid).await.unwrap() {
+                db::touch_session(dbh, &sid).await.unwrap();
                 me.logged_in = true;
+                me.admin = is_admin(dbh, myconfig, &user).await;
                 me.user = Some(user);
-                //rocket::info!("email: {}", user.email);
-                if myconfig.admins.contains(&cookie_user.email.clone()) {
-                    me.admin = true;
-                }
             } else {
                 rocket::warn!(
-                    "Could not find user with email: {} in the database",
-                    &cookie_user.email
+                    "Could not find user with uid: {} in the database",
+                    session.uid
                 );
             }
         }
@@ -124,6 +173,7 @@ impl Visitor {
     }
 
     pub async fn new_after_login(
+        cookies: &CookieJar<'_>,
         email: &str,
         dbh: &State<Surreal<Client>>,
         myconfig: &State<MyConfig>,
@@ -132,26 +182,25 @@ impl Visitor {
             logged_in: false,
             admin: false,
             user: None,
+            csrf_token: csrf_token(cookies),
         };
         rocket::info!("new_after_login");
 
         if let Some(user) = db::get_user_by_email(dbh, email).await.unwrap() {
             me.logged_in = true;
+            me.admin = is_admin(dbh, myconfig, &user).await;
             me.user = Some(user);
-            //rocket::info!("email: {}", user.email);
-            if myconfig.admins.contains(&email.to_owned()) {
-                me.admin = true;
-            }
         }
 
         me
     }
 
-    pub fn new_after_logout() -> Self {
+    pub fn new_after_logout(cookies: &CookieJar<'_>) -> Self {
         Self {
             logged_in: false,
             admin: false,
             user: None,
+            csrf_token: csrf_token(cookies),
         }
     }
 }
@@ -169,13 +218,253 @@ impl<'r> FromRequest<'r> for Visitor {
     }
 }
 
-fn get_logged_in(cookies: &CookieJar<'_>) -> Option<CookieUser> {
-    if let Some(cookie) = cookies.get_private("meet-os") {
-        let email = cookie.value();
-        rocket::info!("get_logged_in: cookie value received from user: {email}");
-        return Some(CookieUser {
-            email: email.to_owned(),
-        });
+/// A request authenticated with a personal API token in the `Authorization: Bearer` header.
+/// It has the permissions of the user who created the token, limited to the scopes of the token.
+#[derive(Debug, Clone)]
+pub struct ApiUser {
+    pub user: User,
+    pub admin: bool,
+    pub scopes: Vec<ApiScope>,
+}
+
+impl ApiUser {
+    #[must_use]
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ApiUser {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, ()> {
+        let Some(token) = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+        else {
+            return Outcome::Error((Status::Unauthorized, ()));
+        };
+
+        let dbh = request.rocket().state::<Surreal<Client>>().unwrap();
+        let myconfig = request.rocket().state::<MyConfig>().unwrap();
+
+        let Some(api_token) = db::use_api_token(dbh, &hash_token(token.trim()))
+            .await
+            .unwrap()
+        else {
+            rocket::warn!("Invalid API token");
+            return Outcome::Error((Status::Unauthorized, ()));
+        };
+
+        let Some(user) = db::get_user_by_uid(dbh, api_token.uid).await.unwrap() else {
+            return Outcome::Error((Status::Unauthorized, ()));
+        };
+
+        let admin = is_admin(dbh, myconfig, &user).await;
+        Outcome::Success(Self {
+            user,
+            admin,
+            scopes: api_token.scopes,
+        })
+    }
+}
+
+/// Site admins are listed in the config, but if `admins_require_2fa` is set
+/// they only get their rights after enabling two-factor authentication.
+async fn is_admin(dbh: &Surreal<Client>, myconfig: &MyConfig, user: &User) -> bool {
+    if !myconfig.admins.contains(&user.email) {
+        return false;
+    }
+    if !myconfig.admins_require_2fa {
+        return true;
+    }
+
+    db::get_totp(dbh, user.uid)
+        .await
+        .unwrap()
+        .is_some_and(|totp| totp.enabled)
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub user_agent: String,
+    /// Empty if Rocket cannot tell the address of the client.
+    /// Behind a reverse proxy set `ip_header` in Rocket.toml to the header the proxy fills,
+    /// otherwise every request seems to come from the proxy.
+    pub ip: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientInfo {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, ()> {
+        let user_agent = request
+            .headers()
+            .get_one("User-Agent")
+            .unwrap_or_default()
+            .to_owned();
+        let ip = request
+            .client_ip()
+            .map(|ip| ip.to_string())
+            .unwrap_or_default();
+
+        Outcome::Success(Self { user_agent, ip })
+    }
+}
+
+/// Create a new session for the user and store its id in the private cookie.
+pub async fn start_session(
+    cookies: &CookieJar<'_>,
+    dbh: &Surreal<Client>,
+    client: &ClientInfo,
+    user: &User,
+) {
+    let utc: DateTime<Utc> = Utc::now();
+    let session = Session {
+        id: Thing::from(("session", Id::ulid())),
+        sid: Uuid::new_v4().to_string(),
+        uid: user.uid,
+        created: utc,
+        last_seen: utc,
+        user_agent: client.user_agent.clone(),
+        ip: client.ip.clone(),
+    };
+    db::add_session(dbh, &session).await.unwrap();
+
+    // Lax so we still know the user when an OAuth provider redirects back to link an account.
+    let cookie = Cookie::build((SESSION_COOKIE, session.sid)).same_site(SameSite::Lax);
+    cookies.add_private(cookie);
+    renew_csrf_token(cookies);
+}
+
+/// Remove the current session from the database and the cookie from the browser.
+pub async fn end_session(cookies: &CookieJar<'_>, dbh: &Surreal<Client>) {
+    if let Some(sid) = get_session_id(cookies) {
+        db::delete_session(dbh, &sid).await.unwrap();
+    }
+    cookies.remove_private(SESSION_COOKIE);
+    renew_csrf_token(cookies);
+}
+
+pub fn get_session_id(cookies: &CookieJar<'_>) -> Option<String> {
+    cookies
+        .get_private(SESSION_COOKIE)
+        .map(|cookie| cookie.value().to_owned())
+}
+
+/// Remember the user who passed the password check until they type in the second factor.
+/// The expiry is also stored in the cookie as the browser might not respect its `max_age`.
+pub fn start_second_factor(cookies: &CookieJar<'_>, uid: usize) {
+    #[expect(clippy::arithmetic_side_effects)]
+    let expires = Utc::now() + Duration::minutes(SECOND_FACTOR_MINUTES);
+    let cookie = Cookie::build((
+        SECOND_FACTOR_COOKIE,
+        format!("{uid}:{}", expires.timestamp()),
+    ))
+    .max_age(time::Duration::minutes(SECOND_FACTOR_MINUTES));
+    cookies.add_private(cookie);
+}
+
+/// The user waiting for the second factor unless their time is up.
+pub fn get_second_factor_uid(cookies: &CookieJar<'_>) -> Option<usize> {
+    let cookie = cookies.get_private(SECOND_FACTOR_COOKIE)?;
+    let (uid, expires) = cookie.value().split_once(':')?;
+    let expires = DateTime::from_timestamp(expires.parse().ok()?, 0)?;
+    if expires < Utc::now() {
+        return None;
+    }
+    uid.parse().ok()
+}
+
+pub fn end_second_factor(cookies: &CookieJar<'_>) {
+    cookies.remove_private(SECOND_FACTOR_COOKIE);
+}
+
+/// Remember the state and the PKCE verifier while the visitor is at the OAuth provider.
+/// The cookie has to be Lax as the provider redirects back from another site.
+pub fn start_oauth(cookies: &CookieJar<'_>, provider: &str, state: &str, verifier: &str) {
+    let cookie = Cookie::build((OAUTH_COOKIE, format!("{provider} {state} {verifier}")))
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::minutes(10));
+    cookies.add_private(cookie);
+}
+
+/// Returns the provider, the state and the verifier saved by `start_oauth`. It can be used only once.
+pub fn take_oauth(cookies: &CookieJar<'_>) -> Option<(String, String, String)> {
+    let value = cookies.get_private(OAUTH_COOKIE)?.value().to_owned();
+    cookies.remove_private(OAUTH_COOKIE);
+
+    let mut parts = value.split(' ').map(ToOwned::to_owned);
+    Some((parts.next()?, parts.next()?, parts.next()?))
+}
+
+/// The CSRF token of the browser, created on the first visit and replaced at every login and logout.
+/// It is kept in a private cookie so other sites can neither read nor set it.
+pub fn csrf_token(cookies: &CookieJar<'_>) -> String {
+    if let Some(cookie) = cookies.get_private(CSRF_COOKIE) {
+        // The token replaced in this request is only among the pending cookies
+        return cookies.get_pending(CSRF_COOKIE).map_or_else(
+            || cookie.value().to_owned(),
+            |pending| pending.value().to_owned(),
+        );
+    }
+
+    renew_csrf_token(cookies)
+}
+
+/// Replace the CSRF token of the browser, so the forms of the previous session cannot be submitted.
+fn renew_csrf_token(cookies: &CookieJar<'_>) -> String {
+    let token = Uuid::new_v4().simple().to_string();
+    let cookie = Cookie::build((CSRF_COOKIE, token.clone())).same_site(SameSite::Lax);
+    cookies.add_private(cookie);
+    token
+}
+
+/// A form that has to be submitted with the `csrf_token` of the visitor.
+/// If the token is missing or wrong the request fails with 403 Forbidden.
+pub struct CsrfForm<T>(T);
+
+impl<T> Deref for CsrfForm<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+struct FormBody(String);
+
+#[rocket::async_trait]
+impl<'r, T: FromForm<'r>> FromData<'r> for CsrfForm<T> {
+    type Error = ();
+
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let limit = req.limits().get("form").unwrap_or(Limits::FORM);
+        let body = match data.open(limit).into_string().await {
+            Ok(body) if body.is_complete() => body.into_inner(),
+            Ok(_) => return Outcome::Error((Status::PayloadTooLarge, ())),
+            Err(_) => return Outcome::Error((Status::BadRequest, ())),
+        };
+        let body = &req.local_cache(|| FormBody(body)).0;
+
+        let expected = req
+            .cookies()
+            .get_private(CSRF_COOKIE)
+            .map(|cookie| cookie.value().to_owned());
+        let submitted = Form::values(body)
+            .find(|field| field.name == "csrf_token")
+            .map(|field| field.value.to_owned());
+        if expected.is_none() || submitted != expected {
+            rocket::warn!("CSRF token mismatch on {}", req.uri());
+            return Outcome::Error((Status::Forbidden, ()));
+        }
+
+        match Form::<T>::parse(body) {
+            Ok(input) => Outcome::Success(Self(input)),
+            Err(_) => Outcome::Error((Status::UnprocessableEntity, ())),
+        }
     }
-    None
 }
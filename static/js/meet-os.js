@@ -40,38 +40,14 @@ document.addEventListener('DOMContentLoaded', () => {
     }
   }
 
-  function set_local_timezone() {
-    if (document.getElementById("add-event") || document.getElementById("edit-event")) {
-      const date = new Date();
-      //console.log(Intl.DateTimeFormat().resolvedOptions().timeZone); // e.g. Asia/Jerusalem
-      //console.log(date.toLocaleDateString(undefined, {day:'2-digit',timeZoneName: 'long' }).substring(4)); // Israel Daylight Time
-      //console.log(date.toLocaleDateString(undefined, {day:'2-digit',timeZoneName: 'short' }).substring(4)); // GMT+3
-      //const offset = date.getTimezoneOffset();
-      //console.log(offset); // -180
-      let text = date.toLocaleDateString(undefined, {day:'2-digit',timeZoneName: 'short' }).substring(4);
-      text += "(" + date.toLocaleDateString(undefined, {day:'2-digit',timeZoneName: 'long' }).substring(4) + ")";
-      document.getElementById("timezone").innerHTML = text;
-      document.getElementById("offset").value = date.getTimezoneOffset();
+  function set_browser_timezone() {
+    // Help the organizers pick the zone of the event
+    const browser_timezone = document.getElementById("browser-timezone");
+    if (browser_timezone) {
+      browser_timezone.innerHTML = "(your browser is in " + Intl.DateTimeFormat().resolvedOptions().timeZone + ")";
     }
-
-    if (document.getElementById("edit-event")) {
-      let date_str = document.getElementById("date").getAttribute("original-value");
-      console.log(date_str);
-      let date = new Date(date_str);
-      console.log(date);
-      //let text = `${date.getFullYear()}-${date.getMonth() + 1}-${date.getDate()}`;
-      let text = date.getFullYear() + '-';
-      text += String(date.getMonth() + 1).padStart(2, '0') + '-';
-      text += String(date.getDate()).padStart(2, '0') + ' '
-      text += String(date.getHours()).padStart(2, '0') + ':';
-      text += String(date.getMinutes()).padStart(2, '0');
-
-      console.log(text);
-      document.getElementById("date").value = text;
-    }
-
   }
 
-  set_local_timezone();
+  set_browser_timezone();
   set_local_date();
 });
@@ -1,3 +1,5 @@
+#![allow(clippy::all, clippy::pedantic, clippy::restriction)]
+
 use std::net::TcpListener;
 use std::os::unix::process::CommandExt;
 use std::process::Command;